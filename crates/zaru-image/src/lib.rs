@@ -7,11 +7,13 @@
 //! - The [`AsImageView`] and [`AsImageViewMut`] traits to abstract over images and views.
 //! - A variety of [`draw`] functions to quickly visualize objects.
 //! - [`Rect`] and [`RotatedRect`], integer-valued rectangles representing parts of an image.
+//! - [`RemapTable`], for geometric transformations like lens distortion correction.
 
 mod blend;
 pub mod draw;
 mod jpeg;
 mod rect;
+mod remap;
 mod resolution;
 
 #[cfg(test)]
@@ -24,6 +26,7 @@ use image::{GenericImage, GenericImageView, ImageBuffer, Rgba, RgbaImage};
 
 pub use blend::*;
 pub use rect::*;
+pub use remap::*;
 pub use resolution::*;
 
 #[derive(Debug, Clone, Copy)]
@@ -0,0 +1,237 @@
+use zaru_utils::camera::CameraModel;
+
+use crate::{Image, Resolution};
+
+/// A precomputed geometric transformation of an image.
+///
+/// A remap table stores, for every pixel of the output image, the (sub-pixel) source position in
+/// the input image that it is sampled from. Computing the table can be expensive, but applying it
+/// to an image is fast, so a table is typically created once and then applied to every frame of a
+/// video stream.
+///
+/// The most common use is correcting lens distortion with [`RemapTable::undistort`].
+///
+/// Pixel coordinates used by remap tables have the center of the top left pixel at `(0, 0)`.
+#[derive(Clone)]
+pub struct RemapTable {
+    src_res: Resolution,
+    dest_res: Resolution,
+    entries: Vec<Option<Entry>>,
+}
+
+/// Bilinear sampling information for a single output pixel.
+#[derive(Clone, Copy)]
+struct Entry {
+    /// Indices of the top left, top right, bottom left and bottom right source pixels.
+    indices: [u32; 4],
+    /// Horizontal and vertical interpolation weights of the right and bottom pixels.
+    weights: [f32; 2],
+}
+
+impl RemapTable {
+    /// Creates a remap table from a function mapping output pixel coordinates to source pixel
+    /// coordinates.
+    ///
+    /// `map` is called once for every pixel of the `dest_res`-sized output image. If it returns
+    /// [`None`], or a position outside of the `src_res`-sized source image, the output pixel will
+    /// be set to [`Color::NULL`][crate::Color::NULL].
+    pub fn new(
+        src_res: Resolution,
+        dest_res: Resolution,
+        mut map: impl FnMut([f32; 2]) -> Option<[f32; 2]>,
+    ) -> Self {
+        let mut entries = Vec::with_capacity(dest_res.num_pixels() as usize);
+        for y in 0..dest_res.height() {
+            for x in 0..dest_res.width() {
+                let entry = map([x as f32, y as f32]).and_then(|src| Entry::new(src_res, src));
+                entries.push(entry);
+            }
+        }
+
+        Self {
+            src_res,
+            dest_res,
+            entries,
+        }
+    }
+
+    /// Creates a remap table that removes the lens distortion of `camera` from images of size
+    /// `res`.
+    ///
+    /// The output image is what the [ideal camera][CameraModel::undistorted] (with the same focal
+    /// length and principal point, but without distortion) would have captured. Points in the
+    /// source and output image can be converted into each other with
+    /// [`CameraModel::undistort_pixel`] and [`CameraModel::distort_pixel`].
+    ///
+    /// Output pixels that lie outside of the captured image (for example, in the corners when
+    /// removing pincushion distortion) are set to [`Color::NULL`][crate::Color::NULL].
+    pub fn undistort(camera: &CameraModel, res: Resolution) -> Self {
+        Self::new(res, res, |pixel| Some(camera.distort_pixel(pixel)))
+    }
+
+    /// Returns the resolution of the images this table can be applied to.
+    #[inline]
+    pub fn source_resolution(&self) -> Resolution {
+        self.src_res
+    }
+
+    /// Returns the resolution of the images this table produces.
+    #[inline]
+    pub fn resolution(&self) -> Resolution {
+        self.dest_res
+    }
+
+    /// Applies the transformation to `src`, returning the transformed image.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the resolution of `src` does not match [`RemapTable::source_resolution`].
+    pub fn apply(&self, src: &Image) -> Image {
+        let mut dest = Image::new(self.dest_res.width(), self.dest_res.height());
+        self.apply_into(src, &mut dest);
+        dest
+    }
+
+    /// Applies the transformation to `src`, writing the result to `dest`.
+    ///
+    /// This allows reusing the output image between frames.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the resolution of `src` does not match [`RemapTable::source_resolution`], or
+    /// if the resolution of `dest` does not match [`RemapTable::resolution`].
+    pub fn apply_into(&self, src: &Image, dest: &mut Image) {
+        assert_eq!(
+            src.resolution(),
+            self.src_res,
+            "source image resolution does not match remap table"
+        );
+        assert_eq!(
+            dest.resolution(),
+            self.dest_res,
+            "destination image resolution does not match remap table"
+        );
+
+        let src = src.buf.as_raw();
+        for (entry, out) in self.entries.iter().zip(dest.buf.chunks_exact_mut(4)) {
+            let Some(entry) = entry else {
+                out.copy_from_slice(&[0; 4]);
+                continue;
+            };
+
+            let [fx, fy] = entry.weights;
+            let pixel = |i: usize| &src[entry.indices[i] as usize * 4..][..4];
+            let (tl, tr, bl, br) = (pixel(0), pixel(1), pixel(2), pixel(3));
+            for c in 0..4 {
+                let top = tl[c] as f32 * (1.0 - fx) + tr[c] as f32 * fx;
+                let bottom = bl[c] as f32 * (1.0 - fx) + br[c] as f32 * fx;
+                out[c] = (top * (1.0 - fy) + bottom * fy).round() as u8;
+            }
+        }
+    }
+}
+
+impl Entry {
+    fn new(res: Resolution, [x, y]: [f32; 2]) -> Option<Self> {
+        let (width, height) = (res.width() as f32, res.height() as f32);
+        if !(x >= -0.5 && y >= -0.5 && x < width - 0.5 && y < height - 0.5) {
+            return None;
+        }
+
+        // Clamp to the image edges, so that samples close to the edge don't blend with black.
+        let x = x.clamp(0.0, width - 1.0);
+        let y = y.clamp(0.0, height - 1.0);
+        let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+        let x1 = (x0 + 1).min(res.width() - 1);
+        let y1 = (y0 + 1).min(res.height() - 1);
+        let index = |x, y| y * res.width() + x;
+        Some(Self {
+            indices: [index(x0, y0), index(x1, y0), index(x0, y1), index(x1, y1)],
+            weights: [x - x0 as f32, y - y0 as f32],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zaru_utils::camera::Distortion;
+
+    use crate::{AsImageView, Color};
+
+    use super::*;
+
+    fn gradient(res: Resolution) -> Image {
+        let buf = (0..res.height())
+            .flat_map(|y| (0..res.width()).map(move |x| [x as u8, y as u8, 0, 255]))
+            .flatten()
+            .collect::<Vec<_>>();
+        Image::from_rgba8(res, &buf)
+    }
+
+    #[test]
+    fn identity() {
+        let res = Resolution::new(16, 8);
+        let image = gradient(res);
+        let table = RemapTable::new(res, res, Some);
+        assert_eq!(table.apply(&image).data(), image.data());
+    }
+
+    #[test]
+    fn bilinear() {
+        let res = Resolution::new(16, 8);
+        let image = gradient(res);
+        let table = RemapTable::new(res, Resolution::new(2, 1), |[x, _]| Some([x + 3.5, 2.25]));
+        let out = table.apply(&image);
+        let view = out.as_view();
+        // Rounded from (3.5, 2.25) and (4.5, 2.25).
+        assert_eq!(view.get(0, 0), Color::from_rgb8(4, 2, 0));
+        assert_eq!(view.get(1, 0), Color::from_rgb8(5, 2, 0));
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let res = Resolution::new(4, 4);
+        let image = gradient(res);
+        let table = RemapTable::new(res, res, |[x, y]| match x as u32 {
+            0 => None,
+            1 => Some([x - 2.0, y]),
+            _ => Some([x, y]),
+        });
+        let out = table.apply(&image);
+        let view = out.as_view();
+        assert_eq!(view.get(0, 0), Color::NULL);
+        assert_eq!(view.get(1, 1), Color::NULL);
+        assert_eq!(view.get(2, 1), Color::from_rgb8(2, 1, 0));
+    }
+
+    #[test]
+    fn undistort() {
+        let res = Resolution::new(64, 48);
+        let camera = CameraModel::new([50.0, 50.0], [32.0, 24.0])
+            .with_distortion(Distortion::new([-0.3, 0.1, 0.0], [0.0, 0.0]));
+        let image = gradient(res);
+        let table = RemapTable::undistort(&camera, res);
+        let out = table.apply(&image);
+        let view = out.as_view();
+
+        // The principal point stays where it is.
+        assert_eq!(view.get(32, 24), image.as_view().get(32, 24));
+
+        // Every output pixel is sampled from the point `distort_pixel` maps it to.
+        for (x, y) in [(10, 10), (50, 30), (20, 40)] {
+            let [sx, sy] = camera.distort_pixel([x as f32, y as f32]);
+            let color = view.get(x, y);
+            assert_eq!(color.r(), sx.round() as u8);
+            assert_eq!(color.g(), sy.round() as u8);
+        }
+
+        // Barrel distortion is removed by stretching the image, so the whole output is covered.
+        assert_ne!(view.get(0, 0), Color::NULL);
+
+        // Pincushion distortion is removed by shrinking the image, so the corners are empty.
+        let camera = camera.with_distortion(Distortion::new([0.3, 0.0, 0.0], [0.0, 0.0]));
+        let out = RemapTable::undistort(&camera, res).apply(&image);
+        assert_eq!(out.as_view().get(0, 0), Color::NULL);
+        assert_ne!(out.as_view().get(32, 24), Color::NULL);
+    }
+}
@@ -0,0 +1,198 @@
+//! Solvers for the linear assignment problem.
+
+use nalgebra::DMatrix;
+
+/// Solves the rectangular linear assignment problem using the Hungarian algorithm.
+///
+/// `costs` is a matrix where element `(r, c)` is the cost of assigning row `r` to column `c`.
+/// Every row is assigned to at most one column and vice versa, such that the number of assignments
+/// is maximized and their total cost is minimized.
+///
+/// Returns a [`Vec`] with one entry per row of `costs`, containing the index of the column that row
+/// was assigned to. If there are more rows than columns, some rows will remain unassigned (`None`).
+///
+/// Runs in `O(n²m)` time, where `n` is the smaller and `m` the larger dimension of `costs`.
+///
+/// # Panics
+///
+/// This function will panic if any element of `costs` is not finite.
+pub fn hungarian(costs: &DMatrix<f32>) -> Vec<Option<usize>> {
+    assert!(
+        costs.iter().all(|c| c.is_finite()),
+        "assignment cost matrix must only contain finite values"
+    );
+
+    if costs.nrows() <= costs.ncols() {
+        let cols = hungarian_impl(costs.nrows(), costs.ncols(), |r, c| costs[(r, c)]);
+        cols.into_iter().map(Some).collect()
+    } else {
+        let rows = hungarian_impl(costs.ncols(), costs.nrows(), |c, r| costs[(r, c)]);
+        let mut assignment = vec![None; costs.nrows()];
+        for (col, row) in rows.into_iter().enumerate() {
+            assignment[row] = Some(col);
+        }
+        assignment
+    }
+}
+
+/// Assigns every one of `n` rows to one of `m` columns (`n <= m`), returning the column index of
+/// each row.
+///
+/// This is the potential-based variant of the algorithm, which finds a shortest augmenting path
+/// for each row in turn (see <https://cp-algorithms.com/graph/hungarian-algorithm.html>).
+fn hungarian_impl(n: usize, m: usize, cost: impl Fn(usize, usize) -> f32) -> Vec<usize> {
+    debug_assert!(n <= m);
+
+    // All arrays are 1-based, index 0 is used as a sentinel for "unassigned".
+    let mut u = vec![0.0f64; n + 1];
+    let mut v = vec![0.0f64; m + 1];
+    // Row assigned to each column.
+    let mut p = vec![0usize; m + 1];
+    let mut way = vec![0usize; m + 1];
+
+    for i in 1..=n {
+        p[0] = i;
+        let mut j0 = 0;
+        let mut minv = vec![f64::INFINITY; m + 1];
+        let mut used = vec![false; m + 1];
+        loop {
+            used[j0] = true;
+            let i0 = p[j0];
+            let mut delta = f64::INFINITY;
+            let mut j1 = 0;
+            for j in 1..=m {
+                if used[j] {
+                    continue;
+                }
+                let cur = f64::from(cost(i0 - 1, j - 1)) - u[i0] - v[j];
+                if cur < minv[j] {
+                    minv[j] = cur;
+                    way[j] = j0;
+                }
+                if minv[j] < delta {
+                    delta = minv[j];
+                    j1 = j;
+                }
+            }
+            for j in 0..=m {
+                if used[j] {
+                    u[p[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    minv[j] -= delta;
+                }
+            }
+            j0 = j1;
+            if p[j0] == 0 {
+                break;
+            }
+        }
+
+        // Flip the augmenting path.
+        loop {
+            let j1 = way[j0];
+            p[j0] = p[j1];
+            j0 = j1;
+            if j0 == 0 {
+                break;
+            }
+        }
+    }
+
+    let mut assignment = vec![0; n];
+    for j in 1..=m {
+        if p[j] != 0 {
+            assignment[p[j] - 1] = j - 1;
+        }
+    }
+    assignment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total_cost(costs: &DMatrix<f32>, assignment: &[Option<usize>]) -> f32 {
+        assignment
+            .iter()
+            .enumerate()
+            .filter_map(|(r, c)| c.map(|c| costs[(r, c)]))
+            .sum()
+    }
+
+    /// Finds the optimal cost by trying every permutation.
+    fn brute_force(costs: &DMatrix<f32>) -> f32 {
+        fn go(costs: &DMatrix<f32>, row: usize, used: &mut Vec<bool>, assigned: usize) -> f32 {
+            let target = costs.nrows().min(costs.ncols());
+            if assigned == target {
+                return 0.0;
+            }
+            if row == costs.nrows() {
+                return f32::INFINITY;
+            }
+
+            // Skipping a row is only allowed if there are more rows than columns.
+            let mut best = if costs.nrows() - row > target - assigned {
+                go(costs, row + 1, used, assigned)
+            } else {
+                f32::INFINITY
+            };
+            for c in 0..costs.ncols() {
+                if !used[c] {
+                    used[c] = true;
+                    let cost = costs[(row, c)] + go(costs, row + 1, used, assigned + 1);
+                    best = best.min(cost);
+                    used[c] = false;
+                }
+            }
+            best
+        }
+
+        go(costs, 0, &mut vec![false; costs.ncols()], 0)
+    }
+
+    #[test]
+    fn square() {
+        let costs = DMatrix::from_row_slice(3, 3, &[4.0, 1.0, 3.0, 2.0, 0.0, 5.0, 3.0, 2.0, 2.0]);
+        let assignment = hungarian(&costs);
+        assert_eq!(assignment, [Some(1), Some(0), Some(2)]);
+        assert_eq!(total_cost(&costs, &assignment), 5.0);
+    }
+
+    #[test]
+    fn rectangular() {
+        let wide = DMatrix::from_row_slice(2, 3, &[10.0, 1.0, 10.0, 10.0, 2.0, 3.0]);
+        assert_eq!(hungarian(&wide), [Some(1), Some(2)]);
+
+        let tall = wide.transpose();
+        assert_eq!(hungarian(&tall), [None, Some(0), Some(1)]);
+
+        assert!(hungarian(&DMatrix::zeros(0, 3)).is_empty());
+        assert_eq!(hungarian(&DMatrix::zeros(2, 0)), [None, None]);
+    }
+
+    #[test]
+    fn random_matrices_are_optimal() {
+        let rng = fastrand::Rng::with_seed(0x5eed);
+        for _ in 0..200 {
+            let rows = rng.usize(1..=5);
+            let cols = rng.usize(1..=5);
+            let costs = DMatrix::from_fn(rows, cols, |_, _| rng.u8(0..20) as f32);
+
+            let assignment = hungarian(&costs);
+            let assigned = assignment.iter().flatten().count();
+            assert_eq!(assigned, rows.min(cols));
+
+            let mut cols_used = assignment.iter().flatten().collect::<Vec<_>>();
+            cols_used.sort();
+            cols_used.dedup();
+            assert_eq!(cols_used.len(), assigned, "column assigned twice");
+
+            assert_eq!(
+                total_cost(&costs, &assignment),
+                brute_force(&costs),
+                "{costs}"
+            );
+        }
+    }
+}
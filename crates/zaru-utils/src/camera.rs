@@ -0,0 +1,251 @@
+//! Pinhole camera model with lens distortion.
+
+use nalgebra::Vector3;
+
+/// Number of iterations used to invert the lens distortion model.
+const UNDISTORT_ITERATIONS: usize = 20;
+
+/// Lens distortion coefficients of the [Brown–Conrady] model, as used by OpenCV.
+///
+/// The model consists of 3 radial (`k1`, `k2`, `k3`) and 2 tangential (`p1`, `p2`) coefficients,
+/// and operates on normalized image coordinates (see [`CameraModel`]).
+///
+/// [Brown–Conrady]: https://en.wikipedia.org/wiki/Distortion_(optics)#Software_correction
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Distortion {
+    radial: [f32; 3],
+    tangential: [f32; 2],
+}
+
+impl Distortion {
+    /// A lens without any distortion.
+    pub const NONE: Self = Self {
+        radial: [0.0; 3],
+        tangential: [0.0; 2],
+    };
+
+    /// Creates a set of distortion coefficients from the radial coefficients `[k1, k2, k3]` and the
+    /// tangential coefficients `[p1, p2]`.
+    pub fn new(radial: [f32; 3], tangential: [f32; 2]) -> Self {
+        Self { radial, tangential }
+    }
+
+    /// Returns the radial distortion coefficients `[k1, k2, k3]`.
+    pub fn radial(&self) -> [f32; 3] {
+        self.radial
+    }
+
+    /// Returns the tangential distortion coefficients `[p1, p2]`.
+    pub fn tangential(&self) -> [f32; 2] {
+        self.tangential
+    }
+
+    /// Applies the distortion to a point in normalized image coordinates.
+    pub fn distort(&self, [x, y]: [f32; 2]) -> [f32; 2] {
+        let [k1, k2, k3] = self.radial;
+        let [p1, p2] = self.tangential;
+        let r2 = x * x + y * y;
+        let radial = 1.0 + r2 * (k1 + r2 * (k2 + r2 * k3));
+        [
+            x * radial + 2.0 * p1 * x * y + p2 * (r2 + 2.0 * x * x),
+            y * radial + p1 * (r2 + 2.0 * y * y) + 2.0 * p2 * x * y,
+        ]
+    }
+
+    /// Removes the distortion from a point in normalized image coordinates.
+    ///
+    /// The distortion model has no closed-form inverse, so this uses a fixed number of fixed-point
+    /// iterations. This is accurate for the moderate distortion of typical webcam lenses.
+    pub fn undistort(&self, [x0, y0]: [f32; 2]) -> [f32; 2] {
+        let [k1, k2, k3] = self.radial;
+        let [p1, p2] = self.tangential;
+        if self == &Self::NONE {
+            return [x0, y0];
+        }
+
+        let (mut x, mut y) = (x0, y0);
+        for _ in 0..UNDISTORT_ITERATIONS {
+            let r2 = x * x + y * y;
+            let radial = 1.0 + r2 * (k1 + r2 * (k2 + r2 * k3));
+            let dx = 2.0 * p1 * x * y + p2 * (r2 + 2.0 * x * x);
+            let dy = p1 * (r2 + 2.0 * y * y) + 2.0 * p2 * x * y;
+            x = (x0 - dx) / radial;
+            y = (y0 - dy) / radial;
+        }
+        [x, y]
+    }
+}
+
+/// Intrinsic parameters of a camera.
+///
+/// The camera model maps points in *camera space* to pixel coordinates. Camera space has its origin
+/// at the camera's center of projection, with X pointing right, Y pointing down, and Z pointing
+/// forward, into the scene. This matches the conventions used by OpenCV.
+///
+/// Projection happens in 3 steps:
+///
+/// - Points are divided by their Z coordinate, yielding *normalized image coordinates*.
+/// - The lens [`Distortion`] is applied to the normalized image coordinates.
+/// - The result is scaled by the focal length and offset by the principal point, yielding pixel
+///   coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraModel {
+    focal_length: [f32; 2],
+    principal_point: [f32; 2],
+    distortion: Distortion,
+}
+
+impl CameraModel {
+    /// Creates a camera model without lens distortion.
+    ///
+    /// `focal_length` and `principal_point` are given in pixels, as `[x, y]` pairs.
+    pub fn new(focal_length: [f32; 2], principal_point: [f32; 2]) -> Self {
+        Self {
+            focal_length,
+            principal_point,
+            distortion: Distortion::NONE,
+        }
+    }
+
+    /// Approximates the camera model of an uncalibrated camera from its horizontal field of view.
+    ///
+    /// This assumes square pixels, no lens distortion, and a principal point in the image center.
+    /// `fov` is the horizontal field of view in radians.
+    pub fn from_fov(width: u32, height: u32, fov: f32) -> Self {
+        let focal_length = width as f32 / 2.0 / (fov / 2.0).tan();
+        Self::new([focal_length; 2], [width as f32 / 2.0, height as f32 / 2.0])
+    }
+
+    /// Returns a copy of `self` with different lens distortion coefficients.
+    pub fn with_distortion(self, distortion: Distortion) -> Self {
+        Self { distortion, ..self }
+    }
+
+    /// Returns the focal length in pixels, as `[fx, fy]`.
+    pub fn focal_length(&self) -> [f32; 2] {
+        self.focal_length
+    }
+
+    /// Returns the principal point in pixels, as `[cx, cy]`.
+    pub fn principal_point(&self) -> [f32; 2] {
+        self.principal_point
+    }
+
+    /// Returns the lens distortion coefficients.
+    pub fn distortion(&self) -> &Distortion {
+        &self.distortion
+    }
+
+    /// Returns the *ideal* camera corresponding to `self`.
+    ///
+    /// The ideal camera has the same focal length and principal point, but no lens distortion.
+    /// [`CameraModel::undistort_pixel`] and [`CameraModel::distort_pixel`] convert between pixel
+    /// coordinates of `self` and of the ideal camera.
+    pub fn undistorted(&self) -> Self {
+        Self {
+            distortion: Distortion::NONE,
+            ..*self
+        }
+    }
+
+    /// Projects a point in camera space to pixel coordinates.
+    ///
+    /// The point should be in front of the camera (have a positive Z coordinate), otherwise the
+    /// result is meaningless.
+    pub fn project(&self, point: Vector3<f32>) -> [f32; 2] {
+        self.normalized_to_pixel([point.x / point.z, point.y / point.z])
+    }
+
+    /// Computes the ray through the center of a pixel.
+    ///
+    /// The returned point lies on the ray, at a Z coordinate of 1.0.
+    pub fn unproject(&self, pixel: [f32; 2]) -> Vector3<f32> {
+        let [x, y] = self.pixel_to_normalized(pixel);
+        Vector3::new(x, y, 1.0)
+    }
+
+    /// Converts undistorted normalized image coordinates to pixel coordinates.
+    pub fn normalized_to_pixel(&self, normalized: [f32; 2]) -> [f32; 2] {
+        let [x, y] = self.distortion.distort(normalized);
+        [
+            x * self.focal_length[0] + self.principal_point[0],
+            y * self.focal_length[1] + self.principal_point[1],
+        ]
+    }
+
+    /// Converts pixel coordinates to undistorted normalized image coordinates.
+    pub fn pixel_to_normalized(&self, [x, y]: [f32; 2]) -> [f32; 2] {
+        self.distortion.undistort([
+            (x - self.principal_point[0]) / self.focal_length[0],
+            (y - self.principal_point[1]) / self.focal_length[1],
+        ])
+    }
+
+    /// Maps pixel coordinates in an image taken by this camera to the coordinates of the same
+    /// point in an image taken by the [ideal camera][CameraModel::undistorted].
+    pub fn undistort_pixel(&self, pixel: [f32; 2]) -> [f32; 2] {
+        let [x, y] = self.pixel_to_normalized(pixel);
+        [
+            x * self.focal_length[0] + self.principal_point[0],
+            y * self.focal_length[1] + self.principal_point[1],
+        ]
+    }
+
+    /// Maps pixel coordinates in an image taken by the [ideal camera][CameraModel::undistorted] to
+    /// the coordinates of the same point in an image taken by this camera.
+    ///
+    /// This is the inverse of [`CameraModel::undistort_pixel`].
+    pub fn distort_pixel(&self, [x, y]: [f32; 2]) -> [f32; 2] {
+        self.normalized_to_pixel([
+            (x - self.principal_point[0]) / self.focal_length[0],
+            (y - self.principal_point[1]) / self.focal_length[1],
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_project_unproject() {
+        let camera = CameraModel::new([800.0, 810.0], [320.0, 240.0])
+            .with_distortion(Distortion::new([-0.2, 0.05, 0.0], [0.001, -0.002]));
+
+        for pixel in [[320.0, 240.0], [10.0, 20.0], [600.0, 450.0], [100.0, 400.0]] {
+            let ray = camera.unproject(pixel);
+            let [x, y] = camera.project(ray * 3.0);
+            assert_relative_eq!(x, pixel[0], epsilon = 1e-2);
+            assert_relative_eq!(y, pixel[1], epsilon = 1e-2);
+        }
+    }
+
+    #[test]
+    fn test_undistort_pixel() {
+        let camera = CameraModel::new([600.0, 600.0], [320.0, 240.0])
+            .with_distortion(Distortion::new([-0.2, 0.05, 0.0], [0.0, 0.0]));
+
+        // The principal point is unaffected by radial distortion.
+        assert_eq!(camera.undistort_pixel([320.0, 240.0]), [320.0, 240.0]);
+
+        // Barrel distortion moves points towards the center, so undistorting moves them out.
+        let [x, y] = camera.undistort_pixel([600.0, 400.0]);
+        assert!(x > 600.0 && y > 400.0);
+        let [x, y] = camera.distort_pixel([x, y]);
+        assert_relative_eq!(x, 600.0, epsilon = 1e-2);
+        assert_relative_eq!(y, 400.0, epsilon = 1e-2);
+    }
+
+    #[test]
+    fn test_from_fov() {
+        let camera = CameraModel::from_fov(640, 480, 90f32.to_radians());
+        assert_relative_eq!(camera.focal_length()[0], 320.0, epsilon = 1e-3);
+        assert_eq!(camera.principal_point(), [320.0, 240.0]);
+
+        // A point at 45° is projected onto the image edge.
+        let [x, _] = camera.project(Vector3::new(1.0, 0.0, 1.0));
+        assert_relative_eq!(x, 640.0, epsilon = 1e-3);
+    }
+}
@@ -26,12 +26,42 @@
 //! argument. This allows users to pass custom time deltas for unit testing, data replay and other
 //! use cases. In the common case where the real world time should be used, [`TimedFilterAdapter`]
 //! can be used to adapt a [`TimeBasedFilter`] to the [`Filter`] trait.
+//!
+//! Windowed filters ([`MedianFilter`][median::MedianFilter],
+//! [`HampelFilter`][hampel::HampelFilter] and
+//! [`SavitzkyGolayFilter`][savitzky_golay::SavitzkyGolayFilter]) operate on the last few values,
+//! independent of the time between them. They implement [`TimeBasedFilter`] too, by ignoring the
+//! time delta, so that they can be chained with time-based filters.
+//!
+//! # Filtered Types
+//!
+//! Most filters operate on `f32`. Filters that smooth by interpolating between values, like
+//! [`Ema`][ema::Ema] and [`OneEuroFilter`][one_euro::OneEuroFilter], work on any type implementing
+//! [`Interpolate`] (and [`Differentiate`]), which includes fixed-size arrays, nalgebra vectors and
+//! [`UnitQuaternion`]s. Filtering the components of a vector or quaternion separately would not
+//! preserve its magnitude or couple the smoothing of its components.
+//!
+//! # Composing Filters
+//!
+//! Filters can be applied in sequence with [`Chain`]. For example, chaining a
+//! [`HampelFilter`][hampel::HampelFilter] and a [`OneEuroFilter`][one_euro::OneEuroFilter] first
+//! rejects single-value spikes, and then smoothes the remaining jitter.
+//!
+//! Angles wrap around at ±π, which confuses filters that treat them like any other scalar. The
+//! [`Angular`][angle::Angular] adapter makes any scalar filter wrap-aware.
+
+use std::{fmt, time::Instant};
 
-use std::time::Instant;
+use nalgebra::{SVector, UnitQuaternion};
 
 pub mod alpha_beta;
+pub mod angle;
 pub mod ema;
+pub mod hampel;
+pub mod kalman;
+pub mod median;
 pub mod one_euro;
+pub mod savitzky_golay;
 
 /// Base trait for filtering algorithms that defines the per-variable state of the filter.
 ///
@@ -85,6 +115,122 @@ pub trait TimeBasedFilter<V>: FilterBase<V> {
     }
 }
 
+/// Trait for [`TimeBasedFilter`]s that model the rate of change of the filtered variable, and can
+/// therefore extrapolate it into the future.
+///
+/// This is useful for motion prediction, where the position of a tracked object has to be estimated
+/// before the next measurement is available.
+pub trait PredictiveFilter<V>: TimeBasedFilter<V> {
+    /// Predicts the value of the filtered variable `elapsed` seconds after the last value passed to
+    /// [`TimeBasedFilter::filter`].
+    ///
+    /// `state` is not modified. If no value has been filtered yet, [`None`] is returned.
+    fn predict(&self, state: &Self::State, elapsed: f32) -> Option<V>;
+}
+
+/// Values that can be interpolated, for filters that smooth by blending new values into old ones.
+pub trait Interpolate: Clone {
+    /// Interpolates between `self` (at `t = 0.0`) and `other` (at `t = 1.0`).
+    ///
+    /// Vectors are interpolated linearly, while rotations are interpolated along the shortest arc.
+    fn interpolate(&self, other: &Self, t: f32) -> Self;
+}
+
+/// Values that have a rate of change.
+pub trait Differentiate: Interpolate {
+    /// Type describing the rate of change of a value (eg. a velocity vector for positions, or an
+    /// angular velocity for rotations).
+    type Rate: Interpolate + fmt::Debug;
+
+    /// Returns the rate at which the value changed from `prev` to `self` in `elapsed` seconds.
+    fn rate(&self, prev: &Self, elapsed: f32) -> Self::Rate;
+
+    /// Returns the magnitude of `rate` (eg. the speed for a velocity vector).
+    fn rate_magnitude(rate: &Self::Rate) -> f32;
+}
+
+impl Interpolate for f32 {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        t * other + (1.0 - t) * self
+    }
+}
+
+impl Differentiate for f32 {
+    type Rate = f32;
+
+    fn rate(&self, prev: &Self, elapsed: f32) -> f32 {
+        (self - prev) / elapsed
+    }
+
+    fn rate_magnitude(rate: &f32) -> f32 {
+        rate.abs()
+    }
+}
+
+impl<const N: usize> Interpolate for [f32; N] {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        std::array::from_fn(|i| self[i].interpolate(&other[i], t))
+    }
+}
+
+impl<const N: usize> Differentiate for [f32; N] {
+    type Rate = [f32; N];
+
+    fn rate(&self, prev: &Self, elapsed: f32) -> Self::Rate {
+        std::array::from_fn(|i| self[i].rate(&prev[i], elapsed))
+    }
+
+    fn rate_magnitude(rate: &Self::Rate) -> f32 {
+        rate.iter().map(|x| x * x).sum::<f32>().sqrt()
+    }
+}
+
+impl<const N: usize> Interpolate for SVector<f32, N> {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        self.lerp(other, t)
+    }
+}
+
+impl<const N: usize> Differentiate for SVector<f32, N> {
+    type Rate = SVector<f32, N>;
+
+    fn rate(&self, prev: &Self, elapsed: f32) -> Self::Rate {
+        (self - prev) / elapsed
+    }
+
+    fn rate_magnitude(rate: &Self::Rate) -> f32 {
+        rate.norm()
+    }
+}
+
+impl Interpolate for UnitQuaternion<f32> {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        // `slerp` takes the shortest path, so `q` and `-q` are treated as the same rotation.
+        self.slerp(other, t)
+    }
+}
+
+/// The rate of change of a rotation is its angular velocity, as a scaled axis in radians per
+/// second.
+impl Differentiate for UnitQuaternion<f32> {
+    type Rate = SVector<f32, 3>;
+
+    fn rate(&self, prev: &Self, elapsed: f32) -> Self::Rate {
+        let delta = prev.rotation_to(self);
+        // Take the shorter way around if `self` and `prev` lie in different hemispheres.
+        let delta = if delta.w < 0.0 {
+            UnitQuaternion::new_unchecked(-delta.into_inner())
+        } else {
+            delta
+        };
+        delta.scaled_axis() / elapsed
+    }
+
+    fn rate_magnitude(rate: &Self::Rate) -> f32 {
+        rate.norm()
+    }
+}
+
 /// Adapts a [`TimeBasedFilter`] to the [`Filter`] trait by supplying time deltas derived from the
 /// current system time.
 pub struct TimedFilterAdapter<F> {
@@ -148,6 +294,42 @@ impl<A: Filter<V>, V> SimpleFilter<A, V> {
     }
 }
 
+/// Applies two filters in sequence.
+///
+/// Values are first passed through the first filter, and its output is passed through the second
+/// filter. Each filter keeps its own state. [`Chain`] implements [`Filter`] and [`TimeBasedFilter`]
+/// if both filters do. Longer chains can be built by nesting [`Chain`]s.
+#[derive(Debug, Clone, Copy)]
+pub struct Chain<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A, B> Chain<A, B> {
+    /// Creates a filter that applies `first`, followed by `second`.
+    pub fn new(first: A, second: B) -> Self {
+        Self { first, second }
+    }
+}
+
+impl<A: FilterBase<V>, B: FilterBase<V>, V> FilterBase<V> for Chain<A, B> {
+    type State = (A::State, B::State);
+}
+
+impl<A: Filter<V>, B: Filter<V>, V> Filter<V> for Chain<A, B> {
+    fn filter(&self, state: &mut Self::State, value: V) -> V {
+        let value = self.first.filter(&mut state.0, value);
+        self.second.filter(&mut state.1, value)
+    }
+}
+
+impl<A: TimeBasedFilter<V>, B: TimeBasedFilter<V>, V> TimeBasedFilter<V> for Chain<A, B> {
+    fn filter(&self, state: &mut Self::State, value: V, elapsed: f32) -> V {
+        let value = self.first.filter(&mut state.0, value, elapsed);
+        self.second.filter(&mut state.1, value, elapsed)
+    }
+}
+
 /// A filter that passes filtered values through as-is.
 #[derive(Default, Clone, Copy)]
 pub struct NoopFilter {
@@ -181,3 +363,20 @@ impl<V> TimeBasedFilter<V> for NoopFilter {
         value
     }
 }
+
+/// Implements [`TimeBasedFilter`] for windowed filters, which ignore the time delta.
+macro_rules! impl_time_based_windowed {
+    ($($filter:ty),+ $(,)?) => {$(
+        impl TimeBasedFilter<f32> for $filter {
+            fn filter(&self, state: &mut Self::State, value: f32, _elapsed: f32) -> f32 {
+                Filter::filter(self, state, value)
+            }
+        }
+    )+};
+}
+
+impl_time_based_windowed!(
+    hampel::HampelFilter,
+    median::MedianFilter,
+    savitzky_golay::SavitzkyGolayFilter,
+);
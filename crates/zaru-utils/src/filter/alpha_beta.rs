@@ -2,7 +2,7 @@
 //!
 //! [Alpha beta filter]: https://en.wikipedia.org/wiki/Alpha_beta_filter
 
-use super::{FilterBase, TimeBasedFilter};
+use super::{FilterBase, PredictiveFilter, TimeBasedFilter};
 
 /// An [alpha beta filter] that predicts a variable using its previous value and estimated rate of
 /// change.
@@ -61,6 +61,12 @@ impl TimeBasedFilter<f32> for AlphaBetaFilter {
     }
 }
 
+impl PredictiveFilter<f32> for AlphaBetaFilter {
+    fn predict(&self, state: &Self::State, elapsed: f32) -> Option<f32> {
+        state.x.map(|x| x + state.v * elapsed)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -79,4 +85,21 @@ mod tests {
         assert_eq!(filter.filter(state, -10.0, 0.2), -6.0);
         assert_eq!(filter.filter(state, -10.0, 0.2), -9.4);
     }
+
+    #[test]
+    fn test_alpha_beta_predict() {
+        let filter = AlphaBetaFilter::new(0.5, 0.5);
+        let state = &mut Default::default();
+        assert_eq!(filter.predict(state, 1.0), None);
+
+        filter.filter(state, 0.0, 0.0);
+        assert_eq!(filter.predict(state, 1.0), Some(0.0));
+
+        // A variable moving at a constant rate is extrapolated along its velocity.
+        for i in 1..100 {
+            filter.filter(state, i as f32, 1.0);
+        }
+        let prediction = filter.predict(state, 2.0).unwrap();
+        assert!((prediction - 101.0).abs() < 0.01, "{prediction}");
+    }
 }
@@ -0,0 +1,121 @@
+//! Wrap-aware filtering of angles.
+
+use std::f32::consts::{PI, TAU};
+
+use super::{Filter, FilterBase, PredictiveFilter, TimeBasedFilter};
+
+/// Adapts a scalar filter to filter angles in radians.
+///
+/// Angles jump by 2π when they wrap around (eg. from just below π to just above -π). Filtering them
+/// directly would make the filter output sweep through all angles in between. This adapter instead
+/// *unwraps* the angles before passing them to the inner filter, by choosing the representation of
+/// each angle that is closest to the previous one. The filtered angle is wrapped back into the range
+/// [-π, π).
+///
+/// This works with any [`Filter`], [`TimeBasedFilter`] or [`PredictiveFilter`] operating on `f32`.
+#[derive(Debug, Clone, Copy)]
+pub struct Angular<F> {
+    inner: F,
+}
+
+impl<F> Angular<F> {
+    /// Wraps `filter` so that it can filter angles.
+    pub fn new(filter: F) -> Self {
+        Self { inner: filter }
+    }
+}
+
+/// State of an [`Angular`] filter.
+#[derive(Debug, Default)]
+pub struct AngularState<S> {
+    inner: S,
+    /// The last unwrapped angle passed to the inner filter.
+    last: Option<f32>,
+}
+
+impl<S> AngularState<S> {
+    /// Returns the representation of `angle` that is closest to the last angle.
+    fn unwrap(&mut self, angle: f32) -> f32 {
+        let unwrapped = match self.last {
+            Some(last) => last + wrap(angle - last),
+            None => angle,
+        };
+        self.last = Some(unwrapped);
+        unwrapped
+    }
+}
+
+/// Wraps an angle in radians into the range [-π, π).
+pub fn wrap(angle: f32) -> f32 {
+    angle - TAU * ((angle + PI) / TAU).floor()
+}
+
+impl<F: FilterBase<f32>> FilterBase<f32> for Angular<F> {
+    type State = AngularState<F::State>;
+}
+
+impl<F: Filter<f32>> Filter<f32> for Angular<F> {
+    fn filter(&self, state: &mut Self::State, value: f32) -> f32 {
+        let value = state.unwrap(value);
+        wrap(self.inner.filter(&mut state.inner, value))
+    }
+}
+
+impl<F: TimeBasedFilter<f32>> TimeBasedFilter<f32> for Angular<F> {
+    fn filter(&self, state: &mut Self::State, value: f32, elapsed: f32) -> f32 {
+        let value = state.unwrap(value);
+        wrap(self.inner.filter(&mut state.inner, value, elapsed))
+    }
+}
+
+impl<F: PredictiveFilter<f32>> PredictiveFilter<f32> for Angular<F> {
+    fn predict(&self, state: &Self::State, elapsed: f32) -> Option<f32> {
+        self.inner.predict(&state.inner, elapsed).map(wrap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use crate::filter::{alpha_beta::AlphaBetaFilter, ema::Ema};
+
+    use super::*;
+
+    #[test]
+    fn test_wrap() {
+        assert_relative_eq!(wrap(0.5), 0.5);
+        assert_relative_eq!(wrap(PI + 0.5), -PI + 0.5);
+        assert_relative_eq!(wrap(-PI - 0.5), PI - 0.5);
+        assert_relative_eq!(wrap(5.0 * TAU + 1.0), 1.0, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn test_angular_ema() {
+        let filter = Angular::new(Ema::new(0.5));
+        let state = &mut Default::default();
+
+        assert_relative_eq!(filter.filter(state, PI - 0.1), PI - 0.1);
+        // The average of the two angles lies at ±π, not at 0.
+        assert_relative_eq!(
+            wrap(filter.filter(state, -PI + 0.1) - PI),
+            0.0,
+            epsilon = 1e-5
+        );
+    }
+
+    #[test]
+    fn test_angular_predict() {
+        let filter = Angular::new(AlphaBetaFilter::new(1.0, 1.0));
+        let state = &mut Default::default();
+
+        // A constant rotation across the wrap-around point is extrapolated correctly.
+        for i in 0..10 {
+            let angle = wrap(2.0 + i as f32 * 0.5);
+            let elapsed = if i == 0 { 0.0 } else { 1.0 };
+            assert_relative_eq!(filter.filter(state, angle, elapsed), angle, epsilon = 1e-4);
+        }
+        let prediction = filter.predict(state, 1.0).unwrap();
+        assert_relative_eq!(prediction, wrap(2.0 + 10.0 * 0.5), epsilon = 1e-4);
+    }
+}
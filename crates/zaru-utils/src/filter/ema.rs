@@ -1,8 +1,12 @@
 //! Exponential Moving Average.
 
-use super::{Filter, FilterBase};
+use super::{Filter, FilterBase, Interpolate};
 
 /// An Exponential Moving Average (EMA) filter.
+///
+/// This filter can be applied to any [`Interpolate`] type. Rotations are averaged via spherical
+/// linear interpolation, so filtering a [`UnitQuaternion`][nalgebra::UnitQuaternion] always yields
+/// a valid rotation.
 #[derive(Debug, Clone, Copy)]
 pub struct Ema {
     alpha: f32,
@@ -25,25 +29,30 @@ impl Ema {
 }
 
 /// Filter state for [`Ema`] filters.
-#[derive(Debug, Default)]
-pub struct EmaState {
-    last: Option<f32>,
+#[derive(Debug)]
+pub struct EmaState<V = f32> {
+    last: Option<V>,
 }
 
-impl FilterBase<f32> for Ema {
-    type State = EmaState;
+impl<V> Default for EmaState<V> {
+    fn default() -> Self {
+        Self { last: None }
+    }
+}
+
+impl<V: Interpolate> FilterBase<V> for Ema {
+    type State = EmaState<V>;
 }
 
-impl Filter<f32> for Ema {
-    fn filter(&self, state: &mut Self::State, value: f32) -> f32 {
-        match state.last {
+impl<V: Interpolate> Filter<V> for Ema {
+    fn filter(&self, state: &mut Self::State, value: V) -> V {
+        match &mut state.last {
             Some(last) => {
-                let avg = self.alpha * value + (1.0 - self.alpha) * last;
-                state.last = Some(avg);
-                avg
+                *last = last.interpolate(&value, self.alpha);
+                last.clone()
             }
             None => {
-                state.last = Some(value);
+                state.last = Some(value.clone());
                 value
             }
         }
@@ -52,6 +61,11 @@ impl Filter<f32> for Ema {
 
 #[cfg(test)]
 mod tests {
+    use std::f32::consts::PI;
+
+    use approx::assert_relative_eq;
+    use nalgebra::UnitQuaternion;
+
     use crate::filter::SimpleFilter;
 
     use super::*;
@@ -63,4 +77,26 @@ mod tests {
         assert_eq!(filter.filter(2.0), 1.5);
         assert_eq!(filter.filter(2.0), 1.75);
     }
+
+    #[test]
+    fn test_ema_quaternion() {
+        let filter = Ema::new(0.5);
+        let state = &mut Default::default();
+        let a = UnitQuaternion::from_euler_angles(0.0, 0.0, 3.0);
+        let b = UnitQuaternion::from_euler_angles(0.0, 0.0, -3.0);
+
+        assert_eq!(filter.filter(state, a), a);
+        // Interpolation takes the short way around through ±π, instead of passing through 0.
+        let avg = filter.filter(state, b);
+        assert_relative_eq!(avg.angle(), PI, epsilon = 1e-5);
+        assert_relative_eq!(avg.into_inner().norm(), 1.0, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_ema_vector() {
+        let filter = Ema::new(0.25);
+        let state = &mut Default::default();
+        assert_eq!(filter.filter(state, [4.0, 0.0]), [4.0, 0.0]);
+        assert_eq!(filter.filter(state, [0.0, 8.0]), [3.0, 2.0]);
+    }
 }
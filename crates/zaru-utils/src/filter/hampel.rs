@@ -0,0 +1,112 @@
+//! Outlier rejection via the [Hampel filter].
+//!
+//! [Hampel filter]: https://en.wikipedia.org/wiki/Median_absolute_deviation
+
+use super::{
+    median::{median, WindowState},
+    Filter, FilterBase,
+};
+
+/// Scale factor that makes the median absolute deviation of normally distributed data an estimate
+/// of its standard deviation.
+const MAD_SCALE: f32 = 1.4826;
+
+/// A Hampel filter, which replaces outliers with the median of the last few values.
+///
+/// A value is considered an outlier if it deviates from the median of the window by more than
+/// `threshold` times the (scaled) median absolute deviation of the window. All other values are
+/// passed through unchanged, so unlike [`MedianFilter`][super::median::MedianFilter], this filter
+/// does not add any lag or smoothing to well-behaved data.
+///
+/// This makes it a good first stage before a smoothing filter (see [`Chain`][super::Chain]), which
+/// would otherwise smear spikes over several values.
+#[derive(Debug, Clone, Copy)]
+pub struct HampelFilter {
+    window: usize,
+    threshold: f32,
+}
+
+impl HampelFilter {
+    /// Creates a Hampel filter that considers the last `window` values (including the filtered
+    /// value itself).
+    ///
+    /// `threshold` is the number of standard deviations (as estimated from the median absolute
+    /// deviation) a value has to deviate from the median to be rejected. A common value is 3.0.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `window` is 0 or `threshold` is negative.
+    pub fn new(window: usize, threshold: f32) -> Self {
+        assert!(window > 0, "window size must not be 0");
+        assert!(threshold >= 0.0);
+        Self { window, threshold }
+    }
+}
+
+impl FilterBase<f32> for HampelFilter {
+    type State = WindowState;
+}
+
+impl Filter<f32> for HampelFilter {
+    fn filter(&self, state: &mut Self::State, value: f32) -> f32 {
+        // The raw value is kept in the window, so that a sustained change is accepted once it
+        // makes up half the window.
+        state.push(value, self.window);
+        let mut values = state.values().collect::<Vec<_>>();
+        let center = median(&mut values);
+        for value in &mut values {
+            *value = (*value - center).abs();
+        }
+        let mad = median(&mut values);
+
+        if (value - center).abs() > self.threshold * MAD_SCALE * mad {
+            center
+        } else {
+            value
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::filter::{ema::Ema, Chain};
+
+    use super::*;
+
+    #[test]
+    fn test_hampel_rejects_spike() {
+        let filter = HampelFilter::new(5, 3.0);
+        let state = &mut Default::default();
+
+        let inputs = [1.0, 1.2, 0.9, 1.1, 8.0, 1.0, 0.8];
+        let outputs = inputs.map(|value| Filter::filter(&filter, state, value));
+        assert_eq!(outputs, [1.0, 1.2, 0.9, 1.1, 1.1, 1.0, 0.8]);
+    }
+
+    #[test]
+    fn test_hampel_accepts_step() {
+        let filter = HampelFilter::new(5, 3.0);
+        let state = &mut Default::default();
+
+        let inputs = [1.0, 1.1, 0.9, 1.0, 5.0, 5.1, 4.9, 5.0];
+        let outputs = inputs.map(|value| Filter::filter(&filter, state, value));
+        // The step is rejected until it makes up the majority of the window.
+        assert_eq!(outputs, [1.0, 1.1, 0.9, 1.0, 1.0, 1.1, 4.9, 5.0]);
+    }
+
+    #[test]
+    fn test_chain_with_ema() {
+        let ema = Ema::new(0.5);
+        let chain = Chain::new(HampelFilter::new(5, 3.0), ema);
+        let (chain_state, ema_state) = (&mut Default::default(), &mut Default::default());
+
+        for value in [2.0, 2.0, 2.0, 2.0, 9.0, 2.0] {
+            let smeared = ema.filter(ema_state, value);
+            let filtered = Filter::filter(&chain, chain_state, value);
+            assert_eq!(filtered, 2.0);
+            if value != 2.0 {
+                assert_ne!(smeared, 2.0);
+            }
+        }
+    }
+}
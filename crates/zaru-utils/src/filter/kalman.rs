@@ -0,0 +1,446 @@
+//! Linear [Kalman filter] implementation.
+//!
+//! This module provides a scalar [`KalmanFilter`] and a [`VectorKalmanFilter`] that filters
+//! N-dimensional points with a single, coupled state. Both support the kinematic motion models in
+//! [`KalmanModel`].
+//!
+//! [Kalman filter]: https://en.wikipedia.org/wiki/Kalman_filter
+
+use nalgebra::{Matrix3, SMatrix, SVector};
+
+use super::{FilterBase, PredictiveFilter, TimeBasedFilter};
+
+/// Initial variance of the derivatives (velocity, acceleration) estimated by a Kalman filter.
+///
+/// Nothing is known about the derivatives before the first measurements arrive, so this is chosen
+/// large enough to not meaningfully bias the estimate.
+const INITIAL_DERIVATIVE_VARIANCE: f32 = 1e6;
+
+/// The highest [`KalmanModel::order`] of any model.
+const MAX_ORDER: usize = 3;
+
+/// The kinematic model used by a Kalman filter to predict how the filtered variable evolves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KalmanModel {
+    /// The variable is assumed to stay constant, with changes modeled as white noise of the
+    /// variable's velocity (a random walk).
+    ConstantPosition,
+    /// The variable is assumed to change at a constant rate, with changes of that rate modeled as
+    /// white noise of the variable's acceleration.
+    ConstantVelocity,
+    /// The variable's rate of change is assumed to change at a constant rate, with changes modeled
+    /// as white noise of the variable's jerk.
+    ConstantAcceleration,
+}
+
+impl KalmanModel {
+    /// Returns the number of state variables per filtered dimension (the variable itself, plus the
+    /// modeled derivatives).
+    fn order(self) -> usize {
+        match self {
+            KalmanModel::ConstantPosition => 1,
+            KalmanModel::ConstantVelocity => 2,
+            KalmanModel::ConstantAcceleration => 3,
+        }
+    }
+
+    /// Returns the state transition matrix for a single dimension and a time step of `dt` seconds.
+    ///
+    /// Rows and columns beyond [`KalmanModel::order`] are zero.
+    fn transition(self, dt: f32) -> Matrix3<f32> {
+        let n = self.order();
+        Matrix3::from_fn(|i, j| {
+            if j < i || j >= n {
+                0.0
+            } else {
+                dt.powi((j - i) as i32) / factorial(j - i)
+            }
+        })
+    }
+
+    /// Returns the discretized process noise matrix for a single dimension, assuming continuous
+    /// white noise with unit spectral density on the highest modeled derivative.
+    ///
+    /// Rows and columns beyond [`KalmanModel::order`] are zero.
+    fn process_noise(self, dt: f32) -> Matrix3<f32> {
+        let n = self.order();
+        Matrix3::from_fn(|i, j| {
+            if i >= n || j >= n {
+                return 0.0;
+            }
+            let power = 2 * n - 1 - i - j;
+            dt.powi(power as i32) / (factorial(n - 1 - i) * factorial(n - 1 - j) * power as f32)
+        })
+    }
+}
+
+fn factorial(n: usize) -> f32 {
+    (1..=n).product::<usize>() as f32
+}
+
+/// A linear [Kalman filter] for scalar values.
+///
+/// The filter tracks the value and (depending on the [`KalmanModel`]) its rate of change, and
+/// weighs new measurements against its prediction according to the configured noise levels:
+///
+/// - The *process noise* is the spectral density of the white noise that drives the highest
+///   modeled derivative. Larger values make the filter follow changes more quickly.
+/// - The *measurement noise* is the variance of the measurement error. Larger values result in
+///   smoother output.
+///
+/// [Kalman filter]: https://en.wikipedia.org/wiki/Kalman_filter
+#[derive(Debug, Clone)]
+pub struct KalmanFilter {
+    inner: VectorKalmanFilter<1>,
+}
+
+impl KalmanFilter {
+    /// Creates a filter using the kinematic `model`, with the given process and measurement noise.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `process_noise` is negative, or if `measurement_noise` is not
+    /// positive.
+    pub fn new(model: KalmanModel, process_noise: f32, measurement_noise: f32) -> Self {
+        Self {
+            inner: VectorKalmanFilter::new(model, process_noise, measurement_noise),
+        }
+    }
+}
+
+impl FilterBase<f32> for KalmanFilter {
+    type State = KalmanState;
+}
+
+impl TimeBasedFilter<f32> for KalmanFilter {
+    fn filter(&self, state: &mut Self::State, value: f32, elapsed: f32) -> f32 {
+        let [x] = self.inner.filter(state, [value], elapsed);
+        x
+    }
+}
+
+impl PredictiveFilter<f32> for KalmanFilter {
+    fn predict(&self, state: &Self::State, elapsed: f32) -> Option<f32> {
+        self.inner.predict(state, elapsed).map(|[x]| x)
+    }
+}
+
+/// A linear Kalman filter for `N`-dimensional points.
+///
+/// Unlike filtering each coordinate with a separate [`KalmanFilter`], this filter estimates all
+/// coordinates with a single state and covariance, so that correlated noise (configured via
+/// [`VectorKalmanFilter::with_process_covariance`] and
+/// [`VectorKalmanFilter::with_measurement_covariance`]) is accounted for.
+///
+/// With the default isotropic noise, the results are identical to those of per-coordinate
+/// [`KalmanFilter`]s.
+///
+/// If the covariance of a measurement's innovation is singular (which can only happen with a
+/// singular measurement covariance matrix), the measurement is ignored, and the predicted state is
+/// kept.
+#[derive(Debug, Clone)]
+pub struct VectorKalmanFilter<const N: usize> {
+    model: KalmanModel,
+    process_noise: SMatrix<f32, N, N>,
+    measurement_noise: SMatrix<f32, N, N>,
+}
+
+impl<const N: usize> VectorKalmanFilter<N> {
+    /// Creates a filter with isotropic process and measurement noise.
+    ///
+    /// See [`KalmanFilter`] for the meaning of the noise parameters.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `process_noise` is negative, or if `measurement_noise` is not
+    /// positive.
+    pub fn new(model: KalmanModel, process_noise: f32, measurement_noise: f32) -> Self {
+        assert!(process_noise >= 0.0);
+        assert!(measurement_noise > 0.0);
+        Self {
+            model,
+            process_noise: SMatrix::identity() * process_noise,
+            measurement_noise: SMatrix::identity() * measurement_noise,
+        }
+    }
+
+    /// Sets the covariance matrix of the process noise, replacing the isotropic process noise
+    /// passed to [`VectorKalmanFilter::new`].
+    pub fn with_process_covariance(self, covariance: SMatrix<f32, N, N>) -> Self {
+        Self {
+            process_noise: covariance,
+            ..self
+        }
+    }
+
+    /// Sets the covariance matrix of the measurement noise, replacing the isotropic measurement
+    /// noise passed to [`VectorKalmanFilter::new`].
+    pub fn with_measurement_covariance(self, covariance: SMatrix<f32, N, N>) -> Self {
+        Self {
+            measurement_noise: covariance,
+            ..self
+        }
+    }
+
+    /// Propagates `estimate` forward by `elapsed` seconds.
+    fn predict_estimate(&self, estimate: &Estimate<N>, elapsed: f32) -> Estimate<N> {
+        let n = self.model.order();
+        let f = self.model.transition(elapsed);
+        let q = self.model.process_noise(elapsed);
+
+        let mut predicted = Estimate::zeros();
+        for i in 0..n {
+            for k in 0..n {
+                predicted.x[i] += estimate.x[k] * f[(i, k)];
+            }
+            for j in 0..n {
+                // `F P Fᵀ + Q`, computed block-wise.
+                predicted.p[i][j] = self.process_noise * q[(i, j)];
+                for k in 0..n {
+                    for l in 0..n {
+                        predicted.p[i][j] += estimate.p[k][l] * (f[(i, k)] * f[(j, l)]);
+                    }
+                }
+            }
+        }
+        predicted
+    }
+
+    /// Incorporates the measurement `z` into `estimate`.
+    ///
+    /// Returns `None` if the innovation covariance is singular.
+    fn update(&self, estimate: &Estimate<N>, z: SVector<f32, N>) -> Option<Estimate<N>> {
+        let n = self.model.order();
+        let (x, p, r) = (&estimate.x, &estimate.p, &self.measurement_noise);
+
+        // The measurement matrix `H` selects the value block of the state.
+        let innovation = z - x[0];
+        let s_inv = (p[0][0] + r).try_inverse()?;
+        let mut k = [SMatrix::<f32, N, N>::zeros(); MAX_ORDER];
+        for i in 0..n {
+            k[i] = p[i][0] * s_inv;
+        }
+
+        // Joseph form of the covariance update, for numerical stability:
+        // `(I - KH) P (I - KH)ᵀ + K R Kᵀ`, where `M = (I - KH) P`.
+        let mut m = [[SMatrix::<f32, N, N>::zeros(); MAX_ORDER]; MAX_ORDER];
+        for i in 0..n {
+            for j in 0..n {
+                m[i][j] = p[i][j] - k[i] * p[0][j];
+            }
+        }
+        let mut updated = Estimate::zeros();
+        for i in 0..n {
+            updated.x[i] = x[i] + k[i] * innovation;
+            for j in 0..n {
+                updated.p[i][j] =
+                    m[i][j] - m[i][0] * k[j].transpose() + k[i] * r * k[j].transpose();
+            }
+        }
+        Some(updated)
+    }
+}
+
+/// State of a [`KalmanFilter`] or [`VectorKalmanFilter`].
+#[derive(Debug, Default, Clone)]
+pub struct KalmanState<const N: usize = 1> {
+    /// Estimated state and its covariance. Initially `None`.
+    estimate: Option<Estimate<N>>,
+}
+
+/// A state estimate of a [`VectorKalmanFilter`].
+///
+/// Only the first [`KalmanModel::order`] entries of each array are used.
+#[derive(Debug, Clone)]
+struct Estimate<const N: usize> {
+    /// The estimated value, followed by its derivatives.
+    x: [SVector<f32, N>; MAX_ORDER],
+    /// The covariance of `x`, as `N×N` blocks.
+    p: [[SMatrix<f32, N, N>; MAX_ORDER]; MAX_ORDER],
+}
+
+impl<const N: usize> Estimate<N> {
+    fn zeros() -> Self {
+        Self {
+            x: [SVector::zeros(); MAX_ORDER],
+            p: [[SMatrix::zeros(); MAX_ORDER]; MAX_ORDER],
+        }
+    }
+}
+
+impl<const N: usize> FilterBase<[f32; N]> for VectorKalmanFilter<N> {
+    type State = KalmanState<N>;
+}
+
+impl<const N: usize> TimeBasedFilter<[f32; N]> for VectorKalmanFilter<N> {
+    fn filter(&self, state: &mut Self::State, value: [f32; N], elapsed: f32) -> [f32; N] {
+        let z = SVector::from(value);
+
+        let estimate = match &state.estimate {
+            None => {
+                // Initialize the estimate with the first measurement, and derivatives with 0.
+                let mut estimate = Estimate::zeros();
+                estimate.x[0] = z;
+                estimate.p[0][0] = self.measurement_noise;
+                for i in 1..self.model.order() {
+                    estimate.p[i][i] = SMatrix::identity() * INITIAL_DERIVATIVE_VARIANCE;
+                }
+                estimate
+            }
+            Some(estimate) => {
+                let predicted = self.predict_estimate(estimate, elapsed);
+                self.update(&predicted, z).unwrap_or(predicted)
+            }
+        };
+
+        let out = estimate.x[0].into();
+        state.estimate = Some(estimate);
+        out
+    }
+}
+
+impl<const N: usize> PredictiveFilter<[f32; N]> for VectorKalmanFilter<N> {
+    fn predict(&self, state: &Self::State, elapsed: f32) -> Option<[f32; N]> {
+        let estimate = state.estimate.as_ref()?;
+        let f = self.model.transition(elapsed);
+        let mut x = SVector::<f32, N>::zeros();
+        for k in 0..self.model.order() {
+            x += estimate.x[k] * f[(0, k)];
+        }
+        Some(x.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_constant_position_is_running_mean() {
+        // Without process noise, the estimate is the mean of all measurements, and its variance is
+        // the measurement variance divided by the number of measurements.
+        let filter = KalmanFilter::new(KalmanModel::ConstantPosition, 0.0, 4.0);
+        let state = &mut KalmanState::default();
+
+        let values = [3.0, 7.0, 2.0, 9.0, 4.0, 5.0];
+        for (i, &value) in values.iter().enumerate() {
+            let n = (i + 1) as f32;
+            let mean = values[..=i].iter().sum::<f32>() / n;
+            assert_relative_eq!(filter.filter(state, value, 0.1), mean, epsilon = 1e-5);
+            assert_relative_eq!(
+                state.estimate.as_ref().unwrap().p[0][0][(0, 0)],
+                4.0 / n,
+                epsilon = 1e-5
+            );
+        }
+    }
+
+    #[test]
+    fn test_constant_position_steady_state() {
+        // For a random walk, the steady-state prior variance solves `P² - qtP - qtr = 0`.
+        let (q, r, dt) = (2.0, 0.5, 0.1);
+        let filter = KalmanFilter::new(KalmanModel::ConstantPosition, q, r);
+        let state = &mut KalmanState::default();
+        for _ in 0..100 {
+            filter.filter(state, 1.0, dt);
+        }
+
+        let qt = q * dt;
+        let prior = (qt + (qt * qt + 4.0 * qt * r).sqrt()) / 2.0;
+        let gain = prior / (prior + r);
+        assert_relative_eq!(
+            state.estimate.as_ref().unwrap().p[0][0][(0, 0)],
+            (1.0 - gain) * prior,
+            epsilon = 1e-5
+        );
+
+        // A step of size 1 is thus followed by exactly `gain`.
+        assert_relative_eq!(filter.filter(state, 2.0, dt), 1.0 + gain, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn test_constant_velocity_tracks_line() {
+        let filter = KalmanFilter::new(KalmanModel::ConstantVelocity, 0.0, 1e-3);
+        let state = &mut KalmanState::default();
+        let line = |t: f32| 3.0 - 2.0 * t;
+
+        for i in 0..20 {
+            let t = i as f32 * 0.1;
+            filter.filter(state, line(t), if i == 0 { 0.0 } else { 0.1 });
+        }
+        let x = &state.estimate.as_ref().unwrap().x;
+        assert_relative_eq!(x[0][0], line(1.9), epsilon = 1e-3);
+        assert_relative_eq!(x[1][0], -2.0, epsilon = 1e-2);
+        assert_relative_eq!(
+            filter.predict(state, 0.5).unwrap(),
+            line(2.4),
+            epsilon = 1e-2
+        );
+    }
+
+    #[test]
+    fn test_constant_acceleration_tracks_parabola() {
+        let filter = KalmanFilter::new(KalmanModel::ConstantAcceleration, 0.0, 1e-3);
+        let state = &mut KalmanState::default();
+        let parabola = |t: f32| 1.0 + t + 2.0 * t * t;
+
+        for i in 0..30 {
+            let t = i as f32 * 0.1;
+            filter.filter(state, parabola(t), if i == 0 { 0.0 } else { 0.1 });
+        }
+        let x = &state.estimate.as_ref().unwrap().x;
+        assert_relative_eq!(x[2][0], 4.0, epsilon = 0.1);
+        assert_relative_eq!(
+            filter.predict(state, 0.2).unwrap(),
+            parabola(3.1),
+            epsilon = 0.05
+        );
+    }
+
+    #[test]
+    fn test_vector_matches_scalar() {
+        let vector = VectorKalmanFilter::<2>::new(KalmanModel::ConstantVelocity, 5.0, 0.2);
+        let scalar = KalmanFilter::new(KalmanModel::ConstantVelocity, 5.0, 0.2);
+        let vstate = &mut KalmanState::default();
+        let (xstate, ystate) = (&mut KalmanState::default(), &mut KalmanState::default());
+
+        for i in 0..20 {
+            let t = i as f32;
+            let value = [t.sin(), (t * 0.7).cos() * 3.0];
+            let elapsed = if i == 0 { 0.0 } else { 0.05 };
+            let [x, y] = vector.filter(vstate, value, elapsed);
+            assert_relative_eq!(x, scalar.filter(xstate, value[0], elapsed), epsilon = 1e-4);
+            assert_relative_eq!(y, scalar.filter(ystate, value[1], elapsed), epsilon = 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_vector_correlated_noise() {
+        // With correlated measurement noise, an innovation in `x` alone also moves `y`. In the
+        // eigenbasis of the covariances, the gains are `(λ + qt) / (2λ + qt)`.
+        let (c, q, dt) = (0.5, 10.0, 0.1);
+        let filter = VectorKalmanFilter::<2>::new(KalmanModel::ConstantPosition, q, 1.0)
+            .with_measurement_covariance(SMatrix::from([[1.0, c], [c, 1.0]]));
+        let state = &mut KalmanState::default();
+        filter.filter(state, [0.0, 0.0], 0.0);
+        let [x, y] = filter.filter(state, [1.0, 0.0], dt);
+
+        let gain = |lambda: f32| (lambda + q * dt) / (2.0 * lambda + q * dt);
+        let (k_sum, k_diff) = (gain(1.0 + c), gain(1.0 - c));
+        assert_relative_eq!(x, (k_sum + k_diff) / 2.0, epsilon = 1e-5);
+        assert_relative_eq!(y, (k_sum - k_diff) / 2.0, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn test_singular_innovation_covariance() {
+        // Without measurement and process noise, the innovation covariance becomes zero after the
+        // first measurement. Further measurements are ignored instead of causing a panic.
+        let filter = VectorKalmanFilter::<2>::new(KalmanModel::ConstantPosition, 0.0, 1.0)
+            .with_measurement_covariance(SMatrix::zeros());
+        let state = &mut KalmanState::default();
+        assert_eq!(filter.filter(state, [1.0, 2.0], 0.0), [1.0, 2.0]);
+        assert_eq!(filter.filter(state, [5.0, 5.0], 0.1), [1.0, 2.0]);
+    }
+}
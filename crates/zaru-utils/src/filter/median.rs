@@ -0,0 +1,103 @@
+//! Moving median filter.
+
+use std::collections::VecDeque;
+
+use super::{Filter, FilterBase};
+
+/// A filter that outputs the median of the last few values.
+///
+/// Unlike averaging filters, the moving median completely removes spikes that last for less than
+/// half the window, and preserves sharp edges in the data. It does however introduce a delay of
+/// about half the window size.
+#[derive(Debug, Clone, Copy)]
+pub struct MedianFilter {
+    window: usize,
+}
+
+impl MedianFilter {
+    /// Creates a moving median filter that considers the last `window` values.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `window` is 0.
+    pub fn new(window: usize) -> Self {
+        assert!(window > 0, "window size must not be 0");
+        Self { window }
+    }
+}
+
+/// Filter state of a [`MedianFilter`], [`HampelFilter`][super::hampel::HampelFilter] or
+/// [`SavitzkyGolayFilter`][super::savitzky_golay::SavitzkyGolayFilter].
+#[derive(Debug, Default)]
+pub struct WindowState {
+    values: VecDeque<f32>,
+}
+
+impl WindowState {
+    /// Adds `value` to the window, evicting the oldest values to keep at most `window` values.
+    pub(crate) fn push(&mut self, value: f32, window: usize) {
+        if self.values.len() == window {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+    }
+
+    /// Returns the values in the window, from oldest to newest.
+    pub(crate) fn values(&self) -> impl ExactSizeIterator<Item = f32> + '_ {
+        self.values.iter().copied()
+    }
+}
+
+impl FilterBase<f32> for MedianFilter {
+    type State = WindowState;
+}
+
+impl Filter<f32> for MedianFilter {
+    fn filter(&self, state: &mut Self::State, value: f32) -> f32 {
+        state.push(value, self.window);
+        median(&mut state.values().collect::<Vec<_>>())
+    }
+}
+
+/// Computes the median of `values`, reordering them in the process.
+///
+/// For an even number of values, the mean of the two middle values is returned.
+///
+/// # Panics
+///
+/// This function will panic if `values` is empty.
+pub(crate) fn median(values: &mut [f32]) -> f32 {
+    let len = values.len();
+    let mid = len / 2;
+    let (below, upper, _) = values.select_nth_unstable_by(mid, f32::total_cmp);
+    if len % 2 == 1 {
+        *upper
+    } else {
+        // `below` holds the `mid` smallest values, so the other middle value is its maximum.
+        let lower = below.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        (lower + *upper) / 2.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_median() {
+        assert_eq!(median(&mut [3.0]), 3.0);
+        assert_eq!(median(&mut [3.0, 1.0, 2.0]), 2.0);
+        assert_eq!(median(&mut [4.0, 1.0, 3.0, 2.0]), 2.5);
+    }
+
+    #[test]
+    fn test_median_filter() {
+        let filter = MedianFilter::new(3);
+        let state = &mut Default::default();
+
+        let outputs =
+            [1.0, 1.0, 9.0, 1.0, 2.0, 2.0, 2.0].map(|value| Filter::filter(&filter, state, value));
+        // The spike is removed entirely, while the step is preserved.
+        assert_eq!(outputs, [1.0, 1.0, 1.0, 1.0, 2.0, 2.0, 2.0]);
+    }
+}
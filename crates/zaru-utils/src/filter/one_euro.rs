@@ -4,7 +4,7 @@
 
 use std::f32::consts::PI;
 
-use super::{FilterBase, TimeBasedFilter};
+use super::{Differentiate, FilterBase, Interpolate, TimeBasedFilter};
 
 /// [1€ Filter] parameters.
 ///
@@ -48,38 +48,50 @@ impl OneEuroFilter {
 }
 
 /// Filter state for the [`OneEuroFilter`].
-#[derive(Debug, Default)]
-pub struct OneEuroFilterState {
-    prev: Option<Prev>,
+#[derive(Debug)]
+pub struct OneEuroFilterState<V: Differentiate = f32> {
+    prev: Option<Prev<V>>,
+}
+
+impl<V: Differentiate> Default for OneEuroFilterState<V> {
+    fn default() -> Self {
+        Self { prev: None }
+    }
 }
 
 #[derive(Debug)]
-struct Prev {
-    x: f32,
-    dx: f32,
+struct Prev<V: Differentiate> {
+    x: V,
+    dx: V::Rate,
 }
 
-impl FilterBase<f32> for OneEuroFilter {
-    type State = OneEuroFilterState;
+impl<V: Differentiate> FilterBase<V> for OneEuroFilter {
+    type State = OneEuroFilterState<V>;
 }
 
-impl TimeBasedFilter<f32> for OneEuroFilter {
-    fn filter(&self, state: &mut Self::State, x: f32, elapsed: f32) -> f32 {
+/// The 1€ Filter can be applied to any [`Differentiate`] type.
+///
+/// For vectors and rotations, the cutoff frequency is adapted to the magnitude of the (angular)
+/// velocity, and all components are smoothed with the same factor.
+impl<V: Differentiate> TimeBasedFilter<V> for OneEuroFilter {
+    fn filter(&self, state: &mut Self::State, x: V, elapsed: f32) -> V {
         match &mut state.prev {
             None => {
-                state.prev = Some(Prev { x, dx: 0.0 });
+                // A value does not change relative to itself, so this is a rate of 0.
+                let dx = x.rate(&x, 1.0);
+                state.prev = Some(Prev { x: x.clone(), dx });
                 x
             }
             Some(prev) => {
                 let a_d = smoothing_factor(elapsed, self.d_cutoff);
-                let dx = (x - prev.x) / elapsed;
-                let dx_hat = exponential_smoothing(a_d, dx, prev.dx);
+                let dx = x.rate(&prev.x, elapsed);
+                let dx_hat = prev.dx.interpolate(&dx, a_d);
 
-                let cutoff = self.min_cutoff + self.beta * dx_hat.abs();
+                let cutoff = self.min_cutoff + self.beta * V::rate_magnitude(&dx_hat);
                 let a = smoothing_factor(elapsed, cutoff);
-                let x_hat = exponential_smoothing(a, x, prev.x);
+                let x_hat = prev.x.interpolate(&x, a);
 
-                prev.x = x_hat;
+                prev.x = x_hat.clone();
                 prev.dx = dx_hat;
 
                 x_hat
@@ -93,6 +105,36 @@ fn smoothing_factor(t_e: f32, cutoff: f32) -> f32 {
     r / (r + 1.0)
 }
 
-fn exponential_smoothing(a: f32, x: f32, x_prev: f32) -> f32 {
-    a * x + (1.0 - a) * x_prev
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use nalgebra::{UnitQuaternion, Vector3};
+
+    use super::*;
+
+    #[test]
+    fn test_one_euro_quaternion() {
+        let filter = OneEuroFilter::new(1.0, 0.0);
+        let state = &mut Default::default();
+        let axis = Vector3::z_axis();
+
+        // Rotating around a single axis matches filtering the rotation angle as a scalar, even
+        // when the angle wraps around.
+        let scalar_state = &mut Default::default();
+        for i in 0..20 {
+            let angle = 2.5 + i as f32 * 0.1;
+            let elapsed = if i == 0 { 0.0 } else { 0.1 };
+            let expected = filter.filter(scalar_state, angle, elapsed);
+            let rotation = filter.filter(
+                state,
+                UnitQuaternion::from_axis_angle(&axis, angle),
+                elapsed,
+            );
+            assert_relative_eq!(
+                rotation,
+                UnitQuaternion::from_axis_angle(&axis, expected),
+                epsilon = 1e-4
+            );
+        }
+    }
 }
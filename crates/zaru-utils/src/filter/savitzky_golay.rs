@@ -0,0 +1,122 @@
+//! [Savitzky–Golay filter] implementation.
+//!
+//! [Savitzky–Golay filter]: https://en.wikipedia.org/wiki/Savitzky%E2%80%93Golay_filter
+
+use nalgebra::DMatrix;
+
+use super::{median::WindowState, Filter, FilterBase};
+
+/// A [Savitzky–Golay filter] that smoothes values by fitting a polynomial to the last few values.
+///
+/// The filter fits a polynomial of a configurable degree to the values in its window using least
+/// squares, and outputs the value of the polynomial at the newest value. Compared to a moving
+/// average, this preserves the shape of peaks and valleys much better and introduces less lag, at
+/// the cost of less noise reduction. Any data that is described by a polynomial of the configured
+/// degree passes through the filter unchanged.
+///
+/// Until the window is filled, the filter fits polynomials to the available values, lowering the
+/// degree if there are too few values for it.
+///
+/// [Savitzky–Golay filter]: https://en.wikipedia.org/wiki/Savitzky%E2%80%93Golay_filter
+#[derive(Debug, Clone)]
+pub struct SavitzkyGolayFilter {
+    /// Convolution coefficients for each possible number of values in the window.
+    coefficients: Vec<Vec<f32>>,
+}
+
+impl SavitzkyGolayFilter {
+    /// Creates a Savitzky–Golay filter that fits a polynomial of degree `degree` to the last
+    /// `window` values.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `degree` is not less than `window`.
+    pub fn new(window: usize, degree: usize) -> Self {
+        assert!(
+            degree < window,
+            "polynomial degree must be less than the window size"
+        );
+        Self {
+            coefficients: (1..=window)
+                .map(|len| coefficients(len, degree.min(len - 1)))
+                .collect(),
+        }
+    }
+
+    fn window(&self) -> usize {
+        self.coefficients.len()
+    }
+}
+
+/// Computes the coefficients that evaluate the least-squares polynomial fit of `len` evenly spaced
+/// values at the newest value.
+fn coefficients(len: usize, degree: usize) -> Vec<f32> {
+    // Sample times are relative to the newest value, so evaluating the polynomial there just
+    // yields its constant term.
+    let design = DMatrix::from_fn(len, degree + 1, |i, k| {
+        (i as f64 - (len - 1) as f64).powi(k as i32)
+    });
+    let normal = design.transpose() * &design;
+    let pseudo_inverse = normal
+        .try_inverse()
+        .expect("Savitzky–Golay normal matrix is singular")
+        * design.transpose();
+    pseudo_inverse.row(0).iter().map(|&c| c as f32).collect()
+}
+
+impl FilterBase<f32> for SavitzkyGolayFilter {
+    type State = WindowState;
+}
+
+impl Filter<f32> for SavitzkyGolayFilter {
+    fn filter(&self, state: &mut Self::State, value: f32) -> f32 {
+        state.push(value, self.window());
+        let values = state.values();
+        let coefficients = &self.coefficients[values.len() - 1];
+        values.zip(coefficients).map(|(x, c)| x * c).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_degree_zero_is_moving_average() {
+        let filter = SavitzkyGolayFilter::new(4, 0);
+        let state = &mut Default::default();
+
+        let values = [1.0, 5.0, 3.0, 7.0, 2.0, 8.0];
+        for (i, &value) in values.iter().enumerate() {
+            let window = &values[i.saturating_sub(3)..=i];
+            let mean = window.iter().sum::<f32>() / window.len() as f32;
+            assert_relative_eq!(Filter::filter(&filter, state, value), mean, epsilon = 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_polynomial_passes_unchanged() {
+        let filter = SavitzkyGolayFilter::new(7, 2);
+        let state = &mut Default::default();
+
+        for i in 0..20 {
+            let t = i as f32;
+            let value = 3.0 - 0.5 * t + 0.25 * t * t;
+            assert_relative_eq!(Filter::filter(&filter, state, value), value, epsilon = 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_known_coefficients() {
+        // Linear fit over 3 points, evaluated at the newest one.
+        let coefficients = coefficients(3, 1);
+        for (c, expected) in coefficients
+            .into_iter()
+            .zip([-1.0 / 6.0, 1.0 / 3.0, 5.0 / 6.0])
+        {
+            assert_relative_eq!(c, expected, epsilon = 1e-6);
+        }
+    }
+}
@@ -1,6 +1,9 @@
+pub mod assignment;
+pub mod camera;
 pub mod filter;
 pub mod iter;
 pub mod num;
+pub mod pnp;
 pub mod procrustes;
 pub mod slice;
 pub mod timer;
@@ -0,0 +1,584 @@
+//! Pose estimation from 3D-2D point correspondences ([Perspective-n-Point]).
+//!
+//! [Perspective-n-Point]: https://en.wikipedia.org/wiki/Perspective-n-Point
+
+use nalgebra::{
+    DMatrix, DVector, Matrix3, Rotation3, SMatrix, SVector, SymmetricEigen, UnitQuaternion,
+    Vector2, Vector3, Vector6,
+};
+
+use crate::{camera::CameraModel, iter::zip_exact};
+
+/// Solves the [Perspective-n-Point] problem for a fixed set of object points.
+///
+/// The solver computes the pose of an object with known geometry (the *object points*) from the
+/// pixel positions of those points in an image taken by a camera with known [`CameraModel`].
+///
+/// An initial pose is computed with the non-iterative [EPnP] algorithm, which is then refined by
+/// minimizing the reprojection error with the Levenberg–Marquardt algorithm.
+///
+/// [Perspective-n-Point]: https://en.wikipedia.org/wiki/Perspective-n-Point
+/// [EPnP]: https://doi.org/10.1007/s11263-008-0152-6
+#[derive(Clone)]
+pub struct PnpSolver {
+    object_points: Vec<Vector3<f64>>,
+    /// World-space control points used by EPnP.
+    control_points: [Vector3<f64>; 4],
+    /// Barycentric coordinates of each object point relative to the control points.
+    alphas: Vec<[f64; 4]>,
+    max_iterations: u32,
+    /// Undistorted normalized image coordinates of the points passed to `solve`.
+    buf: Vec<Vector2<f64>>,
+}
+
+impl PnpSolver {
+    /// Default number of Levenberg–Marquardt iterations used to refine the pose.
+    pub const DEFAULT_MAX_ITERATIONS: u32 = 20;
+
+    /// Creates a solver for an object made up of `object_points`.
+    ///
+    /// The pose computed by [`PnpSolver::solve`] will transform these points into camera space.
+    /// Distances in camera space will use the same unit as the object points.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `object_points` yields fewer than 4 points, or if all points lie on a plane.
+    pub fn new(object_points: impl Iterator<Item = (f32, f32, f32)>) -> Self {
+        let object_points = object_points
+            .map(|(x, y, z)| Vector3::new(x, y, z).cast::<f64>())
+            .collect::<Vec<_>>();
+        assert!(
+            object_points.len() >= 4,
+            "need at least 4 points for pose estimation"
+        );
+
+        // Control points are the centroid, plus one point along each principal axis.
+        let centroid = object_points.iter().sum::<Vector3<f64>>() / object_points.len() as f64;
+        let covariance = object_points
+            .iter()
+            .map(|p| (p - centroid) * (p - centroid).transpose())
+            .sum::<Matrix3<f64>>()
+            / object_points.len() as f64;
+        let eigen = SymmetricEigen::new(covariance);
+        let max_variance = eigen.eigenvalues.max();
+        assert!(
+            eigen.eigenvalues.min() > max_variance * 1e-6,
+            "object points must not be coplanar"
+        );
+
+        let mut control_points = [centroid; 4];
+        for (i, cp) in control_points[1..].iter_mut().enumerate() {
+            *cp += eigen.eigenvectors.column(i) * eigen.eigenvalues[i].sqrt();
+        }
+
+        let basis = Matrix3::from_columns(&[
+            control_points[1] - centroid,
+            control_points[2] - centroid,
+            control_points[3] - centroid,
+        ]);
+        let basis_inv = basis.try_inverse().unwrap();
+        let alphas = object_points
+            .iter()
+            .map(|p| {
+                let a = basis_inv * (p - centroid);
+                [1.0 - a.sum(), a.x, a.y, a.z]
+            })
+            .collect();
+
+        Self {
+            object_points,
+            control_points,
+            alphas,
+            max_iterations: Self::DEFAULT_MAX_ITERATIONS,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Sets the maximum number of Levenberg–Marquardt iterations used to refine the pose.
+    ///
+    /// A value of 0 disables the refinement, and returns the EPnP solution as-is.
+    ///
+    /// By default, [`Self::DEFAULT_MAX_ITERATIONS`] is used.
+    pub fn set_max_iterations(&mut self, iterations: u32) {
+        self.max_iterations = iterations;
+    }
+
+    /// Computes the pose of the object from the pixel coordinates of its points.
+    ///
+    /// `image_points` must yield the pixel coordinates of the object points passed to
+    /// [`PnpSolver::new`], in the same order.
+    ///
+    /// Returns [`None`] if no valid pose could be computed (for example, because the image points
+    /// are degenerate).
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `image_points` yields a different number of points than there are
+    /// object points.
+    pub fn solve(
+        &mut self,
+        camera: &CameraModel,
+        image_points: impl Iterator<Item = (f32, f32)>,
+    ) -> Option<PnpResult> {
+        self.buf.clear();
+        self.buf.extend(
+            image_points.map(|(x, y)| Vector2::from(camera.pixel_to_normalized([x, y])).cast()),
+        );
+        assert_eq!(
+            self.buf.len(),
+            self.object_points.len(),
+            "number of image points does not match number of object points"
+        );
+
+        let (mut rotation, mut translation) = self.epnp()?;
+        if self.max_iterations > 0 {
+            (rotation, translation) = self.refine(rotation, translation);
+        }
+
+        let squared_error = zip_exact(&self.object_points, &self.buf)
+            .map(|(p, &observed)| {
+                let p = rotation * p + translation;
+                let [x, y] = camera.normalized_to_pixel([(p.x / p.z) as f32, (p.y / p.z) as f32]);
+                let [ox, oy] = camera.normalized_to_pixel([observed.x as f32, observed.y as f32]);
+                (x - ox).powi(2) + (y - oy).powi(2)
+            })
+            .sum::<f32>();
+        let reprojection_error = (squared_error / self.object_points.len() as f32).sqrt();
+        if !reprojection_error.is_finite() {
+            return None;
+        }
+
+        Some(PnpResult {
+            rotation: rotation.cast(),
+            translation: translation.cast(),
+            reprojection_error,
+        })
+    }
+
+    /// Computes a pose using EPnP, trying all null space dimensionalities up to 3 and returning
+    /// the solution with the lowest reprojection error.
+    fn epnp(&self) -> Option<(UnitQuaternion<f64>, Vector3<f64>)> {
+        let mut mtm = SMatrix::<f64, 12, 12>::zeros();
+        for (a, uv) in zip_exact(&self.alphas, &self.buf) {
+            let mut rows = [SVector::<f64, 12>::zeros(); 2];
+            for (j, &alpha) in a.iter().enumerate() {
+                for (row, coord) in rows.iter_mut().zip([uv.x, uv.y]) {
+                    row[3 * j + 2] = -alpha * coord;
+                }
+                rows[0][3 * j] = alpha;
+                rows[1][3 * j + 1] = alpha;
+            }
+            for row in rows {
+                mtm += row * row.transpose();
+            }
+        }
+
+        // The eigenvectors with the smallest eigenvalues span the (approximate) null space.
+        let eigen = SymmetricEigen::new(mtm);
+        let mut order = (0..12).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| eigen.eigenvalues[a].total_cmp(&eigen.eigenvalues[b]));
+        let v: [SVector<f64, 12>; 4] =
+            std::array::from_fn(|i| eigen.eigenvectors.column(order[i]).into_owned());
+
+        let (l, rho) = self.distance_constraints(&v);
+        [betas_n1(&l, &rho), betas_n2(&l, &rho), betas_n3(&l, &rho)]
+            .into_iter()
+            .map(|betas| gauss_newton(&l, &rho, betas))
+            .filter(|betas| betas.iter().all(|b| b.is_finite()))
+            .map(|betas| self.pose_from_betas(&v, &betas))
+            .min_by(|a, b| self.residual(a).total_cmp(&self.residual(b)))
+    }
+
+    /// Computes the matrix `L` and vector `ρ` relating the null space coefficients (`β`) to the
+    /// squared distances between control points.
+    fn distance_constraints(
+        &self,
+        v: &[SVector<f64, 12>; 4],
+    ) -> (SMatrix<f64, 6, 10>, SVector<f64, 6>) {
+        let mut l = SMatrix::<f64, 6, 10>::zeros();
+        let mut rho = SVector::<f64, 6>::zeros();
+        for (row, (a, b)) in CONTROL_POINT_PAIRS.into_iter().enumerate() {
+            let dv: [Vector3<f64>; 4] =
+                std::array::from_fn(|k| v[k].fixed_rows::<3>(3 * a) - v[k].fixed_rows::<3>(3 * b));
+            let mut col = 0;
+            for i in 0..4 {
+                for j in 0..=i {
+                    let factor = if i == j { 1.0 } else { 2.0 };
+                    l[(row, col)] = factor * dv[j].dot(&dv[i]);
+                    col += 1;
+                }
+            }
+            rho[row] = (self.control_points[a] - self.control_points[b]).norm_squared();
+        }
+        (l, rho)
+    }
+
+    /// Computes the camera-space control points and the resulting pose for a set of `β`s.
+    fn pose_from_betas(
+        &self,
+        v: &[SVector<f64, 12>; 4],
+        betas: &[f64; 4],
+    ) -> (UnitQuaternion<f64>, Vector3<f64>) {
+        let ccs = zip_exact(v, betas)
+            .map(|(v, b)| v * *b)
+            .sum::<SVector<f64, 12>>();
+        let mut points = self
+            .alphas
+            .iter()
+            .map(|a| {
+                (0..4)
+                    .map(|j| ccs.fixed_rows::<3>(3 * j) * a[j])
+                    .sum::<Vector3<f64>>()
+            })
+            .collect::<Vec<_>>();
+        // The null space is only determined up to sign; the object has to be in front of the
+        // camera.
+        if points.iter().map(|p| p.z).sum::<f64>() < 0.0 {
+            for p in &mut points {
+                *p = -*p;
+            }
+        }
+        absolute_orientation(&self.object_points, &points)
+    }
+
+    /// Returns the sum of squared residuals (in normalized image coordinates) of a pose.
+    fn residual(&self, (rotation, translation): &(UnitQuaternion<f64>, Vector3<f64>)) -> f64 {
+        zip_exact(&self.object_points, &self.buf)
+            .map(|(p, uv)| {
+                let p = rotation * p + translation;
+                (p.xy() / p.z - uv).norm_squared()
+            })
+            .sum()
+    }
+
+    /// Refines a pose with the Levenberg–Marquardt algorithm.
+    ///
+    /// The pose is parameterized by a rotation update (as a scaled axis, applied on top of the
+    /// current rotation) and the translation.
+    fn refine(
+        &self,
+        mut rotation: UnitQuaternion<f64>,
+        mut translation: Vector3<f64>,
+    ) -> (UnitQuaternion<f64>, Vector3<f64>) {
+        const EPS: f64 = 1e-7;
+
+        let apply =
+            |rotation: &UnitQuaternion<f64>, translation: &Vector3<f64>, delta: &Vector6<f64>| {
+                (
+                    UnitQuaternion::new(delta.fixed_rows::<3>(0).into_owned()) * rotation,
+                    translation + delta.fixed_rows::<3>(3),
+                )
+            };
+
+        let mut lambda = 1e-3;
+        let mut error = self.residual(&(rotation, translation));
+        for _ in 0..self.max_iterations {
+            let mut jtj = SMatrix::<f64, 6, 6>::zeros();
+            let mut jtr = Vector6::<f64>::zeros();
+            for (p, uv) in zip_exact(&self.object_points, &self.buf) {
+                let project = |r: &UnitQuaternion<f64>, t: &Vector3<f64>| {
+                    let p = r * p + t;
+                    p.xy() / p.z
+                };
+                let residual = project(&rotation, &translation) - uv;
+                let mut jacobian = SMatrix::<f64, 2, 6>::zeros();
+                for k in 0..6 {
+                    let mut delta = Vector6::zeros();
+                    delta[k] = EPS;
+                    let (r, t) = apply(&rotation, &translation, &delta);
+                    jacobian.set_column(k, &((project(&r, &t) - uv - residual) / EPS));
+                }
+                jtj += jacobian.transpose() * jacobian;
+                jtr += jacobian.transpose() * residual;
+            }
+
+            // Try increasingly damped steps until one reduces the error.
+            let mut improved = false;
+            while lambda < 1e10 {
+                let mut damped = jtj;
+                for i in 0..6 {
+                    damped[(i, i)] *= 1.0 + lambda;
+                }
+                let Some(step) = damped.cholesky().map(|c| c.solve(&-jtr)) else {
+                    lambda *= 10.0;
+                    continue;
+                };
+                let candidate = apply(&rotation, &translation, &step);
+                let candidate_error = self.residual(&candidate);
+                if candidate_error < error {
+                    (rotation, translation) = candidate;
+                    improved = error - candidate_error > error * 1e-12;
+                    error = candidate_error;
+                    lambda = (lambda / 10.0).max(1e-12);
+                    break;
+                }
+                lambda *= 10.0;
+            }
+            if !improved {
+                break;
+            }
+        }
+
+        (rotation, translation)
+    }
+}
+
+/// Pairs of control points whose distance constrains the EPnP solution.
+const CONTROL_POINT_PAIRS: [(usize, usize); 6] = [(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)];
+
+/// Index of the `βi * βj` term in the columns of `L`.
+const fn beta_term(i: usize, j: usize) -> usize {
+    let (i, j) = if i > j { (i, j) } else { (j, i) };
+    i * (i + 1) / 2 + j
+}
+
+/// Solves `L * b = ρ` for a subset of the columns of `L` in the least-squares sense.
+fn solve_terms<const N: usize>(
+    l: &SMatrix<f64, 6, 10>,
+    rho: &SVector<f64, 6>,
+    terms: [(usize, usize); N],
+) -> [f64; N] {
+    let sub = DMatrix::from_fn(6, N, |row, col| {
+        let (i, j) = terms[col];
+        l[(row, beta_term(i, j))]
+    });
+    let rho = DVector::from_column_slice(rho.as_slice());
+    let b = sub
+        .svd(true, true)
+        .solve(&rho, 1e-12)
+        .unwrap_or_else(|_| DVector::zeros(N));
+    std::array::from_fn(|i| b[i])
+}
+
+/// Initial `β`s assuming a 1-dimensional null space.
+///
+/// Like the reference implementation, this linearizes the problem for all 4 `β`s, using only the
+/// terms that involve `β1`.
+fn betas_n1(l: &SMatrix<f64, 6, 10>, rho: &SVector<f64, 6>) -> [f64; 4] {
+    let [b11, b12, b13, b14] = solve_terms(l, rho, [(0, 0), (0, 1), (0, 2), (0, 3)]);
+    let b1 = b11.abs().sqrt();
+    let sign = if b11 < 0.0 { -1.0 } else { 1.0 };
+    [b1, sign * b12 / b1, sign * b13 / b1, sign * b14 / b1]
+}
+
+/// Initial `β`s assuming a 2-dimensional null space.
+fn betas_n2(l: &SMatrix<f64, 6, 10>, rho: &SVector<f64, 6>) -> [f64; 4] {
+    let [b11, b12, b22] = solve_terms(l, rho, [(0, 0), (0, 1), (1, 1)]);
+    let (b1, b2) = betas_from_squares(b11, b12, b22);
+    [b1, b2, 0.0, 0.0]
+}
+
+/// Initial `β`s assuming a 3-dimensional null space.
+fn betas_n3(l: &SMatrix<f64, 6, 10>, rho: &SVector<f64, 6>) -> [f64; 4] {
+    let [b11, b12, b22, b13, _b23] = solve_terms(l, rho, [(0, 0), (0, 1), (1, 1), (0, 2), (1, 2)]);
+    let (b1, b2) = betas_from_squares(b11, b12, b22);
+    [b1, b2, if b1 == 0.0 { 0.0 } else { b13 / b1 }, 0.0]
+}
+
+/// Recovers `β1` and `β2` from estimates of `β1²`, `β1β2` and `β2²`.
+fn betas_from_squares(b11: f64, b12: f64, b22: f64) -> (f64, f64) {
+    let (mut b1, b2) = if b11 < 0.0 {
+        ((-b11).sqrt(), if b22 < 0.0 { (-b22).sqrt() } else { 0.0 })
+    } else {
+        (b11.sqrt(), if b22 > 0.0 { b22.sqrt() } else { 0.0 })
+    };
+    if b12 < 0.0 {
+        b1 = -b1;
+    }
+    (b1, b2)
+}
+
+/// Refines the `β`s to satisfy the control point distance constraints with a few Gauss-Newton
+/// iterations.
+fn gauss_newton(l: &SMatrix<f64, 6, 10>, rho: &SVector<f64, 6>, mut betas: [f64; 4]) -> [f64; 4] {
+    const ITERATIONS: usize = 5;
+
+    for _ in 0..ITERATIONS {
+        let mut jacobian = SMatrix::<f64, 6, 4>::zeros();
+        let mut residual = SVector::<f64, 6>::zeros();
+        for row in 0..6 {
+            let mut value = 0.0;
+            for i in 0..4 {
+                for j in 0..=i {
+                    let coeff = l[(row, beta_term(i, j))];
+                    value += coeff * betas[i] * betas[j];
+                    jacobian[(row, i)] += coeff * betas[j];
+                    jacobian[(row, j)] += coeff * betas[i];
+                }
+            }
+            residual[row] = rho[row] - value;
+        }
+
+        let Ok(step) = jacobian.svd(true, true).solve(&residual, 1e-12) else {
+            break;
+        };
+        for (beta, step) in betas.iter_mut().zip(step.iter()) {
+            *beta += step;
+        }
+    }
+    betas
+}
+
+/// Computes the rigid transform that maps `from` onto `to` in the least-squares sense (Kabsch
+/// algorithm).
+fn absolute_orientation(
+    from: &[Vector3<f64>],
+    to: &[Vector3<f64>],
+) -> (UnitQuaternion<f64>, Vector3<f64>) {
+    let n = from.len() as f64;
+    let from_centroid = from.iter().sum::<Vector3<f64>>() / n;
+    let to_centroid = to.iter().sum::<Vector3<f64>>() / n;
+    let h = zip_exact(from, to)
+        .map(|(f, t)| (t - to_centroid) * (f - from_centroid).transpose())
+        .sum::<Matrix3<f64>>();
+
+    let svd = h.svd(true, true);
+    let (u, v_t) = (svd.u.unwrap(), svd.v_t.unwrap());
+    let mut correction = Matrix3::identity();
+    if (u * v_t).determinant() < 0.0 {
+        correction[(2, 2)] = -1.0;
+    }
+    let rotation = u * correction * v_t;
+    let rotation =
+        UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(rotation));
+    (rotation, to_centroid - rotation * from_centroid)
+}
+
+/// An object pose computed by [`PnpSolver::solve`].
+///
+/// The pose transforms points from object space into camera space (see [`CameraModel`]), by first
+/// applying [`PnpResult::rotation`] and then [`PnpResult::translation`].
+#[derive(Debug, Clone, Copy)]
+pub struct PnpResult {
+    rotation: UnitQuaternion<f32>,
+    translation: Vector3<f32>,
+    reprojection_error: f32,
+}
+
+impl PnpResult {
+    /// Returns the rotation from object space to camera space.
+    pub fn rotation(&self) -> UnitQuaternion<f32> {
+        self.rotation
+    }
+
+    /// Returns the position of the object space origin in camera space.
+    ///
+    /// This uses the same unit as the object points passed to [`PnpSolver::new`].
+    pub fn translation(&self) -> Vector3<f32> {
+        self.translation
+    }
+
+    /// Returns the root mean square distance between the observed image points and the projected
+    /// object points, in pixels.
+    pub fn reprojection_error(&self) -> f32 {
+        self.reprojection_error
+    }
+
+    /// Transforms a point from object space to camera space.
+    pub fn transform_point(&self, point: Vector3<f32>) -> Vector3<f32> {
+        self.rotation * point + self.translation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use crate::camera::Distortion;
+
+    use super::*;
+
+    fn object_points() -> Vec<(f32, f32, f32)> {
+        let rng = fastrand::Rng::with_seed(0x5eed);
+        (0..50)
+            .map(|_| {
+                (
+                    rng.f32() * 100.0 - 50.0,
+                    rng.f32() * 120.0 - 60.0,
+                    rng.f32() * 60.0 - 30.0,
+                )
+            })
+            .collect()
+    }
+
+    fn check(camera: CameraModel, rotation: UnitQuaternion<f32>, translation: Vector3<f32>) {
+        let points = object_points();
+        let image_points = points
+            .iter()
+            .map(|&(x, y, z)| {
+                let [u, v] = camera.project(rotation * Vector3::new(x, y, z) + translation);
+                (u, v)
+            })
+            .collect::<Vec<_>>();
+
+        let mut solver = PnpSolver::new(points.into_iter());
+        let result = solver
+            .solve(&camera, image_points.into_iter())
+            .expect("no solution");
+        assert!(
+            result.reprojection_error() < 0.01,
+            "{}",
+            result.reprojection_error()
+        );
+        assert_relative_eq!(result.rotation(), rotation, epsilon = 1e-3);
+        assert_relative_eq!(
+            result.translation(),
+            translation,
+            epsilon = translation.norm() * 1e-3
+        );
+    }
+
+    #[test]
+    fn test_frontal() {
+        let camera = CameraModel::new([900.0, 900.0], [640.0, 360.0]);
+        check(
+            camera,
+            UnitQuaternion::identity(),
+            Vector3::new(0.0, 0.0, 600.0),
+        );
+    }
+
+    #[test]
+    fn test_rotated_and_distorted() {
+        let camera = CameraModel::new([700.0, 720.0], [320.0, 250.0])
+            .with_distortion(Distortion::new([-0.1, 0.02, 0.0], [0.001, 0.0005]));
+        check(
+            camera,
+            UnitQuaternion::from_euler_angles(0.3, -0.5, 2.0),
+            Vector3::new(-80.0, 40.0, 700.0),
+        );
+    }
+
+    #[test]
+    fn test_far_away() {
+        // Almost orthographic projection, where EPnP's null space is larger.
+        let camera = CameraModel::new([3000.0, 3000.0], [960.0, 540.0]);
+        check(
+            camera,
+            UnitQuaternion::from_euler_angles(-0.2, 0.4, 0.1),
+            Vector3::new(150.0, -100.0, 5000.0),
+        );
+    }
+
+    #[test]
+    fn test_epnp_without_refinement() {
+        let camera = CameraModel::new([800.0, 800.0], [400.0, 300.0]);
+        let rotation = UnitQuaternion::from_euler_angles(0.1, 0.2, 0.3);
+        let translation = Vector3::new(10.0, 20.0, 500.0);
+
+        let points = object_points();
+        let image_points = points
+            .iter()
+            .map(|&(x, y, z)| {
+                let [u, v] = camera.project(rotation * Vector3::new(x, y, z) + translation);
+                (u, v)
+            })
+            .collect::<Vec<_>>();
+        let mut solver = PnpSolver::new(points.into_iter());
+        solver.set_max_iterations(0);
+        let result = solver.solve(&camera, image_points.into_iter()).unwrap();
+        // With exact correspondences, EPnP alone recovers the pose.
+        assert_relative_eq!(result.rotation(), rotation, epsilon = 1e-3);
+        assert_relative_eq!(result.translation(), translation, epsilon = 0.5);
+    }
+}
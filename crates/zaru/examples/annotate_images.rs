@@ -0,0 +1,121 @@
+//! Runs a detector over a folder of images and writes the detections as annotation files.
+//!
+//! Usage:
+//! ```text
+//! annotate_images <face|palm|pose> <image-dir> <coco|voc|yolo> <output>
+//! ```
+//!
+//! For COCO, `<output>` is the path of the JSON file to write. For VOC and YOLO, it is the
+//! directory the per-image annotation files are written to.
+
+use std::{fs, path::Path, process};
+
+use anyhow::Context;
+use zaru::{
+    body::detection::PoseDetector,
+    detection::{
+        annotation::{coco, voc, yolo, ImageAnnotation},
+        Detector,
+    },
+    face::detection::{self, ShortRangeNetwork},
+    hand::detection::{LiteNetwork, PalmDetector},
+    image::Image,
+};
+
+fn usage() -> ! {
+    eprintln!("usage: annotate_images <face|palm|pose> <image-dir> <coco|voc|yolo> <output>");
+    process::exit(1);
+}
+
+fn main() -> anyhow::Result<()> {
+    zaru::init_logger!();
+
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let [detector, image_dir, format, output] = &args[..] else {
+        usage();
+    };
+    if !matches!(&**format, "coco" | "voc" | "yolo") {
+        usage();
+    }
+
+    let images = match &**detector {
+        "face" => annotate(
+            detection::Detector::new(ShortRangeNetwork),
+            "face",
+            image_dir,
+        )?,
+        "palm" => annotate(PalmDetector::new(LiteNetwork), "hand", image_dir)?,
+        "pose" => annotate(PoseDetector::new(), "person", image_dir)?,
+        _ => usage(),
+    };
+    let objects = images
+        .iter()
+        .map(|image| image.objects.len())
+        .sum::<usize>();
+    println!("{} objects in {} images", objects, images.len());
+
+    // Write the annotations, then load them back to make sure they can be read by the importer.
+    let reloaded = match &**format {
+        "coco" => {
+            coco::save(output, &images)?;
+            coco::load(output)?
+        }
+        "voc" => {
+            voc::save_dir(output, &images)?;
+            voc::load_dir(output)?
+        }
+        "yolo" => {
+            yolo::save_dir(output, &images)?;
+            yolo::load_dir(output, image_dir)?
+        }
+        _ => unreachable!(),
+    };
+    println!(
+        "wrote annotations to '{}' ({} objects in {} images after reloading)",
+        output,
+        reloaded
+            .iter()
+            .map(|image| image.objects.len())
+            .sum::<usize>(),
+        reloaded.len(),
+    );
+
+    Ok(())
+}
+
+fn annotate<D: Detector>(
+    mut detector: D,
+    label: &str,
+    image_dir: &str,
+) -> anyhow::Result<Vec<ImageAnnotation>> {
+    let mut paths = fs::read_dir(image_dir)
+        .with_context(|| format!("failed to read directory '{}'", image_dir))?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    paths.sort();
+
+    let mut images = Vec::new();
+    for path in paths.iter().filter(|path| path.is_file()) {
+        let image = match Image::load(path) {
+            Ok(image) => image,
+            Err(e) => {
+                log::warn!("skipping '{}': {}", path.display(), e);
+                continue;
+            }
+        };
+        let detections = detector.detect(image.as_view());
+        println!("{}: {} detections", path.display(), detections.len());
+
+        let file_name = path
+            .strip_prefix(image_dir)
+            .unwrap_or_else(|_| Path::new(path.file_name().unwrap()));
+        images.push(ImageAnnotation::from_detections(
+            file_name.to_string_lossy(),
+            image.resolution(),
+            label,
+            detections,
+        ));
+    }
+
+    Ok(images)
+}
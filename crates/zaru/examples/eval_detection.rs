@@ -0,0 +1,109 @@
+//! Evaluates a detector against an annotated dataset.
+//!
+//! Usage:
+//! ```text
+//! eval_detection <face|face-full|palm|pose> <coco|wider> <annotations> <image-dir>
+//!     [--category <name>] [--iou <thresholds>] [--output <report.json>]
+//! ```
+//!
+//! `--iou` takes a comma-separated list of IoU thresholds (default: `0.5,0.75`). `--category`
+//! restricts COCO datasets to annotations of the named category.
+
+use std::{fs, process};
+
+use anyhow::{bail, Context};
+use zaru::{
+    body::detection::PoseDetector,
+    detection::{
+        eval::{Dataset, Evaluator},
+        Detector,
+    },
+    face::detection::{self, FullRangeNetwork, ShortRangeNetwork},
+    hand::detection::{LiteNetwork, PalmDetector},
+};
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: eval_detection <face|face-full|palm|pose> <coco|wider> <annotations> <image-dir> \
+        [--category <name>] [--iou <thresholds>] [--output <report.json>]"
+    );
+    process::exit(1);
+}
+
+fn main() -> anyhow::Result<()> {
+    zaru::init_logger!();
+
+    let mut positional = Vec::new();
+    let mut category = None;
+    let mut iou = None;
+    let mut output = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "--category" => category = Some(args.next().unwrap_or_else(|| usage())),
+            "--iou" => iou = Some(args.next().unwrap_or_else(|| usage())),
+            "--output" => output = Some(args.next().unwrap_or_else(|| usage())),
+            _ => positional.push(arg),
+        }
+    }
+    let [detector, format, annotations, image_dir] = &positional[..] else {
+        usage();
+    };
+
+    let dataset = match &**format {
+        "coco" => Dataset::load_coco(annotations, image_dir, category.as_deref())?,
+        "wider" => Dataset::load_wider_face(annotations, image_dir)?,
+        _ => usage(),
+    };
+    println!("loaded {} annotated images", dataset.len());
+
+    let mut evaluator = Evaluator::new();
+    if let Some(iou) = iou {
+        let thresholds = iou
+            .split(',')
+            .map(|s| s.trim().parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("invalid IoU thresholds '{}'", iou))?;
+        if thresholds.iter().any(|t| !(0.0..=1.0).contains(t)) {
+            bail!("IoU thresholds must be between 0 and 1");
+        }
+        evaluator.set_iou_thresholds(&thresholds);
+    }
+
+    match &**detector {
+        "face" => run(
+            detection::Detector::new(ShortRangeNetwork),
+            &mut evaluator,
+            &dataset,
+        )?,
+        "face-full" => run(
+            detection::Detector::new(FullRangeNetwork),
+            &mut evaluator,
+            &dataset,
+        )?,
+        "palm" => run(PalmDetector::new(LiteNetwork), &mut evaluator, &dataset)?,
+        "pose" => run(PoseDetector::new(), &mut evaluator, &dataset)?,
+        _ => usage(),
+    }
+
+    let report = evaluator.report();
+    println!("{}", report);
+
+    if let Some(path) = output {
+        let json = serde_json::to_string_pretty(&report)?;
+        fs::write(&path, json).with_context(|| format!("failed to write '{}'", path))?;
+        println!("wrote report to '{}'", path);
+    }
+
+    Ok(())
+}
+
+fn run<D: Detector>(
+    mut detector: D,
+    evaluator: &mut Evaluator,
+    dataset: &Dataset,
+) -> anyhow::Result<()> {
+    // Lower the threshold to get the whole precision/recall curve.
+    detector.set_threshold(0.1);
+    evaluator.run(&mut detector, dataset)
+}
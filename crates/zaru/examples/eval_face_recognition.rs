@@ -1,58 +1,21 @@
 //! Face recognition testbed and evaluation.
 //!
-//! To be added to the main library once I understand it better.
-//!
-//! With uncorrected images:
-//! ```text
-//! diff=26.585608 in /home/sludge/Downloads/lfw/Hugo_Chavez
-//! diff=24.186064 in /home/sludge/Downloads/lfw/George_W_Bush
-//! diff=24.090973 in /home/sludge/Downloads/lfw/Yoriko_Kawaguchi
-//! diff=23.725494 in /home/sludge/Downloads/lfw/Gerhard_Schroeder
-//! ```
+//! Expects a directory containing one subdirectory of face images per person, and prints the
+//! identities whose images are the least similar to each other.
 
 use std::{collections::HashMap, convert::identity, fs, time::Instant};
 
 use itertools::Itertools;
-use nalgebra::RealField;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use zaru::{
-    face::detection::Detector,
+    face::{alignment::Alignment, detection::Detector, recognition::FaceEmbedder},
     image::Image,
-    iter::zip_exact,
-    nn::{create_linear_color_mapper, Cnn, CnnInputShape, NeuralNetwork},
     num::TotalF32,
 };
 
-pub struct Embedding {
-    raw: [f32; 128],
-}
-
-impl Embedding {
-    pub fn difference(&self, other: &Self) -> f32 {
-        let mut diff = [0.0; 128];
-        for (diff, (a, b)) in zip_exact(
-            diff.iter_mut(),
-            zip_exact(self.raw.iter(), other.raw.iter()),
-        ) {
-            *diff = *a - *b;
-        }
-
-        let norm = diff.iter().map(|&d| d * d).sum::<f32>();
-        norm.sqrt()
-    }
-}
-
 fn main() -> anyhow::Result<()> {
     let face_dir = std::env::args_os().skip(1).next().unwrap();
 
-    let nn = NeuralNetwork::from_path("3rdparty/onnx/mobilefacenet.onnx")?.load()?;
-    let cnn = Cnn::new(
-        nn,
-        CnnInputShape::NCHW,
-        create_linear_color_mapper(-1.0..=1.0),
-    )?;
-    let target_aspect = cnn.input_resolution().aspect_ratio().unwrap();
-
     let mut classes = Vec::new();
     let mut image_paths = Vec::new();
     for subdir in fs::read_dir(&face_dir)? {
@@ -70,8 +33,8 @@ fn main() -> anyhow::Result<()> {
     let embeddings = image_paths
         .par_iter()
         .map_init(
-            || Detector::default(),
-            |det, (path, class)| {
+            || (Detector::default(), FaceEmbedder::new()),
+            |(det, embedder), (path, class)| {
                 let image = Image::load(path).unwrap();
 
                 let dets = det.detect(&image);
@@ -79,18 +42,8 @@ fn main() -> anyhow::Result<()> {
                     println!("No faces detected in '{}'", path.display());
                     return None;
                 }
-                let grow_by = 0.4;
-                let rect = dets[0]
-                    .bounding_rect_raw()
-                    .grow_rel(grow_by)
-                    .grow_to_fit_aspect(target_aspect);
-                let face = image.view(rect).aspect_aware_resize(cnn.input_resolution());
-                let out = cnn.estimate(&face).unwrap();
-                let view = out[0].index([0]);
-                let f = view.as_slice();
-                let emb = Embedding {
-                    raw: f.try_into().unwrap(),
-                };
+                let alignment = Alignment::from_detection(&dets[0]);
+                let emb = embedder.embed_aligned(&image, &alignment);
                 Some((emb, *class))
             },
         )
@@ -111,24 +64,24 @@ fn main() -> anyhow::Result<()> {
             .push(emb);
     }
 
-    let mut max_intra_class_difference = Vec::new();
+    let mut min_intra_class_similarity = Vec::new();
     for (class, embeddings) in &class_map {
         if embeddings.len() == 1 {
             continue;
         }
 
-        let mut max_diff = 0.0;
+        let mut min_sim = 1.0f32;
         for (a, b) in embeddings.iter().tuple_combinations() {
-            max_diff = max_diff.max(a.difference(*b));
+            min_sim = min_sim.min(a.similarity(b));
         }
 
-        max_intra_class_difference.push((*class, max_diff));
+        min_intra_class_similarity.push((*class, min_sim));
     }
 
-    println!("Max. intra-class differences:");
-    max_intra_class_difference.sort_by_key(|(_, diff)| TotalF32(-*diff));
-    for (class, diff) in max_intra_class_difference.iter().take(10) {
-        println!("diff={} in {}", diff, classes[*class].display());
+    println!("Min. intra-class similarities:");
+    min_intra_class_similarity.sort_by_key(|(_, sim)| TotalF32(*sim));
+    for (class, sim) in min_intra_class_similarity.iter().take(10) {
+        println!("similarity={} in {}", sim, classes[*class].display());
     }
 
     Ok(())
@@ -0,0 +1,152 @@
+//! Benchmarks face verification on LFW-style image pairs.
+//!
+//! Usage:
+//! ```text
+//! eval_face_verification <image-dir> [--pairs <pairs.txt>] [--alignment <detection|none>]
+//!     [--far <rates>] [--output <report.json>]
+//! ```
+//!
+//! With `--pairs`, the pairs are loaded from an LFW `pairs.txt` file (with images in `image-dir`).
+//! Otherwise, 10 folds of 600 pairs are generated from the subdirectories of `image-dir` (one per
+//! person). `--alignment none` skips face alignment and embeds the detected face rectangle instead,
+//! padded like the initial RoI of the face tracker.
+//! `--far` takes a comma-separated list of false accept rates to report the true accept rate at.
+
+use std::{collections::HashMap, fs, path::Path, process, time::Instant};
+
+use anyhow::{bail, Context};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use zaru::{
+    face::{
+        alignment::Alignment,
+        detection::Detector,
+        recognition::{
+            eval::{Evaluator, PairList},
+            Embedding, FaceEmbedder,
+        },
+        tracking::FaceTracker,
+    },
+    image::Image,
+};
+
+const FOLDS: usize = 10;
+const PAIRS_PER_FOLD: usize = 600;
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: eval_face_verification <image-dir> [--pairs <pairs.txt>] \
+        [--alignment <detection|none>] [--far <rates>] [--output <report.json>]"
+    );
+    process::exit(1);
+}
+
+fn main() -> anyhow::Result<()> {
+    zaru::init_logger!();
+
+    let mut positional = Vec::new();
+    let mut pairs = None;
+    let mut align = true;
+    let mut far = None;
+    let mut output = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "--pairs" => pairs = Some(args.next().unwrap_or_else(|| usage())),
+            "--alignment" => match args.next().as_deref() {
+                Some("detection") => align = true,
+                Some("none") => align = false,
+                _ => usage(),
+            },
+            "--far" => far = Some(args.next().unwrap_or_else(|| usage())),
+            "--output" => output = Some(args.next().unwrap_or_else(|| usage())),
+            _ => positional.push(arg),
+        }
+    }
+    let [image_dir] = &positional[..] else {
+        usage();
+    };
+
+    let pairs = match pairs {
+        Some(pairs) => PairList::load_lfw(pairs, image_dir)?,
+        None => PairList::from_directory(image_dir, FOLDS, PAIRS_PER_FOLD)?,
+    };
+    println!("loaded {} pairs in {} folds", pairs.len(), pairs.folds());
+
+    let mut evaluator = Evaluator::new();
+    if let Some(far) = far {
+        let targets = far
+            .split(',')
+            .map(|s| s.trim().parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("invalid false accept rates '{}'", far))?;
+        if targets.iter().any(|t| !(0.0..=1.0).contains(t)) {
+            bail!("false accept rates must be between 0 and 1");
+        }
+        evaluator.set_far_targets(&targets);
+    }
+
+    let start = Instant::now();
+    let paths = pairs.image_paths();
+    let embeddings = paths
+        .par_iter()
+        .map_init(
+            || (Detector::default(), FaceEmbedder::new()),
+            |(det, embedder), path| -> anyhow::Result<_> {
+                Ok((*path, embed(det, embedder, path, align)?))
+            },
+        )
+        .collect::<anyhow::Result<HashMap<_, _>>>()?;
+    println!(
+        "computed {} embeddings in {:?}",
+        embeddings.len(),
+        start.elapsed(),
+    );
+
+    for pair in pairs.pairs() {
+        let similarity = match (&embeddings[&*pair.a], &embeddings[&*pair.b]) {
+            (Some(a), Some(b)) => Some(a.similarity(b)),
+            _ => None,
+        };
+        evaluator.add_pair(pair, similarity);
+    }
+
+    let report = evaluator.report();
+    println!("{}", report);
+
+    if let Some(path) = output {
+        let json = serde_json::to_string_pretty(&report)?;
+        fs::write(&path, json).with_context(|| format!("failed to write '{}'", path))?;
+        println!("wrote report to '{}'", path);
+    }
+
+    Ok(())
+}
+
+/// Computes the embedding of the most confidently detected face in the image at `path`.
+fn embed(
+    det: &mut Detector,
+    embedder: &mut FaceEmbedder,
+    path: &Path,
+    align: bool,
+) -> anyhow::Result<Option<Embedding>> {
+    let image =
+        Image::load(path).with_context(|| format!("failed to load '{}'", path.display()))?;
+    let dets = det.detect(&image);
+    let Some(face) = dets
+        .iter()
+        .max_by(|a, b| a.confidence().total_cmp(&b.confidence()))
+    else {
+        log::warn!("no face detected in '{}'", path.display());
+        return Ok(None);
+    };
+
+    Ok(Some(if align {
+        embedder.embed_aligned(&image, &Alignment::from_detection(face))
+    } else {
+        // Use the same margin around the detection as the face tracker.
+        let rect = face
+            .bounding_rect_raw()
+            .grow_rel(FaceTracker::DETECTION_PADDING);
+        embedder.embed(&image.view(rect))
+    }))
+}
@@ -1,17 +1,17 @@
 use std::any::type_name;
 
 use zaru::{
+    draw,
     face::{
         detection::Detector,
         landmark::multipie68::{self, LandmarkResult},
     },
     gui,
     image::Color,
-    landmark::{Estimation, Estimator, Network},
+    landmark::{Estimator, Network},
     timer::FpsCounter,
     video::webcam::{Webcam, WebcamOptions},
 };
-use zaru_image::draw;
 
 struct Algo {
     estimator: Estimator<LandmarkResult>,
@@ -48,11 +48,12 @@ fn main() -> anyhow::Result<()> {
                     .grow_rel(0.15)
                     .grow_to_fit_aspect(algo.estimator.input_resolution().aspect_ratio().unwrap());
                 draw::rect(&mut image, rect).color(algo.color);
+                let topology = algo.estimator.topology().unwrap();
                 let mut view = image.view_mut(rect);
                 let lms = algo.estimator.estimate(&view);
-                for &[x, y, _] in lms.landmarks_mut().positions() {
-                    draw::marker(&mut view, x as i32, y as i32).color(algo.color);
-                }
+                draw::skeleton(&mut view, lms.landmarks(), topology)
+                    .color(algo.color)
+                    .marker_size(3);
                 algo.fps.tick_with(algo.estimator.timers());
             }
         }
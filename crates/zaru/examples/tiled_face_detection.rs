@@ -0,0 +1,28 @@
+use zaru::{
+    detection::tiling::TiledDetector,
+    face::detection::{Detector, ShortRangeNetwork},
+    gui,
+    image::Resolution,
+    timer::FpsCounter,
+    video::webcam::{Webcam, WebcamOptions},
+};
+
+fn main() -> anyhow::Result<()> {
+    zaru::init_logger!();
+
+    let mut detector = TiledDetector::new(Detector::new(ShortRangeNetwork));
+
+    let mut fps = FpsCounter::new("tiled face detector");
+    let mut webcam = Webcam::open(WebcamOptions::default().resolution(Resolution::RES_1080P))?;
+    loop {
+        let mut image = webcam.read()?;
+
+        for detection in detector.detect(&image) {
+            detection.draw(&mut image);
+        }
+
+        gui::show_image("tiled face detection", &image);
+
+        fps.tick_with(webcam.timers().chain(detector.detector().timers()));
+    }
+}
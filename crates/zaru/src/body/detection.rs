@@ -1,19 +1,21 @@
 //! Human body detection.
 
 use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 use zaru_utils::num::sigmoid;
 
 use crate::{
     detection::{
-        nms::NonMaxSuppression,
+        nms::{IouMode, NonMaxSuppression},
         ssd::{Anchor, AnchorParams, Anchors, LayerInfo},
-        BoundingRect, RawDetection,
+        BoundingRect, DetectionLike, Detector, RawDetection,
     },
     nn::{create_linear_color_mapper, point_to_img, Cnn, CnnInputShape, NeuralNetwork},
     timer::Timer,
 };
 use zaru_image::{
     draw, AsImageView, AsImageViewMut, Color, ImageView, ImageViewMut, Rect, Resolution,
+    RotatedRect,
 };
 
 static MODEL: Lazy<Cnn> = Lazy::new(|| {
@@ -45,6 +47,8 @@ impl PoseDetector {
     const DEFAULT_THRESH: f32 = 0.5;
 
     pub fn new() -> Self {
+        let mut nms = NonMaxSuppression::new();
+        nms.set_iou_mode(IouMode::Rotated);
         Self {
             cnn: &MODEL,
             anchors: Anchors::calculate(&AnchorParams {
@@ -54,7 +58,7 @@ impl PoseDetector {
                     LayerInfo::new(6, 7, 7),
                 ],
             }),
-            nms: NonMaxSuppression::new(),
+            nms,
             thresh: Self::DEFAULT_THRESH,
             t_resize: Timer::new("resize"),
             t_infer: Timer::new("infer"),
@@ -130,6 +134,34 @@ impl PoseDetector {
     }
 }
 
+impl Detector for PoseDetector {
+    type Detection = Detection;
+
+    fn input_resolution(&self) -> Resolution {
+        self.input_resolution()
+    }
+
+    fn detect(&mut self, image: ImageView<'_>) -> &[Detection] {
+        self.detect_impl(image)
+    }
+
+    fn timers(&self) -> Box<dyn Iterator<Item = &Timer> + '_> {
+        Box::new(self.timers())
+    }
+
+    fn threshold(&self) -> f32 {
+        self.thresh
+    }
+
+    fn set_threshold(&mut self, threshold: f32) {
+        self.thresh = threshold;
+    }
+
+    fn nms_mut(&mut self) -> &mut NonMaxSuppression {
+        &mut self.nms
+    }
+}
+
 fn extract_detection(
     anchor: &Anchor,
     input_res: Resolution,
@@ -152,7 +184,7 @@ fn extract_detection(
         )
     };
 
-    RawDetection::with_keypoints(
+    let mut det = RawDetection::with_keypoints(
         confidence,
         BoundingRect::from_center(xc, yc, w, h),
         vec![
@@ -161,9 +193,13 @@ fn extract_detection(
             lm(box_params[8], box_params[9]),
             lm(box_params[10], box_params[11]),
         ],
-    )
+    );
+    // Keypoint 0 is the center of the hips, keypoint 1 encodes size and rotation of the full body.
+    det.set_rotation_from_keypoints(0, 1);
+    det
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Detection {
     raw: RawDetection,
     full_res: Resolution,
@@ -178,6 +214,18 @@ impl Detection {
         self.raw.bounding_rect().to_rect(&self.full_res)
     }
 
+    /// Returns the bounding rectangle of the body, rotated by [`Detection::rotation_radians`].
+    pub fn rotated_bounding_rect(&self) -> RotatedRect {
+        self.raw
+            .rotated_bounding_rect()
+            .to_rotated_rect_in(&self.full_res)
+    }
+
+    /// Returns the clockwise rotation of the body compared to an upright position.
+    pub fn rotation_radians(&self) -> f32 {
+        self.raw.rotation_radians()
+    }
+
     pub fn keypoints(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
         self.raw
             .keypoints()
@@ -217,3 +265,17 @@ impl Detection {
         }
     }
 }
+
+impl DetectionLike for Detection {
+    fn from_raw(raw: RawDetection, full_res: Resolution) -> Self {
+        Self { raw, full_res }
+    }
+
+    fn raw(&self) -> &RawDetection {
+        &self.raw
+    }
+
+    fn full_resolution(&self) -> Resolution {
+        self.full_res
+    }
+}
@@ -4,11 +4,13 @@
 
 use once_cell::sync::Lazy;
 use zaru_image::{
-    draw, AsImageView, AsImageViewMut, AspectRatio, Color, ImageView, ImageViewMut, Resolution,
+    AsImageView, AsImageViewMut, AspectRatio, Color, ImageView, ImageViewMut, Resolution,
 };
 use zaru_utils::{iter::zip_exact, num::sigmoid};
 
 use crate::{
+    draw,
+    landmark::{self, topology::LandmarkTopology, Landmarks},
     nn::{create_linear_color_mapper, unadjust_aspect_ratio, Cnn, CnnInputShape, NeuralNetwork},
     slice::SliceExt,
     timer::Timer,
@@ -29,13 +31,14 @@ impl Landmarker {
             t_resize: Timer::new("resize"),
             t_infer: Timer::new("infer"),
             result_buffer: LandmarkResult {
-                landmarks: [Landmark {
+                raw_landmarks: [Landmark {
                     x: 0.0,
                     y: 0.0,
                     z: 0.0,
                     presence: 0.0,
                     visibility: 0.0,
                 }; 39],
+                landmarks: Landmarks::new(39),
                 pose_presence: 0.0,
                 orig_res: Resolution::new(1, 1),
                 orig_aspect: AspectRatio::SQUARE,
@@ -84,13 +87,16 @@ impl Landmarker {
 
         self.result_buffer.pose_presence = pose_flag.index([0, 0]).as_singular();
 
-        for (&[x, y, z, visibility, presence], out) in zip_exact(
+        let landmarks = &mut self.result_buffer.landmarks;
+        for (i, (&[x, y, z, visibility, presence], out)) in zip_exact(
             screen_landmarks
                 .index([0])
                 .as_slice()
                 .array_chunks_exact::<5>(),
-            &mut self.result_buffer.landmarks,
-        ) {
+            &mut self.result_buffer.raw_landmarks,
+        )
+        .enumerate()
+        {
             let (x, y) = unadjust_aspect_ratio(
                 x / input_res.width() as f32,
                 y / input_res.height() as f32,
@@ -103,6 +109,10 @@ impl Landmarker {
             out.z = z;
             out.visibility = visibility;
             out.presence = presence;
+
+            landmarks.positions_mut()[i] = [x, y, z];
+            landmarks.visibilities_mut()[i] = sigmoid(visibility);
+            landmarks.presences_mut()[i] = sigmoid(presence);
         }
 
         &self.result_buffer
@@ -120,16 +130,37 @@ pub struct LandmarkResult {
     orig_aspect: AspectRatio,
 
     pose_presence: f32,
-    landmarks: [Landmark; 39],
+    raw_landmarks: [Landmark; 39],
+    landmarks: Landmarks,
 }
 
 impl LandmarkResult {
+    /// Returns all landmarks, including visibility and presence values.
+    ///
+    /// This contains the [`pose_landmarks`][Self::pose_landmarks], followed by the
+    /// [`aux_landmarks`][Self::aux_landmarks].
+    pub fn landmarks(&self) -> &Landmarks {
+        &self.landmarks
+    }
+
     pub fn pose_landmarks(&self) -> &[Landmark] {
-        &self.landmarks[..33]
+        &self.raw_landmarks[..33]
     }
 
     pub fn aux_landmarks(&self) -> &[Landmark] {
-        &self.landmarks[33..]
+        &self.raw_landmarks[33..]
+    }
+
+    /// Returns an iterator over the [`pose_landmarks`][Self::pose_landmarks] as generic
+    /// [`landmark::Landmark`]s.
+    pub fn iter_pose_landmarks(&self) -> impl Iterator<Item = landmark::Landmark> + '_ {
+        self.landmarks.iter().take(33)
+    }
+
+    /// Returns an iterator over the [`aux_landmarks`][Self::aux_landmarks] as generic
+    /// [`landmark::Landmark`]s.
+    pub fn iter_aux_landmarks(&self) -> impl Iterator<Item = landmark::Landmark> + '_ {
+        self.landmarks.iter().skip(33)
     }
 
     pub fn presence(&self) -> f32 {
@@ -141,18 +172,9 @@ impl LandmarkResult {
     }
 
     fn draw_impl(&self, target: &mut ImageViewMut<'_>) {
-        for (a, b) in COARSE_CONNECTIVITY {
-            let a = &self.landmarks[*a as usize];
-            let b = &self.landmarks[*b as usize];
-            draw::line(target, a.x() as _, a.y() as _, b.x() as _, b.y() as _).stroke_width(3);
-        }
-
-        for lm in self.pose_landmarks() {
-            draw::marker(target, lm.x() as _, lm.y() as _).size(9);
-        }
-        for lm in self.aux_landmarks() {
-            draw::marker(target, lm.x() as _, lm.y() as _).color(Color::YELLOW);
-        }
+        draw::skeleton(target, &self.landmarks, &TOPOLOGY)
+            .stroke_width(3)
+            .marker_size(9);
     }
 }
 
@@ -193,24 +215,128 @@ pub enum LandmarkIdx {
     RightFootIndex = 32,
 }
 
-const COARSE_CONNECTIVITY: &[(LandmarkIdx, LandmarkIdx)] = {
+static TOPOLOGY: Lazy<LandmarkTopology> = Lazy::new(|| {
     use LandmarkIdx::*;
-    &[
-        (LeftShoulder, RightShoulder),
-        (LeftShoulder, LeftElbow),
-        (LeftElbow, LeftWrist),
-        (RightShoulder, RightElbow),
-        (RightElbow, RightWrist),
-        (LeftShoulder, LeftHip),
-        (LeftHip, LeftAnkle),
-        (LeftAnkle, LeftHeel),
-        (LeftAnkle, LeftFootIndex),
-        (RightShoulder, RightHip),
-        (RightHip, RightAnkle),
-        (RightAnkle, RightHeel),
-        (RightAnkle, RightFootIndex),
-    ]
-};
+
+    let mut topology = LandmarkTopology::new(39);
+    topology.set_color(Color::BLUE);
+    for (idx, name) in [
+        (Nose, "nose"),
+        (LeftEyeInner, "left eye inner"),
+        (LeftEye, "left eye"),
+        (LeftEyeOuter, "left eye outer"),
+        (RightEyeInner, "right eye inner"),
+        (RightEye, "right eye"),
+        (RightEyeOuter, "right eye outer"),
+        (LeftEar, "left ear"),
+        (RightEar, "right ear"),
+        (MouthLeft, "mouth left"),
+        (MouthRight, "mouth right"),
+        (LeftShoulder, "left shoulder"),
+        (RightShoulder, "right shoulder"),
+        (LeftElbow, "left elbow"),
+        (RightElbow, "right elbow"),
+        (LeftWrist, "left wrist"),
+        (RightWrist, "right wrist"),
+        (LeftPinky, "left pinky"),
+        (RightPinky, "right pinky"),
+        (LeftIndex, "left index"),
+        (RightIndex, "right index"),
+        (LeftThumb, "left thumb"),
+        (RightThumb, "right thumb"),
+        (LeftHip, "left hip"),
+        (RightHip, "right hip"),
+        (LeftKnee, "left knee"),
+        (RightKnee, "right knee"),
+        (LeftAnkle, "left ankle"),
+        (RightAnkle, "right ankle"),
+        (LeftHeel, "left heel"),
+        (RightHeel, "right heel"),
+        (LeftFootIndex, "left foot index"),
+        (RightFootIndex, "right foot index"),
+    ] {
+        topology.set_name(idx as usize, name);
+    }
+
+    // Coarse connectivity that leaves out the face, hands, and knees.
+    topology
+        .add_edge(LeftShoulder as _, RightShoulder as _)
+        .add_chain(&[LeftShoulder as _, LeftElbow as _, LeftWrist as _])
+        .add_chain(&[RightShoulder as _, RightElbow as _, RightWrist as _])
+        .add_chain(&[
+            LeftShoulder as _,
+            LeftHip as _,
+            LeftAnkle as _,
+            LeftHeel as _,
+        ])
+        .add_edge(LeftAnkle as _, LeftFootIndex as _)
+        .add_chain(&[
+            RightShoulder as _,
+            RightHip as _,
+            RightAnkle as _,
+            RightHeel as _,
+        ])
+        .add_edge(RightAnkle as _, RightFootIndex as _);
+
+    topology
+        .add_group(
+            "face",
+            [
+                Nose,
+                LeftEyeInner,
+                LeftEye,
+                LeftEyeOuter,
+                RightEyeInner,
+                RightEye,
+                RightEyeOuter,
+                LeftEar,
+                RightEar,
+                MouthLeft,
+                MouthRight,
+            ]
+            .map(|lm| lm as usize),
+            None,
+        )
+        .add_group(
+            "left arm",
+            [
+                LeftShoulder,
+                LeftElbow,
+                LeftWrist,
+                LeftPinky,
+                LeftIndex,
+                LeftThumb,
+            ]
+            .map(|lm| lm as usize),
+            None,
+        )
+        .add_group(
+            "right arm",
+            [
+                RightShoulder,
+                RightElbow,
+                RightWrist,
+                RightPinky,
+                RightIndex,
+                RightThumb,
+            ]
+            .map(|lm| lm as usize),
+            None,
+        )
+        .add_group(
+            "left leg",
+            [LeftHip, LeftKnee, LeftAnkle, LeftHeel, LeftFootIndex].map(|lm| lm as usize),
+            None,
+        )
+        .add_group(
+            "right leg",
+            [RightHip, RightKnee, RightAnkle, RightHeel, RightFootIndex].map(|lm| lm as usize),
+            None,
+        )
+        // The 6 auxiliary landmarks following the 33 pose landmarks.
+        .add_group("auxiliary", 33..39, Some(Color::YELLOW));
+    topology
+});
 
 #[derive(Debug, Clone, Copy)]
 pub struct Landmark {
@@ -245,6 +371,11 @@ impl Landmark {
 
 pub trait LandmarkNetwork {
     fn cnn() -> &'static Cnn;
+
+    /// Returns the [`LandmarkTopology`] of the 33 pose landmarks and 6 auxiliary landmarks.
+    fn topology() -> &'static LandmarkTopology {
+        &TOPOLOGY
+    }
 }
 
 pub struct LiteNetwork;
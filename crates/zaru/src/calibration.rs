@@ -0,0 +1,1170 @@
+//! Camera calibration from checkerboard images.
+//!
+//! Calibration determines the [`CameraModel`] of a camera (its focal length, principal point and
+//! lens distortion), which is needed to reconstruct metric 3D information from images, for example
+//! by [`FaceTracker::set_camera`][crate::face::tracking::FaceTracker::set_camera].
+//!
+//! The procedure works as follows:
+//!
+//! - Print a [`Checkerboard`] pattern and measure the size of its squares.
+//! - Capture 10-20 images of the board with the camera to calibrate, covering the whole field of
+//!   view and tilting the board in different directions.
+//! - Pass the images to [`Calibrator::add_image`], which detects the inner corners of the board.
+//! - Call [`Calibrator::calibrate`] to compute the [`Calibration`], and [`Calibration::save`] it
+//!   for later use.
+//!
+//! The intrinsics are initialized with [Zhang's method], and are then refined together with the
+//! lens distortion and the board poses by minimizing the reprojection error with the
+//! Levenberg–Marquardt algorithm.
+//!
+//! [Zhang's method]: https://doi.org/10.1109/34.888718
+
+use std::{
+    collections::{HashMap, VecDeque},
+    f32::consts::PI,
+    fs,
+    path::Path,
+};
+
+use anyhow::{bail, ensure, Context};
+use nalgebra::{DMatrix, DVector, Matrix3, Rotation3, Vector2, Vector3};
+use serde::{Deserialize, Serialize};
+use zaru_image::{AsImageView, Resolution};
+
+use crate::camera::{CameraModel, Distortion};
+
+/// Radius of the ring of pixels sampled by the corner detector.
+///
+/// Checkerboard squares should be at least 3 times as large as this in the image.
+const RING_RADIUS: i32 = 5;
+
+/// Minimum corner detector response of a checkerboard corner.
+const MIN_RESPONSE: f32 = 1.0;
+
+/// Half-size of the window used for sub-pixel corner refinement.
+const REFINE_RADIUS: i32 = RING_RADIUS;
+
+const REFINE_ITERATIONS: usize = 20;
+
+/// Minimum number of views required for calibration.
+const MIN_VIEWS: usize = 3;
+
+const MAX_ITERATIONS: usize = 100;
+
+/// A checkerboard calibration pattern.
+///
+/// The board is described by the number of *inner* corners (where 4 squares meet) along each
+/// axis, so a board with 8x6 squares has 7x5 inner corners. The outermost squares should be
+/// surrounded by a white border that is at least one square wide.
+///
+/// The board defines a coordinate system with the first inner corner at the origin, X pointing
+/// along the columns, Y pointing along the rows, and Z pointing into the board. Since the board is
+/// symmetric, the first corner is chosen to be the one closest to the top left corner of the image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Checkerboard {
+    columns: u32,
+    rows: u32,
+    square_size: f32,
+}
+
+impl Checkerboard {
+    /// Creates a checkerboard with `columns` x `rows` inner corners.
+    ///
+    /// `square_size` is the side length of the squares. The camera poses computed during
+    /// calibration are expressed in the same unit.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `columns` or `rows` is less than 2.
+    pub fn new(columns: u32, rows: u32, square_size: f32) -> Self {
+        assert!(
+            columns >= 2 && rows >= 2,
+            "checkerboard needs at least 2x2 inner corners (got {}x{})",
+            columns,
+            rows,
+        );
+        Self {
+            columns,
+            rows,
+            square_size,
+        }
+    }
+
+    /// Returns the number of inner corners along the X axis of the board.
+    #[inline]
+    pub fn columns(&self) -> u32 {
+        self.columns
+    }
+
+    /// Returns the number of inner corners along the Y axis of the board.
+    #[inline]
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    /// Returns the side length of the squares.
+    #[inline]
+    pub fn square_size(&self) -> f32 {
+        self.square_size
+    }
+
+    /// Returns the total number of inner corners.
+    #[inline]
+    pub fn corner_count(&self) -> usize {
+        (self.columns * self.rows) as usize
+    }
+
+    /// Returns the positions of the inner corners on the board, in row-major order.
+    ///
+    /// All corners lie in the board plane (Z = 0).
+    pub fn object_points(&self) -> impl Iterator<Item = [f32; 2]> + '_ {
+        (0..self.rows).flat_map(move |row| {
+            (0..self.columns)
+                .map(move |col| [col as f32 * self.square_size, row as f32 * self.square_size])
+        })
+    }
+
+    /// Detects the inner corners of the board in an image.
+    ///
+    /// Returns the pixel coordinates of all inner corners with sub-pixel accuracy, in the same
+    /// order as [`Checkerboard::object_points`]. If the board is not fully visible, or cannot be
+    /// detected, [`None`] is returned.
+    pub fn detect<V: AsImageView>(&self, image: &V) -> Option<Vec<[f32; 2]>> {
+        let gray = GrayImage::from_view(image).blurred();
+        let gradients = gray.gradients();
+
+        let mut corners: Vec<[f32; 2]> = Vec::new();
+        for candidate in gray.saddle_points() {
+            let Some(corner) = gradients.refine_corner(candidate) else {
+                continue;
+            };
+            // Multiple candidates can converge to the same corner.
+            if corners.iter().all(|c| distance(*c, corner) > 2.0) {
+                corners.push(corner);
+            }
+        }
+
+        self.assemble_grid(&corners)
+    }
+
+    /// Finds a `columns` x `rows` grid in a set of detected corners, and orders its corners.
+    fn assemble_grid(&self, corners: &[[f32; 2]]) -> Option<Vec<[f32; 2]>> {
+        if corners.len() < self.corner_count() {
+            return None;
+        }
+
+        // Grow the grid from the corner closest to the center of all detections.
+        let centroid = corners
+            .iter()
+            .fold([0.0, 0.0], |[x, y], c| [x + c[0], y + c[1]]);
+        let centroid = [
+            centroid[0] / corners.len() as f32,
+            centroid[1] / corners.len() as f32,
+        ];
+        let seed = (0..corners.len()).min_by(|&a, &b| {
+            distance(corners[a], centroid).total_cmp(&distance(corners[b], centroid))
+        })?;
+
+        // The grid axes at the seed are given by its nearest neighbor, and the next-nearest
+        // neighbor that is roughly orthogonal to it.
+        let mut neighbors = (0..corners.len())
+            .filter(|&i| i != seed)
+            .collect::<Vec<_>>();
+        neighbors.sort_by(|&a, &b| {
+            distance(corners[a], corners[seed]).total_cmp(&distance(corners[b], corners[seed]))
+        });
+        let axis_u = sub(corners[neighbors[0]], corners[seed]);
+        let axis_v = neighbors[1..neighbors.len().min(4)]
+            .iter()
+            .map(|&i| sub(corners[i], corners[seed]))
+            .filter(|v| cos_angle(axis_u, *v).abs() < 0.5)
+            .min_by(|a, b| {
+                cos_angle(axis_u, *a)
+                    .abs()
+                    .total_cmp(&cos_angle(axis_u, *b).abs())
+            })?;
+
+        let mut grid = HashMap::new();
+        let mut used = vec![false; corners.len()];
+        let mut queue = VecDeque::new();
+        grid.insert((0, 0), seed);
+        used[seed] = true;
+        queue.push_back((0, 0));
+        while let Some((i, j)) = queue.pop_front() {
+            let pos = corners[grid[&(i, j)]];
+            for (di, dj) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                let target = (i + di, j + dj);
+                if grid.contains_key(&target) {
+                    continue;
+                }
+
+                let step = local_step(&grid, corners, (i, j), (di, dj)).unwrap_or_else(|| {
+                    let (axis, sign) = if di != 0 { (axis_u, di) } else { (axis_v, dj) };
+                    [axis[0] * sign as f32, axis[1] * sign as f32]
+                });
+                let predicted = [pos[0] + step[0], pos[1] + step[1]];
+                let tolerance = 0.3 * length(step);
+                let found = (0..corners.len())
+                    .filter(|&k| !used[k])
+                    .map(|k| (k, distance(corners[k], predicted)))
+                    .filter(|&(_, dist)| dist < tolerance)
+                    .min_by(|a, b| a.1.total_cmp(&b.1));
+                if let Some((k, _)) = found {
+                    grid.insert(target, k);
+                    used[k] = true;
+                    queue.push_back(target);
+                }
+            }
+        }
+
+        if grid.len() != self.corner_count() {
+            return None;
+        }
+        let min_i = grid.keys().map(|k| k.0).min()?;
+        let min_j = grid.keys().map(|k| k.1).min()?;
+        let size_i = grid.keys().map(|k| k.0).max()? - min_i + 1;
+        let size_j = grid.keys().map(|k| k.1).max()? - min_j + 1;
+
+        // Out of the 8 ways to map the grid onto the board, keep those that match the board
+        // dimensions and don't mirror it, then pick the one whose first corner is closest to the
+        // top left of the image.
+        let (columns, rows) = (self.columns as i32, self.rows as i32);
+        let mut best: Option<Vec<[f32; 2]>> = None;
+        for swap in [false, true] {
+            let (board_i, board_j) = if swap {
+                (size_j, size_i)
+            } else {
+                (size_i, size_j)
+            };
+            if (board_i, board_j) != (columns, rows) {
+                continue;
+            }
+            for flip_col in [false, true] {
+                for flip_row in [false, true] {
+                    let mut ordered = Vec::with_capacity(self.corner_count());
+                    for row in 0..rows {
+                        for col in 0..columns {
+                            let a = if flip_col { columns - 1 - col } else { col };
+                            let b = if flip_row { rows - 1 - row } else { row };
+                            let (i, j) = if swap { (b, a) } else { (a, b) };
+                            ordered.push(corners[*grid.get(&(min_i + i, min_j + j))?]);
+                        }
+                    }
+
+                    let x_axis = sub(ordered[1], ordered[0]);
+                    let y_axis = sub(ordered[self.columns as usize], ordered[0]);
+                    if x_axis[0] * y_axis[1] - x_axis[1] * y_axis[0] <= 0.0 {
+                        continue;
+                    }
+                    let better = match &best {
+                        Some(best) => ordered[0][0] + ordered[0][1] < best[0][0] + best[0][1],
+                        None => true,
+                    };
+                    if better {
+                        best = Some(ordered);
+                    }
+                }
+            }
+        }
+        best
+    }
+}
+
+/// Estimates the offset from grid cell `cell` to its neighbor in direction `dir` from the
+/// already-assembled part of the grid.
+fn local_step(
+    grid: &HashMap<(i32, i32), usize>,
+    corners: &[[f32; 2]],
+    (i, j): (i32, i32),
+    (di, dj): (i32, i32),
+) -> Option<[f32; 2]> {
+    let pos = |cell: (i32, i32)| grid.get(&cell).map(|&k| corners[k]);
+
+    if let (Some(a), Some(b)) = (pos((i - di, j - dj)), pos((i, j))) {
+        return Some(sub(b, a));
+    }
+    // Look at the step between the cells next to this one, perpendicular to `dir`.
+    for k in [-1, 1] {
+        let side = (i + k * dj, j + k * di);
+        for (from, to) in [
+            (side, (side.0 + di, side.1 + dj)),
+            ((side.0 - di, side.1 - dj), side),
+        ] {
+            if let (Some(a), Some(b)) = (pos(from), pos(to)) {
+                return Some(sub(b, a));
+            }
+        }
+    }
+    None
+}
+
+fn sub(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+fn length(v: [f32; 2]) -> f32 {
+    v[0].hypot(v[1])
+}
+
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    length(sub(a, b))
+}
+
+fn cos_angle(a: [f32; 2], b: [f32; 2]) -> f32 {
+    (a[0] * b[0] + a[1] * b[1]) / (length(a) * length(b))
+}
+
+/// A single-channel floating-point image used for corner detection.
+struct GrayImage {
+    width: i32,
+    height: i32,
+    data: Vec<f32>,
+}
+
+impl GrayImage {
+    fn from_view<V: AsImageView>(image: &V) -> Self {
+        let view = image.as_view();
+        let (width, height) = (view.width(), view.height());
+        let mut data = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let c = view.get(x, y);
+                let luma = 0.299 * c.r() as f32 + 0.587 * c.g() as f32 + 0.114 * c.b() as f32;
+                data.push(luma / 255.0);
+            }
+        }
+        Self {
+            width: width as i32,
+            height: height as i32,
+            data,
+        }
+    }
+
+    fn get(&self, x: i32, y: i32) -> f32 {
+        let x = x.clamp(0, self.width - 1);
+        let y = y.clamp(0, self.height - 1);
+        self.data[(y * self.width + x) as usize]
+    }
+
+    /// Applies a 3x3 binomial blur to suppress noise.
+    fn blurred(&self) -> Self {
+        let mut data = Vec::with_capacity(self.data.len());
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let row = |y| self.get(x - 1, y) + 2.0 * self.get(x, y) + self.get(x + 1, y);
+                data.push((row(y - 1) + 2.0 * row(y) + row(y + 1)) / 16.0);
+            }
+        }
+        Self { data, ..*self }
+    }
+
+    fn gradients(&self) -> Gradients {
+        let mut data = Vec::with_capacity(self.data.len());
+        for y in 0..self.height {
+            for x in 0..self.width {
+                data.push([
+                    (self.get(x + 1, y) - self.get(x - 1, y)) / 2.0,
+                    (self.get(x, y + 1) - self.get(x, y - 1)) / 2.0,
+                ]);
+            }
+        }
+        Gradients {
+            width: self.width,
+            height: self.height,
+            data,
+        }
+    }
+
+    /// Finds candidate checkerboard corners with the [ChESS] detector.
+    ///
+    /// ChESS samples a ring of pixels around each location. At a checkerboard corner, opposite
+    /// samples on the ring have the same intensity, while samples a quarter turn apart differ.
+    ///
+    /// [ChESS]: https://arxiv.org/abs/1301.5491
+    fn saddle_points(&self) -> Vec<[f32; 2]> {
+        let ring: [(i32, i32); 16] = std::array::from_fn(|k| {
+            let angle = k as f32 * PI / 8.0;
+            (
+                (RING_RADIUS as f32 * angle.cos()).round() as i32,
+                (RING_RADIUS as f32 * angle.sin()).round() as i32,
+            )
+        });
+
+        let r = RING_RADIUS;
+        let mut response = vec![0.0; self.data.len()];
+        for y in r..self.height - r {
+            for x in r..self.width - r {
+                let s = ring.map(|(dx, dy)| self.get(x + dx, y + dy));
+                let sum_response = (0..4)
+                    .map(|n| ((s[n] + s[n + 8]) - (s[n + 4] + s[n + 12])).abs())
+                    .sum::<f32>();
+                let diff_response = (0..8).map(|n| (s[n] - s[n + 8]).abs()).sum::<f32>();
+                let ring_mean = s.iter().sum::<f32>() / 16.0;
+                let local_mean = (self.get(x, y)
+                    + self.get(x - 1, y)
+                    + self.get(x + 1, y)
+                    + self.get(x, y - 1)
+                    + self.get(x, y + 1))
+                    / 5.0;
+                response[(y * self.width + x) as usize] =
+                    sum_response - diff_response - 16.0 * (ring_mean - local_mean).abs();
+            }
+        }
+
+        // Non-maximum suppression.
+        let mut candidates = Vec::new();
+        for y in r..self.height - r {
+            'pixel: for x in r..self.width - r {
+                let value = response[(y * self.width + x) as usize];
+                if value < MIN_RESPONSE {
+                    continue;
+                }
+                for ny in y - r..=y + r {
+                    for nx in x - r..=x + r {
+                        let other = response[(ny * self.width + nx) as usize];
+                        if other > value || (other == value && (ny, nx) < (y, x)) {
+                            continue 'pixel;
+                        }
+                    }
+                }
+                candidates.push([x as f32, y as f32]);
+            }
+        }
+        candidates
+    }
+}
+
+/// Image gradients, used for sub-pixel refinement.
+struct Gradients {
+    width: i32,
+    height: i32,
+    data: Vec<[f32; 2]>,
+}
+
+impl Gradients {
+    /// Bilinearly interpolates the gradient at a sub-pixel position.
+    fn sample(&self, x: f32, y: f32) -> Option<Vector2<f32>> {
+        let (x0, y0) = (x.floor() as i32, y.floor() as i32);
+        if x0 < 0 || y0 < 0 || x0 + 1 >= self.width || y0 + 1 >= self.height {
+            return None;
+        }
+        let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+        let get = |x: i32, y: i32| Vector2::from(self.data[(y * self.width + x) as usize]);
+        let top = get(x0, y0) * (1.0 - fx) + get(x0 + 1, y0) * fx;
+        let bottom = get(x0, y0 + 1) * (1.0 - fx) + get(x0 + 1, y0 + 1) * fx;
+        Some(top * (1.0 - fy) + bottom * fy)
+    }
+
+    /// Refines the position of a checkerboard corner to sub-pixel accuracy.
+    ///
+    /// This uses the same approach as OpenCV's `cornerSubPix`: every image gradient near a
+    /// checkerboard corner is orthogonal to the vector from the corner to the gradient's position,
+    /// so the corner is the point that minimizes the sum of the squared dot products.
+    fn refine_corner(&self, start: [f32; 2]) -> Option<[f32; 2]> {
+        let start = Vector2::from(start);
+        let r = REFINE_RADIUS;
+        let mut pos = start;
+        for _ in 0..REFINE_ITERATIONS {
+            let mut a = nalgebra::Matrix2::<f32>::zeros();
+            let mut b = Vector2::zeros();
+            for dy in -r..=r {
+                for dx in -r..=r {
+                    let q = pos + Vector2::new(dx as f32, dy as f32);
+                    let g = self.sample(q.x, q.y)?;
+                    let weight = (-((dx * dx + dy * dy) as f32) / (r * r) as f32).exp();
+                    let ggt = g * g.transpose() * weight;
+                    a += ggt;
+                    b += ggt * q;
+                }
+            }
+
+            let new = a.try_inverse()? * b;
+            if (new - start).norm() > r as f32 {
+                return None;
+            }
+            let delta = (new - pos).norm();
+            pos = new;
+            if delta < 0.005 {
+                break;
+            }
+        }
+        Some([pos.x, pos.y])
+    }
+}
+
+/// Collects checkerboard views and computes the camera [`Calibration`] from them.
+pub struct Calibrator {
+    board: Checkerboard,
+    resolution: Option<Resolution>,
+    views: Vec<Vec<[f32; 2]>>,
+}
+
+impl Calibrator {
+    /// Creates a calibrator that uses the given checkerboard pattern.
+    pub fn new(board: Checkerboard) -> Self {
+        Self {
+            board,
+            resolution: None,
+            views: Vec::new(),
+        }
+    }
+
+    /// Returns the checkerboard pattern used for calibration.
+    #[inline]
+    pub fn board(&self) -> &Checkerboard {
+        &self.board
+    }
+
+    /// Returns the number of views added so far.
+    #[inline]
+    pub fn view_count(&self) -> usize {
+        self.views.len()
+    }
+
+    /// Detects the checkerboard in `image` and adds it as a view.
+    ///
+    /// Returns whether the checkerboard was detected. Images without a fully visible checkerboard
+    /// are ignored.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the resolution of `image` differs from that of previously added views.
+    pub fn add_image<V: AsImageView>(&mut self, image: &V) -> bool {
+        match self.board.detect(image) {
+            Some(corners) => {
+                self.add_corners(image.as_view().resolution(), corners);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Adds a view with checkerboard corners that were detected externally.
+    ///
+    /// `corners` must be pixel coordinates in the order of [`Checkerboard::object_points`].
+    ///
+    /// # Panics
+    ///
+    /// This panics if the number of corners does not match the checkerboard, or if `resolution`
+    /// differs from that of previously added views.
+    pub fn add_corners(&mut self, resolution: Resolution, corners: Vec<[f32; 2]>) {
+        assert_eq!(
+            corners.len(),
+            self.board.corner_count(),
+            "corner count does not match checkerboard"
+        );
+        let res = *self.resolution.get_or_insert(resolution);
+        assert!(
+            res == resolution,
+            "all views must have the same resolution (expected {}, got {})",
+            res,
+            resolution,
+        );
+        self.views.push(corners);
+    }
+
+    /// Computes the camera calibration from all views added so far.
+    ///
+    /// At least 3 views are required, and the board should be tilted differently in each of them.
+    /// Returns an error if there are too few views, or if they don't constrain the camera model
+    /// (for example, because the board has the same orientation in all of them).
+    pub fn calibrate(&self) -> anyhow::Result<Calibration> {
+        ensure!(
+            self.views.len() >= MIN_VIEWS,
+            "calibration needs at least {} views, got {}",
+            MIN_VIEWS,
+            self.views.len(),
+        );
+        let resolution = self.resolution.unwrap();
+
+        let object_points = self
+            .board
+            .object_points()
+            .map(|[x, y]| Vector3::new(x as f64, y as f64, 0.0))
+            .collect::<Vec<_>>();
+        let views = self
+            .views
+            .iter()
+            .map(|view| {
+                view.iter()
+                    .map(|&[x, y]| Vector2::new(x as f64, y as f64))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let homographies = views
+            .iter()
+            .map(|view| homography(&object_points, view))
+            .collect::<Option<Vec<_>>>()
+            .context("failed to compute board homography")?;
+        let intrinsics = zhang_intrinsics(&homographies, resolution)?;
+        let poses = homographies
+            .iter()
+            .map(|h| board_pose(&intrinsics, h))
+            .collect::<Option<Vec<_>>>()
+            .context("failed to compute board pose")?;
+
+        let mut problem = Problem {
+            object_points: &object_points,
+            views: &views,
+            params: Vec::new(),
+        };
+        problem.params.extend_from_slice(&[
+            intrinsics[(0, 0)],
+            intrinsics[(1, 1)],
+            intrinsics[(0, 2)],
+            intrinsics[(1, 2)],
+        ]);
+        problem.params.extend_from_slice(&[0.0; DISTORTION_PARAMS]);
+        for (rotation, translation) in poses {
+            problem
+                .params
+                .extend(rotation.scaled_axis().iter().copied());
+            problem.params.extend(translation.iter().copied());
+        }
+        let rms = problem.optimize();
+        ensure!(rms.is_finite(), "calibration failed to converge");
+
+        let p = &problem.params;
+        let camera = CameraModel::new([p[0] as f32, p[1] as f32], [p[2] as f32, p[3] as f32])
+            .with_distortion(Distortion::new(
+                [p[4] as f32, p[5] as f32, 0.0],
+                [p[6] as f32, p[7] as f32],
+            ));
+        Ok(Calibration {
+            resolution,
+            camera,
+            reprojection_error: rms as f32,
+        })
+    }
+}
+
+/// Number of camera intrinsics (`fx`, `fy`, `cx`, `cy`) in the parameter vector.
+const INTRINSIC_PARAMS: usize = 4;
+/// Number of distortion coefficients (`k1`, `k2`, `p1`, `p2`) in the parameter vector.
+///
+/// The 6th-order radial coefficient `k3` is not estimated, since it tends to overfit unless the
+/// views cover the image corners very well. Typical webcam lenses don't need it.
+const DISTORTION_PARAMS: usize = 4;
+const CAMERA_PARAMS: usize = INTRINSIC_PARAMS + DISTORTION_PARAMS;
+/// Number of parameters of each board pose (rotation vector and translation).
+const POSE_PARAMS: usize = 6;
+
+/// Computes the homography mapping board coordinates to pixel coordinates with the normalized DLT
+/// algorithm.
+fn homography(from: &[Vector3<f64>], to: &[Vector2<f64>]) -> Option<Matrix3<f64>> {
+    let from = from.iter().map(|p| p.xy()).collect::<Vec<_>>();
+    let (t_from, from) = normalize_points(&from);
+    let (t_to, to) = normalize_points(to);
+
+    let mut a = DMatrix::zeros(from.len() * 2, 9);
+    for (i, (p, q)) in from.iter().zip(&to).enumerate() {
+        let (x, y, u, v) = (p.x, p.y, q.x, q.y);
+        a.row_mut(2 * i)
+            .copy_from_slice(&[x, y, 1.0, 0.0, 0.0, 0.0, -u * x, -u * y, -u]);
+        a.row_mut(2 * i + 1)
+            .copy_from_slice(&[0.0, 0.0, 0.0, x, y, 1.0, -v * x, -v * y, -v]);
+    }
+    let h = null_vector(a.transpose() * a);
+    let h = Matrix3::from_row_slice(h.as_slice());
+
+    let h = t_to.try_inverse()? * h * t_from;
+    Some(h / h[(2, 2)])
+}
+
+/// Translates and scales points so that their centroid is at the origin and their average distance
+/// from it is √2. Returns the transform and the transformed points.
+fn normalize_points(points: &[Vector2<f64>]) -> (Matrix3<f64>, Vec<Vector2<f64>>) {
+    let n = points.len() as f64;
+    let centroid = points.iter().sum::<Vector2<f64>>() / n;
+    let mean_dist = points.iter().map(|p| (p - centroid).norm()).sum::<f64>() / n;
+    let scale = std::f64::consts::SQRT_2 / mean_dist.max(f64::EPSILON);
+    let transform = Matrix3::new(
+        scale,
+        0.0,
+        -scale * centroid.x,
+        0.0,
+        scale,
+        -scale * centroid.y,
+        0.0,
+        0.0,
+        1.0,
+    );
+    let points = points.iter().map(|p| (p - centroid) * scale).collect();
+    (transform, points)
+}
+
+/// Returns the unit vector `x` minimizing `xᵀ * m * x`, for a symmetric matrix `m`.
+fn null_vector(m: DMatrix<f64>) -> DVector<f64> {
+    let eigen = m.symmetric_eigen();
+    let min = eigen.eigenvalues.argmin().0;
+    eigen.eigenvectors.column(min).into_owned()
+}
+
+/// Computes the camera matrix from board homographies with Zhang's method, assuming zero skew.
+fn zhang_intrinsics(
+    homographies: &[Matrix3<f64>],
+    resolution: Resolution,
+) -> anyhow::Result<Matrix3<f64>> {
+    // Move the pixel coordinates into a well-conditioned range first.
+    let scale = 2.0 / (resolution.width() + resolution.height()) as f64;
+    let norm = Matrix3::new(
+        scale,
+        0.0,
+        -scale * resolution.width() as f64 / 2.0,
+        0.0,
+        scale,
+        -scale * resolution.height() as f64 / 2.0,
+        0.0,
+        0.0,
+        1.0,
+    );
+
+    let v = |h: &Matrix3<f64>, i: usize, j: usize| {
+        let (hi, hj) = (h.column(i), h.column(j));
+        [
+            hi[0] * hj[0],
+            hi[0] * hj[1] + hi[1] * hj[0],
+            hi[1] * hj[1],
+            hi[2] * hj[0] + hi[0] * hj[2],
+            hi[2] * hj[1] + hi[1] * hj[2],
+            hi[2] * hj[2],
+        ]
+    };
+    let mut rows = Vec::new();
+    for h in homographies {
+        let h = norm * h;
+        let (v12, v11, v22) = (v(&h, 0, 1), v(&h, 0, 0), v(&h, 1, 1));
+        rows.push(v12);
+        rows.push(std::array::from_fn(|k| v11[k] - v22[k]));
+    }
+    // Zero skew: B12 = 0.
+    rows.push([0.0, 1.0, 0.0, 0.0, 0.0, 0.0]);
+    let v = DMatrix::from_fn(rows.len(), 6, |r, c| rows[r][c]);
+
+    let mut b = null_vector(v.transpose() * v);
+    if b[0] < 0.0 {
+        b = -b;
+    }
+    let [b11, b12, b22, b13, b23, b33] = [b[0], b[1], b[2], b[3], b[4], b[5]];
+    let v0 = (b12 * b13 - b11 * b23) / (b11 * b22 - b12 * b12);
+    let lambda = b33 - (b13 * b13 + v0 * (b12 * b13 - b11 * b23)) / b11;
+    let alpha = (lambda / b11).sqrt();
+    let beta = (lambda * b11 / (b11 * b22 - b12 * b12)).sqrt();
+    let u0 = -b13 * alpha * alpha / lambda;
+    if ![alpha, beta, u0, v0].iter().all(|v| v.is_finite()) || alpha <= 0.0 || beta <= 0.0 {
+        bail!(
+            "views do not constrain the camera intrinsics; tilt the board in different directions"
+        );
+    }
+
+    let intrinsics = Matrix3::new(alpha, 0.0, u0, 0.0, beta, v0, 0.0, 0.0, 1.0);
+    Ok(norm.try_inverse().unwrap() * intrinsics)
+}
+
+/// Computes the pose of the board from its homography and the camera matrix.
+fn board_pose(
+    intrinsics: &Matrix3<f64>,
+    homography: &Matrix3<f64>,
+) -> Option<(Rotation3<f64>, Vector3<f64>)> {
+    let inv = intrinsics.try_inverse()?;
+    let h1 = inv * homography.column(0);
+    let h2 = inv * homography.column(1);
+    let h3 = inv * homography.column(2);
+    let mut lambda = 1.0 / h1.norm();
+    // The board must be in front of the camera.
+    if h3.z < 0.0 {
+        lambda = -lambda;
+    }
+    let r1 = h1 * lambda;
+    let r2 = h2 * lambda;
+    let r3 = r1.cross(&r2);
+    let rotation = Rotation3::from_matrix(&Matrix3::from_columns(&[r1, r2, r3]));
+    Some((rotation, h3 * lambda))
+}
+
+/// The nonlinear least-squares problem solved during calibration.
+///
+/// The parameter vector consists of the [`CAMERA_PARAMS`] camera parameters, followed by
+/// [`POSE_PARAMS`] parameters for each view.
+struct Problem<'a> {
+    object_points: &'a [Vector3<f64>],
+    views: &'a [Vec<Vector2<f64>>],
+    params: Vec<f64>,
+}
+
+impl Problem<'_> {
+    /// Writes the reprojection residuals of view `index` to `out`.
+    fn view_residuals(&self, params: &[f64], index: usize, out: &mut [f64]) {
+        let [fx, fy, cx, cy, k1, k2, p1, p2] = std::array::from_fn(|i| params[i]);
+        let pose = &params[CAMERA_PARAMS + index * POSE_PARAMS..][..POSE_PARAMS];
+        let rotation = Rotation3::from_scaled_axis(Vector3::new(pose[0], pose[1], pose[2]));
+        let translation = Vector3::new(pose[3], pose[4], pose[5]);
+
+        for (i, (object, image)) in self
+            .object_points
+            .iter()
+            .zip(&self.views[index])
+            .enumerate()
+        {
+            let p = rotation * object + translation;
+            let (x, y) = (p.x / p.z, p.y / p.z);
+            let r2 = x * x + y * y;
+            let radial = 1.0 + r2 * (k1 + r2 * k2);
+            let xd = x * radial + 2.0 * p1 * x * y + p2 * (r2 + 2.0 * x * x);
+            let yd = y * radial + p1 * (r2 + 2.0 * y * y) + 2.0 * p2 * x * y;
+            out[2 * i] = fx * xd + cx - image.x;
+            out[2 * i + 1] = fy * yd + cy - image.y;
+        }
+    }
+
+    fn residuals(&self, params: &[f64]) -> DVector<f64> {
+        let per_view = self.object_points.len() * 2;
+        let mut out = DVector::zeros(per_view * self.views.len());
+        for index in 0..self.views.len() {
+            let range = index * per_view..(index + 1) * per_view;
+            self.view_residuals(params, index, &mut out.as_mut_slice()[range]);
+        }
+        out
+    }
+
+    /// Computes the Jacobian of the residuals with central differences.
+    ///
+    /// Pose parameters only affect the residuals of their own view, so only those are evaluated.
+    fn jacobian(&self) -> DMatrix<f64> {
+        let per_view = self.object_points.len() * 2;
+        let mut jacobian = DMatrix::zeros(per_view * self.views.len(), self.params.len());
+        let mut params = self.params.clone();
+        let (mut plus, mut minus) = (vec![0.0; per_view], vec![0.0; per_view]);
+        for col in 0..params.len() {
+            let step = 1e-6 * (1.0 + params[col].abs());
+            let views = if col < CAMERA_PARAMS {
+                0..self.views.len()
+            } else {
+                let view = (col - CAMERA_PARAMS) / POSE_PARAMS;
+                view..view + 1
+            };
+            for view in views {
+                let original = params[col];
+                params[col] = original + step;
+                self.view_residuals(&params, view, &mut plus);
+                params[col] = original - step;
+                self.view_residuals(&params, view, &mut minus);
+                params[col] = original;
+                for (i, (p, m)) in plus.iter().zip(&minus).enumerate() {
+                    jacobian[(view * per_view + i, col)] = (p - m) / (2.0 * step);
+                }
+            }
+        }
+        jacobian
+    }
+
+    /// Minimizes the reprojection error with the Levenberg–Marquardt algorithm, and returns the
+    /// final RMS reprojection error in pixels.
+    fn optimize(&mut self) -> f64 {
+        let mut residuals = self.residuals(&self.params);
+        let mut cost = residuals.norm_squared();
+        let mut damping = 1e-3;
+        for _ in 0..MAX_ITERATIONS {
+            let jacobian = self.jacobian();
+            let jtj = jacobian.transpose() * &jacobian;
+            let jtr = jacobian.transpose() * &residuals;
+
+            let mut improved = false;
+            while damping < 1e10 {
+                let mut lhs = jtj.clone();
+                for i in 0..lhs.nrows() {
+                    lhs[(i, i)] += damping * jtj[(i, i)].max(1e-9);
+                }
+                let Some(step) = lhs.cholesky().map(|c| c.solve(&-&jtr)) else {
+                    damping *= 10.0;
+                    continue;
+                };
+                let params = self
+                    .params
+                    .iter()
+                    .zip(step.iter())
+                    .map(|(p, s)| p + s)
+                    .collect::<Vec<_>>();
+                let new_residuals = self.residuals(&params);
+                let new_cost = new_residuals.norm_squared();
+                if new_cost < cost {
+                    let converged = cost - new_cost < 1e-12 * cost;
+                    self.params = params;
+                    residuals = new_residuals;
+                    cost = new_cost;
+                    damping = (damping / 10.0).max(1e-12);
+                    improved = !converged;
+                    break;
+                }
+                damping *= 10.0;
+            }
+            if !improved {
+                break;
+            }
+        }
+
+        (cost / (residuals.len() / 2) as f64).sqrt()
+    }
+}
+
+/// The result of a camera calibration.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calibration {
+    resolution: Resolution,
+    camera: CameraModel,
+    reprojection_error: f32,
+}
+
+impl Calibration {
+    /// Creates a calibration for a camera with a known camera model.
+    pub fn new(resolution: Resolution, camera: CameraModel) -> Self {
+        Self {
+            resolution,
+            camera,
+            reprojection_error: 0.0,
+        }
+    }
+
+    /// Returns the resolution of the images the camera was calibrated with.
+    ///
+    /// The [`CameraModel`] is only valid for images of this resolution.
+    #[inline]
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    /// Returns the calibrated camera model.
+    #[inline]
+    pub fn camera(&self) -> &CameraModel {
+        &self.camera
+    }
+
+    /// Returns the root-mean-square reprojection error of the calibration views, in pixels.
+    ///
+    /// Good calibrations typically have an error below 0.5 pixels.
+    #[inline]
+    pub fn reprojection_error(&self) -> f32 {
+        self.reprojection_error
+    }
+
+    /// Parses a calibration from its JSON representation.
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let file: CalibrationFile = serde_json::from_str(json)?;
+        Ok(Self {
+            resolution: Resolution::new(file.width, file.height),
+            camera: CameraModel::new(file.focal_length, file.principal_point).with_distortion(
+                Distortion::new(file.radial_distortion, file.tangential_distortion),
+            ),
+            reprojection_error: file.reprojection_error,
+        })
+    }
+
+    /// Serializes this calibration to JSON.
+    pub fn to_json(&self) -> String {
+        let file = CalibrationFile {
+            width: self.resolution.width(),
+            height: self.resolution.height(),
+            focal_length: self.camera.focal_length(),
+            principal_point: self.camera.principal_point(),
+            radial_distortion: self.camera.distortion().radial(),
+            tangential_distortion: self.camera.distortion().tangential(),
+            reprojection_error: self.reprojection_error,
+        };
+        serde_json::to_string_pretty(&file).unwrap()
+    }
+
+    /// Loads a calibration from a JSON file.
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("failed to read '{}'", path.display()))?;
+        Self::from_json(&json).with_context(|| format!("failed to parse '{}'", path.display()))
+    }
+
+    /// Writes this calibration to a JSON file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_json())
+            .with_context(|| format!("failed to write '{}'", path.display()))
+    }
+}
+
+/// On-disk representation of a [`Calibration`].
+#[derive(Serialize, Deserialize)]
+struct CalibrationFile {
+    width: u32,
+    height: u32,
+    focal_length: [f32; 2],
+    principal_point: [f32; 2],
+    radial_distortion: [f32; 3],
+    tangential_distortion: [f32; 2],
+    #[serde(default)]
+    reprojection_error: f32,
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use zaru_image::Image;
+
+    use super::*;
+
+    const WIDTH: u32 = 640;
+    const HEIGHT: u32 = 480;
+
+    fn board() -> Checkerboard {
+        Checkerboard::new(7, 5, 30.0)
+    }
+
+    /// Computes the pose of a board that is rotated by the given Euler angles (in degrees) around
+    /// its center, with the center placed at `position` in camera space.
+    fn pose(
+        board: &Checkerboard,
+        [x, y, z]: [f32; 3],
+        position: [f32; 3],
+    ) -> (Rotation3<f32>, Vector3<f32>) {
+        let rotation = Rotation3::from_euler_angles(x.to_radians(), y.to_radians(), z.to_radians());
+        let center = Vector3::new(
+            (board.columns() - 1) as f32 * board.square_size() / 2.0,
+            (board.rows() - 1) as f32 * board.square_size() / 2.0,
+            0.0,
+        );
+        (rotation, Vector3::from(position) - rotation * center)
+    }
+
+    /// Renders the checkerboard as seen by `camera`.
+    fn render(
+        camera: &CameraModel,
+        board: &Checkerboard,
+        (rotation, translation): (Rotation3<f32>, Vector3<f32>),
+    ) -> Image {
+        const SUBSAMPLES: u32 = 4;
+
+        // Undistorting is expensive, so only compute the rays through the pixel corners, and
+        // interpolate between them. Pixel centers are at integer coordinates.
+        let corner_rays = (0..=HEIGHT)
+            .flat_map(|y| (0..=WIDTH).map(move |x| (x, y)))
+            .map(|(x, y)| camera.pixel_to_normalized([x as f32 - 0.5, y as f32 - 0.5]))
+            .collect::<Vec<_>>();
+        let corner_ray = |x: u32, y: u32| corner_rays[(y * (WIDTH + 1) + x) as usize];
+
+        // Rays are intersected with the board plane, and the intersection is transformed into board
+        // space. This is written out with scalars, since it's too slow in unoptimized builds
+        // otherwise.
+        let normal: [f32; 3] = (rotation * Vector3::z()).into();
+        let inverse = rotation.inverse().into_inner();
+        let offset: [f32; 3] = (-(inverse * translation)).into();
+        let inverse: [[f32; 3]; 3] = inverse.transpose().into();
+        let plane_dist = (rotation * Vector3::z()).dot(&translation);
+        let size = board.square_size();
+        let inside = |v: f32, max: u32| v >= 0.0 && v <= max as f32;
+        let margin = |v: f32, max: u32| v >= -1.0 && v <= max as f32 + 1.0;
+        let mut buf = Vec::with_capacity((WIDTH * HEIGHT * 4) as usize);
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let (tl, tr) = (corner_ray(x, y), corner_ray(x + 1, y));
+                let (bl, br) = (corner_ray(x, y + 1), corner_ray(x + 1, y + 1));
+                let mut sum = 0.0;
+                for sy in 0..SUBSAMPLES {
+                    for sx in 0..SUBSAMPLES {
+                        let fx = (sx as f32 + 0.5) / SUBSAMPLES as f32;
+                        let fy = (sy as f32 + 0.5) / SUBSAMPLES as f32;
+                        let [rx, ry] = [0, 1].map(|i| {
+                            let top = tl[i] * (1.0 - fx) + tr[i] * fx;
+                            let bottom = bl[i] * (1.0 - fx) + br[i] * fx;
+                            top * (1.0 - fy) + bottom * fy
+                        });
+
+                        let t = plane_dist / (normal[0] * rx + normal[1] * ry + normal[2]);
+                        let [px, py] = [0, 1].map(|i| {
+                            (inverse[i][0] * rx + inverse[i][1] * ry + inverse[i][2]) * t
+                                + offset[i]
+                        });
+                        let (col, row) = ((px / size).floor() + 1.0, (py / size).floor() + 1.0);
+                        sum += if inside(col, board.columns()) && inside(row, board.rows()) {
+                            ((col + row) as i32 % 2) as f32
+                        } else if margin(col, board.columns()) && margin(row, board.rows()) {
+                            1.0
+                        } else {
+                            0.5
+                        };
+                    }
+                }
+                let v = (sum / (SUBSAMPLES * SUBSAMPLES) as f32 * 255.0).round() as u8;
+                buf.extend_from_slice(&[v, v, v, 255]);
+            }
+        }
+        Image::from_rgba8(Resolution::new(WIDTH, HEIGHT), &buf)
+    }
+
+    #[test]
+    fn test_detect() {
+        let camera = CameraModel::new([600.0, 600.0], [320.0, 240.0]);
+        let board = board();
+        let pose = pose(&board, [10.0, -15.0, 5.0], [0.0, 0.0, 500.0]);
+        let image = render(&camera, &board, pose);
+        let corners = board.detect(&image).expect("board not detected");
+
+        let (rotation, translation) = pose;
+        for (corner, [x, y]) in corners.iter().zip(board.object_points()) {
+            let expected = camera.project(rotation * Vector3::new(x, y, 0.0) + translation);
+            assert_relative_eq!(corner[0], expected[0], epsilon = 0.1);
+            assert_relative_eq!(corner[1], expected[1], epsilon = 0.1);
+        }
+    }
+
+    #[test]
+    fn test_detect_rejects_partial_board() {
+        let camera = CameraModel::new([600.0, 600.0], [320.0, 240.0]);
+        let board = board();
+        let image = render(&camera, &board, pose(&board, [0.0; 3], [0.0, 0.0, 150.0]));
+        assert!(board.detect(&image).is_none());
+    }
+
+    #[test]
+    fn test_calibrate() {
+        let camera = CameraModel::new([620.0, 610.0], [330.0, 235.0])
+            .with_distortion(Distortion::new([-0.15, 0.05, 0.0], [0.001, -0.0005]));
+        let board = board();
+        let mut calibrator = Calibrator::new(board);
+        for (angles, position) in [
+            ([0.0, 0.0, 0.0], [0.0, 0.0, 450.0]),
+            ([25.0, 0.0, 0.0], [-80.0, -60.0, 450.0]),
+            ([-25.0, 5.0, 10.0], [80.0, 60.0, 450.0]),
+            ([0.0, 30.0, -5.0], [-90.0, 60.0, 450.0]),
+            ([5.0, -30.0, 0.0], [90.0, -60.0, 450.0]),
+            ([20.0, 20.0, 30.0], [0.0, 0.0, 400.0]),
+        ] {
+            let image = render(&camera, &board, pose(&board, angles, position));
+            assert!(calibrator.add_image(&image), "board not detected");
+        }
+
+        let calibration = calibrator.calibrate().unwrap();
+        let result = calibration.camera();
+        assert!(calibration.reprojection_error() < 0.1);
+        assert_relative_eq!(result.focal_length()[0], 620.0, max_relative = 0.01);
+        assert_relative_eq!(result.focal_length()[1], 610.0, max_relative = 0.01);
+        assert_relative_eq!(result.principal_point()[0], 330.0, epsilon = 3.0);
+        assert_relative_eq!(result.principal_point()[1], 235.0, epsilon = 3.0);
+        assert_relative_eq!(result.distortion().radial()[0], -0.15, epsilon = 0.02);
+        assert_relative_eq!(result.distortion().radial()[1], 0.05, epsilon = 0.05);
+    }
+
+    #[test]
+    fn test_calibrate_needs_views() {
+        let mut calibrator = Calibrator::new(board());
+        let corners = board().object_points().collect::<Vec<_>>();
+        calibrator.add_corners(Resolution::new(WIDTH, HEIGHT), corners);
+        assert!(calibrator.calibrate().is_err());
+    }
+
+    #[test]
+    fn test_json_roundtrip() {
+        let camera = CameraModel::new([620.0, 610.0], [330.0, 235.0])
+            .with_distortion(Distortion::new([-0.15, 0.05, 0.0], [0.001, -0.0005]));
+        let calibration = Calibration::new(Resolution::new(WIDTH, HEIGHT), camera);
+        let parsed = Calibration::from_json(&calibration.to_json()).unwrap();
+        assert_eq!(parsed, calibration);
+    }
+}
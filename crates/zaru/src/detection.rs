@@ -3,12 +3,120 @@
 //! The functionality defined in this module (and submodules) is meant to be reusable across
 //! different detectors.
 
+pub mod annotation;
+pub mod augment;
+pub mod eval;
 pub mod nms;
 pub mod ssd;
+pub mod tiling;
+pub mod tracking;
 
-use zaru_image::{Rect, Resolution};
+mod geometry;
+#[cfg(test)]
+mod test;
+
+use nalgebra::{Point2, Rotation2, Vector2};
+use serde::{Deserialize, Serialize};
+use zaru_image::{ImageView, Rect, Resolution, RotatedRect};
+
+use crate::{nn::point_to_img, timer::Timer};
+
+use self::nms::NonMaxSuppression;
+
+/// Common interface of Zaru's object detectors.
+///
+/// Every detector in Zaru ([`crate::face::detection::Detector`],
+/// [`crate::hand::detection::PalmDetector`], and [`crate::body::detection::PoseDetector`])
+/// implements this trait. It allows writing detection strategies like [`tiling::TiledDetector`],
+/// as well as tracking and evaluation code, once, and using it with any detector.
+pub trait Detector {
+    /// The type of detection produced by this detector.
+    type Detection: DetectionLike;
+
+    /// Returns the expected input resolution of the detector's neural network.
+    fn input_resolution(&self) -> Resolution;
+
+    /// Runs the detector on `image`, returning the filtered detections.
+    ///
+    /// The image will be scaled to the input size expected by the neural network, and detections
+    /// will be back-mapped to input image coordinates.
+    fn detect(&mut self, image: ImageView<'_>) -> &[Self::Detection];
+
+    /// Returns profiling timers for this detector.
+    fn timers(&self) -> Box<dyn Iterator<Item = &Timer> + '_>;
+
+    /// Returns the confidence threshold below which detections are discarded.
+    fn threshold(&self) -> f32;
+
+    /// Sets the confidence threshold below which detections are discarded.
+    ///
+    /// Every detector comes with a default threshold that works well for most applications.
+    fn set_threshold(&mut self, threshold: f32);
+
+    /// Returns the [`NonMaxSuppression`] used to filter the detector's raw output.
+    ///
+    /// This can be used to configure the IoU threshold and suppression mode.
+    fn nms_mut(&mut self) -> &mut NonMaxSuppression;
+}
 
-use crate::nn::point_to_img;
+/// Common interface of the detection types returned by [`Detector`]s.
+///
+/// All of these wrap a [`RawDetection`] together with the resolution of the image the detection
+/// was performed on. The [`RawDetection`] uses coordinates in range 0.0 to 1.0, relative to the
+/// image after it was padded to a square aspect ratio (like
+/// [`aspect_aware_resize`][zaru_image::Image::aspect_aware_resize] does).
+///
+/// The provided methods map the [`RawDetection`] to the coordinate system of the image the
+/// detection was performed on. Detection types may override them with more appropriate
+/// implementations.
+pub trait DetectionLike: Clone {
+    /// Creates a detection from a [`RawDetection`] located in an image of resolution `full_res`.
+    fn from_raw(raw: RawDetection, full_res: Resolution) -> Self;
+
+    /// Returns the underlying [`RawDetection`].
+    fn raw(&self) -> &RawDetection;
+
+    /// Returns the resolution of the image this detection was performed on.
+    fn full_resolution(&self) -> Resolution;
+
+    /// Returns the confidence of this detection (typically in range 0 to 1).
+    fn confidence(&self) -> f32 {
+        self.raw().confidence()
+    }
+
+    /// Returns the axis-aligned bounding rectangle of the detected object.
+    fn bounding_rect(&self) -> Rect {
+        self.raw().bounding_rect().to_rect(&self.full_resolution())
+    }
+
+    /// Returns the clockwise rotation of the detected object, in radians.
+    ///
+    /// If the detector does not estimate object rotation, this returns 0.0.
+    fn rotation_radians(&self) -> f32 {
+        self.raw().rotation_radians()
+    }
+
+    /// Returns the bounding rectangle of the detected object, rotated by
+    /// [`DetectionLike::rotation_radians`].
+    fn rotated_bounding_rect(&self) -> RotatedRect {
+        self.raw()
+            .rotated_bounding_rect()
+            .to_rotated_rect_in(&self.full_resolution())
+    }
+
+    /// Returns an iterator over the keypoints of this detection.
+    ///
+    /// The meaning of each keypoint depends on the detector that produced it.
+    fn keypoints(&self) -> Box<dyn Iterator<Item = (i32, i32)> + '_> {
+        let full_res = self.full_resolution();
+        Box::new(
+            self.raw()
+                .keypoints()
+                .iter()
+                .map(move |kp| point_to_img(kp.x(), kp.y(), &full_res)),
+        )
+    }
+}
 
 /// A detected object.
 ///
@@ -20,13 +128,19 @@ use crate::nn::point_to_img;
 /// consulted). The confidence value is used when performing non-maximum suppression with
 /// [`nms::SuppressionMode::Average`], so it has to have the expected range when making use of that.
 ///
+/// A detection can optionally carry a clockwise rotation (in radians). The rotation is applied
+/// around the center of the [`BoundingRect`], and is used by [`RawDetection::rotated_bounding_rect`]
+/// and rotation-aware non-maximum suppression ([`nms::IouMode::Rotated`]). It defaults to 0.
+///
 /// Called "raw" because it does not reside in any defined coordinate system. Detector
 /// implementations typically provide a wrapper around this type that allows accessing the detection
 /// as a [`Rect`] in input image coordinates.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RawDetection {
     confidence: f32,
     rect: BoundingRect,
+    #[serde(default)]
+    radians: f32,
     keypoints: Vec<Keypoint>,
 }
 
@@ -35,6 +149,7 @@ impl RawDetection {
         Self {
             confidence,
             rect,
+            radians: 0.0,
             keypoints: Vec::new(),
         }
     }
@@ -43,6 +158,7 @@ impl RawDetection {
         Self {
             confidence,
             rect,
+            radians: 0.0,
             keypoints,
         }
     }
@@ -67,6 +183,38 @@ impl RawDetection {
         self.rect = rect;
     }
 
+    /// Returns the clockwise rotation of the detected object, in radians.
+    pub fn rotation_radians(&self) -> f32 {
+        self.radians
+    }
+
+    pub fn set_rotation_radians(&mut self, radians: f32) {
+        self.radians = radians;
+    }
+
+    /// Sets the rotation of this detection so that the vector from keypoint `start` to keypoint
+    /// `end` points upwards when the rotation is undone.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `start` or `end` are out of bounds of the keypoint list.
+    pub fn set_rotation_from_keypoints(&mut self, start: usize, end: usize) {
+        let start = self.keypoints[start];
+        let end = self.keypoints[end];
+        let rel = Vector2::new(end.x - start.x, end.y - start.y);
+        self.radians = Rotation2::rotation_between(&-Vector2::y(), &rel).angle();
+    }
+
+    /// Returns the [`BoundingRect`] of this detection, rotated by its rotation.
+    pub fn rotated_bounding_rect(&self) -> RotatedBoundingRect {
+        RotatedBoundingRect::new(self.rect, self.radians)
+    }
+
+    pub fn set_rotated_bounding_rect(&mut self, rect: RotatedBoundingRect) {
+        self.rect = rect.rect;
+        self.radians = rect.radians;
+    }
+
     pub fn keypoints(&self) -> &[Keypoint] {
         &self.keypoints
     }
@@ -86,7 +234,7 @@ impl RawDetection {
 ///
 /// Not all detectors output keypoints. Some may just output bounding rectangles with confidence
 /// scores.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Keypoint {
     x: f32,
     y: f32,
@@ -109,7 +257,7 @@ impl Keypoint {
 /// Axis-aligned bounding rectangle of a detected object.
 ///
 /// This primarily differs from [`Rect`] in that it uses float coordinates instead of integers.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct BoundingRect {
     xc: f32,
     yc: f32,
@@ -123,6 +271,11 @@ impl BoundingRect {
         Self { xc, yc, w, h }
     }
 
+    /// Creates a bounding rectangle with its top left corner at `(x,y)`.
+    pub fn from_top_left(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Self::from_center(x + w / 2.0, y + h / 2.0, w, h)
+    }
+
     pub(crate) fn grow_rel(&self, left: f32, right: f32, top: f32, bottom: f32) -> Self {
         let left = left * self.w;
         let right = right * self.w;
@@ -157,11 +310,26 @@ impl BoundingRect {
         Rect::from_corners(top_left, bottom_right)
     }
 
-    fn top_left(&self) -> (f32, f32) {
+    /// Returns the center coordinates of `self`.
+    pub fn center(&self) -> (f32, f32) {
+        (self.xc, self.yc)
+    }
+
+    pub fn width(&self) -> f32 {
+        self.w
+    }
+
+    pub fn height(&self) -> f32 {
+        self.h
+    }
+
+    /// Returns the coordinates of the top left corner of `self`.
+    pub fn top_left(&self) -> (f32, f32) {
         (self.xc - self.w / 2.0, self.yc - self.h / 2.0)
     }
 
-    fn bottom_right(&self) -> (f32, f32) {
+    /// Returns the coordinates of the bottom right corner of `self`.
+    pub fn bottom_right(&self) -> (f32, f32) {
         (self.xc + self.w / 2.0, self.yc + self.h / 2.0)
     }
 
@@ -194,7 +362,9 @@ impl BoundingRect {
     }
 
     fn intersection_area(&self, other: &Self) -> f32 {
-        self.intersection(other).area()
+        // Disjoint rectangles produce a negative width and/or height.
+        let i = self.intersection(other);
+        i.w.max(0.0) * i.h.max(0.0)
     }
 
     fn union_area(&self, other: &Self) -> f32 {
@@ -207,8 +377,189 @@ impl BoundingRect {
     }
 }
 
+/// A [`BoundingRect`], rotated clockwise around its center.
+///
+/// This is the floating-point equivalent of [`RotatedRect`]. Converting a [`RotatedRect`] to a
+/// [`RotatedBoundingRect`] and back via [`RotatedBoundingRect::to_rotated_rect`] is lossless.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RotatedBoundingRect {
+    rect: BoundingRect,
+    radians: f32,
+}
+
+impl RotatedBoundingRect {
+    /// Creates a new rotated bounding rectangle.
+    ///
+    /// `radians` is the clockwise rotation to apply to `rect`.
+    pub fn new(rect: BoundingRect, radians: f32) -> Self {
+        Self { rect, radians }
+    }
+
+    /// Returns the underlying non-rotated [`BoundingRect`].
+    pub fn rect(&self) -> BoundingRect {
+        self.rect
+    }
+
+    /// Returns the clockwise rotation in radians.
+    pub fn rotation_radians(&self) -> f32 {
+        self.radians
+    }
+
+    /// Returns the amount of area covered by `self`.
+    pub fn area(&self) -> f32 {
+        self.rect.area()
+    }
+
+    /// Returns the rotated rectangle's corners.
+    ///
+    /// The order is: top-left, top-right, bottom-right, bottom-left, as seen from the non-rotated
+    /// rect (same as [`RotatedRect::rotated_corners`]).
+    pub fn rotated_corners(&self) -> [(f32, f32); 4] {
+        let BoundingRect { xc, yc, w, h } = self.rect;
+        let center = Point2::new(xc, yc);
+        let rotation = Rotation2::new(self.radians);
+        [
+            (-w / 2.0, -h / 2.0),
+            (w / 2.0, -h / 2.0),
+            (w / 2.0, h / 2.0),
+            (-w / 2.0, h / 2.0),
+        ]
+        .map(|(x, y)| {
+            let p = center + rotation * Vector2::new(x, y);
+            (p.x, p.y)
+        })
+    }
+
+    /// Computes the exact Intersection over Union (IOU) of `self` and `other`, taking the rotation
+    /// of both rectangles into account.
+    ///
+    /// Returns 0.0 if both rectangles have an area of 0.
+    pub fn iou(&self, other: &Self) -> f32 {
+        let intersection = polygon_area(&clip_convex(
+            &self.rotated_corners(),
+            &other.rotated_corners(),
+        ));
+        let union = self.area() + other.area() - intersection;
+        if union <= 0.0 {
+            return 0.0;
+        }
+        intersection / union
+    }
+
+    /// Converts this rectangle to a [`RotatedRect`], interpreting its coordinates as pixel
+    /// coordinates.
+    pub fn to_rotated_rect(&self) -> RotatedRect {
+        let (x, y) = self.rect.top_left();
+        RotatedRect::new(
+            Rect::from_top_left(
+                x.round() as i32,
+                y.round() as i32,
+                self.rect.w.round() as u32,
+                self.rect.h.round() as u32,
+            ),
+            self.radians,
+        )
+    }
+
+    /// Converts this rectangle to a [`RotatedRect`] in the coordinates of an image with resolution
+    /// `full_res`, assuming the rectangle is in normalized network coordinates (like
+    /// [`BoundingRect`]s output by detectors).
+    pub(crate) fn to_rotated_rect_in(&self, full_res: &Resolution) -> RotatedRect {
+        RotatedRect::new(self.rect.to_rect(full_res), self.radians)
+    }
+}
+
+impl From<Rect> for BoundingRect {
+    fn from(rect: Rect) -> Self {
+        Self::from_top_left(
+            rect.x() as f32,
+            rect.y() as f32,
+            rect.width() as f32,
+            rect.height() as f32,
+        )
+    }
+}
+
+impl From<BoundingRect> for RotatedBoundingRect {
+    fn from(rect: BoundingRect) -> Self {
+        Self::new(rect, 0.0)
+    }
+}
+
+impl From<RotatedRect> for RotatedBoundingRect {
+    fn from(rect: RotatedRect) -> Self {
+        let r = rect.rect();
+        let (w, h) = (r.width() as f32, r.height() as f32);
+        Self::new(
+            BoundingRect::from_center(r.x() as f32 + w / 2.0, r.y() as f32 + h / 2.0, w, h),
+            rect.rotation_radians(),
+        )
+    }
+}
+
+/// Clips the convex polygon `subject` against the convex polygon `clip` (Sutherland-Hodgman).
+///
+/// Both polygons must use the same winding order.
+fn clip_convex(subject: &[(f32, f32)], clip: &[(f32, f32)]) -> Vec<(f32, f32)> {
+    // Orientation of the clip polygon, so that the inside test works for both winding orders.
+    let orientation = polygon_signed_area(clip).signum();
+    let cross = |a: (f32, f32), b: (f32, f32), p: (f32, f32)| {
+        ((b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0)) * orientation
+    };
+
+    let mut output = subject.to_vec();
+    for i in 0..clip.len() {
+        if output.is_empty() {
+            break;
+        }
+        let a = clip[i];
+        let b = clip[(i + 1) % clip.len()];
+
+        let input = std::mem::take(&mut output);
+        for j in 0..input.len() {
+            let cur = input[j];
+            let prev = input[(j + input.len() - 1) % input.len()];
+            let d_cur = cross(a, b, cur);
+            let d_prev = cross(a, b, prev);
+
+            if d_cur >= 0.0 {
+                if d_prev < 0.0 {
+                    output.push(lerp_point(prev, cur, d_prev / (d_prev - d_cur)));
+                }
+                output.push(cur);
+            } else if d_prev >= 0.0 {
+                output.push(lerp_point(prev, cur, d_prev / (d_prev - d_cur)));
+            }
+        }
+    }
+
+    output
+}
+
+fn lerp_point(a: (f32, f32), b: (f32, f32), t: f32) -> (f32, f32) {
+    (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+}
+
+fn polygon_signed_area(points: &[(f32, f32)]) -> f32 {
+    let mut area = 0.0;
+    for i in 0..points.len() {
+        let (x0, y0) = points[i];
+        let (x1, y1) = points[(i + 1) % points.len()];
+        area += x0 * y1 - x1 * y0;
+    }
+    area / 2.0
+}
+
+fn polygon_area(points: &[(f32, f32)]) -> f32 {
+    polygon_signed_area(points).abs()
+}
+
 #[cfg(test)]
 mod tests {
+    use std::f32::consts::{FRAC_1_SQRT_2, FRAC_PI_2, FRAC_PI_4};
+
+    use approx::assert_relative_eq;
+
     use super::*;
 
     #[test]
@@ -261,6 +612,35 @@ mod tests {
         assert_eq!(ac.yc, 0.0);
         assert_eq!(ac.w, 0.5);
         assert_eq!(ac.h, 1.0);
+
+        // Disjoint along both axes.
+        let d = BoundingRect::from_center(3.0, 3.0, 1.0, 1.0);
+        assert_eq!(a.intersection_area(&d), 0.0);
+        assert_eq!(a.iou(&d), 0.0);
+    }
+
+    #[test]
+    fn test_disjoint_iou() {
+        let a = BoundingRect::from_center(0.5, 0.5, 1.0, 1.0);
+
+        // Diagonally disjoint: both the width and height of the intersection are negative, so their
+        // product must not be used as the intersection area.
+        let b = BoundingRect::from_center(3.5, 3.5, 1.0, 1.0);
+        assert_eq!(a.intersection_area(&b), 0.0);
+        assert_eq!(a.iou(&b), 0.0);
+
+        // Disjoint along only one axis.
+        let c = BoundingRect::from_center(3.5, 0.5, 1.0, 1.0);
+        assert_eq!(a.intersection_area(&c), 0.0);
+        assert_eq!(a.iou(&c), 0.0);
+
+        // Touching at a corner or along an edge.
+        let d = BoundingRect::from_center(1.5, 1.5, 1.0, 1.0);
+        assert_eq!(a.intersection_area(&d), 0.0);
+        assert_eq!(a.iou(&d), 0.0);
+        let e = BoundingRect::from_center(1.5, 0.5, 1.0, 1.0);
+        assert_eq!(a.intersection_area(&e), 0.0);
+        assert_eq!(a.iou(&e), 0.0);
     }
 
     #[test]
@@ -299,4 +679,63 @@ mod tests {
         assert_eq!(smaller.iou(&bigger), 1.0 / 4.0);
         assert_eq!(bigger.iou(&smaller), 1.0 / 4.0);
     }
+
+    #[test]
+    fn test_rotated_iou() {
+        let rect = BoundingRect::from_center(3.0, -2.0, 1.0, 1.0);
+
+        // Without rotation, the result must match the axis-aligned IoU.
+        let a = RotatedBoundingRect::from(rect);
+        let b = RotatedBoundingRect::from(BoundingRect::from_center(3.5, -2.0, 1.0, 1.0));
+        assert_relative_eq!(a.iou(&b), rect.iou(&b.rect()));
+
+        // Identical rotated rects overlap completely.
+        let a = RotatedBoundingRect::new(rect, 0.7);
+        assert_relative_eq!(a.iou(&a), 1.0, epsilon = 1e-5);
+
+        // A square and the same square rotated by 45° intersect in a regular octagon.
+        let b = RotatedBoundingRect::new(rect, FRAC_PI_4);
+        let c = RotatedBoundingRect::from(rect);
+        assert_relative_eq!(b.iou(&c), FRAC_1_SQRT_2, epsilon = 1e-5);
+        assert_relative_eq!(c.iou(&b), FRAC_1_SQRT_2, epsilon = 1e-5);
+
+        // Parallel diagonal strips don't overlap, even though their unrotated rects do.
+        let strip = |offset: f32| {
+            RotatedBoundingRect::new(BoundingRect::from_center(offset, 0.0, 1.0, 0.1), FRAC_PI_4)
+        };
+        assert!(strip(0.0).rect().iou(&strip(0.2).rect()) > 0.5);
+        assert_eq!(strip(0.0).iou(&strip(0.2)), 0.0);
+
+        let zero = RotatedBoundingRect::new(BoundingRect::from_center(0.0, 0.0, 0.0, 0.0), 1.0);
+        assert_eq!(zero.iou(&zero), 0.0);
+    }
+
+    #[test]
+    fn test_rotated_rect_roundtrip() {
+        for rect in [
+            RotatedRect::new(Rect::from_top_left(-5, 7, 10, 3), 0.0),
+            RotatedRect::new(Rect::from_top_left(100, 20, 33, 15), 1.234),
+            RotatedRect::new(Rect::from_top_left(0, 0, 1, 1), -3.0),
+        ] {
+            let rotated = RotatedBoundingRect::from(rect);
+            assert_eq!(rotated.to_rotated_rect(), rect);
+            assert_eq!(rotated.rotated_corners(), rect.rotated_corners());
+        }
+    }
+
+    #[test]
+    fn test_rotation_from_keypoints() {
+        let mut det = RawDetection::with_keypoints(
+            1.0,
+            BoundingRect::from_center(0.0, 0.0, 1.0, 1.0),
+            vec![Keypoint::new(0.0, 0.0), Keypoint::new(0.0, -1.0)],
+        );
+        det.set_rotation_from_keypoints(0, 1);
+        assert_relative_eq!(det.rotation_radians(), 0.0);
+
+        // Pointing right means the object is rotated clockwise by 90°.
+        det.keypoints_mut()[1] = Keypoint::new(1.0, 0.0);
+        det.set_rotation_from_keypoints(0, 1);
+        assert_relative_eq!(det.rotation_radians(), FRAC_PI_2);
+    }
 }
@@ -0,0 +1,268 @@
+//! Import and export of detections in common annotation formats.
+//!
+//! This module defines a format-independent representation of annotated images
+//! ([`ImageAnnotation`] and [`ObjectAnnotation`]), which can be created from the output of any
+//! [`Detector`][super::Detector] and converted back into [`RawDetection`]s.
+//!
+//! The submodules implement reading and writing of these annotations in the following formats:
+//!
+//! - [`coco`]: A single COCO JSON file describing a whole dataset.
+//! - [`voc`]: One Pascal VOC XML file per image.
+//! - [`yolo`]: One YOLO text file per image, plus a `classes.txt` file listing the class labels.
+//!
+//! This allows turning detection runs over image folders into pre-annotations for labelling tools,
+//! and reloading annotations created by other tools.
+//!
+//! All coordinates in this module are pixel coordinates of the annotated image.
+
+pub mod coco;
+pub mod voc;
+pub mod yolo;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use zaru_image::Resolution;
+
+use super::{
+    geometry::{from_pixels, scale, to_pixels},
+    BoundingRect, DetectionLike, Keypoint, RawDetection,
+};
+
+/// An annotated object in an image.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObjectAnnotation {
+    /// The class label of the object.
+    pub label: String,
+    /// Axis-aligned bounding rectangle of the object, in pixel coordinates.
+    pub rect: BoundingRect,
+    /// Detection confidence, if the annotation was produced by a detector.
+    pub confidence: Option<f32>,
+    /// Keypoints of the object, in pixel coordinates.
+    ///
+    /// Only the COCO format supports storing keypoints.
+    pub keypoints: Vec<Keypoint>,
+    /// Whether the object is hard to recognize and should be ignored during evaluation.
+    ///
+    /// This corresponds to the `difficult` flag in Pascal VOC and the `iscrowd` flag in COCO.
+    pub difficult: bool,
+}
+
+impl ObjectAnnotation {
+    /// Creates an annotation for an object of class `label` located at `rect`.
+    pub fn new(label: impl Into<String>, rect: BoundingRect) -> Self {
+        Self {
+            label: label.into(),
+            rect,
+            confidence: None,
+            keypoints: Vec::new(),
+            difficult: false,
+        }
+    }
+
+    /// Creates an annotation from a detection of an object of class `label`.
+    ///
+    /// The detection's rotation is not preserved.
+    pub fn from_detection<D: DetectionLike>(label: impl Into<String>, detection: &D) -> Self {
+        Self::from_raw(label, detection.raw(), detection.full_resolution())
+    }
+
+    /// Creates an annotation from a [`RawDetection`] made in an image of resolution `res`.
+    pub fn from_raw(label: impl Into<String>, raw: &RawDetection, res: Resolution) -> Self {
+        let rect = raw.bounding_rect();
+        let (xc, yc) = to_pixels(rect.xc, rect.yc, res);
+        let s = scale(res);
+        Self {
+            label: label.into(),
+            rect: BoundingRect::from_center(xc, yc, rect.w * s, rect.h * s),
+            confidence: Some(raw.confidence()),
+            keypoints: raw
+                .keypoints()
+                .iter()
+                .map(|kp| {
+                    let (x, y) = to_pixels(kp.x, kp.y, res);
+                    Keypoint::new(x, y)
+                })
+                .collect(),
+            difficult: false,
+        }
+    }
+
+    /// Converts this annotation to a [`RawDetection`] in an image of resolution `res`.
+    ///
+    /// The result can be turned into a detector-specific type with [`DetectionLike::from_raw`].
+    /// If the annotation has no confidence value, a confidence of 1.0 is used.
+    pub fn to_raw_detection(&self, res: Resolution) -> RawDetection {
+        let (xc, yc) = from_pixels(self.rect.xc, self.rect.yc, res);
+        let s = scale(res);
+        RawDetection::with_keypoints(
+            self.confidence.unwrap_or(1.0),
+            BoundingRect::from_center(xc, yc, self.rect.w / s, self.rect.h / s),
+            self.keypoints
+                .iter()
+                .map(|kp| {
+                    let (x, y) = from_pixels(kp.x, kp.y, res);
+                    Keypoint::new(x, y)
+                })
+                .collect(),
+        )
+    }
+}
+
+/// The annotations of a single image.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageAnnotation {
+    /// File name of the image, typically relative to the dataset's image directory.
+    pub file_name: String,
+    /// Resolution of the image.
+    pub resolution: Resolution,
+    /// The objects in the image.
+    pub objects: Vec<ObjectAnnotation>,
+}
+
+impl ImageAnnotation {
+    /// Creates an [`ImageAnnotation`] without any objects.
+    pub fn new(file_name: impl Into<String>, resolution: Resolution) -> Self {
+        Self {
+            file_name: file_name.into(),
+            resolution,
+            objects: Vec::new(),
+        }
+    }
+
+    /// Creates an [`ImageAnnotation`] from the `detections` made in an image of resolution `res`,
+    /// all of which are labeled as `label`.
+    pub fn from_detections<D: DetectionLike>(
+        file_name: impl Into<String>,
+        res: Resolution,
+        label: &str,
+        detections: &[D],
+    ) -> Self {
+        Self {
+            file_name: file_name.into(),
+            resolution: res,
+            objects: detections
+                .iter()
+                .map(|det| ObjectAnnotation::from_raw(label, det.raw(), res))
+                .collect(),
+        }
+    }
+
+    /// Converts all object annotations to [`RawDetection`]s.
+    pub fn to_raw_detections(&self) -> Vec<RawDetection> {
+        self.objects
+            .iter()
+            .map(|obj| obj.to_raw_detection(self.resolution))
+            .collect()
+    }
+}
+
+/// Collects the distinct object labels used in `images`, in order of first appearance.
+pub fn labels(images: &[ImageAnnotation]) -> Vec<String> {
+    let mut labels: Vec<String> = Vec::new();
+    for obj in images.iter().flat_map(|image| &image.objects) {
+        if !labels.contains(&obj.label) {
+            labels.push(obj.label.clone());
+        }
+    }
+    labels
+}
+
+/// Extensions of the image files considered by the loaders in this module.
+///
+/// These are the formats enabled in the `image` dependency; other files would fail to load.
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif"];
+
+/// Returns whether `path` has the file extension of a supported image format.
+fn is_image_file(path: &Path) -> bool {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => IMAGE_EXTENSIONS
+            .iter()
+            .any(|img| ext.eq_ignore_ascii_case(img)),
+        None => false,
+    }
+}
+
+/// Recursively collects all files in `dir` (and its subdirectories) for which `filter` returns
+/// `true`, ordered by path.
+fn find_files(dir: &Path, filter: impl Fn(&Path) -> bool) -> anyhow::Result<Vec<PathBuf>> {
+    fn visit(
+        dir: &Path,
+        filter: &dyn Fn(&Path) -> bool,
+        out: &mut Vec<PathBuf>,
+    ) -> anyhow::Result<()> {
+        let entries = fs::read_dir(dir)
+            .with_context(|| format!("failed to read directory '{}'", dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_dir() {
+                visit(&path, filter, out)?;
+            } else if path.is_file() && filter(&path) {
+                out.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    let mut paths = Vec::new();
+    visit(dir, &filter, &mut paths)?;
+    paths.sort();
+    Ok(paths)
+}
+
+/// Replaces the extension of `file_name`'s last path component with `ext`.
+fn with_extension(file_name: &str, ext: &str) -> String {
+    Path::new(file_name)
+        .with_extension(ext)
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    #[test]
+    fn detection_roundtrip() {
+        let res = Resolution::new(400, 200);
+        let raw = RawDetection::with_keypoints(
+            0.75,
+            BoundingRect::from_center(0.5, 0.5, 0.1, 0.2),
+            vec![Keypoint::new(0.25, 0.375)],
+        );
+
+        let obj = ObjectAnnotation::from_raw("face", &raw, res);
+        assert_eq!(
+            obj.rect,
+            BoundingRect::from_center(200.0, 100.0, 40.0, 80.0)
+        );
+        assert_eq!(obj.keypoints, [Keypoint::new(100.0, 50.0)]);
+        assert_eq!(obj.confidence, Some(0.75));
+
+        let back = obj.to_raw_detection(res);
+        assert_eq!(back.confidence(), 0.75);
+        assert_eq!(back.bounding_rect(), raw.bounding_rect());
+        assert_relative_eq!(back.keypoints()[0].x(), 0.25);
+        assert_relative_eq!(back.keypoints()[0].y(), 0.375);
+    }
+
+    #[test]
+    fn collect_labels() {
+        let res = Resolution::new(10, 10);
+        let rect = BoundingRect::from_center(5.0, 5.0, 2.0, 2.0);
+        let mut a = ImageAnnotation::new("a.jpg", res);
+        a.objects.push(ObjectAnnotation::new("dog", rect));
+        a.objects.push(ObjectAnnotation::new("cat", rect));
+        let mut b = ImageAnnotation::new("b.jpg", res);
+        b.objects.push(ObjectAnnotation::new("cat", rect));
+        b.objects.push(ObjectAnnotation::new("bird", rect));
+
+        assert_eq!(labels(&[a, b]), ["dog", "cat", "bird"]);
+    }
+}
@@ -0,0 +1,288 @@
+//! COCO JSON annotation files.
+//!
+//! A COCO file describes a whole dataset: it lists all images, all object categories, and all
+//! annotated objects. Detection results are written with a non-standard (but widely supported)
+//! `score` field, and keypoints are written in the `[x, y, visibility]` triple format.
+//!
+//! Category IDs are assigned in order of first appearance of each label, starting at 1.
+
+use std::{collections::HashMap, fs, path::Path};
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use zaru_image::Resolution;
+
+use crate::detection::{BoundingRect, Keypoint};
+
+use super::{labels, ImageAnnotation, ObjectAnnotation};
+
+#[derive(Serialize, Deserialize)]
+struct Coco {
+    images: Vec<CocoImage>,
+    annotations: Vec<CocoAnnotation>,
+    #[serde(default)]
+    categories: Vec<CocoCategory>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CocoImage {
+    id: u64,
+    file_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    height: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CocoAnnotation {
+    #[serde(default)]
+    id: u64,
+    image_id: u64,
+    #[serde(default)]
+    category_id: u64,
+    bbox: [f32; 4],
+    #[serde(default)]
+    area: f32,
+    #[serde(default)]
+    iscrowd: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    score: Option<f32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    keypoints: Vec<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    num_keypoints: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CocoCategory {
+    id: u64,
+    name: String,
+}
+
+/// Serializes `images` into a COCO JSON document.
+pub fn to_string(images: &[ImageAnnotation]) -> String {
+    let categories = labels(images)
+        .into_iter()
+        .zip(1..)
+        .map(|(name, id)| CocoCategory { id, name })
+        .collect::<Vec<_>>();
+    let category_ids = categories
+        .iter()
+        .map(|cat| (cat.name.as_str(), cat.id))
+        .collect::<HashMap<_, _>>();
+
+    let mut coco = Coco {
+        images: Vec::with_capacity(images.len()),
+        annotations: Vec::new(),
+        categories: Vec::new(),
+    };
+    for (image, image_id) in images.iter().zip(1..) {
+        coco.images.push(CocoImage {
+            id: image_id,
+            file_name: image.file_name.clone(),
+            width: Some(image.resolution.width()),
+            height: Some(image.resolution.height()),
+        });
+
+        for obj in &image.objects {
+            let (x, y) = obj.rect.top_left();
+            let (w, h) = (obj.rect.width(), obj.rect.height());
+            coco.annotations.push(CocoAnnotation {
+                id: coco.annotations.len() as u64 + 1,
+                image_id,
+                category_id: category_ids[obj.label.as_str()],
+                bbox: [x, y, w, h],
+                area: w * h,
+                iscrowd: obj.difficult.into(),
+                score: obj.confidence,
+                keypoints: obj
+                    .keypoints
+                    .iter()
+                    .flat_map(|kp| [kp.x(), kp.y(), 2.0])
+                    .collect(),
+                num_keypoints: (!obj.keypoints.is_empty()).then_some(obj.keypoints.len()),
+            });
+        }
+    }
+    coco.categories = categories;
+
+    serde_json::to_string_pretty(&coco).unwrap()
+}
+
+/// Parses a COCO JSON document.
+///
+/// Crowd annotations are marked as [`ObjectAnnotation::difficult`]. Keypoints are only loaded if
+/// all of them are labeled (visibility flag is non-zero).
+///
+/// The `width` and `height` of every image must be specified. Use [`from_str_with_image_dir`] to
+/// load documents that omit them.
+pub fn from_str(json: &str) -> anyhow::Result<Vec<ImageAnnotation>> {
+    Ok(parse(json, None)?.images)
+}
+
+/// Parses a COCO JSON document describing the images in `image_dir`.
+///
+/// Behaves like [`from_str`], except that the resolution of images without a `width` and `height`
+/// is read from the image file in `image_dir` (only the image header is read).
+pub fn from_str_with_image_dir<P: AsRef<Path>>(
+    json: &str,
+    image_dir: P,
+) -> anyhow::Result<Vec<ImageAnnotation>> {
+    Ok(parse(json, Some(image_dir.as_ref()))?.images)
+}
+
+/// A parsed COCO document.
+pub(crate) struct Dataset {
+    pub(crate) images: Vec<ImageAnnotation>,
+    /// Names of all categories declared by the document.
+    pub(crate) categories: Vec<String>,
+}
+
+pub(crate) fn parse(json: &str, image_dir: Option<&Path>) -> anyhow::Result<Dataset> {
+    let coco: Coco = serde_json::from_str(json)?;
+
+    let category_names = coco.categories.iter().map(|cat| cat.name.clone()).collect();
+    let categories = coco
+        .categories
+        .into_iter()
+        .map(|cat| (cat.id, cat.name))
+        .collect::<HashMap<_, _>>();
+
+    let mut index = HashMap::new();
+    let mut images = Vec::with_capacity(coco.images.len());
+    for image in coco.images {
+        let resolution = match (image.width, image.height, image_dir) {
+            (Some(width), Some(height), _) => Resolution::new(width, height),
+            (_, _, Some(dir)) => {
+                let path = dir.join(&image.file_name);
+                let (width, height) = image::image_dimensions(&path)
+                    .with_context(|| format!("failed to read '{}'", path.display()))?;
+                Resolution::new(width, height)
+            }
+            _ => bail!("image '{}' has no width and height", image.file_name),
+        };
+        index.insert(image.id, images.len());
+        images.push(ImageAnnotation::new(image.file_name, resolution));
+    }
+
+    for ann in coco.annotations {
+        let Some(&i) = index.get(&ann.image_id) else {
+            bail!("annotation references unknown image ID {}", ann.image_id);
+        };
+        let label = match categories.get(&ann.category_id) {
+            Some(name) => name.clone(),
+            None if categories.is_empty() => String::new(),
+            None => bail!("annotation references unknown category {}", ann.category_id),
+        };
+
+        let [x, y, w, h] = ann.bbox;
+        let mut obj = ObjectAnnotation::new(label, BoundingRect::from_top_left(x, y, w, h));
+        obj.confidence = ann.score;
+        obj.difficult = ann.iscrowd != 0;
+        if ann
+            .keypoints
+            .chunks(3)
+            .all(|kp| kp.len() == 3 && kp[2] != 0.0)
+        {
+            obj.keypoints = ann
+                .keypoints
+                .chunks(3)
+                .map(|kp| Keypoint::new(kp[0], kp[1]))
+                .collect();
+        }
+        images[i].objects.push(obj);
+    }
+
+    Ok(Dataset {
+        images,
+        categories: category_names,
+    })
+}
+
+/// Writes `images` to a COCO JSON file at `path`.
+pub fn save<P: AsRef<Path>>(path: P, images: &[ImageAnnotation]) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    fs::write(path, to_string(images))
+        .with_context(|| format!("failed to write '{}'", path.display()))
+}
+
+/// Loads a COCO JSON file.
+pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Vec<ImageAnnotation>> {
+    let path = path.as_ref();
+    let json =
+        fs::read_to_string(path).with_context(|| format!("failed to read '{}'", path.display()))?;
+    from_str(&json).with_context(|| format!("failed to parse '{}'", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use zaru_image::Image;
+
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let mut image = ImageAnnotation::new("dir/a.jpg", Resolution::new(640, 480));
+        let mut face =
+            ObjectAnnotation::new("face", BoundingRect::from_top_left(10.0, 20.0, 30.0, 40.0));
+        face.confidence = Some(0.9);
+        face.keypoints = vec![Keypoint::new(15.0, 25.0), Keypoint::new(35.0, 25.0)];
+        image.objects.push(face);
+        let mut crowd =
+            ObjectAnnotation::new("person", BoundingRect::from_top_left(0.0, 0.0, 100.0, 50.0));
+        crowd.difficult = true;
+        image.objects.push(crowd);
+        let images = vec![
+            image,
+            ImageAnnotation::new("b.png", Resolution::new(10, 10)),
+        ];
+
+        let json = to_string(&images);
+        assert_eq!(from_str(&json).unwrap(), images);
+    }
+
+    #[test]
+    fn parse() {
+        let json = r#"{
+            "images": [{"id": 7, "file_name": "a.jpg", "width": 640, "height": 480}],
+            "annotations": [
+                {"id": 1, "image_id": 7, "category_id": 3, "bbox": [1, 2, 3, 4], "iscrowd": 0,
+                 "keypoints": [5, 6, 2, 0, 0, 0], "num_keypoints": 1}
+            ],
+            "categories": [{"id": 3, "name": "hand"}]
+        }"#;
+        let images = from_str(json).unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].resolution, Resolution::new(640, 480));
+
+        let obj = &images[0].objects[0];
+        assert_eq!(obj.label, "hand");
+        assert_eq!(obj.rect, BoundingRect::from_top_left(1.0, 2.0, 3.0, 4.0));
+        assert_eq!(obj.confidence, None);
+        // Not all keypoints are labeled.
+        assert!(obj.keypoints.is_empty());
+
+        let bad = json.replace("\"image_id\": 7", "\"image_id\": 8");
+        assert!(from_str(&bad).is_err());
+    }
+
+    #[test]
+    fn missing_resolution() {
+        let json = r#"{
+            "images": [{"id": 1, "file_name": "a.png"}],
+            "annotations": [{"image_id": 1, "bbox": [1, 2, 3, 4]}]
+        }"#;
+        assert!(from_str(json).is_err());
+
+        let dir = std::env::temp_dir().join(format!("zaru-coco-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        Image::new(40, 20).save(dir.join("a.png")).unwrap();
+        let images = from_str_with_image_dir(json, &dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let images = images.unwrap();
+        assert_eq!(images[0].resolution, Resolution::new(40, 20));
+        assert_eq!(images[0].objects.len(), 1);
+    }
+}
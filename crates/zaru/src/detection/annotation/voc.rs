@@ -0,0 +1,231 @@
+//! Pascal VOC XML annotation files.
+//!
+//! Every image is described by its own XML file. Bounding boxes are stored as 1-based, inclusive
+//! pixel coordinates, as is customary for VOC datasets.
+//!
+//! VOC has no notion of detection confidence, so it is written to a non-standard `<confidence>`
+//! element, which other tools will ignore. Keypoints are not supported.
+
+use std::{fmt::Write, fs, path::Path};
+
+use anyhow::{anyhow, Context};
+use roxmltree::{Document, Node};
+use zaru_image::Resolution;
+
+use crate::detection::BoundingRect;
+
+use super::{find_files, with_extension, ImageAnnotation, ObjectAnnotation};
+
+/// Serializes `image` into a Pascal VOC XML document.
+pub fn to_string(image: &ImageAnnotation) -> String {
+    let mut xml = String::new();
+    writeln!(xml, "<annotation>").unwrap();
+    writeln!(xml, "  <filename>{}</filename>", escape(&image.file_name)).unwrap();
+    writeln!(xml, "  <size>").unwrap();
+    writeln!(xml, "    <width>{}</width>", image.resolution.width()).unwrap();
+    writeln!(xml, "    <height>{}</height>", image.resolution.height()).unwrap();
+    writeln!(xml, "    <depth>3</depth>").unwrap();
+    writeln!(xml, "  </size>").unwrap();
+    writeln!(xml, "  <segmented>0</segmented>").unwrap();
+    for obj in &image.objects {
+        let (left, top) = obj.rect.top_left();
+        let (right, bottom) = obj.rect.bottom_right();
+
+        writeln!(xml, "  <object>").unwrap();
+        writeln!(xml, "    <name>{}</name>", escape(&obj.label)).unwrap();
+        writeln!(xml, "    <pose>Unspecified</pose>").unwrap();
+        writeln!(xml, "    <truncated>0</truncated>").unwrap();
+        writeln!(
+            xml,
+            "    <difficult>{}</difficult>",
+            u8::from(obj.difficult)
+        )
+        .unwrap();
+        if let Some(confidence) = obj.confidence {
+            writeln!(xml, "    <confidence>{}</confidence>", confidence).unwrap();
+        }
+        writeln!(xml, "    <bndbox>").unwrap();
+        writeln!(xml, "      <xmin>{}</xmin>", left.round() + 1.0).unwrap();
+        writeln!(xml, "      <ymin>{}</ymin>", top.round() + 1.0).unwrap();
+        writeln!(xml, "      <xmax>{}</xmax>", right.round()).unwrap();
+        writeln!(xml, "      <ymax>{}</ymax>", bottom.round()).unwrap();
+        writeln!(xml, "    </bndbox>").unwrap();
+        writeln!(xml, "  </object>").unwrap();
+    }
+    writeln!(xml, "</annotation>").unwrap();
+    xml
+}
+
+/// Parses a Pascal VOC XML document.
+pub fn from_str(xml: &str) -> anyhow::Result<ImageAnnotation> {
+    let doc = Document::parse(xml)?;
+    let root = doc.root_element();
+
+    let size = child(root, "size")?;
+    let resolution = Resolution::new(number(size, "width")?, number(size, "height")?);
+    let mut image = ImageAnnotation::new(text(root, "filename")?, resolution);
+
+    for obj in root.children().filter(|n| n.has_tag_name("object")) {
+        let bndbox = child(obj, "bndbox")?;
+        let (xmin, ymin): (f32, f32) = (number(bndbox, "xmin")?, number(bndbox, "ymin")?);
+        let (xmax, ymax): (f32, f32) = (number(bndbox, "xmax")?, number(bndbox, "ymax")?);
+
+        let mut annotation = ObjectAnnotation::new(
+            text(obj, "name")?,
+            BoundingRect::from_top_left(
+                xmin - 1.0,
+                ymin - 1.0,
+                xmax - xmin + 1.0,
+                ymax - ymin + 1.0,
+            ),
+        );
+        annotation.difficult = optional::<u8>(obj, "difficult")?.unwrap_or(0) != 0;
+        annotation.confidence = optional(obj, "confidence")?;
+        image.objects.push(annotation);
+    }
+
+    Ok(image)
+}
+
+/// Writes one XML file per image into `dir`.
+///
+/// The XML files are named after the images, with the extension replaced by `.xml`.
+pub fn save_dir<P: AsRef<Path>>(dir: P, images: &[ImageAnnotation]) -> anyhow::Result<()> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+    for image in images {
+        let path = dir.join(with_extension(&image.file_name, "xml"));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, to_string(image))
+            .with_context(|| format!("failed to write '{}'", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Loads all `.xml` files in `dir` and its subdirectories, ordered by path.
+///
+/// This loads everything written by [`save_dir`], including the files of images located in
+/// subdirectories.
+pub fn load_dir<P: AsRef<Path>>(dir: P) -> anyhow::Result<Vec<ImageAnnotation>> {
+    let paths = find_files(dir.as_ref(), |path| {
+        path.extension() == Some("xml".as_ref())
+    })?;
+
+    paths
+        .iter()
+        .map(|path| {
+            let xml = fs::read_to_string(path)
+                .with_context(|| format!("failed to read '{}'", path.display()))?;
+            from_str(&xml).with_context(|| format!("failed to parse '{}'", path.display()))
+        })
+        .collect()
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> anyhow::Result<Node<'a, 'input>> {
+    node.children()
+        .find(|n| n.has_tag_name(name))
+        .ok_or_else(|| anyhow!("missing <{}> element", name))
+}
+
+fn text<'a>(node: Node<'a, '_>, name: &str) -> anyhow::Result<&'a str> {
+    Ok(child(node, name)?.text().unwrap_or("").trim())
+}
+
+fn number<T: std::str::FromStr>(node: Node<'_, '_>, name: &str) -> anyhow::Result<T> {
+    let text = text(node, name)?;
+    text.parse()
+        .map_err(|_| anyhow!("invalid number '{}' in <{}> element", text, name))
+}
+
+fn optional<T: std::str::FromStr>(node: Node<'_, '_>, name: &str) -> anyhow::Result<Option<T>> {
+    match child(node, name) {
+        Ok(_) => number(node, name).map(Some),
+        Err(_) => Ok(None),
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let mut image = ImageAnnotation::new("cats & dogs.jpg", Resolution::new(640, 480));
+        let mut cat =
+            ObjectAnnotation::new("cat", BoundingRect::from_top_left(10.0, 20.0, 30.0, 40.0));
+        cat.confidence = Some(0.5);
+        image.objects.push(cat);
+        let mut dog =
+            ObjectAnnotation::new("dog", BoundingRect::from_top_left(0.0, 0.0, 640.0, 480.0));
+        dog.difficult = true;
+        image.objects.push(dog);
+
+        assert_eq!(from_str(&to_string(&image)).unwrap(), image);
+    }
+
+    #[test]
+    fn directory_roundtrip() {
+        let rect = BoundingRect::from_top_left(0.0, 0.0, 4.0, 4.0);
+        let mut a = ImageAnnotation::new("a.jpg", Resolution::new(640, 480));
+        a.objects.push(ObjectAnnotation::new("cat", rect));
+        let mut b = ImageAnnotation::new("sub/b.jpg", Resolution::new(640, 480));
+        b.objects.push(ObjectAnnotation::new("dog", rect));
+        let images = vec![a, b];
+
+        let dir = std::env::temp_dir().join(format!("zaru-voc-{}", std::process::id()));
+        save_dir(&dir, &images).unwrap();
+        let loaded = load_dir(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(loaded.unwrap(), images);
+    }
+
+    #[test]
+    fn parse() {
+        // Excerpt from VOC2007 (000001.xml).
+        let xml = "\
+<annotation>
+	<folder>VOC2007</folder>
+	<filename>000001.jpg</filename>
+	<size>
+		<width>353</width>
+		<height>500</height>
+		<depth>3</depth>
+	</size>
+	<segmented>0</segmented>
+	<object>
+		<name>dog</name>
+		<pose>Left</pose>
+		<truncated>1</truncated>
+		<difficult>0</difficult>
+		<bndbox>
+			<xmin>48</xmin>
+			<ymin>240</ymin>
+			<xmax>195</xmax>
+			<ymax>371</ymax>
+		</bndbox>
+	</object>
+</annotation>
+";
+        let image = from_str(xml).unwrap();
+        assert_eq!(image.file_name, "000001.jpg");
+        assert_eq!(image.resolution, Resolution::new(353, 500));
+        assert_eq!(image.objects.len(), 1);
+        assert_eq!(image.objects[0].label, "dog");
+        assert_eq!(
+            image.objects[0].rect,
+            BoundingRect::from_top_left(47.0, 239.0, 148.0, 132.0)
+        );
+        assert!(!image.objects[0].difficult);
+
+        assert!(from_str("<annotation></annotation>").is_err());
+    }
+}
@@ -0,0 +1,233 @@
+//! YOLO text annotation files.
+//!
+//! Every image is described by its own text file, containing one line per object:
+//!
+//! ```text
+//! <class index> <x center> <y center> <width> <height> [<confidence>]
+//! ```
+//!
+//! Coordinates are normalized to the range 0.0 to 1.0 by dividing them by the image width and
+//! height. The class index refers to the list of class labels in a `classes.txt` file (one label per
+//! line). The optional confidence column matches the output of YOLOv5's `--save-conf` option.
+//! Keypoints are not supported.
+//!
+//! Since the format does not store the image resolution, it has to be provided when loading
+//! annotations.
+
+use std::{fmt::Write, fs, path::Path};
+
+use anyhow::{anyhow, bail, Context};
+use zaru_image::Resolution;
+
+use crate::detection::BoundingRect;
+
+use super::{find_files, is_image_file, labels, with_extension, ImageAnnotation, ObjectAnnotation};
+
+/// Name of the file listing the class labels.
+pub const CLASSES_FILE: &str = "classes.txt";
+
+/// Serializes the objects in `image` into a YOLO annotation file.
+///
+/// `classes` is the list of class labels. An error is returned if any object's label is not in
+/// `classes`.
+pub fn to_string(image: &ImageAnnotation, classes: &[String]) -> anyhow::Result<String> {
+    let (w, h) = (
+        image.resolution.width() as f32,
+        image.resolution.height() as f32,
+    );
+
+    let mut text = String::new();
+    for obj in &image.objects {
+        let class = classes
+            .iter()
+            .position(|c| *c == obj.label)
+            .ok_or_else(|| anyhow!("label '{}' is not in the class list", obj.label))?;
+        let (xc, yc) = obj.rect.center();
+        write!(
+            text,
+            "{} {} {} {} {}",
+            class,
+            xc / w,
+            yc / h,
+            obj.rect.width() / w,
+            obj.rect.height() / h,
+        )
+        .unwrap();
+        if let Some(confidence) = obj.confidence {
+            write!(text, " {}", confidence).unwrap();
+        }
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+/// Parses a YOLO annotation file describing the image `file_name` with resolution `res`.
+pub fn from_str(
+    text: &str,
+    file_name: impl Into<String>,
+    res: Resolution,
+    classes: &[String],
+) -> anyhow::Result<ImageAnnotation> {
+    let (w, h) = (res.width() as f32, res.height() as f32);
+
+    let mut image = ImageAnnotation::new(file_name, res);
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let mut fields = line.split_whitespace();
+        let class: usize = fields
+            .next()
+            .unwrap()
+            .parse()
+            .with_context(|| format!("invalid class index in line '{}'", line))?;
+        let Some(label) = classes.get(class) else {
+            bail!("class index {} out of range", class);
+        };
+        let values = fields
+            .map(str::parse)
+            .collect::<Result<Vec<f32>, _>>()
+            .with_context(|| format!("invalid annotation '{}'", line))?;
+        let [xc, yc, bw, bh, rest @ ..] = &values[..] else {
+            bail!("invalid annotation '{}'", line);
+        };
+
+        let mut obj = ObjectAnnotation::new(
+            label.clone(),
+            BoundingRect::from_center(xc * w, yc * h, bw * w, bh * h),
+        );
+        obj.confidence = rest.first().copied();
+        image.objects.push(obj);
+    }
+
+    Ok(image)
+}
+
+/// Writes one text file per image, as well as a `classes.txt` file, into `dir`.
+///
+/// The text files are named after the images, with the extension replaced by `.txt`. The class
+/// list contains all labels used in `images`.
+pub fn save_dir<P: AsRef<Path>>(dir: P, images: &[ImageAnnotation]) -> anyhow::Result<()> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+
+    let classes = labels(images);
+    let mut list = classes.join("\n");
+    list.push('\n');
+    fs::write(dir.join(CLASSES_FILE), list)?;
+
+    for image in images {
+        let path = dir.join(with_extension(&image.file_name, "txt"));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, to_string(image, &classes)?)
+            .with_context(|| format!("failed to write '{}'", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Loads the annotations of all images in `image_dir` from the YOLO files in `dir`.
+///
+/// The class list is read from the `classes.txt` file in `dir`. Images are searched for
+/// recursively, and the annotation file of an image is expected at the same relative path in
+/// `dir`, so `dir` and `image_dir` may also be the same directory. Images without an annotation
+/// file are skipped. Only the image headers are read to determine the image resolution.
+pub fn load_dir<P: AsRef<Path>, I: AsRef<Path>>(
+    dir: P,
+    image_dir: I,
+) -> anyhow::Result<Vec<ImageAnnotation>> {
+    let (dir, image_dir) = (dir.as_ref(), image_dir.as_ref());
+
+    let classes_path = dir.join(CLASSES_FILE);
+    let classes = fs::read_to_string(&classes_path)
+        .with_context(|| format!("failed to read '{}'", classes_path.display()))?
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(String::from)
+        .collect::<Vec<_>>();
+
+    let mut images = Vec::new();
+    for image_path in find_files(image_dir, is_image_file)? {
+        let Some(file_name) = image_path
+            .strip_prefix(image_dir)
+            .ok()
+            .and_then(|rel| rel.to_str())
+        else {
+            continue;
+        };
+        let path = dir.join(with_extension(file_name, "txt"));
+        if !path.is_file() {
+            continue;
+        }
+
+        let (width, height) = image::image_dimensions(&image_path)
+            .with_context(|| format!("failed to read '{}'", image_path.display()))?;
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read '{}'", path.display()))?;
+        let image = from_str(&text, file_name, Resolution::new(width, height), &classes)
+            .with_context(|| format!("failed to parse '{}'", path.display()))?;
+        images.push(image);
+    }
+
+    Ok(images)
+}
+
+#[cfg(test)]
+mod tests {
+    use zaru_image::Image;
+
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let classes = vec!["face".to_string(), "hand".to_string()];
+        let mut image = ImageAnnotation::new("a.jpg", Resolution::new(400, 200));
+        let mut hand =
+            ObjectAnnotation::new("hand", BoundingRect::from_center(100.0, 50.0, 40.0, 20.0));
+        hand.confidence = Some(0.25);
+        image.objects.push(hand);
+        image.objects.push(ObjectAnnotation::new(
+            "face",
+            BoundingRect::from_center(200.0, 100.0, 400.0, 200.0),
+        ));
+
+        let text = to_string(&image, &classes).unwrap();
+        assert_eq!(text, "1 0.25 0.25 0.1 0.1 0.25\n0 0.5 0.5 1 1\n");
+        assert_eq!(
+            from_str(&text, "a.jpg", image.resolution, &classes).unwrap(),
+            image
+        );
+
+        assert!(to_string(&image, &classes[..1]).is_err());
+        assert!(from_str(&text, "a.jpg", image.resolution, &classes[..1]).is_err());
+        assert!(from_str("0 0.5 0.5", "a.jpg", image.resolution, &classes).is_err());
+    }
+
+    #[test]
+    fn shared_directory() {
+        // The standard YOLO layout stores the annotation files next to the images.
+        let dir = std::env::temp_dir().join(format!("zaru-yolo-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        Image::new(40, 20).save(dir.join("a.png")).unwrap();
+        Image::new(10, 10).save(dir.join("sub/b.png")).unwrap();
+        Image::new(10, 10)
+            .save(dir.join("unannotated.png"))
+            .unwrap();
+
+        let mut a = ImageAnnotation::new("a.png", Resolution::new(40, 20));
+        a.objects.push(ObjectAnnotation::new(
+            "face",
+            BoundingRect::from_center(10.0, 5.0, 4.0, 2.0),
+        ));
+        let mut b = ImageAnnotation::new("sub/b.png", Resolution::new(10, 10));
+        b.objects.push(ObjectAnnotation::new(
+            "hand",
+            BoundingRect::from_center(5.0, 5.0, 10.0, 10.0),
+        ));
+        let images = vec![a, b];
+        save_dir(&dir, &images).unwrap();
+
+        let loaded = load_dir(&dir, &dir);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(loaded.unwrap(), images);
+    }
+}
@@ -0,0 +1,354 @@
+//! Multi-scale and rotation-augmented detection.
+//!
+//! Detection networks are typically trained on objects within a limited range of sizes and
+//! orientations. For example, the BlazeFace networks in [`crate::face::detection`] miss faces that
+//! are rotated by more than ~45°, as well as faces that are very large or very small relative to
+//! the image.
+//!
+//! [`AugmentedDetector`] improves recall for those cases by running the wrapped [`Detector`] over
+//! several views of the input image:
+//!
+//! - An image pyramid: for every configured scale factor, the image is zoomed in (by running the
+//!   detector on overlapping windows) or out (by padding the image), so that objects appear larger
+//!   or smaller to the detector.
+//! - Rotated views of the whole image, which make rotated objects appear upright.
+//!
+//! Detections of all passes are mapped back to full-image coordinates and merged with
+//! [`NonMaxSuppression`]. Every pass costs a full invocation of the wrapped detector, so the
+//! configured number of passes directly trades latency for recall.
+
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use zaru_image::{AsImageView, ImageView, Rect, Resolution, RotatedRect};
+
+use crate::timer::Timer;
+
+use super::{
+    geometry::{from_pixels, is_cut_off, scale, tile_positions, to_pixels},
+    nms::NonMaxSuppression,
+    DetectionLike, Detector, RawDetection,
+};
+
+/// Runs a [`Detector`] over an image pyramid and rotated views of the input image.
+///
+/// See the [module documentation][self] for details.
+pub struct AugmentedDetector<D: Detector> {
+    detector: D,
+    scales: Vec<f32>,
+    rotations: Vec<f32>,
+    nms: NonMaxSuppression,
+    raw_detections: Vec<RawDetection>,
+    detections: Vec<D::Detection>,
+}
+
+impl<D: Detector> AugmentedDetector<D> {
+    /// The default scale factors of the image pyramid.
+    ///
+    /// This runs the detector once on the padded image, once on the original image, and on 9
+    /// windows of half the image size.
+    pub const DEFAULT_SCALES: &'static [f32] = &[0.5, 1.0, 2.0];
+
+    /// The default rotations (in radians) of the additional rotated passes.
+    ///
+    /// Together with the upright pass, this covers objects at any rotation for detectors that
+    /// tolerate rotations of at least ±45°.
+    pub const DEFAULT_ROTATIONS: &'static [f32] = &[-FRAC_PI_2, FRAC_PI_2, PI];
+
+    /// Overlap between neighboring windows of zoomed-in pyramid levels.
+    const WINDOW_OVERLAP: f32 = 0.25;
+
+    /// Creates a new [`AugmentedDetector`] wrapping `detector`.
+    ///
+    /// Detections of all passes are merged with the [`IouMode`][super::nms::IouMode] used by
+    /// `detector`.
+    pub fn new(mut detector: D) -> Self {
+        let mut nms = NonMaxSuppression::new();
+        nms.set_iou_mode(detector.nms_mut().iou_mode());
+        Self {
+            detector,
+            scales: Self::DEFAULT_SCALES.to_vec(),
+            rotations: Self::DEFAULT_ROTATIONS.to_vec(),
+            nms,
+            raw_detections: Vec::new(),
+            detections: Vec::new(),
+        }
+    }
+
+    /// Returns a reference to the wrapped [`Detector`].
+    pub fn detector(&self) -> &D {
+        &self.detector
+    }
+
+    /// Returns a mutable reference to the wrapped [`Detector`].
+    pub fn detector_mut(&mut self) -> &mut D {
+        &mut self.detector
+    }
+
+    /// Sets the scale factors of the image pyramid.
+    ///
+    /// A scale factor of 1.0 runs the detector on the unmodified image. Factors larger than 1.0
+    /// zoom into the image, which helps detect small objects, but requires running the detector on
+    /// several overlapping windows (a factor of 2.0 results in 9 passes, see
+    /// [`AugmentedDetector::passes`]).
+    /// Factors smaller than 1.0 pad the image, which helps detect objects that fill most of the
+    /// image, and require a single pass.
+    ///
+    /// By default, [`Self::DEFAULT_SCALES`] is used. An empty list disables all upright passes.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if any scale factor is not a positive, finite number.
+    pub fn set_scales(&mut self, scales: &[f32]) {
+        for &scale in scales {
+            assert!(
+                scale.is_finite() && scale > 0.0,
+                "invalid scale factor {scale}"
+            );
+        }
+        self.scales = scales.to_vec();
+    }
+
+    /// Sets the clockwise rotations (in radians) of the additional rotated passes.
+    ///
+    /// Each rotation results in one additional pass over a rotated view of the whole image.
+    ///
+    /// By default, [`Self::DEFAULT_ROTATIONS`] is used. An empty list disables the rotated passes.
+    pub fn set_rotations(&mut self, rotations: &[f32]) {
+        self.rotations = rotations.to_vec();
+    }
+
+    /// Computes the views of an image of resolution `res` that the detector will be run on.
+    pub fn passes(&self, res: Resolution) -> Vec<RotatedRect> {
+        let (width, height) = (res.width() as f32, res.height() as f32);
+        let centered = |w: u32, h: u32| {
+            Rect::from_top_left(
+                ((width - w as f32) / 2.0).round() as i32,
+                ((height - h as f32) / 2.0).round() as i32,
+                w,
+                h,
+            )
+        };
+
+        let mut passes = Vec::new();
+        for &scale in &self.scales {
+            let w = ((width / scale).round() as u32).max(1);
+            let h = ((height / scale).round() as u32).max(1);
+            if scale < 1.0 {
+                passes.push(centered(w, h).into());
+            } else {
+                for y in tile_positions(res.height(), h, Self::WINDOW_OVERLAP) {
+                    for x in tile_positions(res.width(), w, Self::WINDOW_OVERLAP) {
+                        passes.push(Rect::from_top_left(x as i32, y as i32, w, h).into());
+                    }
+                }
+            }
+        }
+
+        for &radians in &self.rotations {
+            // Make the view large enough to contain the whole image.
+            let (sin, cos) = (radians.sin().abs(), radians.cos().abs());
+            let w = (width * cos + height * sin).round() as u32;
+            let h = (width * sin + height * cos).round() as u32;
+            passes.push(RotatedRect::new(centered(w.max(1), h.max(1)), radians));
+        }
+
+        passes
+    }
+
+    /// Runs the detector on all views of `image`, returning the merged detections.
+    pub fn detect<V: AsImageView>(&mut self, image: &V) -> &[D::Detection] {
+        self.detect_impl(image.as_view())
+    }
+
+    fn detect_impl(&mut self, image: ImageView<'_>) -> &[D::Detection] {
+        self.raw_detections.clear();
+        self.detections.clear();
+
+        let full_res = image.resolution();
+        for pass in self.passes(full_res) {
+            for det in self.detector.detect(image.view(pass)) {
+                // Rotated views contain the whole image, so nothing is cut off by their edges.
+                if pass.rotation_radians() == 0.0 && is_cut_off(det.raw(), *pass.rect(), full_res) {
+                    continue;
+                }
+
+                let mut raw = det.raw().clone();
+                remap(&mut raw, &pass, full_res);
+                self.raw_detections.push(raw);
+            }
+        }
+
+        for raw in self.nms.process(&mut self.raw_detections) {
+            self.detections.push(D::Detection::from_raw(raw, full_res));
+        }
+
+        &self.detections
+    }
+}
+
+impl<D: Detector> Detector for AugmentedDetector<D> {
+    type Detection = D::Detection;
+
+    fn input_resolution(&self) -> Resolution {
+        self.detector.input_resolution()
+    }
+
+    fn detect(&mut self, image: ImageView<'_>) -> &[Self::Detection] {
+        self.detect_impl(image)
+    }
+
+    fn timers(&self) -> Box<dyn Iterator<Item = &Timer> + '_> {
+        self.detector.timers()
+    }
+
+    fn threshold(&self) -> f32 {
+        self.detector.threshold()
+    }
+
+    fn set_threshold(&mut self, threshold: f32) {
+        self.detector.set_threshold(threshold);
+    }
+
+    /// Returns the [`NonMaxSuppression`] used to merge the detections of all passes.
+    ///
+    /// The wrapped detector's [`NonMaxSuppression`] can be accessed via
+    /// [`AugmentedDetector::detector_mut`].
+    fn nms_mut(&mut self) -> &mut NonMaxSuppression {
+        &mut self.nms
+    }
+}
+
+/// Maps `raw` from the `view` of an image to the image itself, which has resolution `to`.
+fn remap(raw: &mut RawDetection, view: &RotatedRect, to: Resolution) {
+    let from = Resolution::new(view.rect().width(), view.rect().height());
+    let map = |x, y| {
+        let (x, y) = to_pixels(x, y, from);
+        let [x, y] = view.transform_out_f32(x, y);
+        from_pixels(x, y, to)
+    };
+    let size_factor = scale(from) / scale(to);
+
+    let rect = &mut raw.rect;
+    (rect.xc, rect.yc) = map(rect.xc, rect.yc);
+    rect.w *= size_factor;
+    rect.h *= size_factor;
+    raw.radians += view.rotation_radians();
+
+    for kp in &mut raw.keypoints {
+        (kp.x, kp.y) = map(kp.x, kp.y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use crate::detection::{
+        nms::IouMode,
+        test::{image_with_rect, WhiteDetector},
+    };
+
+    use super::*;
+
+    /// Accepts objects whose size is between 10% and 50% of the view size.
+    fn medium_sized(rect: Rect, res: Resolution) -> bool {
+        let rel = rect.width().max(rect.height()) as f32 / scale(res);
+        (0.1..=0.5).contains(&rel)
+    }
+
+    #[test]
+    fn test_passes() {
+        let mut det = AugmentedDetector::new(WhiteDetector::with_filter(medium_sized));
+        let res = Resolution::new(300, 200);
+
+        det.set_scales(&[1.0]);
+        det.set_rotations(&[]);
+        assert_eq!(
+            det.passes(res),
+            [RotatedRect::from(Rect::from_top_left(0, 0, 300, 200))]
+        );
+
+        det.set_scales(&[0.5, 2.0]);
+        let passes = det.passes(res);
+        assert_eq!(passes[0], Rect::from_top_left(-150, -100, 600, 400).into());
+        assert_eq!(passes.len(), 1 + 9);
+        for pass in &passes[1..] {
+            assert_eq!(pass.rect().width(), 150);
+            assert_eq!(pass.rect().height(), 100);
+        }
+
+        det.set_scales(&[]);
+        det.set_rotations(&[FRAC_PI_2]);
+        let passes = det.passes(res);
+        assert_eq!(passes.len(), 1);
+        assert_eq!(*passes[0].rect(), Rect::from_top_left(50, -50, 200, 300));
+    }
+
+    #[test]
+    fn image_pyramid() {
+        let res = Resolution::new(300, 200);
+        let small = Rect::from_top_left(200, 60, 12, 12);
+        let large = Rect::from_top_left(40, 10, 180, 180);
+
+        for (object, scale_factor) in [(small, 4.0), (large, 0.5)] {
+            let image = image_with_rect(res, object);
+
+            let mut det = AugmentedDetector::new(WhiteDetector::with_filter(medium_sized));
+            det.set_rotations(&[]);
+            det.set_scales(&[1.0]);
+            assert!(det.detect(&image).is_empty());
+
+            det.set_scales(&[1.0, scale_factor]);
+            let detections = det.detect(&image);
+            assert_eq!(detections.len(), 1);
+            let rect = detections[0].bounding_rect();
+            assert!(rect.iou(&object) > 0.7, "{rect:?} vs {object:?}");
+        }
+    }
+
+    #[test]
+    fn rotated_passes() {
+        // Only detects objects that are at least twice as wide as they are tall.
+        fn wide(rect: Rect, _: Resolution) -> bool {
+            rect.width() >= rect.height() * 2
+        }
+
+        let res = Resolution::new(300, 200);
+        let tall = Rect::from_top_left(100, 40, 30, 100);
+        let image = image_with_rect(res, tall);
+
+        let mut det = AugmentedDetector::new(WhiteDetector::with_filter(wide));
+        det.set_scales(&[1.0]);
+        det.set_rotations(&[]);
+        assert!(det.detect(&image).is_empty());
+
+        det.set_rotations(&[FRAC_PI_2]);
+        let detections = det.detect(&image);
+        assert_eq!(detections.len(), 1);
+        let det = &detections[0];
+        assert_relative_eq!(det.rotation_radians(), FRAC_PI_2);
+
+        // The rotated bounding rectangle must cover the original object.
+        let rect = det.rotated_bounding_rect();
+        let (cx, cy) = rect.center();
+        assert_relative_eq!(cx, 115.0, epsilon = 1.5);
+        assert_relative_eq!(cy, 90.0, epsilon = 1.5);
+        let bounds = Rect::bounding(
+            rect.rotated_corners()
+                .map(|(x, y)| (x.round() as i32, y.round() as i32)),
+        )
+        .unwrap();
+        assert!(bounds.iou(&tall) > 0.8, "{bounds:?} vs {tall:?}");
+    }
+
+    #[test]
+    fn inherits_iou_mode() {
+        let mut det = WhiteDetector::new();
+        det.nms_mut().set_iou_mode(IouMode::Rotated);
+        let mut augmented = AugmentedDetector::new(det);
+        assert_eq!(augmented.nms_mut().iou_mode(), IouMode::Rotated);
+
+        let mut augmented = AugmentedDetector::new(WhiteDetector::new());
+        assert_eq!(augmented.nms_mut().iou_mode(), IouMode::AxisAligned);
+    }
+}
@@ -0,0 +1,755 @@
+//! Detection quality evaluation against annotated datasets.
+//!
+//! This module can load ground-truth bounding boxes from COCO-style JSON files and the WIDER FACE
+//! text format, run a [`Detector`] over the annotated images, and compute precision/recall curves,
+//! average precision (AP) at configurable IoU thresholds, and recall per object size.
+//!
+//! Detections are matched to ground-truth boxes greedily, in order of descending confidence, like
+//! the COCO evaluation does. Ground-truth boxes can be marked as *ignored* (for example crowd
+//! annotations, or faces WIDER FACE marks as invalid): detections matching them count as neither
+//! true nor false positives, and they do not count towards recall.
+//!
+//! All rectangles in this module use pixel coordinates of the annotated image.
+
+use std::{
+    cmp::Ordering,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+use serde::Serialize;
+use zaru_image::{AsImageView, Image};
+
+use super::{annotation::coco, BoundingRect, DetectionLike, Detector};
+
+/// A ground-truth bounding box.
+#[derive(Debug, Clone, Copy)]
+pub struct Annotation {
+    rect: BoundingRect,
+    ignore: bool,
+}
+
+impl Annotation {
+    /// Creates an annotation covering `rect`.
+    pub fn new(rect: BoundingRect) -> Self {
+        Self {
+            rect,
+            ignore: false,
+        }
+    }
+
+    /// Creates an annotation that detections may match, but that is not required to be detected.
+    pub fn ignored(rect: BoundingRect) -> Self {
+        Self { rect, ignore: true }
+    }
+
+    pub fn rect(&self) -> BoundingRect {
+        self.rect
+    }
+
+    pub fn is_ignored(&self) -> bool {
+        self.ignore
+    }
+}
+
+/// An image file and the ground-truth boxes of the objects it contains.
+#[derive(Debug, Clone)]
+pub struct AnnotatedImage {
+    pub path: PathBuf,
+    pub annotations: Vec<Annotation>,
+}
+
+/// A list of [`AnnotatedImage`]s.
+#[derive(Debug, Clone, Default)]
+pub struct Dataset {
+    images: Vec<AnnotatedImage>,
+}
+
+impl Dataset {
+    pub fn new(images: Vec<AnnotatedImage>) -> Self {
+        Self { images }
+    }
+
+    /// Loads a dataset from a COCO-style annotation JSON file.
+    ///
+    /// Image paths are resolved relative to `image_dir`. If `category` is given, only annotations
+    /// of the category with that name are loaded. Crowd annotations (`iscrowd`) are marked as
+    /// ignored.
+    pub fn load_coco<A: AsRef<Path>, I: AsRef<Path>>(
+        annotations: A,
+        image_dir: I,
+        category: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let path = annotations.as_ref();
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("failed to read '{}'", path.display()))?;
+        Self::parse_coco(&json, image_dir.as_ref(), category)
+            .with_context(|| format!("failed to parse '{}'", path.display()))
+    }
+
+    /// Loads a dataset from a WIDER FACE ground-truth file (eg. `wider_face_val_bbx_gt.txt`).
+    ///
+    /// Image paths are resolved relative to `image_dir`. Faces marked as invalid, and faces with an
+    /// empty bounding box, are marked as ignored.
+    pub fn load_wider_face<A: AsRef<Path>, I: AsRef<Path>>(
+        annotations: A,
+        image_dir: I,
+    ) -> anyhow::Result<Self> {
+        let path = annotations.as_ref();
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read '{}'", path.display()))?;
+        Self::parse_wider_face(&text, image_dir.as_ref())
+            .with_context(|| format!("failed to parse '{}'", path.display()))
+    }
+
+    fn parse_coco(json: &str, image_dir: &Path, category: Option<&str>) -> anyhow::Result<Self> {
+        let coco = coco::parse(json, Some(image_dir))?;
+        if let Some(category) = category {
+            if !coco.categories.iter().any(|name| name == category) {
+                bail!("dataset has no category named '{}'", category);
+            }
+        }
+
+        let images = coco
+            .images
+            .into_iter()
+            .map(|image| AnnotatedImage {
+                path: image_dir.join(image.file_name),
+                annotations: image
+                    .objects
+                    .into_iter()
+                    .filter(|obj| category.is_none() || category == Some(&*obj.label))
+                    .map(|obj| {
+                        if obj.difficult {
+                            Annotation::ignored(obj.rect)
+                        } else {
+                            Annotation::new(obj.rect)
+                        }
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Ok(Self { images })
+    }
+
+    fn parse_wider_face(text: &str, image_dir: &Path) -> anyhow::Result<Self> {
+        let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+        let mut images = Vec::new();
+        while let Some(file_name) = lines.next() {
+            let count: usize = match lines.next() {
+                Some(count) => count
+                    .parse()
+                    .with_context(|| format!("invalid face count for '{}'", file_name))?,
+                None => bail!("missing face count for '{}'", file_name),
+            };
+
+            // Images without faces are still followed by a single line of zeroes.
+            let mut annotations = Vec::with_capacity(count);
+            for _ in 0..count.max(1) {
+                let Some(line) = lines.next() else {
+                    bail!("unexpected end of file in annotations of '{}'", file_name);
+                };
+                if count == 0 {
+                    continue;
+                }
+
+                // x1 y1 w h blur expression illumination invalid occlusion pose
+                let fields = line
+                    .split_whitespace()
+                    .map(str::parse)
+                    .collect::<Result<Vec<f32>, _>>()
+                    .with_context(|| format!("invalid face annotation '{}'", line))?;
+                if fields.len() < 4 {
+                    bail!("invalid face annotation '{}'", line);
+                }
+
+                let (w, h) = (fields[2], fields[3]);
+                let invalid = fields.get(7).copied().unwrap_or(0.0) != 0.0;
+                let rect = BoundingRect::from_top_left(fields[0], fields[1], w, h);
+                annotations.push(if invalid || w <= 0.0 || h <= 0.0 {
+                    Annotation::ignored(rect)
+                } else {
+                    Annotation::new(rect)
+                });
+            }
+
+            images.push(AnnotatedImage {
+                path: image_dir.join(file_name),
+                annotations,
+            });
+        }
+
+        Ok(Self { images })
+    }
+
+    pub fn images(&self) -> &[AnnotatedImage] {
+        &self.images
+    }
+
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+}
+
+/// Object size categories used for per-size recall, following the COCO definitions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SizeBucket {
+    /// Objects with an area of less than 32x32 pixels.
+    Small,
+    /// Objects with an area between 32x32 and 96x96 pixels.
+    Medium,
+    /// Objects with an area of more than 96x96 pixels.
+    Large,
+}
+
+impl SizeBucket {
+    pub const ALL: [Self; 3] = [Self::Small, Self::Medium, Self::Large];
+
+    /// Returns the size bucket an object with bounding rectangle `rect` falls into.
+    pub fn of(rect: &BoundingRect) -> Self {
+        let area = rect.area();
+        if area < 32.0 * 32.0 {
+            Self::Small
+        } else if area < 96.0 * 96.0 {
+            Self::Medium
+        } else {
+            Self::Large
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl fmt::Display for SizeBucket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Small => "small",
+            Self::Medium => "medium",
+            Self::Large => "large",
+        })
+    }
+}
+
+/// Accumulates detection results and computes evaluation metrics from them.
+pub struct Evaluator {
+    images: usize,
+    detections: usize,
+    thresholds: Vec<ThresholdState>,
+}
+
+struct ThresholdState {
+    iou_threshold: f32,
+    /// Confidence and true-positive flag of every non-ignored detection.
+    results: Vec<(f32, bool)>,
+    /// Number of (non-ignored) ground-truth boxes per size bucket.
+    ground_truth: [usize; 3],
+    /// Number of detected ground-truth boxes per size bucket.
+    detected: [usize; 3],
+}
+
+impl Evaluator {
+    /// The default IoU thresholds a detection has to reach to match a ground-truth box.
+    pub const DEFAULT_IOU_THRESHOLDS: [f32; 2] = [0.5, 0.75];
+
+    pub fn new() -> Self {
+        let mut this = Self {
+            images: 0,
+            detections: 0,
+            thresholds: Vec::new(),
+        };
+        this.set_iou_thresholds(&Self::DEFAULT_IOU_THRESHOLDS);
+        this
+    }
+
+    /// Sets the IoU thresholds to compute metrics for.
+    ///
+    /// This resets all accumulated results.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `thresholds` is empty or contains values outside of 0.0 to 1.0.
+    pub fn set_iou_thresholds(&mut self, thresholds: &[f32]) {
+        assert!(!thresholds.is_empty(), "no IoU thresholds given");
+        for &thresh in thresholds {
+            assert!(
+                (0.0..=1.0).contains(&thresh),
+                "IoU threshold {thresh} out of range"
+            );
+        }
+
+        self.images = 0;
+        self.detections = 0;
+        self.thresholds = thresholds
+            .iter()
+            .map(|&iou_threshold| ThresholdState {
+                iou_threshold,
+                results: Vec::new(),
+                ground_truth: [0; 3],
+                detected: [0; 3],
+            })
+            .collect();
+    }
+
+    /// Runs `detector` on every image in `dataset` and records the results.
+    pub fn run<D: Detector>(&mut self, detector: &mut D, dataset: &Dataset) -> anyhow::Result<()> {
+        for (i, image) in dataset.images().iter().enumerate() {
+            let img = Image::load(&image.path)
+                .with_context(|| format!("failed to load '{}'", image.path.display()))?;
+            let detections = detector.detect(img.as_view());
+            self.add_detections(&image.annotations, detections);
+
+            if (i + 1) % 100 == 0 {
+                log::info!("evaluated {}/{} images", i + 1, dataset.len());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Records the `detections` made in an image with ground truth `annotations`.
+    pub fn add_detections<D: DetectionLike>(
+        &mut self,
+        annotations: &[Annotation],
+        detections: &[D],
+    ) {
+        self.add_image(
+            annotations,
+            detections
+                .iter()
+                .map(|det| (det.confidence(), BoundingRect::from(det.bounding_rect()))),
+        );
+    }
+
+    /// Records the `(confidence, rect)` pairs detected in an image with ground truth
+    /// `annotations`.
+    pub fn add_image<I>(&mut self, annotations: &[Annotation], detections: I)
+    where
+        I: IntoIterator<Item = (f32, BoundingRect)>,
+    {
+        let mut detections = detections.into_iter().collect::<Vec<_>>();
+        detections.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+
+        self.images += 1;
+        self.detections += detections.len();
+
+        for state in &mut self.thresholds {
+            let mut matched = vec![false; annotations.len()];
+            for (confidence, rect) in &detections {
+                // Prefer the best-matching ground truth box that hasn't been matched yet; only
+                // fall back to ignored boxes if there is none.
+                let mut best: Option<(usize, f32)> = None;
+                for (i, ann) in annotations.iter().enumerate() {
+                    if matched[i] && !ann.ignore {
+                        continue;
+                    }
+                    let iou = ann.rect.iou(rect);
+                    if iou < state.iou_threshold {
+                        continue;
+                    }
+                    let better = match best {
+                        None => true,
+                        Some((b, best_iou)) => {
+                            (annotations[b].ignore && !ann.ignore)
+                                || (annotations[b].ignore == ann.ignore && iou > best_iou)
+                        }
+                    };
+                    if better {
+                        best = Some((i, iou));
+                    }
+                }
+
+                match best {
+                    Some((i, _)) if annotations[i].ignore => {}
+                    Some((i, _)) => {
+                        matched[i] = true;
+                        state.results.push((*confidence, true));
+                    }
+                    None => state.results.push((*confidence, false)),
+                }
+            }
+
+            for (ann, matched) in annotations.iter().zip(&matched) {
+                if ann.ignore {
+                    continue;
+                }
+                let bucket = SizeBucket::of(&ann.rect).index();
+                state.ground_truth[bucket] += 1;
+                if *matched {
+                    state.detected[bucket] += 1;
+                }
+            }
+        }
+    }
+
+    /// Computes the evaluation metrics of all results recorded so far.
+    pub fn report(&self) -> Report {
+        Report {
+            images: self.images,
+            detections: self.detections,
+            annotations: self.thresholds[0].ground_truth.iter().sum(),
+            thresholds: self.thresholds.iter().map(ThresholdState::report).collect(),
+        }
+    }
+}
+
+impl Default for Evaluator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ThresholdState {
+    fn report(&self) -> ThresholdReport {
+        let mut results = self.results.clone();
+        results.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+
+        let total = self.ground_truth.iter().sum::<usize>();
+        let mut curve = Vec::new();
+        let (mut tp, mut fp) = (0, 0);
+        for (i, &(confidence, is_tp)) in results.iter().enumerate() {
+            if is_tp {
+                tp += 1;
+            } else {
+                fp += 1;
+            }
+
+            // Only emit a point once all detections with the same confidence have been counted.
+            if results.get(i + 1).map(|next| next.0) == Some(confidence) {
+                continue;
+            }
+            curve.push(PrecisionRecall {
+                confidence,
+                precision: tp as f32 / (tp + fp) as f32,
+                recall: if total == 0 {
+                    0.0
+                } else {
+                    tp as f32 / total as f32
+                },
+            });
+        }
+
+        ThresholdReport {
+            iou_threshold: self.iou_threshold,
+            average_precision: average_precision(&curve),
+            recall: if total == 0 {
+                0.0
+            } else {
+                self.detected.iter().sum::<usize>() as f32 / total as f32
+            },
+            size_recall: SizeBucket::ALL
+                .iter()
+                .map(|&bucket| {
+                    let (gt, det) = (
+                        self.ground_truth[bucket.index()],
+                        self.detected[bucket.index()],
+                    );
+                    SizeRecall {
+                        bucket,
+                        annotations: gt,
+                        recall: if gt == 0 { 0.0 } else { det as f32 / gt as f32 },
+                    }
+                })
+                .collect(),
+            precision_recall: curve,
+        }
+    }
+}
+
+/// Computes the area under a precision/recall curve, using all-point interpolation.
+///
+/// The curve has to be ordered by descending confidence (and thus non-decreasing recall).
+fn average_precision(curve: &[PrecisionRecall]) -> f32 {
+    let mut ap = 0.0;
+    let mut max_precision = 0.0f32;
+    let mut prev_recall = curve.last().map_or(0.0, |p| p.recall);
+    for point in curve.iter().rev() {
+        ap += (prev_recall - point.recall) * max_precision;
+        max_precision = max_precision.max(point.precision);
+        prev_recall = point.recall;
+    }
+    ap + prev_recall * max_precision
+}
+
+/// Evaluation results computed by [`Evaluator::report`].
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    /// Number of evaluated images.
+    pub images: usize,
+    /// Number of non-ignored ground-truth boxes.
+    pub annotations: usize,
+    /// Number of detections made by the detector.
+    pub detections: usize,
+    /// Metrics for every configured IoU threshold.
+    pub thresholds: Vec<ThresholdReport>,
+}
+
+impl Report {
+    /// Returns the mean of the average precision over all IoU thresholds.
+    pub fn mean_average_precision(&self) -> f32 {
+        self.thresholds
+            .iter()
+            .map(|t| t.average_precision)
+            .sum::<f32>()
+            / self.thresholds.len() as f32
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} images, {} annotations, {} detections",
+            self.images, self.annotations, self.detections
+        )?;
+        writeln!(f)?;
+
+        write!(f, "{:>8} {:>8} {:>8}", "IoU", "AP", "recall")?;
+        for bucket in SizeBucket::ALL {
+            write!(f, " {:>8}", bucket.to_string())?;
+        }
+        writeln!(f)?;
+
+        for t in &self.thresholds {
+            write!(
+                f,
+                "{:>8.2} {:>8.4} {:>8.4}",
+                t.iou_threshold, t.average_precision, t.recall
+            )?;
+            for size in &t.size_recall {
+                write!(f, " {:>8.4}", size.recall)?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f)?;
+
+        write!(f, "mAP: {:.4}", self.mean_average_precision())
+    }
+}
+
+/// Metrics computed for a single IoU threshold.
+#[derive(Debug, Clone, Serialize)]
+pub struct ThresholdReport {
+    pub iou_threshold: f32,
+    pub average_precision: f32,
+    /// Fraction of ground-truth boxes that were detected, at any confidence.
+    pub recall: f32,
+    pub size_recall: Vec<SizeRecall>,
+    /// Precision/recall curve, ordered by descending confidence threshold.
+    pub precision_recall: Vec<PrecisionRecall>,
+}
+
+/// Recall of ground-truth boxes of a specific [`SizeBucket`].
+#[derive(Debug, Clone, Serialize)]
+pub struct SizeRecall {
+    pub bucket: SizeBucket,
+    /// Number of non-ignored ground-truth boxes in this bucket.
+    pub annotations: usize,
+    pub recall: f32,
+}
+
+/// A point on a precision/recall curve.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PrecisionRecall {
+    /// The confidence threshold at which this point is reached.
+    pub confidence: f32,
+    pub precision: f32,
+    pub recall: f32,
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    fn rect(x: f32, y: f32, w: f32, h: f32) -> BoundingRect {
+        BoundingRect::from_top_left(x, y, w, h)
+    }
+
+    #[test]
+    fn perfect_detector() {
+        let annotations = [
+            Annotation::new(rect(0.0, 0.0, 10.0, 10.0)),
+            Annotation::new(rect(100.0, 100.0, 50.0, 50.0)),
+            Annotation::new(rect(200.0, 0.0, 200.0, 200.0)),
+        ];
+        let mut eval = Evaluator::new();
+        eval.add_image(&annotations, annotations.iter().map(|a| (0.9, a.rect())));
+
+        let report = eval.report();
+        assert_eq!(report.annotations, 3);
+        assert_eq!(report.detections, 3);
+        for t in &report.thresholds {
+            assert_relative_eq!(t.average_precision, 1.0);
+            assert_relative_eq!(t.recall, 1.0);
+            for size in &t.size_recall {
+                assert_eq!(size.annotations, 1);
+                assert_relative_eq!(size.recall, 1.0);
+            }
+        }
+        assert_relative_eq!(report.mean_average_precision(), 1.0);
+    }
+
+    #[test]
+    fn false_positives_and_misses() {
+        let annotations = [
+            Annotation::new(rect(0.0, 0.0, 10.0, 10.0)),
+            Annotation::new(rect(100.0, 100.0, 10.0, 10.0)),
+        ];
+        let mut eval = Evaluator::new();
+        eval.set_iou_thresholds(&[0.5]);
+        eval.add_image(
+            &annotations,
+            [
+                // High-confidence false positive, diagonally offset from a ground-truth box.
+                (0.9, rect(10.0, 10.0, 10.0, 10.0)),
+                (0.8, rect(0.0, 0.0, 10.0, 10.0)),
+                // Duplicate detection of the same object.
+                (0.7, rect(1.0, 0.0, 10.0, 10.0)),
+            ],
+        );
+
+        let report = eval.report();
+        let t = &report.thresholds[0];
+        let curve = t
+            .precision_recall
+            .iter()
+            .map(|p| (p.precision, p.recall))
+            .collect::<Vec<_>>();
+        assert_eq!(curve, [(0.0, 0.0), (0.5, 0.5), (1.0 / 3.0, 0.5)]);
+        assert_relative_eq!(t.average_precision, 0.25);
+        assert_relative_eq!(t.recall, 0.5);
+    }
+
+    #[test]
+    fn iou_threshold() {
+        let annotations = [Annotation::new(rect(0.0, 0.0, 10.0, 10.0))];
+        let mut eval = Evaluator::new();
+        // IoU of 0.6
+        eval.add_image(&annotations, [(1.0, rect(0.0, 0.0, 10.0, 6.0))]);
+
+        let report = eval.report();
+        assert_relative_eq!(report.thresholds[0].average_precision, 1.0);
+        assert_relative_eq!(report.thresholds[1].average_precision, 0.0);
+        assert_relative_eq!(report.mean_average_precision(), 0.5);
+    }
+
+    #[test]
+    fn ignored_annotations() {
+        let annotations = [
+            Annotation::new(rect(0.0, 0.0, 10.0, 10.0)),
+            Annotation::ignored(rect(50.0, 50.0, 10.0, 10.0)),
+        ];
+        let mut eval = Evaluator::new();
+        eval.add_image(
+            &annotations,
+            [
+                (0.9, rect(50.0, 50.0, 10.0, 10.0)),
+                (0.8, rect(0.0, 0.0, 10.0, 10.0)),
+            ],
+        );
+
+        let report = eval.report();
+        assert_eq!(report.annotations, 1);
+        let t = &report.thresholds[0];
+        assert_eq!(t.precision_recall.len(), 1);
+        assert_relative_eq!(t.average_precision, 1.0);
+    }
+
+    #[test]
+    fn size_buckets() {
+        assert_eq!(
+            SizeBucket::of(&rect(0.0, 0.0, 31.0, 32.0)),
+            SizeBucket::Small
+        );
+        assert_eq!(
+            SizeBucket::of(&rect(0.0, 0.0, 32.0, 32.0)),
+            SizeBucket::Medium
+        );
+        assert_eq!(
+            SizeBucket::of(&rect(0.0, 0.0, 95.0, 96.0)),
+            SizeBucket::Medium
+        );
+        assert_eq!(
+            SizeBucket::of(&rect(0.0, 0.0, 96.0, 96.0)),
+            SizeBucket::Large
+        );
+    }
+
+    #[test]
+    fn parse_wider_face() {
+        let text = "\
+0--Parade/0_Parade_marchingband_1_849.jpg
+1
+449 330 122 149 0 0 0 0 0 0
+0--Parade/0_Parade_Parade_0_904.jpg
+0
+0 0 0 0 0 0 0 0 0 0
+0--Parade/0_Parade_marchingband_1_799.jpg
+2
+78 221 7 8 2 0 0 0 0 0
+78 238 14 17 2 0 0 1 0 0
+";
+        let dataset = Dataset::parse_wider_face(text, Path::new("images")).unwrap();
+        assert_eq!(dataset.len(), 3);
+
+        let images = dataset.images();
+        assert_eq!(
+            images[0].path,
+            Path::new("images/0--Parade/0_Parade_marchingband_1_849.jpg")
+        );
+        assert_eq!(images[0].annotations.len(), 1);
+        assert_eq!(
+            images[0].annotations[0].rect(),
+            rect(449.0, 330.0, 122.0, 149.0)
+        );
+        assert!(images[1].annotations.is_empty());
+        assert!(!images[2].annotations[0].is_ignored());
+        assert!(images[2].annotations[1].is_ignored());
+
+        assert!(Dataset::parse_wider_face("a.jpg\n2\n1 2 3 4\n", Path::new("")).is_err());
+    }
+
+    #[test]
+    fn parse_coco() {
+        let json = r#"{
+            "images": [
+                {"id": 1, "file_name": "a.jpg", "width": 640, "height": 480},
+                {"id": 2, "file_name": "b.jpg", "width": 640, "height": 480}
+            ],
+            "annotations": [
+                {"id": 1, "image_id": 1, "category_id": 1, "bbox": [10, 20, 30, 40], "iscrowd": 0},
+                {"id": 2, "image_id": 1, "category_id": 2, "bbox": [0, 0, 5, 5], "iscrowd": 0},
+                {"id": 3, "image_id": 2, "category_id": 1, "bbox": [0, 0, 100, 100], "iscrowd": 1}
+            ],
+            "categories": [
+                {"id": 1, "name": "person"},
+                {"id": 2, "name": "dog"}
+            ]
+        }"#;
+
+        let all = Dataset::parse_coco(json, Path::new("img"), None).unwrap();
+        assert_eq!(all.images()[0].annotations.len(), 2);
+
+        let people = Dataset::parse_coco(json, Path::new("img"), Some("person")).unwrap();
+        let images = people.images();
+        assert_eq!(images[0].path, Path::new("img/a.jpg"));
+        assert_eq!(images[0].annotations.len(), 1);
+        assert_eq!(
+            images[0].annotations[0].rect(),
+            rect(10.0, 20.0, 30.0, 40.0)
+        );
+        assert!(images[1].annotations[0].is_ignored());
+
+        assert!(Dataset::parse_coco(json, Path::new("img"), Some("cat")).is_err());
+    }
+}
@@ -0,0 +1,122 @@
+//! Geometry helpers shared by the detection submodules.
+//!
+//! These convert between pixel coordinates and the normalized coordinates of [`RawDetection`]s,
+//! and place views (like tiles or pyramid windows) in an image.
+
+use zaru_image::{Rect, Resolution};
+
+use super::RawDetection;
+
+/// Returns the scale factor between pixel coordinates and the normalized coordinates used by
+/// [`RawDetection`]s of an image with resolution `res`.
+pub(super) fn scale(res: Resolution) -> f32 {
+    res.width().max(res.height()) as f32
+}
+
+/// Converts normalized [`RawDetection`] coordinates to pixel coordinates of an image with
+/// resolution `res`.
+pub(super) fn to_pixels(x: f32, y: f32, res: Resolution) -> (f32, f32) {
+    let s = scale(res);
+    (
+        (x - 0.5) * s + res.width() as f32 / 2.0,
+        (y - 0.5) * s + res.height() as f32 / 2.0,
+    )
+}
+
+/// Converts pixel coordinates of an image with resolution `res` to the normalized coordinates
+/// used by [`RawDetection`]s.
+pub(super) fn from_pixels(x: f32, y: f32, res: Resolution) -> (f32, f32) {
+    let s = scale(res);
+    (
+        (x - res.width() as f32 / 2.0) / s + 0.5,
+        (y - res.height() as f32 / 2.0) / s + 0.5,
+    )
+}
+
+/// Computes the axis-aligned bounds (left, top, right, bottom) of the rotated bounding rectangle of
+/// `raw` in pixel coordinates of an image with resolution `res`.
+fn pixel_bounds(raw: &RawDetection, res: Resolution) -> [f32; 4] {
+    let mut bounds = [f32::MAX, f32::MAX, f32::MIN, f32::MIN];
+    for (x, y) in raw.rotated_bounding_rect().rotated_corners() {
+        let (x, y) = to_pixels(x, y, res);
+        bounds[0] = bounds[0].min(x);
+        bounds[1] = bounds[1].min(y);
+        bounds[2] = bounds[2].max(x);
+        bounds[3] = bounds[3].max(y);
+    }
+    bounds
+}
+
+/// Detections closer than this fraction of the view size to an inner view edge are considered to be
+/// cut off.
+const EDGE_MARGIN: f32 = 0.01;
+
+/// Returns whether `raw`, detected in the `view` area of an image with resolution `full_res`, touches
+/// an edge of `view` that lies inside the image.
+///
+/// Such detections were likely cut off by the view boundary.
+pub(super) fn is_cut_off(raw: &RawDetection, view: Rect, full_res: Resolution) -> bool {
+    let view_res = Resolution::new(view.width(), view.height());
+    let margin = EDGE_MARGIN * view.width().max(view.height()) as f32;
+    let inner_edges = [
+        view.x() > 0,
+        view.y() > 0,
+        view.x() + (view.width() as i32) < full_res.width() as i32,
+        view.y() + (view.height() as i32) < full_res.height() as i32,
+    ];
+
+    let [left, top, right, bottom] = pixel_bounds(raw, view_res);
+    let touches = [
+        left <= margin,
+        top <= margin,
+        right >= view_res.width() as f32 - margin,
+        bottom >= view_res.height() as f32 - margin,
+    ];
+    inner_edges
+        .iter()
+        .zip(touches)
+        .any(|(&inner, touches)| inner && touches)
+}
+
+/// Computes the start coordinates of tiles of size `tile` covering a line of length `len`.
+///
+/// Tiles are distributed evenly, so that the first tile starts at 0, the last tile ends at `len`,
+/// and neighboring tiles overlap by at least `overlap * tile`.
+pub(super) fn tile_positions(len: u32, tile: u32, overlap: f32) -> Vec<u32> {
+    if len <= tile {
+        return vec![0];
+    }
+
+    let stride = (tile as f32 * (1.0 - overlap)).max(1.0);
+    let count = ((len - tile) as f32 / stride).ceil() as u32 + 1;
+    let step = (len - tile) as f32 / (count - 1) as f32;
+    (0..count)
+        .map(|i| (i as f32 * step).round() as u32)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tile_positions() {
+        assert_eq!(tile_positions(100, 200, 0.25), [0]);
+        assert_eq!(tile_positions(200, 200, 0.25), [0]);
+        assert_eq!(tile_positions(300, 200, 0.0), [0, 100]);
+        assert_eq!(tile_positions(400, 200, 0.0), [0, 200]);
+        assert_eq!(tile_positions(400, 200, 0.25), [0, 100, 200]);
+
+        for len in [201, 333, 1080, 1920, 3840] {
+            for overlap in [0.0, 0.1, 0.5, 0.9] {
+                let pos = tile_positions(len, 200, overlap);
+                assert_eq!(pos[0], 0);
+                assert_eq!(*pos.last().unwrap(), len - 200);
+                for w in pos.windows(2) {
+                    let actual_overlap = 200 - (w[1] - w[0]);
+                    assert!(actual_overlap as f32 >= (200.0 * overlap).floor());
+                }
+            }
+        }
+    }
+}
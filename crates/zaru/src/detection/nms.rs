@@ -10,6 +10,12 @@
 //! ([`SuppressionMode::Average`]) which instead computes a weighted average of overlapping
 //! detections. Since the latter reduces jitter between frames, and does not seem to have any
 //! appreciable drawbacks (outside of a minor computational cost), it is used by default.
+//!
+//! Independently of that, [`IouMode`] selects whether overlap between detections is computed from
+//! their axis-aligned [`BoundingRect`]s, or from their [`RotatedBoundingRect`]s. The latter works
+//! much better for objects that are often seen at an angle, like hands.
+//!
+//! [`RotatedBoundingRect`]: super::RotatedBoundingRect
 
 use zaru_utils::{iter::zip_exact, num::TotalF32};
 
@@ -21,6 +27,7 @@ pub struct NonMaxSuppression {
     avg_buf: Vec<RawDetection>,
     out_buf: Vec<RawDetection>,
     mode: SuppressionMode,
+    iou_mode: IouMode,
 }
 
 impl NonMaxSuppression {
@@ -29,14 +36,15 @@ impl NonMaxSuppression {
 
     /// Creates a new non-maximum suppressor.
     ///
-    /// The returned suppression algorithm will use [`SuppressionMode::Average`] and a default IOU
-    /// threshold.
+    /// The returned suppression algorithm will use [`SuppressionMode::Average`],
+    /// [`IouMode::AxisAligned`] and a default IOU threshold.
     pub fn new() -> Self {
         Self {
             iou_thresh: Self::DEFAULT_IOU_THRESH,
             avg_buf: Vec::new(),
             out_buf: Vec::new(),
             mode: SuppressionMode::Average,
+            iou_mode: IouMode::AxisAligned,
         }
     }
 
@@ -52,6 +60,16 @@ impl NonMaxSuppression {
         self.mode = mode;
     }
 
+    /// Returns how the intersection-over-union of two detections is computed.
+    pub fn iou_mode(&self) -> IouMode {
+        self.iou_mode
+    }
+
+    /// Sets how the intersection-over-union of two detections is computed.
+    pub fn set_iou_mode(&mut self, iou_mode: IouMode) {
+        self.iou_mode = iou_mode;
+    }
+
     /// Performs non-maximum suppression on `detections`.
     ///
     /// `detections` will be modified in the process. The filtered detections are returned as an
@@ -65,11 +83,12 @@ impl NonMaxSuppression {
         // Sort by ascending confidence, process highest confidence first by starting at the back.
         detections.sort_unstable_by_key(|det| TotalF32(det.confidence));
 
+        let iou_mode = self.iou_mode;
         while let Some(seed) = detections.pop() {
             match self.mode {
                 SuppressionMode::Remove => {
                     detections.retain(|other| {
-                        let iou = seed.bounding_rect().iou(&other.bounding_rect());
+                        let iou = iou_mode.iou(&seed, other);
                         if iou >= self.iou_thresh {
                             false // remove from detection list
                         } else {
@@ -82,7 +101,7 @@ impl NonMaxSuppression {
                     self.avg_buf.clear();
                     self.avg_buf.push(seed.clone());
                     detections.retain(|other| {
-                        let iou = seed.bounding_rect().iou(&other.bounding_rect());
+                        let iou = iou_mode.iou(&seed, other);
                         if iou >= self.iou_thresh {
                             // FIXME: unnecessary clone, required only because `drain_filter` is unstable
                             self.avg_buf.push(other.clone());
@@ -96,6 +115,8 @@ impl NonMaxSuppression {
                     let mut acc_rect = BoundingRect::from_center(0.0, 0.0, 0.0, 0.0);
                     let mut acc = RawDetection::new(seed.confidence(), acc_rect);
                     let mut divisor = 0.0;
+                    // Rotations are averaged as unit vectors to avoid wrap-around issues.
+                    let (mut acc_sin, mut acc_cos) = (0.0, 0.0);
                     for det in &self.avg_buf {
                         if acc.keypoints().is_empty() && !det.keypoints().is_empty() {
                             acc.keypoints_mut()
@@ -119,6 +140,8 @@ impl NonMaxSuppression {
                         acc_rect.yc += rect.yc * factor;
                         acc_rect.w += rect.w * factor;
                         acc_rect.h += rect.h * factor;
+                        acc_sin += det.rotation_radians().sin() * factor;
+                        acc_cos += det.rotation_radians().cos() * factor;
                     }
 
                     for lm in &mut acc.keypoints {
@@ -131,6 +154,7 @@ impl NonMaxSuppression {
                     acc_rect.h /= divisor;
 
                     acc.set_bounding_rect(acc_rect);
+                    acc.set_rotation_radians(f32::atan2(acc_sin, acc_cos));
                     self.out_buf.push(acc);
                 }
             }
@@ -152,6 +176,32 @@ pub enum SuppressionMode {
     Average,
 }
 
+/// Describes how [`NonMaxSuppression`] computes the overlap between two detections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IouMode {
+    /// Compute the intersection-over-union of the axis-aligned [`BoundingRect`]s, ignoring the
+    /// rotation of the detections.
+    AxisAligned,
+
+    /// Compute the exact intersection-over-union of the [`RotatedBoundingRect`]s of the detections.
+    ///
+    /// [`RotatedBoundingRect`]: super::RotatedBoundingRect
+    ///
+    /// This is more expensive than [`IouMode::AxisAligned`], and only makes a difference if the
+    /// detector assigns a rotation to its detections.
+    Rotated,
+}
+
+impl IouMode {
+    fn iou(self, a: &RawDetection, b: &RawDetection) -> f32 {
+        match self {
+            IouMode::AxisAligned => a.bounding_rect().iou(&b.bounding_rect()),
+            IouMode::Rotated => a.rotated_bounding_rect().iou(&b.rotated_bounding_rect()),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -208,4 +258,43 @@ mod tests {
         assert_eq!(rect.w, 2.0);
         assert_eq!(rect.h, 2.0);
     }
+
+    #[test]
+    fn nms_rotated_keeps_parallel_diagonals() {
+        // Two thin, parallel, diagonal boxes. Their unrotated boxes overlap, but the rotated boxes
+        // are disjoint.
+        let diagonal = |offset: f32| {
+            let mut det = RawDetection::new(1.0, BoundingRect::from_center(offset, 0.0, 1.0, 0.1));
+            det.set_rotation_radians(std::f32::consts::FRAC_PI_4);
+            det
+        };
+
+        let mut nms = NonMaxSuppression::new();
+        nms.set_mode(SuppressionMode::Remove);
+        let detections = nms.process(&mut vec![diagonal(0.0), diagonal(0.3)]).count();
+        assert_eq!(detections, 1);
+
+        nms.set_iou_mode(IouMode::Rotated);
+        let detections = nms.process(&mut vec![diagonal(0.0), diagonal(0.3)]).count();
+        assert_eq!(detections, 2);
+    }
+
+    #[test]
+    fn nma_averages_rotation() {
+        let mut nms = NonMaxSuppression::new();
+        nms.set_mode(SuppressionMode::Average);
+        nms.set_iou_mode(IouMode::Rotated);
+
+        let rect = BoundingRect::from_center(0.0, 0.0, 1.0, 1.0);
+        let mut a = RawDetection::new(1.0, rect);
+        a.set_rotation_radians(3.0);
+        let mut b = RawDetection::new(1.0, rect);
+        b.set_rotation_radians(-3.0);
+        let detections = nms.process(&mut vec![a, b]).collect::<Vec<_>>();
+        assert_eq!(detections.len(), 1);
+
+        // Averaging must not produce a rotation of 0 when wrapping around.
+        let radians = detections[0].rotation_radians();
+        assert!(radians.abs() > 3.1, "{radians}");
+    }
 }
@@ -0,0 +1,120 @@
+//! Test helpers shared by the detection submodules.
+
+use zaru_image::{Color, Image, ImageView, Rect, Resolution};
+
+use crate::timer::Timer;
+
+use super::{
+    geometry::{from_pixels, scale},
+    nms::NonMaxSuppression,
+    BoundingRect, DetectionLike, Detector, RawDetection,
+};
+
+/// Detector that "detects" the bounding rectangle of all white pixels in the image.
+///
+/// The detection can be restricted with a filter function, which is passed the detected rectangle
+/// and the resolution of the image.
+pub struct WhiteDetector {
+    filter: fn(Rect, Resolution) -> bool,
+    nms: NonMaxSuppression,
+    detections: Vec<WhiteDetection>,
+}
+
+impl WhiteDetector {
+    /// Creates a detector that reports any group of white pixels.
+    pub fn new() -> Self {
+        Self::with_filter(|_, _| true)
+    }
+
+    /// Creates a detector that only reports the white pixels if `filter` returns `true`.
+    pub fn with_filter(filter: fn(Rect, Resolution) -> bool) -> Self {
+        Self {
+            filter,
+            nms: NonMaxSuppression::new(),
+            detections: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct WhiteDetection {
+    raw: RawDetection,
+    full_res: Resolution,
+}
+
+impl DetectionLike for WhiteDetection {
+    fn from_raw(raw: RawDetection, full_res: Resolution) -> Self {
+        Self { raw, full_res }
+    }
+
+    fn raw(&self) -> &RawDetection {
+        &self.raw
+    }
+
+    fn full_resolution(&self) -> Resolution {
+        self.full_res
+    }
+}
+
+impl Detector for WhiteDetector {
+    type Detection = WhiteDetection;
+
+    fn input_resolution(&self) -> Resolution {
+        Resolution::new(64, 64)
+    }
+
+    fn detect(&mut self, image: ImageView<'_>) -> &[WhiteDetection] {
+        self.detections.clear();
+        let points = image
+            .rect()
+            .iter_coords()
+            .filter(|&(x, y)| image.get(x as u32, y as u32) == Color::WHITE)
+            .map(|(x, y)| (x as i32, y as i32));
+        let res = image.resolution();
+        if let Some(rect) = Rect::bounding(points).filter(|&rect| (self.filter)(rect, res)) {
+            let s = scale(res);
+            let (xc, yc) = rect.center();
+            let (xc, yc) = from_pixels(xc, yc, res);
+            let bounding = BoundingRect::from_center(
+                xc,
+                yc,
+                rect.width() as f32 / s,
+                rect.height() as f32 / s,
+            );
+            self.detections.push(WhiteDetection {
+                raw: RawDetection::new(1.0, bounding),
+                full_res: res,
+            });
+        }
+        &self.detections
+    }
+
+    fn timers(&self) -> Box<dyn Iterator<Item = &Timer> + '_> {
+        Box::new(std::iter::empty())
+    }
+
+    fn threshold(&self) -> f32 {
+        0.5
+    }
+
+    fn set_threshold(&mut self, _threshold: f32) {}
+
+    fn nms_mut(&mut self) -> &mut NonMaxSuppression {
+        &mut self.nms
+    }
+}
+
+/// Creates a black image with resolution `res`, with the pixels inside `white` set to white.
+pub fn image_with_rect(res: Resolution, white: Rect) -> Image {
+    let mut buf = Vec::with_capacity(res.num_pixels() as usize * 4);
+    for y in 0..res.height() as i64 {
+        for x in 0..res.width() as i64 {
+            if white.contains_point(x, y) {
+                buf.extend([255; 4]);
+            } else {
+                buf.extend([0, 0, 0, 255]);
+            }
+        }
+    }
+    Image::from_rgba8(res, &buf)
+}
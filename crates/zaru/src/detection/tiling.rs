@@ -0,0 +1,311 @@
+//! Tiled object detection for high-resolution images.
+//!
+//! Detection networks have a small, fixed input resolution, so the input image has to be scaled
+//! down considerably before it can be processed. In high-resolution images, small objects end up
+//! being only a few pixels large after that and will not be detected anymore.
+//!
+//! [`TiledDetector`] works around this by splitting the input image into overlapping tiles, running
+//! the wrapped [`Detector`] on every tile, and merging the results with [`NonMaxSuppression`].
+//! Optionally, an additional pass over the whole image is performed to find objects that are too
+//! large to fit into a single tile.
+
+use zaru_image::{AsImageView, ImageView, Rect, Resolution};
+
+use crate::timer::Timer;
+
+use super::{
+    geometry::{from_pixels, is_cut_off, scale, tile_positions, to_pixels},
+    nms::NonMaxSuppression,
+    DetectionLike, Detector, RawDetection,
+};
+
+/// Runs a [`Detector`] on overlapping tiles of the input image.
+///
+/// Detections of all tiles are mapped back to full-image coordinates and merged with
+/// [`NonMaxSuppression`].
+///
+/// Detections that touch the edge of a tile are discarded, unless that edge is also an edge of the
+/// input image: the object was likely cut off by the tile boundary, and should be fully visible in
+/// a neighboring tile instead. For this to work, the tile overlap has to be larger than the objects
+/// to detect. Larger objects are found by the full-frame pass (see
+/// [`TiledDetector::set_full_frame_pass`]).
+pub struct TiledDetector<D: Detector> {
+    detector: D,
+    tile_size: u32,
+    overlap: f32,
+    full_frame_pass: bool,
+    nms: NonMaxSuppression,
+    raw_detections: Vec<RawDetection>,
+    detections: Vec<D::Detection>,
+}
+
+impl<D: Detector> TiledDetector<D> {
+    /// The default width and height of each tile, in pixels.
+    pub const DEFAULT_TILE_SIZE: u32 = 512;
+
+    /// The default fraction of each tile that overlaps with its neighbors.
+    pub const DEFAULT_OVERLAP: f32 = 0.25;
+
+    /// Creates a new [`TiledDetector`] wrapping `detector`.
+    ///
+    /// The full-frame pass is enabled by default. Detections are merged with the
+    /// [`IouMode`][super::nms::IouMode] used by `detector`.
+    pub fn new(mut detector: D) -> Self {
+        let mut nms = NonMaxSuppression::new();
+        nms.set_iou_mode(detector.nms_mut().iou_mode());
+        Self {
+            detector,
+            tile_size: Self::DEFAULT_TILE_SIZE,
+            overlap: Self::DEFAULT_OVERLAP,
+            full_frame_pass: true,
+            nms,
+            raw_detections: Vec::new(),
+            detections: Vec::new(),
+        }
+    }
+
+    /// Returns a reference to the wrapped [`Detector`].
+    pub fn detector(&self) -> &D {
+        &self.detector
+    }
+
+    /// Returns a mutable reference to the wrapped [`Detector`].
+    pub fn detector_mut(&mut self) -> &mut D {
+        &mut self.detector
+    }
+
+    /// Sets the width and height of the tiles, in pixels.
+    ///
+    /// Every tile is scaled to the input resolution of the wrapped detector, so smaller tiles allow
+    /// detecting smaller objects, at the cost of having to process more tiles.
+    ///
+    /// By default, [`Self::DEFAULT_TILE_SIZE`] is used.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `tile_size` is 0.
+    pub fn set_tile_size(&mut self, tile_size: u32) {
+        assert_ne!(tile_size, 0);
+        self.tile_size = tile_size;
+    }
+
+    /// Sets the minimum fraction of each tile that overlaps with its neighbors.
+    ///
+    /// By default, [`Self::DEFAULT_OVERLAP`] is used.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `overlap` is not in range `0.0..1.0`.
+    pub fn set_overlap(&mut self, overlap: f32) {
+        assert!((0.0..1.0).contains(&overlap), "invalid overlap {overlap}");
+        self.overlap = overlap;
+    }
+
+    /// Enables or disables the additional detection pass over the whole (downscaled) image.
+    ///
+    /// This pass detects objects that are too large to fit into a single tile. It is skipped if the
+    /// image fits into a single tile.
+    pub fn set_full_frame_pass(&mut self, enable: bool) {
+        self.full_frame_pass = enable;
+    }
+
+    /// Computes the tiles that an image of resolution `res` will be split into.
+    pub fn tiles(&self, res: Resolution) -> Vec<Rect> {
+        let xs = tile_positions(res.width(), self.tile_size, self.overlap);
+        let ys = tile_positions(res.height(), self.tile_size, self.overlap);
+        let (w, h) = (
+            self.tile_size.min(res.width()),
+            self.tile_size.min(res.height()),
+        );
+
+        let mut tiles = Vec::with_capacity(xs.len() * ys.len());
+        for &y in &ys {
+            for &x in &xs {
+                tiles.push(Rect::from_top_left(x as i32, y as i32, w, h));
+            }
+        }
+        tiles
+    }
+
+    /// Runs the detector on all tiles of `image`, returning the merged detections.
+    pub fn detect<V: AsImageView>(&mut self, image: &V) -> &[D::Detection] {
+        self.detect_impl(image.as_view())
+    }
+
+    fn detect_impl(&mut self, image: ImageView<'_>) -> &[D::Detection] {
+        self.raw_detections.clear();
+        self.detections.clear();
+
+        let full_res = image.resolution();
+        let tiles = self.tiles(full_res);
+        for tile in &tiles {
+            for det in self.detector.detect(image.view(*tile)) {
+                let tile_res = det.full_resolution();
+                if is_cut_off(det.raw(), *tile, full_res) {
+                    continue;
+                }
+
+                let mut raw = det.raw().clone();
+                remap(
+                    &mut raw,
+                    tile_res,
+                    (tile.x() as f32, tile.y() as f32),
+                    full_res,
+                );
+                self.raw_detections.push(raw);
+            }
+        }
+
+        if self.full_frame_pass && tiles.len() > 1 {
+            for det in self.detector.detect(image) {
+                self.raw_detections.push(det.raw().clone());
+            }
+        }
+
+        for raw in self.nms.process(&mut self.raw_detections) {
+            self.detections.push(D::Detection::from_raw(raw, full_res));
+        }
+
+        &self.detections
+    }
+}
+
+impl<D: Detector> Detector for TiledDetector<D> {
+    type Detection = D::Detection;
+
+    fn input_resolution(&self) -> Resolution {
+        self.detector.input_resolution()
+    }
+
+    fn detect(&mut self, image: ImageView<'_>) -> &[Self::Detection] {
+        self.detect_impl(image)
+    }
+
+    fn timers(&self) -> Box<dyn Iterator<Item = &Timer> + '_> {
+        self.detector.timers()
+    }
+
+    fn threshold(&self) -> f32 {
+        self.detector.threshold()
+    }
+
+    fn set_threshold(&mut self, threshold: f32) {
+        self.detector.set_threshold(threshold);
+    }
+
+    /// Returns the [`NonMaxSuppression`] used to merge the detections of all tiles.
+    ///
+    /// The wrapped detector's [`NonMaxSuppression`] can be accessed via
+    /// [`TiledDetector::detector_mut`].
+    fn nms_mut(&mut self) -> &mut NonMaxSuppression {
+        &mut self.nms
+    }
+}
+
+/// Maps `raw` from an image of resolution `from` to an image of resolution `to`, where the top left
+/// corner of the `from` image is located at `offset` in the `to` image.
+fn remap(raw: &mut RawDetection, from: Resolution, offset: (f32, f32), to: Resolution) {
+    let map = |x, y| {
+        let (x, y) = to_pixels(x, y, from);
+        from_pixels(x + offset.0, y + offset.1, to)
+    };
+    let size_factor = scale(from) / scale(to);
+
+    let rect = &mut raw.rect;
+    (rect.xc, rect.yc) = map(rect.xc, rect.yc);
+    rect.w *= size_factor;
+    rect.h *= size_factor;
+
+    for kp in &mut raw.keypoints {
+        (kp.x, kp.y) = map(kp.x, kp.y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use crate::detection::{
+        nms::IouMode,
+        test::{image_with_rect, WhiteDetector},
+        BoundingRect, Keypoint,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_remap() {
+        // Tile on the right half of a 2:1 image.
+        let mut raw = RawDetection::with_keypoints(
+            1.0,
+            BoundingRect::from_center(0.5, 0.5, 0.1, 0.2),
+            vec![Keypoint::new(0.0, 0.25)],
+        );
+        remap(
+            &mut raw,
+            Resolution::new(200, 200),
+            (200.0, 0.0),
+            Resolution::new(400, 200),
+        );
+
+        let rect = raw.bounding_rect();
+        assert_relative_eq!(rect.xc, 0.75);
+        assert_relative_eq!(rect.yc, 0.5);
+        assert_relative_eq!(rect.w, 0.05);
+        assert_relative_eq!(rect.h, 0.1);
+        assert_relative_eq!(raw.keypoints()[0].x(), 0.5);
+        assert_relative_eq!(raw.keypoints()[0].y(), 0.375);
+    }
+
+    #[test]
+    fn tiled_detection_finds_object() {
+        let res = Resolution::new(300, 200);
+        let square = Rect::from_top_left(170, 90, 20, 20);
+        let image = image_with_rect(res, square);
+
+        let mut tiled = TiledDetector::new(WhiteDetector::new());
+        tiled.set_tile_size(100);
+        tiled.set_overlap(0.3);
+        tiled.set_full_frame_pass(false);
+
+        let detections = tiled.detect(&image);
+        assert_eq!(detections.len(), 1);
+
+        let rect = detections[0].raw().bounding_rect();
+        let (xc, yc) = to_pixels(rect.xc, rect.yc, res);
+        assert_relative_eq!(xc, 180.0, epsilon = 0.5);
+        assert_relative_eq!(yc, 100.0, epsilon = 0.5);
+        assert_relative_eq!(rect.w * scale(res), 20.0, epsilon = 0.5);
+        assert_relative_eq!(rect.h * scale(res), 20.0, epsilon = 0.5);
+    }
+
+    #[test]
+    fn full_frame_pass_finds_large_object() {
+        let res = Resolution::new(300, 200);
+        // Too large to fit into any tile.
+        let square = Rect::from_top_left(50, 20, 150, 150);
+        let image = image_with_rect(res, square);
+
+        let mut tiled = TiledDetector::new(WhiteDetector::new());
+        tiled.set_tile_size(100);
+        tiled.set_full_frame_pass(false);
+        assert!(tiled.detect(&image).is_empty());
+
+        tiled.set_full_frame_pass(true);
+        let detections = tiled.detect(&image);
+        assert_eq!(detections.len(), 1);
+        let rect = detections[0].raw().bounding_rect();
+        assert_relative_eq!(rect.w * scale(res), 150.0, epsilon = 0.5);
+    }
+
+    #[test]
+    fn inherits_iou_mode() {
+        let mut det = WhiteDetector::new();
+        det.nms_mut().set_iou_mode(IouMode::Rotated);
+        let mut tiled = TiledDetector::new(det);
+        assert_eq!(tiled.nms_mut().iou_mode(), IouMode::Rotated);
+
+        let mut tiled = TiledDetector::new(WhiteDetector::new());
+        assert_eq!(tiled.nms_mut().iou_mode(), IouMode::AxisAligned);
+    }
+}
@@ -0,0 +1,499 @@
+//! SORT-style multi-object tracking on top of detections.
+//!
+//! Detectors work on individual images and have no notion of object identity. [`ObjectTracker`]
+//! associates the detections of consecutive frames with each other, assigning each tracked object
+//! a persistent [`TrackId`].
+//!
+//! The implementation follows [SORT] (Simple Online and Realtime Tracking):
+//!
+//! - Every track estimates the object's bounding rectangle and its velocity with a
+//!   constant-velocity Kalman filter, which is used to predict the object's position in the next
+//!   frame.
+//! - Predicted track positions and new detections are compared with a [`CostMetric`], and the
+//!   globally optimal assignment is computed with the Hungarian algorithm.
+//! - Detections that are not assigned to any track start a new, *tentative* track, which is
+//!   confirmed after it has been detected in several consecutive frames. Tracks that are not
+//!   detected for too many frames are removed.
+//!
+//! [SORT]: https://arxiv.org/abs/1602.00763
+
+use nalgebra::{DMatrix, SMatrix, SVector};
+
+use crate::assignment::hungarian;
+
+use super::{BoundingRect, DetectionLike, RawDetection};
+
+/// Trait for objects that can be tracked by an [`ObjectTracker`].
+///
+/// This is implemented for [`RawDetection`], [`BoundingRect`], and every [`DetectionLike`] type,
+/// so the tracker can be fed with the output of any [`Detector`][super::Detector].
+pub trait Trackable {
+    /// Returns the bounding rectangle of the object.
+    ///
+    /// All objects passed to the same [`ObjectTracker`] have to use the same coordinate system.
+    fn tracking_rect(&self) -> BoundingRect;
+}
+
+impl Trackable for BoundingRect {
+    fn tracking_rect(&self) -> BoundingRect {
+        *self
+    }
+}
+
+impl Trackable for RawDetection {
+    fn tracking_rect(&self) -> BoundingRect {
+        self.bounding_rect()
+    }
+}
+
+impl<D: DetectionLike> Trackable for D {
+    fn tracking_rect(&self) -> BoundingRect {
+        BoundingRect::from(self.bounding_rect())
+    }
+}
+
+/// Metric used to compute the cost of assigning a detection to a track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CostMetric {
+    /// The cost is `1.0 - IoU` of the predicted track rectangle and the detection.
+    ///
+    /// This is the metric used by SORT. It works well when objects move by less than their size
+    /// between frames.
+    Iou,
+
+    /// The cost is the distance between the centers of the predicted track rectangle and the
+    /// detection, divided by the mean side length of the track rectangle.
+    ///
+    /// This allows associating fast-moving or small objects whose rectangles don't overlap between
+    /// frames.
+    CenterDistance,
+}
+
+impl CostMetric {
+    fn cost(self, track: &BoundingRect, detection: &BoundingRect) -> f32 {
+        match self {
+            CostMetric::Iou => 1.0 - track.iou(detection),
+            CostMetric::CenterDistance => {
+                let (dx, dy) = (detection.xc - track.xc, detection.yc - track.yc);
+                let size = (track.w + track.h) * 0.5;
+                (dx * dx + dy * dy).sqrt() / size.max(f32::EPSILON)
+            }
+        }
+    }
+}
+
+/// Identifies a track of an [`ObjectTracker`].
+///
+/// [`TrackId`]s are unique per [`ObjectTracker`] assigning them, and stay the same for as long as
+/// the object is tracked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TrackId(u64);
+
+/// An object tracked across frames.
+#[derive(Debug, Clone)]
+pub struct Track<T> {
+    id: TrackId,
+    kalman: KalmanBox,
+    object: T,
+    hits: u32,
+    misses: u32,
+    confirmed: bool,
+}
+
+impl<T> Track<T> {
+    /// Returns the unique ID of this track.
+    pub fn id(&self) -> TrackId {
+        self.id
+    }
+
+    /// Returns the object that was last assigned to this track.
+    ///
+    /// If the track was not detected in the latest frame ([`Track::misses`] is non-zero), this
+    /// object describes the last known state, and [`Track::rect`] should be used to obtain the
+    /// predicted position instead.
+    pub fn object(&self) -> &T {
+        &self.object
+    }
+
+    /// Returns the estimated bounding rectangle of the tracked object.
+    ///
+    /// This is the Kalman-filtered position of the object in the current frame. If the object was
+    /// not detected in the current frame, it is the predicted position.
+    pub fn rect(&self) -> BoundingRect {
+        self.kalman.rect()
+    }
+
+    /// Returns the estimated velocity of the object's center, in units per frame.
+    pub fn velocity(&self) -> (f32, f32) {
+        (self.kalman.x[4], self.kalman.x[5])
+    }
+
+    /// Returns the number of consecutive frames the object was detected in.
+    pub fn hits(&self) -> u32 {
+        self.hits
+    }
+
+    /// Returns the number of consecutive frames the object was *not* detected in.
+    ///
+    /// This is 0 if the object was detected in the latest frame.
+    pub fn misses(&self) -> u32 {
+        self.misses
+    }
+}
+
+/// Assigns persistent identities to detections across frames.
+///
+/// See the [module documentation][self] for an overview of the algorithm.
+pub struct ObjectTracker<T> {
+    tracks: Vec<Track<T>>,
+    next_id: TrackId,
+    metric: CostMetric,
+    max_cost: f32,
+    min_hits: u32,
+    max_misses: u32,
+}
+
+impl<T: Trackable> ObjectTracker<T> {
+    /// Default maximum assignment cost (with [`CostMetric::Iou`], this requires an IoU of 0.3).
+    pub const DEFAULT_MAX_COST: f32 = 0.7;
+
+    /// Default number of consecutive detections required to confirm a new track.
+    pub const DEFAULT_MIN_HITS: u32 = 3;
+
+    /// Default number of consecutive frames a confirmed track may go undetected before it is
+    /// removed.
+    pub const DEFAULT_MAX_MISSES: u32 = 3;
+
+    pub fn new() -> Self {
+        Self {
+            tracks: Vec::new(),
+            next_id: TrackId(0),
+            metric: CostMetric::Iou,
+            max_cost: Self::DEFAULT_MAX_COST,
+            min_hits: Self::DEFAULT_MIN_HITS,
+            max_misses: Self::DEFAULT_MAX_MISSES,
+        }
+    }
+
+    /// Sets the [`CostMetric`] used to compare tracks and detections.
+    ///
+    /// By default, [`CostMetric::Iou`] is used.
+    pub fn set_cost_metric(&mut self, metric: CostMetric) {
+        self.metric = metric;
+    }
+
+    /// Sets the maximum cost (as computed by the [`CostMetric`]) at which a detection may still be
+    /// assigned to a track.
+    pub fn set_max_cost(&mut self, max_cost: f32) {
+        self.max_cost = max_cost;
+    }
+
+    /// Sets the number of consecutive frames an object has to be detected in before its track is
+    /// confirmed and returned by [`ObjectTracker::tracks`].
+    ///
+    /// Tentative tracks that are not detected in a frame are removed immediately.
+    pub fn set_min_hits(&mut self, min_hits: u32) {
+        self.min_hits = min_hits;
+    }
+
+    /// Sets the number of consecutive frames a confirmed track may go undetected before it is
+    /// removed.
+    ///
+    /// While a track is not detected, its position is extrapolated from its estimated velocity.
+    pub fn set_max_misses(&mut self, max_misses: u32) {
+        self.max_misses = max_misses;
+    }
+
+    /// Removes all tracks.
+    pub fn reset(&mut self) {
+        self.tracks.clear();
+    }
+
+    /// Updates the tracker with the objects detected in the next frame.
+    pub fn update<I: IntoIterator<Item = T>>(&mut self, objects: I) {
+        let objects = objects.into_iter().collect::<Vec<_>>();
+        let rects = objects
+            .iter()
+            .map(Trackable::tracking_rect)
+            .collect::<Vec<_>>();
+
+        for track in &mut self.tracks {
+            track.kalman.predict();
+        }
+
+        // Infeasible pairs get a large cost so that they're only assigned when unavoidable, and
+        // are then rejected below.
+        let infeasible = self.max_cost + 1.0;
+        let costs = DMatrix::from_fn(self.tracks.len(), rects.len(), |t, d| {
+            let cost = self.metric.cost(&self.tracks[t].kalman.rect(), &rects[d]);
+            if cost.is_finite() && cost <= self.max_cost {
+                cost
+            } else {
+                infeasible
+            }
+        });
+        let assignment = hungarian(&costs);
+
+        let mut objects = objects.into_iter().map(Some).collect::<Vec<_>>();
+        for (t, track) in self.tracks.iter_mut().enumerate() {
+            match assignment[t] {
+                Some(d) if costs[(t, d)] <= self.max_cost => {
+                    track.kalman.update(&rects[d]);
+                    track.object = objects[d].take().unwrap();
+                    track.hits += 1;
+                    track.misses = 0;
+                    if track.hits >= self.min_hits {
+                        track.confirmed = true;
+                    }
+                }
+                _ => {
+                    track.hits = 0;
+                    track.misses += 1;
+                }
+            }
+        }
+
+        let max_misses = self.max_misses;
+        self.tracks
+            .retain(|track| track.misses == 0 || (track.confirmed && track.misses <= max_misses));
+
+        for (object, rect) in objects.into_iter().zip(rects) {
+            if let Some(object) = object {
+                let id = self.next_id;
+                self.next_id.0 += 1;
+                self.tracks.push(Track {
+                    id,
+                    kalman: KalmanBox::new(&rect),
+                    object,
+                    hits: 1,
+                    misses: 0,
+                    confirmed: self.min_hits <= 1,
+                });
+            }
+        }
+    }
+
+    /// Returns an iterator over all confirmed tracks.
+    ///
+    /// This includes tracks that were not detected in the latest frame (see [`Track::misses`]), but
+    /// not tentative tracks that have not been detected often enough yet.
+    pub fn tracks(&self) -> impl Iterator<Item = &Track<T>> {
+        self.tracks.iter().filter(|track| track.confirmed)
+    }
+}
+
+impl<T: Trackable> Default for ObjectTracker<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Weights of the position and velocity noise, relative to the object size.
+///
+/// These are the values used by DeepSORT. Scaling the noise by the object size makes the filter
+/// independent of the coordinate system.
+const STD_WEIGHT_POSITION: f32 = 1.0 / 20.0;
+const STD_WEIGHT_VELOCITY: f32 = 1.0 / 160.0;
+
+/// Constant-velocity Kalman filter over the state `[xc, yc, w, h, vxc, vyc, vw, vh]`.
+#[derive(Debug, Clone)]
+struct KalmanBox {
+    x: SVector<f32, 8>,
+    p: SMatrix<f32, 8, 8>,
+}
+
+impl KalmanBox {
+    fn new(rect: &BoundingRect) -> Self {
+        let s = size(rect);
+        let pos = 2.0 * STD_WEIGHT_POSITION * s;
+        let vel = 10.0 * STD_WEIGHT_VELOCITY * s;
+        let std = SVector::<f32, 8>::from([pos, pos, pos, pos, vel, vel, vel, vel]);
+        Self {
+            x: SVector::from([rect.xc, rect.yc, rect.w, rect.h, 0.0, 0.0, 0.0, 0.0]),
+            p: SMatrix::from_diagonal(&std.component_mul(&std)),
+        }
+    }
+
+    fn rect(&self) -> BoundingRect {
+        BoundingRect::from_center(self.x[0], self.x[1], self.x[2].max(0.0), self.x[3].max(0.0))
+    }
+
+    fn predict(&mut self) {
+        let s = size(&self.rect());
+        let pos = STD_WEIGHT_POSITION * s;
+        let vel = STD_WEIGHT_VELOCITY * s;
+        let std = SVector::<f32, 8>::from([pos, pos, pos, pos, vel, vel, vel, vel]);
+        let q = SMatrix::<f32, 8, 8>::from_diagonal(&std.component_mul(&std));
+
+        let mut f = SMatrix::<f32, 8, 8>::identity();
+        for i in 0..4 {
+            f[(i, i + 4)] = 1.0;
+        }
+
+        self.x = f * self.x;
+        self.p = f * self.p * f.transpose() + q;
+    }
+
+    fn update(&mut self, rect: &BoundingRect) {
+        let r = STD_WEIGHT_POSITION * size(&self.rect());
+        let r = SMatrix::<f32, 4, 4>::from_diagonal_element(r * r);
+
+        let h = SMatrix::<f32, 4, 8>::identity();
+        let z = SVector::from([rect.xc, rect.yc, rect.w, rect.h]);
+
+        let s = h * self.p * h.transpose() + r;
+        let Some(s_inv) = s.try_inverse() else {
+            log::warn!("singular innovation covariance, skipping Kalman update");
+            return;
+        };
+        let k = self.p * h.transpose() * s_inv;
+
+        self.x += k * (z - h * self.x);
+        self.p = (SMatrix::<f32, 8, 8>::identity() - k * h) * self.p;
+    }
+}
+
+/// Reference size of `rect` used to scale the filter noise.
+fn size(rect: &BoundingRect) -> f32 {
+    ((rect.w + rect.h) * 0.5).max(f32::EPSILON)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 40x40 object moving along a straight line.
+    fn object(frame: u32, start: (f32, f32), velocity: (f32, f32)) -> BoundingRect {
+        let t = frame as f32;
+        BoundingRect::from_center(
+            start.0 + velocity.0 * t,
+            start.1 + velocity.1 * t,
+            40.0,
+            40.0,
+        )
+    }
+
+    fn ids(tracker: &ObjectTracker<BoundingRect>) -> Vec<TrackId> {
+        let mut ids = tracker.tracks().map(|t| t.id()).collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn single_object() {
+        let mut tracker = ObjectTracker::new();
+        for frame in 0..20 {
+            tracker.update([object(frame, (100.0, 100.0), (5.0, 2.0))]);
+
+            let tracks = tracker.tracks().collect::<Vec<_>>();
+            if frame + 1 < ObjectTracker::<BoundingRect>::DEFAULT_MIN_HITS {
+                assert!(tracks.is_empty(), "track confirmed too early");
+            } else {
+                assert_eq!(tracks.len(), 1);
+                assert_eq!(tracks[0].id(), TrackId(0));
+            }
+        }
+
+        let track = tracker.tracks().next().unwrap();
+        let (vx, vy) = track.velocity();
+        assert!((vx - 5.0).abs() < 0.5, "vx={vx}");
+        assert!((vy - 2.0).abs() < 0.5, "vy={vy}");
+        assert!(track.rect().iou(&object(19, (100.0, 100.0), (5.0, 2.0))) > 0.9);
+    }
+
+    #[test]
+    fn objects_keep_ids_when_passing() {
+        let mut tracker = ObjectTracker::new();
+        let mut first_ids = None;
+        for frame in 0..30 {
+            let a = object(frame, (0.0, 0.0), (10.0, 0.0));
+            let b = object(frame, (300.0, 45.0), (-10.0, 0.0));
+            // Detection order must not matter.
+            if frame % 2 == 0 {
+                tracker.update([a, b]);
+            } else {
+                tracker.update([b, a]);
+            }
+
+            if frame < 2 {
+                continue;
+            }
+            let mut tracks = tracker.tracks().collect::<Vec<_>>();
+            assert_eq!(tracks.len(), 2);
+            tracks.sort_by_key(|t| t.id());
+            // Track 0 was created from `a`, which moves to the right.
+            assert!(tracks[0].rect().iou(&a) > 0.8);
+            assert!(tracks[1].rect().iou(&b) > 0.8);
+            match first_ids {
+                None => first_ids = Some(ids(&tracker)),
+                Some(ref first) => assert_eq!(first, &ids(&tracker)),
+            }
+        }
+    }
+
+    #[test]
+    fn coasts_through_occlusion() {
+        let mut tracker = ObjectTracker::new();
+        for frame in 0..10 {
+            tracker.update([object(frame, (0.0, 0.0), (10.0, 0.0))]);
+        }
+
+        // The object is occluded for 2 frames, during which it moves by more than its size.
+        tracker.update([]);
+        tracker.update([]);
+        let track = tracker.tracks().next().unwrap();
+        assert_eq!(track.misses(), 2);
+        assert!(track.rect().iou(&object(11, (0.0, 0.0), (10.0, 0.0))) > 0.8);
+
+        tracker.update([object(12, (0.0, 0.0), (10.0, 0.0))]);
+        assert_eq!(ids(&tracker), [TrackId(0)]);
+        assert_eq!(tracker.tracks().next().unwrap().misses(), 0);
+    }
+
+    #[test]
+    fn track_birth_and_death() {
+        let mut tracker = ObjectTracker::new();
+        tracker.set_min_hits(2);
+        tracker.set_max_misses(1);
+
+        // A single spurious detection never gets confirmed.
+        tracker.update([object(0, (500.0, 500.0), (0.0, 0.0))]);
+        tracker.update([]);
+        assert!(ids(&tracker).is_empty());
+        assert!(tracker.tracks.is_empty());
+
+        for frame in 0..3 {
+            tracker.update([object(frame, (0.0, 0.0), (0.0, 0.0))]);
+        }
+        assert_eq!(ids(&tracker), [TrackId(1)]);
+
+        tracker.update([]);
+        assert_eq!(ids(&tracker), [TrackId(1)]);
+        tracker.update([]);
+        assert!(ids(&tracker).is_empty());
+
+        // The object reappears and gets a new ID.
+        tracker.update([object(0, (0.0, 0.0), (0.0, 0.0))]);
+        tracker.update([object(0, (0.0, 0.0), (0.0, 0.0))]);
+        assert_eq!(ids(&tracker), [TrackId(2)]);
+    }
+
+    #[test]
+    fn center_distance_metric() {
+        // A small, fast object that does not overlap itself between frames.
+        let small = |frame: u32| BoundingRect::from_center(frame as f32 * 12.0, 0.0, 8.0, 8.0);
+
+        let mut iou_tracker = ObjectTracker::new();
+        let mut dist_tracker = ObjectTracker::new();
+        dist_tracker.set_cost_metric(CostMetric::CenterDistance);
+        dist_tracker.set_max_cost(2.0);
+        for frame in 0..10 {
+            iou_tracker.update([small(frame)]);
+            dist_tracker.update([small(frame)]);
+        }
+
+        assert!(ids(&iou_tracker).is_empty());
+        assert_eq!(ids(&dist_tracker), [TrackId(0)]);
+    }
+}
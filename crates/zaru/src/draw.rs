@@ -0,0 +1,121 @@
+//! Drawing of landmark skeletons and other visualizations.
+//!
+//! This module re-exports all drawing primitives from [`zaru_image::draw`], and adds
+//! [`skeleton`], which draws any set of [`Landmarks`] according to its [`LandmarkTopology`].
+
+pub use zaru_image::draw::*;
+
+use zaru_image::{AsImageViewMut, Color, ImageViewMut};
+
+use crate::landmark::{topology::LandmarkTopology, Landmarks, HIDDEN_COLOR};
+
+/// Guard returned by [`skeleton`]; draws the skeleton when dropped and allows customization.
+pub struct DrawSkeleton<'a> {
+    image: ImageViewMut<'a>,
+    landmarks: &'a Landmarks,
+    topology: &'a LandmarkTopology,
+    color: Option<Color>,
+    stroke_width: u32,
+    marker_size: u32,
+}
+
+impl DrawSkeleton<'_> {
+    /// Draws all edges and landmarks in `color`, instead of the colors defined by the topology.
+    ///
+    /// Landmarks that are not visible are still drawn in [`HIDDEN_COLOR`].
+    pub fn color(&mut self, color: Color) -> &mut Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Sets the stroke width of the edges.
+    ///
+    /// By default, a stroke width of 1 is used.
+    pub fn stroke_width(&mut self, width: u32) -> &mut Self {
+        self.stroke_width = width;
+        self
+    }
+
+    /// Sets the size of the landmark markers.
+    ///
+    /// The default size is 5. The size must be *uneven*. A size of 0 disables drawing of the
+    /// markers, leaving only the edges.
+    pub fn marker_size(&mut self, size: u32) -> &mut Self {
+        assert!(
+            size == 0 || size % 2 == 1,
+            "marker size must be an uneven number"
+        );
+        self.marker_size = size;
+        self
+    }
+}
+
+impl Drop for DrawSkeleton<'_> {
+    fn drop(&mut self) {
+        for &(a, b) in self.topology.edges() {
+            let lm_a = self.landmarks.landmark(a);
+            let lm_b = self.landmarks.landmark(b);
+            let color = if !lm_a.is_visible() || !lm_b.is_visible() {
+                HIDDEN_COLOR
+            } else {
+                self.color.unwrap_or_else(|| self.topology.edge_color(a, b))
+            };
+
+            line(
+                &mut self.image,
+                lm_a.x() as _,
+                lm_a.y() as _,
+                lm_b.x() as _,
+                lm_b.y() as _,
+            )
+            .color(color)
+            .stroke_width(self.stroke_width);
+        }
+
+        if self.marker_size == 0 {
+            return;
+        }
+        for (index, lm) in self.landmarks.iter().enumerate() {
+            let color = if !lm.is_visible() {
+                HIDDEN_COLOR
+            } else {
+                self.color
+                    .unwrap_or_else(|| self.topology.landmark_color(index))
+            };
+
+            marker(&mut self.image, lm.x() as _, lm.y() as _)
+                .color(color)
+                .size(self.marker_size);
+        }
+    }
+}
+
+/// Draws the edges and landmarks described by `topology` onto an image.
+///
+/// Edges and landmarks use the colors defined by the topology, and landmarks that are not
+/// [visible][crate::landmark::Landmark::is_visible] (as well as edges ending at them) are drawn in
+/// [`HIDDEN_COLOR`].
+///
+/// # Panics
+///
+/// This function panics if `landmarks` does not contain exactly as many landmarks as `topology`
+/// describes.
+pub fn skeleton<'a, I: AsImageViewMut>(
+    image: &'a mut I,
+    landmarks: &'a Landmarks,
+    topology: &'a LandmarkTopology,
+) -> DrawSkeleton<'a> {
+    assert_eq!(
+        landmarks.len(),
+        topology.landmark_count(),
+        "landmark count does not match topology"
+    );
+    DrawSkeleton {
+        image: image.as_view_mut(),
+        landmarks,
+        topology,
+        color: None,
+        stroke_width: 1,
+        marker_size: 5,
+    }
+}
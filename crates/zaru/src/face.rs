@@ -1,5 +1,9 @@
 //! Detection, registration and recognition of human faces.
 
+pub mod alignment;
 pub mod detection;
 pub mod eye;
+pub mod expression;
 pub mod landmark;
+pub mod recognition;
+pub mod tracking;
@@ -0,0 +1,224 @@
+//! Face alignment for face recognition.
+//!
+//! Face recognition networks like ArcFace and MobileFaceNet are trained on tightly aligned face
+//! crops, in which the eyes, nose and mouth are always at the same position. Feeding them faces
+//! that are merely cropped from a detection rectangle (with arbitrary scale, offset, and in-plane
+//! rotation) considerably reduces their accuracy.
+//!
+//! An [`Alignment`] computes the similarity transform (rotation, uniform scale and translation)
+//! that maps a set of facial keypoints onto the standard ArcFace [`TEMPLATE`], and can then produce
+//! the aligned [`ALIGNED_SIZE`]x[`ALIGNED_SIZE`] crop from the input image.
+
+use nalgebra::{Point2, Similarity2, Vector2};
+use zaru_image::{Image, RemapTable, Resolution};
+
+use super::{
+    detection::Detection,
+    landmark::mediapipe_facemesh::{LandmarkIdx, LandmarkResult},
+};
+
+/// Width and height of aligned face crops, in pixels.
+pub const ALIGNED_SIZE: u32 = 112;
+
+/// Positions of the alignment keypoints in an aligned face crop.
+///
+/// The keypoints are, in order: left eye center, right eye center, nose tip, left mouth corner,
+/// right mouth corner (all from the perspective of the image, not the depicted person). This is the
+/// template used by ArcFace and MobileFaceNet.
+pub const TEMPLATE: [[f32; 2]; 5] = [
+    [38.2946, 51.6963],
+    [73.5318, 51.5014],
+    [56.0252, 71.7366],
+    [41.5493, 92.3655],
+    [70.7299, 92.2041],
+];
+
+/// A similarity transform that maps a face in an image onto the alignment [`TEMPLATE`].
+#[derive(Debug, Clone, Copy)]
+pub struct Alignment {
+    /// Transform from image coordinates to aligned crop coordinates.
+    transform: Similarity2<f32>,
+}
+
+impl Alignment {
+    /// Computes the alignment from the positions of the 5 [`TEMPLATE`] keypoints in the image.
+    pub fn from_keypoints(keypoints: [[f32; 2]; 5]) -> Self {
+        Self::fit(keypoints.into_iter().zip(TEMPLATE))
+    }
+
+    /// Computes the alignment of a face from its [`Detection`].
+    ///
+    /// The face detector does not output the mouth corners, so their midpoint is aligned to the
+    /// mouth center instead. Face mesh landmarks are more precise, so
+    /// [`Alignment::from_landmarks`] should be preferred if they are available.
+    pub fn from_detection(detection: &Detection) -> Self {
+        let [_, _, _, mouth_left, mouth_right] = TEMPLATE;
+        let mouth_center = [
+            (mouth_left[0] + mouth_right[0]) / 2.0,
+            (mouth_left[1] + mouth_right[1]) / 2.0,
+        ];
+        Self::fit(
+            [
+                (detection.keypoint(0), TEMPLATE[0]),
+                (detection.keypoint(1), TEMPLATE[1]),
+                (detection.keypoint(2), TEMPLATE[2]),
+                (detection.keypoint(3), mouth_center),
+            ]
+            .into_iter(),
+        )
+    }
+
+    /// Computes the alignment of a face from its face mesh landmarks.
+    ///
+    /// The landmarks have to be in image coordinates.
+    pub fn from_landmarks(landmarks: &LandmarkResult) -> Self {
+        let pos = |idx: LandmarkIdx| {
+            let [x, y, _] = landmarks.landmarks().landmark(idx as usize).position();
+            [x, y]
+        };
+        let mid = |a: [f32; 2], b: [f32; 2]| [(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0];
+
+        Self::from_keypoints([
+            mid(
+                pos(LandmarkIdx::LeftEyeOuterCorner),
+                pos(LandmarkIdx::LeftEyeInnerCorner),
+            ),
+            mid(
+                pos(LandmarkIdx::RightEyeInnerCorner),
+                pos(LandmarkIdx::RightEyeOuterCorner),
+            ),
+            pos(LandmarkIdx::NoseTip),
+            pos(LandmarkIdx::MouthLeftCorner),
+            pos(LandmarkIdx::MouthRightCorner),
+        ])
+    }
+
+    /// Computes the similarity transform mapping the first point of each pair onto the second in
+    /// the least-squares sense ([Umeyama's method], specialized to 2D).
+    ///
+    /// [Umeyama's method]: https://doi.org/10.1109/34.88573
+    fn fit(pairs: impl Iterator<Item = ([f32; 2], [f32; 2])> + Clone) -> Self {
+        let n = pairs.clone().count() as f32;
+        let (src_mean, dest_mean) = pairs.clone().fold(
+            (Vector2::zeros(), Vector2::zeros()),
+            |(s, d), (src, dest)| (s + Vector2::from(src) / n, d + Vector2::from(dest) / n),
+        );
+
+        let (mut dot, mut cross, mut variance) = (0.0, 0.0, 0.0);
+        for (src, dest) in pairs {
+            let p = Vector2::from(src) - src_mean;
+            let q = Vector2::from(dest) - dest_mean;
+            dot += p.dot(&q);
+            cross += p.perp(&q);
+            variance += p.norm_squared();
+        }
+
+        let angle = cross.atan2(dot);
+        let scale = dot.hypot(cross) / variance;
+        let mut transform = Similarity2::new(Vector2::zeros(), angle, scale);
+        transform.isometry.translation.vector = dest_mean - transform * src_mean;
+        Self { transform }
+    }
+
+    /// Returns the factor by which the face is scaled up when aligning it.
+    pub fn scale(&self) -> f32 {
+        self.transform.scaling()
+    }
+
+    /// Returns the clockwise rotation of the face in the image, in radians.
+    pub fn rotation_radians(&self) -> f32 {
+        -self.transform.isometry.rotation.angle()
+    }
+
+    /// Maps a point in the input image to the aligned crop.
+    pub fn transform_point(&self, [x, y]: [f32; 2]) -> [f32; 2] {
+        let p = self.transform * Point2::new(x, y);
+        [p.x, p.y]
+    }
+
+    /// Maps a point in the aligned crop back to the input image.
+    pub fn inverse_transform_point(&self, [x, y]: [f32; 2]) -> [f32; 2] {
+        let p = self.transform.inverse_transform_point(&Point2::new(x, y));
+        [p.x, p.y]
+    }
+
+    /// Produces the aligned [`ALIGNED_SIZE`]x[`ALIGNED_SIZE`] face crop from `image`.
+    ///
+    /// The crop is sampled with bilinear interpolation. Parts of the crop that lie outside of
+    /// `image` are filled with [`Color::NULL`][zaru_image::Color::NULL].
+    pub fn crop(&self, image: &Image) -> Image {
+        let res = Resolution::new(ALIGNED_SIZE, ALIGNED_SIZE);
+        // Keypoint coordinates put the top left corner of the image at (0, 0), while remap tables
+        // use pixel centers.
+        let table = RemapTable::new(image.resolution(), res, |[x, y]| {
+            let [x, y] = self.inverse_transform_point([x + 0.5, y + 0.5]);
+            Some([x - 0.5, y - 0.5])
+        });
+        table.apply(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use zaru_image::{AsImageView, Color};
+
+    use super::*;
+
+    /// Returns the template keypoints, rotated, scaled and moved into a larger image.
+    fn transformed_template(angle: f32, scale: f32, offset: [f32; 2]) -> [[f32; 2]; 5] {
+        let (sin, cos) = angle.sin_cos();
+        TEMPLATE.map(|[x, y]| {
+            [
+                (x * cos - y * sin) * scale + offset[0],
+                (x * sin + y * cos) * scale + offset[1],
+            ]
+        })
+    }
+
+    #[test]
+    fn recovers_similarity() {
+        let keypoints = transformed_template(0.3, 2.0, [100.0, 40.0]);
+        let alignment = Alignment::from_keypoints(keypoints);
+
+        assert_relative_eq!(alignment.scale(), 0.5, epsilon = 1e-5);
+        assert_relative_eq!(alignment.rotation_radians(), 0.3, epsilon = 1e-5);
+        for (keypoint, expected) in keypoints.iter().zip(TEMPLATE) {
+            let [x, y] = alignment.transform_point(*keypoint);
+            assert_relative_eq!(x, expected[0], epsilon = 1e-3);
+            assert_relative_eq!(y, expected[1], epsilon = 1e-3);
+            let [x, y] = alignment.inverse_transform_point(expected);
+            assert_relative_eq!(x, keypoint[0], epsilon = 1e-3);
+            assert_relative_eq!(y, keypoint[1], epsilon = 1e-3);
+        }
+    }
+
+    #[test]
+    fn crop() {
+        // A 2x scaled face whose left eye is marked with a red square.
+        let keypoints = transformed_template(0.0, 2.0, [50.0, 20.0]);
+        let [eye_x, eye_y] = keypoints[0];
+        let mut buf = Vec::new();
+        for y in 0..300 {
+            for x in 0..300 {
+                let dist = (x as f32 + 0.5 - eye_x)
+                    .abs()
+                    .max((y as f32 + 0.5 - eye_y).abs());
+                let color = if dist < 6.0 { Color::RED } else { Color::WHITE };
+                buf.extend_from_slice(&[color.r(), color.g(), color.b(), color.a()]);
+            }
+        }
+        let image = Image::from_rgba8(Resolution::new(300, 300), &buf);
+
+        let crop = Alignment::from_keypoints(keypoints).crop(&image);
+        assert_eq!(
+            crop.resolution(),
+            Resolution::new(ALIGNED_SIZE, ALIGNED_SIZE)
+        );
+        let view = crop.as_view();
+        let [x, y] = TEMPLATE[0];
+        assert_eq!(view.get(x as u32, y as u32), Color::RED);
+        assert_eq!(view.get(x as u32 + 4, y as u32 - 4), Color::WHITE);
+        assert_eq!(view.get(90, 100), Color::WHITE);
+    }
+}
@@ -7,6 +7,7 @@
 
 use nalgebra::{Rotation2, Vector2};
 use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 use zaru_image::{
     draw, AsImageView, AsImageViewMut, Color, ImageView, ImageViewMut, Rect, Resolution,
     RotatedRect,
@@ -15,11 +16,15 @@ use zaru_utils::num::{sigmoid, TotalF32};
 
 use crate::{
     detection::{
+        self,
         nms::NonMaxSuppression,
         ssd::{Anchor, AnchorParams, Anchors, LayerInfo},
-        BoundingRect, RawDetection,
+        BoundingRect, DetectionLike, RawDetection,
+    },
+    nn::{
+        create_linear_color_mapper, point_to_img, unadjust_aspect_ratio, Cnn, CnnInputShape,
+        NeuralNetwork,
     },
-    nn::{create_linear_color_mapper, point_to_img, Cnn, CnnInputShape, NeuralNetwork},
     timer::Timer,
 };
 
@@ -146,8 +151,36 @@ impl Detector {
     }
 }
 
+impl detection::Detector for Detector {
+    type Detection = Detection;
+
+    fn input_resolution(&self) -> Resolution {
+        self.input_resolution()
+    }
+
+    fn detect(&mut self, image: ImageView<'_>) -> &[Detection] {
+        self.detect_impl(image)
+    }
+
+    fn timers(&self) -> Box<dyn Iterator<Item = &Timer> + '_> {
+        Box::new(self.timers())
+    }
+
+    fn threshold(&self) -> f32 {
+        self.thresh
+    }
+
+    fn set_threshold(&mut self, threshold: f32) {
+        self.thresh = threshold;
+    }
+
+    fn nms_mut(&mut self) -> &mut NonMaxSuppression {
+        &mut self.nms
+    }
+}
+
 /// A detected face, consisting of a bounding box and landmarks.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Detection {
     raw: RawDetection,
     full_res: Resolution,
@@ -184,13 +217,7 @@ impl Detection {
     /// Note that this value is quite imprecise. If you need a more accurate angle, compute facial
     /// landmarks instead and compute their rotation.
     pub fn rotation_radians(&self) -> f32 {
-        let left_eye = self.left_eye();
-        let right_eye = self.right_eye();
-        let left_to_right_eye = Vector2::new(
-            (right_eye.0 - left_eye.0) as f32,
-            (right_eye.1 - left_eye.1) as f32,
-        );
-        Rotation2::rotation_between(&Vector2::x(), &left_to_right_eye).angle()
+        self.raw.rotation_radians()
     }
 
     /// Returns the coordinates of the left eye's landmark (from the perspective of the input image,
@@ -207,6 +234,22 @@ impl Detection {
         point_to_img(lm.x(), lm.y(), &self.full_res)
     }
 
+    /// Returns the position of a keypoint in image coordinates, with sub-pixel precision.
+    ///
+    /// The first 4 keypoints are the left eye, right eye, nose tip, and mouth center (from the
+    /// perspective of the input image).
+    pub(crate) fn keypoint(&self, index: usize) -> [f32; 2] {
+        let lm = self.raw.keypoints()[index];
+        let Some(aspect) = self.full_res.aspect_ratio() else {
+            return [0.0, 0.0];
+        };
+        let (x, y) = unadjust_aspect_ratio(lm.x(), lm.y(), aspect);
+        [
+            x * self.full_res.width() as f32,
+            y * self.full_res.height() as f32,
+        ]
+    }
+
     /// Draws this detection onto an image.
     ///
     /// # Panics
@@ -261,6 +304,20 @@ impl Detection {
     }
 }
 
+impl DetectionLike for Detection {
+    fn from_raw(raw: RawDetection, full_res: Resolution) -> Self {
+        Self { raw, full_res }
+    }
+
+    fn raw(&self) -> &RawDetection {
+        &self.raw
+    }
+
+    fn full_resolution(&self) -> Resolution {
+        self.full_res
+    }
+}
+
 fn extract_detection(
     anchor: &Anchor,
     input_res: Resolution,
@@ -283,7 +340,7 @@ fn extract_detection(
         )
     };
 
-    RawDetection::with_keypoints(
+    let mut det = RawDetection::with_keypoints(
         confidence,
         BoundingRect::from_center(xc, yc, w, h),
         vec![
@@ -294,7 +351,16 @@ fn extract_detection(
             lm(box_params[12], box_params[13]),
             lm(box_params[14], box_params[15]),
         ],
-    )
+    );
+
+    let left_eye = det.keypoints()[0];
+    let right_eye = det.keypoints()[1];
+    let left_to_right_eye =
+        Vector2::new(right_eye.x() - left_eye.x(), right_eye.y() - left_eye.y());
+    det.set_rotation_radians(
+        Rotation2::rotation_between(&Vector2::x(), &left_to_right_eye).angle(),
+    );
+    det
 }
 
 /// Trait for supported face detection networks.
@@ -374,6 +440,8 @@ impl DetectionNetwork for FullRangeNetwork {
 
 #[cfg(test)]
 mod tests {
+    use zaru_image::Image;
+
     use super::*;
     use crate::test;
 
@@ -390,4 +458,30 @@ mod tests {
 
         assert!(detection.left_eye().0 < detection.right_eye().0);
     }
+
+    #[test]
+    fn generic_detector() {
+        fn detect_all<D: detection::Detector>(
+            detector: &mut D,
+            image: &Image,
+        ) -> Vec<D::Detection> {
+            detector.detect(image.as_view()).to_vec()
+        }
+
+        let mut det = Detector::new(ShortRangeNetwork);
+        let detections = detect_all(&mut det, test::sad_linus_full());
+        assert_eq!(detections.len(), 1);
+        let detection = &detections[0];
+        assert_eq!(
+            DetectionLike::bounding_rect(detection),
+            detection.bounding_rect_raw()
+        );
+        assert_eq!(
+            DetectionLike::keypoints(detection).nth(1),
+            Some(detection.right_eye())
+        );
+
+        detection::Detector::set_threshold(&mut det, 1.1);
+        assert!(detect_all(&mut det, test::sad_linus_full()).is_empty());
+    }
 }
@@ -0,0 +1,507 @@
+//! Facial expression estimation.
+//!
+//! An [`ExpressionEstimator`] derives a set of [`Blendshape`] coefficients from the
+//! [`MediaPipeFaceMesh`] landmarks of a face. The blendshapes are a subset of the ones used by
+//! [ARKit], so the resulting [`Expression`] can drive existing avatar rigs directly.
+//!
+//! Before measuring anything, the landmarks are fitted to the reference face model (see
+//! [`mediapipe_facemesh::reference_positions`]) to remove head rotation, translation and scale.
+//! Every blendshape is then computed from a single distance in this canonical space, relative to
+//! the same distance on a *neutral* face. By default, the reference face model serves as the
+//! neutral face, but faces differ quite a bit, so results are much better after calibrating the
+//! neutral expression of the user with [`ExpressionEstimator::calibrate_neutral`].
+//!
+//! [`MediaPipeFaceMesh`]: super::landmark::mediapipe_facemesh::MediaPipeFaceMesh
+//! [ARKit]: https://developer.apple.com/documentation/arkit/arfaceanchor/blendshapelocation
+
+use std::{fmt, ops::Index};
+
+use nalgebra::Vector3;
+use zaru_utils::{iter::zip_exact, procrustes::ProcrustesAnalyzer};
+
+use super::landmark::mediapipe_facemesh::{self, LandmarkResult};
+
+/// A facial expression component, named after the corresponding ARKit blendshape.
+///
+/// Like in ARKit, "left" and "right" are from the perspective of the depicted person, so the
+/// `*Left` blendshapes describe the side of the face that appears on the *right* of a
+/// (non-mirrored) camera image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Blendshape {
+    BrowDownLeft,
+    BrowDownRight,
+    BrowInnerUp,
+    BrowOuterUpLeft,
+    BrowOuterUpRight,
+    CheekPuff,
+    EyeBlinkLeft,
+    EyeBlinkRight,
+    EyeSquintLeft,
+    EyeSquintRight,
+    EyeWideLeft,
+    EyeWideRight,
+    JawOpen,
+    MouthFrownLeft,
+    MouthFrownRight,
+    MouthPucker,
+    MouthSmileLeft,
+    MouthSmileRight,
+}
+
+impl Blendshape {
+    /// The number of supported blendshapes.
+    pub const COUNT: usize = Self::ALL.len();
+
+    /// All supported blendshapes, in alphabetical order.
+    pub const ALL: [Self; 18] = [
+        Self::BrowDownLeft,
+        Self::BrowDownRight,
+        Self::BrowInnerUp,
+        Self::BrowOuterUpLeft,
+        Self::BrowOuterUpRight,
+        Self::CheekPuff,
+        Self::EyeBlinkLeft,
+        Self::EyeBlinkRight,
+        Self::EyeSquintLeft,
+        Self::EyeSquintRight,
+        Self::EyeWideLeft,
+        Self::EyeWideRight,
+        Self::JawOpen,
+        Self::MouthFrownLeft,
+        Self::MouthFrownRight,
+        Self::MouthPucker,
+        Self::MouthSmileLeft,
+        Self::MouthSmileRight,
+    ];
+
+    /// Returns the ARKit name of this blendshape (eg. `"jawOpen"`).
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::BrowDownLeft => "browDownLeft",
+            Self::BrowDownRight => "browDownRight",
+            Self::BrowInnerUp => "browInnerUp",
+            Self::BrowOuterUpLeft => "browOuterUpLeft",
+            Self::BrowOuterUpRight => "browOuterUpRight",
+            Self::CheekPuff => "cheekPuff",
+            Self::EyeBlinkLeft => "eyeBlinkLeft",
+            Self::EyeBlinkRight => "eyeBlinkRight",
+            Self::EyeSquintLeft => "eyeSquintLeft",
+            Self::EyeSquintRight => "eyeSquintRight",
+            Self::EyeWideLeft => "eyeWideLeft",
+            Self::EyeWideRight => "eyeWideRight",
+            Self::JawOpen => "jawOpen",
+            Self::MouthFrownLeft => "mouthFrownLeft",
+            Self::MouthFrownRight => "mouthFrownRight",
+            Self::MouthPucker => "mouthPucker",
+            Self::MouthSmileLeft => "mouthSmileLeft",
+            Self::MouthSmileRight => "mouthSmileRight",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Returns the measurement this blendshape is derived from, and how it maps to the coefficient.
+    fn measurement(self) -> (Measurement, Activation) {
+        use Activation::*;
+        use Measurement::*;
+        use Side::*;
+
+        match self {
+            Self::BrowDownLeft => (InnerBrowHeight(Left), Delta(-BROW_LOWER)),
+            Self::BrowDownRight => (InnerBrowHeight(Right), Delta(-BROW_LOWER)),
+            Self::BrowInnerUp => (InnerBrowsHeight, Delta(BROW_RAISE)),
+            Self::BrowOuterUpLeft => (OuterBrowHeight(Left), Delta(BROW_RAISE)),
+            Self::BrowOuterUpRight => (OuterBrowHeight(Right), Delta(BROW_RAISE)),
+            Self::CheekPuff => (CheekWidth, Delta(CHEEK_PUFF)),
+            Self::EyeBlinkLeft => (EyeOpenness(Left), Closing),
+            Self::EyeBlinkRight => (EyeOpenness(Right), Closing),
+            Self::EyeSquintLeft => (LowerLidHeight(Left), Delta(LID_RAISE)),
+            Self::EyeSquintRight => (LowerLidHeight(Right), Delta(LID_RAISE)),
+            Self::EyeWideLeft => (EyeOpenness(Left), Delta(EYE_WIDEN)),
+            Self::EyeWideRight => (EyeOpenness(Right), Delta(EYE_WIDEN)),
+            Self::JawOpen => (ChinDrop, Delta(JAW_DROP)),
+            Self::MouthFrownLeft => (MouthCornerHeight(Left), Delta(-MOUTH_CORNER_LOWER)),
+            Self::MouthFrownRight => (MouthCornerHeight(Right), Delta(-MOUTH_CORNER_LOWER)),
+            Self::MouthPucker => (MouthWidth, Delta(-MOUTH_NARROW)),
+            Self::MouthSmileLeft => (MouthCornerHeight(Left), Delta(MOUTH_CORNER_RAISE)),
+            Self::MouthSmileRight => (MouthCornerHeight(Right), Delta(MOUTH_CORNER_RAISE)),
+        }
+    }
+}
+
+impl fmt::Display for Blendshape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A facial expression, described by a coefficient for every [`Blendshape`].
+///
+/// Coefficients are in range 0.0 (neutral) to 1.0 (fully expressed).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Expression {
+    coefficients: [f32; Blendshape::COUNT],
+}
+
+impl Expression {
+    /// Returns the coefficient of `blendshape`.
+    #[inline]
+    pub fn get(&self, blendshape: Blendshape) -> f32 {
+        self.coefficients[blendshape.index()]
+    }
+
+    /// Returns an iterator over all blendshapes and their coefficients.
+    pub fn iter(&self) -> impl Iterator<Item = (Blendshape, f32)> + '_ {
+        zip_exact(Blendshape::ALL, self.coefficients)
+    }
+}
+
+impl Index<Blendshape> for Expression {
+    type Output = f32;
+
+    fn index(&self, blendshape: Blendshape) -> &f32 {
+        &self.coefficients[blendshape.index()]
+    }
+}
+
+/// Computes [`Expression`]s from face mesh landmarks.
+///
+/// An estimator stores the neutral expression of a single person, so every tracked face should
+/// have its own estimator.
+pub struct ExpressionEstimator {
+    procrustes: ProcrustesAnalyzer,
+    canonical: Vec<[f32; 3]>,
+    reference_neutral: [f32; Blendshape::COUNT],
+    neutral: [f32; Blendshape::COUNT],
+    neutral_samples: u32,
+}
+
+impl Default for ExpressionEstimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExpressionEstimator {
+    /// Creates an estimator that uses the reference face model as the neutral expression.
+    pub fn new() -> Self {
+        let reference = mediapipe_facemesh::reference_positions()
+            .map(|(x, y, z)| [x, y, z])
+            .collect::<Vec<_>>();
+        let neutral = measure_all(&reference);
+        Self {
+            procrustes: ProcrustesAnalyzer::new(mediapipe_facemesh::reference_positions()),
+            canonical: reference,
+            reference_neutral: neutral,
+            neutral,
+            neutral_samples: 0,
+        }
+    }
+
+    /// Computes the expression of the face described by `landmarks`.
+    pub fn estimate(&mut self, landmarks: &LandmarkResult) -> Expression {
+        self.canonicalize(landmarks);
+        let measurements = measure_all(&self.canonical);
+
+        let mut expression = Expression::default();
+        for blendshape in Blendshape::ALL {
+            let i = blendshape.index();
+            let (_, activation) = blendshape.measurement();
+            expression.coefficients[i] = activation.coefficient(measurements[i], self.neutral[i]);
+        }
+        expression
+    }
+
+    /// Records `landmarks` as a sample of the person's neutral expression.
+    ///
+    /// The neutral expression is the average of all samples recorded since the estimator was
+    /// created (or [reset][Self::reset_neutral]), so calling this for every frame of a short
+    /// period in which the person holds a relaxed, neutral face and looks into the camera gives
+    /// the most robust results.
+    pub fn calibrate_neutral(&mut self, landmarks: &LandmarkResult) {
+        self.canonicalize(landmarks);
+        let measurements = measure_all(&self.canonical);
+
+        self.neutral_samples += 1;
+        let weight = 1.0 / self.neutral_samples as f32;
+        for (neutral, measurement) in zip_exact(&mut self.neutral, measurements) {
+            *neutral += (measurement - *neutral) * weight;
+        }
+    }
+
+    /// Discards the calibrated neutral expression, and goes back to using the reference face
+    /// model.
+    pub fn reset_neutral(&mut self) {
+        self.neutral = self.reference_neutral;
+        self.neutral_samples = 0;
+    }
+
+    /// Returns whether a neutral expression has been calibrated via
+    /// [`ExpressionEstimator::calibrate_neutral`].
+    pub fn is_calibrated(&self) -> bool {
+        self.neutral_samples > 0
+    }
+
+    /// Maps `landmarks` into the coordinate system of the reference face model, removing head
+    /// rotation, translation and scale.
+    fn canonicalize(&mut self, landmarks: &LandmarkResult) {
+        let positions = landmarks.landmarks().positions();
+        // Flip Y to bring us to canonical 3D coordinates (where Y points up).
+        let pose = self
+            .procrustes
+            .analyze(positions.iter().map(|&[x, y, z]| (x, -y, z)));
+
+        let inverse = pose.rotation().inverse().to_rotation_matrix();
+        let m = inverse.matrix();
+        let centroid = pose.centroid();
+        let ref_centroid = self.procrustes.reference_centroid();
+        let scale = if pose.scale() == 0.0 {
+            1.0
+        } else {
+            pose.scale()
+        };
+        for (&[x, y, z], out) in zip_exact(positions, &mut self.canonical) {
+            let p = Vector3::new(x, -y, z) - centroid;
+            for (i, out) in out.iter_mut().enumerate() {
+                *out =
+                    (m[(i, 0)] * p.x + m[(i, 1)] * p.y + m[(i, 2)] * p.z) / scale + ref_centroid[i];
+            }
+        }
+    }
+}
+
+fn measure_all(points: &[[f32; 3]]) -> [f32; Blendshape::COUNT] {
+    Blendshape::ALL.map(|blendshape| blendshape.measurement().0.measure(points))
+}
+
+/// Side of the face, from the perspective of the depicted person.
+#[derive(Clone, Copy)]
+enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    /// Picks the landmark index on this side.
+    ///
+    /// Landmark pairs are given as `(image left, image right)`; the person's left side appears on
+    /// the right of the image.
+    fn pick(self, (image_left, image_right): (usize, usize)) -> usize {
+        match self {
+            Side::Left => image_right,
+            Side::Right => image_left,
+        }
+    }
+}
+
+// Face mesh landmark indices used for the measurements, as `(image left, image right)` pairs.
+const EYE_OUTER_CORNER: (usize, usize) = (33, 263);
+const EYE_INNER_CORNER: (usize, usize) = (133, 362);
+const EYE_TOP: (usize, usize) = (159, 386);
+const EYE_BOTTOM: (usize, usize) = (145, 374);
+const BROW_INNER: (usize, usize) = (107, 336);
+const BROW_OUTER: (usize, usize) = (70, 300);
+const CHEEK: (usize, usize) = (205, 425);
+const MOUTH_CORNER: (usize, usize) = (61, 291);
+const UPPER_LIP: usize = 13;
+const LOWER_LIP: usize = 14;
+const NOSE_TIP: usize = 1;
+const CHIN: usize = 152;
+
+/// A distance on the canonicalized face mesh, in units of the reference face model (centimetres).
+#[derive(Clone, Copy)]
+enum Measurement {
+    /// Height of the inner end of an eyebrow above the inner eye corner.
+    InnerBrowHeight(Side),
+    /// Mean height of both inner eyebrow ends above the inner eye corners.
+    InnerBrowsHeight,
+    /// Height of the outer end of an eyebrow above the outer eye corner.
+    OuterBrowHeight(Side),
+    /// Distance between the cheeks, next to the mouth.
+    CheekWidth,
+    /// Gap between the eyelids, relative to the width of the eye (this one has no unit).
+    EyeOpenness(Side),
+    /// Height of the lower eyelid above the eye corners.
+    LowerLidHeight(Side),
+    /// Distance of the chin below the nose tip.
+    ChinDrop,
+    /// Height of a mouth corner above the center of the mouth.
+    MouthCornerHeight(Side),
+    /// Distance between the mouth corners.
+    MouthWidth,
+}
+
+impl Measurement {
+    fn measure(self, p: &[[f32; 3]]) -> f32 {
+        let y = |i: usize| p[i][1];
+        let dist = |a: usize, b: usize| {
+            let [ax, ay, az] = p[a];
+            let [bx, by, bz] = p[b];
+            ((ax - bx).powi(2) + (ay - by).powi(2) + (az - bz).powi(2)).sqrt()
+        };
+
+        match self {
+            Self::InnerBrowHeight(side) => {
+                y(side.pick(BROW_INNER)) - y(side.pick(EYE_INNER_CORNER))
+            }
+            Self::InnerBrowsHeight => {
+                (Self::InnerBrowHeight(Side::Left).measure(p)
+                    + Self::InnerBrowHeight(Side::Right).measure(p))
+                    / 2.0
+            }
+            Self::OuterBrowHeight(side) => {
+                y(side.pick(BROW_OUTER)) - y(side.pick(EYE_OUTER_CORNER))
+            }
+            Self::CheekWidth => dist(CHEEK.0, CHEEK.1),
+            Self::EyeOpenness(side) => {
+                let width = dist(side.pick(EYE_INNER_CORNER), side.pick(EYE_OUTER_CORNER));
+                let gap = dist(side.pick(EYE_TOP), side.pick(EYE_BOTTOM));
+                if width == 0.0 {
+                    0.0
+                } else {
+                    gap / width
+                }
+            }
+            Self::LowerLidHeight(side) => {
+                let corners =
+                    (y(side.pick(EYE_INNER_CORNER)) + y(side.pick(EYE_OUTER_CORNER))) / 2.0;
+                y(side.pick(EYE_BOTTOM)) - corners
+            }
+            Self::ChinDrop => y(NOSE_TIP) - y(CHIN),
+            Self::MouthCornerHeight(side) => {
+                y(side.pick(MOUTH_CORNER)) - (y(UPPER_LIP) + y(LOWER_LIP)) / 2.0
+            }
+            Self::MouthWidth => dist(MOUTH_CORNER.0, MOUTH_CORNER.1),
+        }
+    }
+}
+
+// Changes of the [`Measurement`]s at which blendshapes are fully expressed, in centimetres unless
+// noted otherwise. These were tuned by hand to roughly match the extent of the motion on an adult
+// face. They are not calibrated per person; only the neutral value they are added to is.
+
+/// Drop of the inner brow ends when frowning; brows can't move down much before hitting the eyes.
+const BROW_LOWER: f32 = 0.5;
+/// Rise of the brows when raised, which has a larger range than lowering them.
+const BROW_RAISE: f32 = 0.8;
+/// Widening of the cheeks next to the mouth when puffed.
+const CHEEK_PUFF: f32 = 1.0;
+/// Rise of the lower eyelid when squinting; it only moves by a fraction of the eye height.
+const LID_RAISE: f32 = 0.2;
+/// Increase of the unitless eye openness ratio when the eyes are opened wide.
+const EYE_WIDEN: f32 = 0.1;
+/// Drop of the chin when the mouth is opened wide; the jaw has by far the largest range.
+const JAW_DROP: f32 = 2.5;
+/// Drop of a mouth corner when frowning.
+const MOUTH_CORNER_LOWER: f32 = 0.4;
+/// Rise of a mouth corner when smiling, which pulls them up further than a frown pulls them down.
+const MOUTH_CORNER_RAISE: f32 = 0.5;
+/// Narrowing of the mouth when puckering, starting from a neutral width of about 5 cm.
+const MOUTH_NARROW: f32 = 1.5;
+
+/// How a [`Measurement`] maps to a blendshape coefficient.
+#[derive(Clone, Copy)]
+enum Activation {
+    /// The blendshape is fully expressed once the measurement differs from the neutral value by
+    /// this amount.
+    Delta(f32),
+    /// The blendshape is fully expressed once the measurement reaches zero.
+    Closing,
+}
+
+impl Activation {
+    fn coefficient(self, value: f32, neutral: f32) -> f32 {
+        let coeff = match self {
+            Self::Delta(delta) => (value - neutral) / delta,
+            Self::Closing if neutral > 0.0 => 1.0 - value / neutral,
+            Self::Closing => 0.0,
+        };
+        coeff.clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use nalgebra::{UnitQuaternion, Vector3};
+
+    use super::*;
+
+    /// Places the reference face in an image, after letting `modify` change its canonical
+    /// positions.
+    fn face(rotation: UnitQuaternion<f32>, modify: impl FnOnce(&mut [[f32; 3]])) -> LandmarkResult {
+        let mut points = mediapipe_facemesh::reference_positions()
+            .map(|(x, y, z)| [x, y, z])
+            .collect::<Vec<_>>();
+        modify(&mut points);
+
+        let mut result = LandmarkResult::default();
+        for (&[x, y, z], out) in zip_exact(&points, result.landmarks_mut().positions_mut()) {
+            let p = rotation * Vector3::new(x, y, z) * 20.0;
+            // Y points down in the image.
+            *out = [p.x + 320.0, -p.y + 240.0, p.z];
+        }
+        result
+    }
+
+    fn tilted() -> UnitQuaternion<f32> {
+        UnitQuaternion::from_euler_angles(0.2, -0.4, 0.3)
+    }
+
+    #[test]
+    fn neutral_face() {
+        let mut estimator = ExpressionEstimator::new();
+        let expression = estimator.estimate(&face(tilted(), |_| {}));
+        for (blendshape, coeff) in expression.iter() {
+            assert_relative_eq!(coeff, 0.0, epsilon = 1e-3);
+            assert_eq!(expression[blendshape], coeff);
+        }
+    }
+
+    #[test]
+    fn expressions() {
+        let mut estimator = ExpressionEstimator::new();
+
+        let open = estimator.estimate(&face(tilted(), |p| p[CHIN][1] -= 1.25));
+        assert_relative_eq!(open[Blendshape::JawOpen], 0.5, epsilon = 0.05);
+
+        // The person's left mouth corner is on the right side of the image.
+        let smirk = estimator.estimate(&face(tilted(), |p| p[MOUTH_CORNER.1][1] += 0.5));
+        assert!(smirk[Blendshape::MouthSmileLeft] > 0.9);
+        assert_relative_eq!(smirk[Blendshape::MouthSmileRight], 0.0, epsilon = 0.02);
+        assert_relative_eq!(smirk[Blendshape::MouthFrownLeft], 0.0, epsilon = 0.02);
+
+        let blink = estimator.estimate(&face(tilted(), |p| {
+            let [top, bottom] = [EYE_TOP.0, EYE_BOTTOM.0];
+            p[top] = p[bottom];
+        }));
+        assert_relative_eq!(blink[Blendshape::EyeBlinkRight], 1.0, epsilon = 0.01);
+        assert_relative_eq!(blink[Blendshape::EyeBlinkLeft], 0.0, epsilon = 0.02);
+        assert_relative_eq!(blink[Blendshape::EyeWideRight], 0.0, epsilon = 0.02);
+    }
+
+    #[test]
+    fn neutral_calibration() {
+        // A person whose brows sit higher than those of the reference face.
+        let raise = |p: &mut [[f32; 3]]| {
+            for i in [BROW_INNER.0, BROW_INNER.1, BROW_OUTER.0, BROW_OUTER.1] {
+                p[i][1] += 0.4;
+            }
+        };
+
+        let mut estimator = ExpressionEstimator::new();
+        let uncalibrated = estimator.estimate(&face(tilted(), raise));
+        assert!(uncalibrated[Blendshape::BrowInnerUp] > 0.4);
+
+        estimator.calibrate_neutral(&face(UnitQuaternion::identity(), raise));
+        assert!(estimator.is_calibrated());
+        let calibrated = estimator.estimate(&face(tilted(), raise));
+        assert_relative_eq!(calibrated[Blendshape::BrowInnerUp], 0.0, epsilon = 1e-3);
+
+        estimator.reset_neutral();
+        assert!(!estimator.is_calibrated());
+        assert_eq!(estimator.estimate(&face(tilted(), raise)), uncalibrated);
+    }
+}
@@ -2,16 +2,21 @@
 //!
 //! This uses the neural network from MediaPipe's [Iris] pipeline.
 //!
+//! The [`features`] module derives the eyelid gap and iris offset from the landmarks.
+//!
 //! [Iris]: https://google.github.io/mediapipe/solutions/iris
 
+pub mod features;
+
 use nalgebra::Point2;
 use once_cell::sync::Lazy;
 
-use zaru_image::{draw, AsImageViewMut, Color, ImageViewMut, Resolution};
+use zaru_image::{AsImageViewMut, Color, ImageViewMut, Resolution};
 use zaru_utils::iter::zip_exact;
 
 use crate::{
-    landmark::{Estimation, Landmarks, Network},
+    draw,
+    landmark::{topology::LandmarkTopology, Estimation, Landmarks, Network},
     nn::{create_linear_color_mapper, Cnn, CnnInputShape, NeuralNetwork, Outputs},
     slice::SliceExt,
 };
@@ -29,6 +34,29 @@ static MODEL: Lazy<Cnn> = Lazy::new(|| {
     .unwrap()
 });
 
+static TOPOLOGY: Lazy<LandmarkTopology> = Lazy::new(|| {
+    let mut topology = LandmarkTopology::new(EyeLandmarks::NUM_LANDMARKS);
+    topology.set_name(0, "iris center");
+
+    // The first 16 eye contour landmarks outline the eyelids: 9 on the lower lid, followed by 7 on
+    // the upper lid. The remaining ones surround the eye region.
+    topology
+        .add_chain(&(5..14).collect::<Vec<_>>())
+        .add_chain(&(14..21).collect::<Vec<_>>())
+        .add_edge(5, 14)
+        .add_edge(13, 20);
+
+    topology
+        .add_group("iris", 0..5, Some(Color::CYAN))
+        .add_group("eyelids", 5..21, Some(Color::MAGENTA))
+        .add_group(
+            "eye region",
+            21..EyeLandmarks::NUM_LANDMARKS,
+            Some(Color::GREEN),
+        );
+    topology
+});
+
 /// A [`Network`] that computes eye landmarks on a cropped image of a left eye.
 ///
 /// Landmarks of a right eye can be computed by flipping both the image and the returned
@@ -44,6 +72,10 @@ impl Network for EyeNetwork {
         &MODEL
     }
 
+    fn topology(&self) -> Option<&'static LandmarkTopology> {
+        Some(&TOPOLOGY)
+    }
+
     fn extract(&self, outputs: &Outputs, estimation: &mut Self::Output) {
         let eye_contour = &outputs[0];
         let iris_contour = &outputs[1];
@@ -136,26 +168,21 @@ impl EyeLandmarks {
     }
 
     fn draw_impl(&self, mut image: ImageViewMut<'_>) {
+        draw::skeleton(&mut image, &self.landmarks, &TOPOLOGY).marker_size(1);
+
         let [x, y, _] = self.iris_center();
         draw::marker(&mut image, x as _, y as _)
             .size(3)
             .color(Color::CYAN);
         draw::circle(&mut image, x as _, y as _, self.iris_diameter() as u32).color(Color::CYAN);
-
-        for [x, y, _] in self.eye_contour().take(16) {
-            draw::marker(&mut image, x as _, y as _)
-                .size(1)
-                .color(Color::MAGENTA);
-        }
-        for [x, y, _] in self.eye_contour().skip(16) {
-            draw::marker(&mut image, x as _, y as _)
-                .size(1)
-                .color(Color::GREEN);
-        }
     }
 }
 
 impl Estimation for EyeLandmarks {
+    fn landmarks(&self) -> &Landmarks {
+        &self.landmarks
+    }
+
     fn landmarks_mut(&mut self) -> &mut Landmarks {
         &mut self.landmarks
     }
@@ -0,0 +1,496 @@
+//! Eyelid and iris features derived from eye landmarks.
+//!
+//! [`EyeMeasurement::new`] turns the [`EyeLandmarks`] of a single frame into a normalized eyelid
+//! gap and iris offset. Both are computed relative to the eye corners, and corrected for the
+//! foreshortening caused by head rotation.
+//!
+//! Single measurements are noisy, and become meaningless when the head is turned too far away from
+//! the camera (or when the eye is closed, in case of the iris). An [`EyeFeatureTracker`] processes a
+//! stream of measurements into [`EyeFeatures`] suitable for driving an avatar: it detects blinks
+//! with hysteresis, and extrapolates from previous values while measurements are untrustworthy.
+//!
+//! All values follow the conventions of the tracking protocol: the eyelid gap is in range 0.0
+//! (closed) to 1.0 (fully open), and the iris offset is in range -1.0 to 1.0, with X pointing right
+//! and Y pointing up.
+
+use std::{
+    ops::{Range, RangeInclusive},
+    time::Duration,
+};
+
+use nalgebra::{UnitQuaternion, Vector2, Vector3};
+
+use super::EyeLandmarks;
+
+// Indices of the eyelid landmarks: 9 on the lower lid, followed by 7 on the upper lid, both
+// starting at the same eye corner.
+const LOWER_LID: Range<usize> = 5..14;
+const UPPER_LID: Range<usize> = 14..21;
+
+/// Landmarks at the middle of the lower and upper lid, averaged to measure the eyelid gap.
+const LOWER_LID_CENTER: [usize; 3] = [8, 9, 10];
+const UPPER_LID_CENTER: [usize; 3] = [16, 17, 18];
+
+/// Fraction of the eye width by which the iris can move up or down.
+///
+/// The eye opening is only about 0.3 eye widths tall (see
+/// [`EyeFeatureTracker::DEFAULT_OPEN_RATIO`]), and the lids partially follow the iris, so its
+/// vertical range is smaller than that. This value was tuned by hand so that looking clearly up or
+/// down maps to an offset close to ±1.0.
+const IRIS_VERTICAL_RANGE: f32 = 0.2;
+
+/// A single, unfiltered measurement of the eyelid gap and iris position.
+#[derive(Debug, Clone, Copy)]
+pub struct EyeMeasurement {
+    gap_ratio: f32,
+    iris_offset: [f32; 2],
+    facing_angle: f32,
+}
+
+impl EyeMeasurement {
+    /// Measures the eye described by `landmarks`.
+    ///
+    /// `head_rotation` is the rotation of the head relative to a face looking straight into the
+    /// camera, as returned by [`Face::head_rotation`][crate::face::tracking::Face::head_rotation].
+    pub fn new(landmarks: &EyeLandmarks, head_rotation: UnitQuaternion<f32>) -> Self {
+        let pos = |i: usize| {
+            let [x, y, _] = landmarks.landmarks().positions()[i];
+            Vector2::new(x, y)
+        };
+        let mean = |indices: &[usize]| {
+            indices.iter().map(|&i| pos(i)).sum::<Vector2<f32>>() / indices.len() as f32
+        };
+
+        let mut corner_a = (pos(LOWER_LID.start) + pos(UPPER_LID.start)) / 2.0;
+        let mut corner_b = (pos(LOWER_LID.end - 1) + pos(UPPER_LID.end - 1)) / 2.0;
+        // Landmarks of right eyes are mirrored, so make sure the eye axis points right.
+        if corner_b.x < corner_a.x {
+            std::mem::swap(&mut corner_a, &mut corner_b);
+        }
+        let center = (corner_a + corner_b) / 2.0;
+        let width = (corner_b - corner_a).norm();
+        let axis = (corner_b - corner_a) / width.max(f32::EPSILON);
+        // Y points down in the image, so this is the upwards direction.
+        let up = Vector2::new(axis.y, -axis.x);
+
+        // Orthographic foreshortening of the face's horizontal and vertical axes. The measurements
+        // are relative to the eye width, so only the *ratio* of the two matters.
+        let projected = |v: Vector3<f32>| (head_rotation * v).xy().norm();
+        let horizontal = projected(Vector3::x());
+        let vertical = projected(Vector3::y());
+        let aspect = if vertical > 0.0 {
+            horizontal / vertical
+        } else {
+            1.0
+        };
+
+        let (gap_ratio, iris_offset) = if width > 0.0 {
+            let gap = (mean(&UPPER_LID_CENTER) - mean(&LOWER_LID_CENTER)).dot(&up);
+            let iris = pos(0) - center;
+            let half_range = (width - landmarks.iris_diameter()).max(width * 0.1) / 2.0;
+            (
+                (gap / width * aspect).max(0.0),
+                [
+                    (iris.dot(&axis) / half_range).clamp(-1.0, 1.0),
+                    (iris.dot(&up) / (width * IRIS_VERTICAL_RANGE) * aspect).clamp(-1.0, 1.0),
+                ],
+            )
+        } else {
+            (0.0, [0.0; 2])
+        };
+
+        // Angle between the face's forward direction and the camera axis.
+        let facing = (head_rotation * Vector3::z()).z.abs().min(1.0);
+
+        Self {
+            gap_ratio,
+            iris_offset,
+            facing_angle: facing.acos(),
+        }
+    }
+
+    /// Returns the gap between the eyelids, relative to the width of the eye.
+    ///
+    /// This is around 0.3 for an open eye.
+    #[inline]
+    pub fn gap_ratio(&self) -> f32 {
+        self.gap_ratio
+    }
+
+    /// Returns the offset of the iris center from the center of the eye.
+    ///
+    /// See [`EyeFeatures::iris_offset`] for the value range.
+    #[inline]
+    pub fn iris_offset(&self) -> [f32; 2] {
+        self.iris_offset
+    }
+
+    /// Returns the angle between the direction the face is facing and the camera axis, in radians.
+    #[inline]
+    pub fn facing_angle(&self) -> f32 {
+        self.facing_angle
+    }
+}
+
+/// Eyelid and iris features of an eye, as computed by an [`EyeFeatureTracker`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EyeFeatures {
+    eyelid_gap: f32,
+    iris_offset: [f32; 2],
+    closed: bool,
+    extrapolated: bool,
+}
+
+impl EyeFeatures {
+    /// Returns the open gap between the eyelids, in range 0.0 (closed) to 1.0 (fully open).
+    #[inline]
+    pub fn eyelid_gap(&self) -> f32 {
+        self.eyelid_gap
+    }
+
+    /// Returns the offset of the iris from the center of the eye.
+    ///
+    /// Both coordinates are in range -1.0 to 1.0. X points right (in the image), and Y points up.
+    #[inline]
+    pub fn iris_offset(&self) -> [f32; 2] {
+        self.iris_offset
+    }
+
+    /// Returns whether the eye is considered closed by the blink detector.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Returns whether any of the values were extrapolated because the current measurement was
+    /// untrustworthy.
+    #[inline]
+    pub fn is_extrapolated(&self) -> bool {
+        self.extrapolated
+    }
+}
+
+/// Computes [`EyeFeatures`] from a stream of eye landmarks.
+///
+/// Every eye of every face needs its own tracker.
+pub struct EyeFeatureTracker {
+    open_ratio: f32,
+    close_threshold: f32,
+    open_threshold: f32,
+    max_facing_angle: f32,
+    closed: bool,
+    gap: Extrapolator<1>,
+    iris: Extrapolator<2>,
+}
+
+impl Default for EyeFeatureTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EyeFeatureTracker {
+    /// Default [gap ratio][EyeMeasurement::gap_ratio] of a fully open eye.
+    ///
+    /// The opening of an adult eye is typically about 9-10 mm tall and 30 mm wide, which gives a
+    /// ratio of about 0.3.
+    pub const DEFAULT_OPEN_RATIO: f32 = 0.3;
+
+    /// Default eyelid gap below which an open eye is considered closed.
+    pub const DEFAULT_CLOSE_THRESHOLD: f32 = 0.2;
+
+    /// Default eyelid gap above which a closed eye is considered open again.
+    pub const DEFAULT_OPEN_THRESHOLD: f32 = 0.35;
+
+    /// Default angle (in radians) between the face's direction and the camera axis, beyond which
+    /// measurements are considered untrustworthy.
+    pub const DEFAULT_MAX_FACING_ANGLE: f32 = 40.0 * std::f32::consts::PI / 180.0;
+
+    /// Time constant of the decay of the velocity used for extrapolation.
+    const EXTRAPOLATION_DECAY: Duration = Duration::from_millis(100);
+
+    /// Creates a tracker for a single eye, using the default settings.
+    ///
+    /// The eye starts out open, with the iris centered.
+    pub fn new() -> Self {
+        Self {
+            open_ratio: Self::DEFAULT_OPEN_RATIO,
+            close_threshold: Self::DEFAULT_CLOSE_THRESHOLD,
+            open_threshold: Self::DEFAULT_OPEN_THRESHOLD,
+            max_facing_angle: Self::DEFAULT_MAX_FACING_ANGLE,
+            closed: false,
+            gap: Extrapolator::new([1.0], 0.0..=1.0),
+            iris: Extrapolator::new([0.0; 2], -1.0..=1.0),
+        }
+    }
+
+    /// Sets the [gap ratio][EyeMeasurement::gap_ratio] that corresponds to a fully open eye.
+    ///
+    /// Eye shapes differ between people, so calibrating this per person improves the results.
+    ///
+    /// By default, [`Self::DEFAULT_OPEN_RATIO`] is used.
+    pub fn set_open_ratio(&mut self, ratio: f32) {
+        self.open_ratio = ratio;
+    }
+
+    /// Sets the eyelid gaps at which the eye is considered closed and open again.
+    ///
+    /// Using a higher threshold for opening than for closing prevents noisy measurements from
+    /// causing rapid flickering between the two states. While the eye is considered closed, the
+    /// eyelid gap is reported as 0.0.
+    ///
+    /// By default, [`Self::DEFAULT_CLOSE_THRESHOLD`] and [`Self::DEFAULT_OPEN_THRESHOLD`] are used.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `close` is greater than `open`.
+    pub fn set_blink_thresholds(&mut self, close: f32, open: f32) {
+        assert!(
+            close <= open,
+            "close threshold {close} is greater than open threshold {open}"
+        );
+        self.close_threshold = close;
+        self.open_threshold = open;
+    }
+
+    /// Sets the maximum angle (in radians) between the face's direction and the camera axis at
+    /// which measurements are still trusted.
+    ///
+    /// By default, [`Self::DEFAULT_MAX_FACING_ANGLE`] is used.
+    pub fn set_max_facing_angle(&mut self, angle: f32) {
+        self.max_facing_angle = angle;
+    }
+
+    /// Measures the eye described by `landmarks` and computes the resulting features.
+    ///
+    /// See [`EyeMeasurement::new`] for the meaning of `head_rotation`. `timestamp` is the time at
+    /// which the landmarks were captured, and is used for extrapolation.
+    pub fn track(
+        &mut self,
+        landmarks: &EyeLandmarks,
+        head_rotation: UnitQuaternion<f32>,
+        timestamp: Duration,
+    ) -> EyeFeatures {
+        self.update(EyeMeasurement::new(landmarks, head_rotation), timestamp)
+    }
+
+    /// Computes the eye features from a [`EyeMeasurement`] taken at `timestamp`.
+    pub fn update(&mut self, measurement: EyeMeasurement, timestamp: Duration) -> EyeFeatures {
+        let trusted = measurement.facing_angle <= self.max_facing_angle;
+
+        if trusted {
+            let gap = (measurement.gap_ratio / self.open_ratio).clamp(0.0, 1.0);
+            if self.closed && gap > self.open_threshold {
+                self.closed = false;
+            } else if !self.closed && gap < self.close_threshold {
+                self.closed = true;
+            }
+            self.gap
+                .update([if self.closed { 0.0 } else { gap }], timestamp);
+        } else {
+            self.gap.extrapolate(timestamp);
+        }
+
+        // The iris is covered by the eyelids while the eye is closed.
+        let iris_trusted = trusted && !self.closed;
+        if iris_trusted {
+            self.iris.update(measurement.iris_offset, timestamp);
+        } else {
+            self.iris.extrapolate(timestamp);
+        }
+
+        let [gap] = self.gap.value;
+        EyeFeatures {
+            eyelid_gap: gap,
+            iris_offset: self.iris.value,
+            closed: self.closed,
+            extrapolated: !iris_trusted,
+        }
+    }
+}
+
+/// Continues a value at its last known velocity, with the velocity decaying over time.
+struct Extrapolator<const N: usize> {
+    value: [f32; N],
+    velocity: [f32; N],
+    /// Range that extrapolated values are clamped to.
+    range: RangeInclusive<f32>,
+    last: Option<Duration>,
+}
+
+impl<const N: usize> Extrapolator<N> {
+    fn new(initial: [f32; N], range: RangeInclusive<f32>) -> Self {
+        Self {
+            value: initial,
+            velocity: [0.0; N],
+            range,
+            last: None,
+        }
+    }
+
+    fn elapsed(&mut self, timestamp: Duration) -> f32 {
+        match self.last.replace(timestamp) {
+            Some(last) => timestamp.saturating_sub(last).as_secs_f32(),
+            None => 0.0,
+        }
+    }
+
+    fn update(&mut self, value: [f32; N], timestamp: Duration) {
+        let elapsed = self.elapsed(timestamp);
+        for ((velocity, new), old) in self.velocity.iter_mut().zip(value).zip(self.value) {
+            *velocity = if elapsed > 0.0 {
+                (new - old) / elapsed
+            } else {
+                0.0
+            };
+        }
+        self.value = value;
+    }
+
+    fn extrapolate(&mut self, timestamp: Duration) {
+        let elapsed = self.elapsed(timestamp);
+        // Integrate the exponentially decaying velocity over the elapsed time.
+        let tau = EyeFeatureTracker::EXTRAPOLATION_DECAY.as_secs_f32();
+        let decay = (-elapsed / tau).exp();
+        for (value, velocity) in self.value.iter_mut().zip(&mut self.velocity) {
+            *value = (*value + *velocity * tau * (1.0 - decay))
+                .clamp(*self.range.start(), *self.range.end());
+            *velocity *= decay;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    /// Creates the landmarks of an eye with corners at (0, 0) and (30, 0).
+    fn eye(gap: f32, iris: [f32; 2]) -> EyeLandmarks {
+        let mut landmarks = EyeLandmarks::default();
+        let positions = landmarks.landmarks_mut().positions_mut();
+        // Lids that are flat in the middle, where the gap is measured.
+        let lid = |t: f32, height: f32| {
+            let profile = (2.0 * (t * std::f32::consts::PI).sin()).min(1.0);
+            [t * 30.0, height * profile, 0.0]
+        };
+        for (i, pos) in positions[LOWER_LID].iter_mut().enumerate() {
+            *pos = lid(i as f32 / 8.0, gap / 2.0);
+        }
+        for (i, pos) in positions[UPPER_LID].iter_mut().enumerate() {
+            *pos = lid(i as f32 / 6.0, -gap / 2.0);
+        }
+
+        // Iris with a diameter of 10 pixels.
+        let [x, y] = iris;
+        positions[0] = [x, y, 0.0];
+        for (pos, [dx, dy]) in
+            positions[1..5]
+                .iter_mut()
+                .zip([[5.0, 0.0], [0.0, 5.0], [-5.0, 0.0], [0.0, -5.0]])
+        {
+            *pos = [x + dx, y + dy, 0.0];
+        }
+        landmarks
+    }
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn measurement() {
+        let m = EyeMeasurement::new(&eye(9.0, [15.0, 0.0]), UnitQuaternion::identity());
+        assert_relative_eq!(m.gap_ratio(), 0.3, epsilon = 0.01);
+        assert_relative_eq!(m.iris_offset()[0], 0.0, epsilon = 1e-5);
+        assert_relative_eq!(m.iris_offset()[1], 0.0, epsilon = 1e-5);
+        assert_relative_eq!(m.facing_angle(), 0.0);
+
+        // Iris moved right by half its range, and up (towards negative image Y).
+        let m = EyeMeasurement::new(&eye(9.0, [20.0, -3.0]), UnitQuaternion::identity());
+        assert_relative_eq!(m.iris_offset()[0], 0.5, epsilon = 1e-5);
+        assert_relative_eq!(m.iris_offset()[1], 0.5, epsilon = 1e-5);
+
+        // Mirrored landmarks (like those of right eyes) give the same result.
+        let mut mirrored = eye(9.0, [20.0, -3.0]);
+        mirrored
+            .landmarks_mut()
+            .map_positions(|[x, y, z]| [30.0 - x, y, z]);
+        let m = EyeMeasurement::new(&mirrored, UnitQuaternion::identity());
+        assert_relative_eq!(m.iris_offset()[0], -0.5, epsilon = 1e-5);
+        assert_relative_eq!(m.iris_offset()[1], 0.5, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn head_rotation_compensation() {
+        // Looking down foreshortens the eye vertically.
+        let pitch = 0.5f32;
+        let rotation = UnitQuaternion::from_euler_angles(pitch, 0.0, 0.0);
+        let m = EyeMeasurement::new(&eye(9.0 * pitch.cos(), [15.0, 0.0]), rotation);
+        assert_relative_eq!(m.gap_ratio(), 0.3, epsilon = 0.01);
+        assert_relative_eq!(m.facing_angle(), pitch, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn blink_hysteresis() {
+        let mut tracker = EyeFeatureTracker::new();
+        let mut track = |gap_ratio: f32, t: u64| {
+            tracker.track(
+                &eye(gap_ratio * 30.0, [15.0, 0.0]),
+                UnitQuaternion::identity(),
+                ms(t),
+            )
+        };
+
+        let f = track(0.3, 0);
+        assert!(!f.is_closed());
+        assert_relative_eq!(f.eyelid_gap(), 1.0, epsilon = 0.02);
+
+        // Between the thresholds, the eye stays open.
+        let f = track(0.08, 10);
+        assert!(!f.is_closed());
+        assert_relative_eq!(f.eyelid_gap(), 0.08 / 0.3, epsilon = 0.02);
+
+        let f = track(0.05, 20);
+        assert!(f.is_closed());
+        assert_eq!(f.eyelid_gap(), 0.0);
+        assert!(f.is_extrapolated());
+
+        // ...and stays closed until it opens past the upper threshold.
+        assert!(track(0.08, 30).is_closed());
+        let f = track(0.12, 40);
+        assert!(!f.is_closed());
+        assert!(!f.is_extrapolated());
+    }
+
+    #[test]
+    fn extrapolation() {
+        let mut tracker = EyeFeatureTracker::new();
+        let turned = UnitQuaternion::from_euler_angles(0.0, 1.0, 0.0);
+
+        // Iris moving right at a constant speed of 2.0 per second.
+        for t in 0..5 {
+            let f = tracker.track(
+                &eye(9.0, [15.0 + t as f32 * 0.2, 0.0]),
+                UnitQuaternion::identity(),
+                ms(t * 10),
+            );
+            assert!(!f.is_extrapolated());
+        }
+        let last = tracker.track(&eye(9.0, [16.0, 0.0]), UnitQuaternion::identity(), ms(50));
+
+        // Once the head is turned away, the motion continues, but slows down.
+        let a = tracker.track(&eye(0.0, [0.0, 0.0]), turned, ms(60));
+        let b = tracker.track(&eye(0.0, [0.0, 0.0]), turned, ms(70));
+        let c = tracker.track(&eye(0.0, [0.0, 0.0]), turned, ms(2000));
+        assert!(a.is_extrapolated() && !a.is_closed());
+        assert!(a.iris_offset()[0] > last.iris_offset()[0]);
+        assert!(
+            b.iris_offset()[0] - a.iris_offset()[0] < a.iris_offset()[0] - last.iris_offset()[0]
+        );
+        // The extrapolated motion is bounded by the decay time constant of 100 ms.
+        assert_relative_eq!(c.iris_offset()[0], 0.1 + 0.2, epsilon = 1e-3);
+        assert_relative_eq!(c.eyelid_gap(), last.eyelid_gap(), epsilon = 1e-5);
+    }
+}
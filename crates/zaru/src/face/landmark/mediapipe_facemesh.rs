@@ -12,14 +12,15 @@
 use itertools::Itertools;
 use nalgebra::{Rotation2, Vector2};
 use once_cell::sync::Lazy;
-use zaru_image::{draw, AsImageViewMut, Color, ImageViewMut, RotatedRect};
+use zaru_image::{AsImageViewMut, Color, ImageViewMut, RotatedRect};
 use zaru_utils::{
     iter::zip_exact,
     num::{sigmoid, TotalF32},
 };
 
 use crate::{
-    landmark::{self, Landmarks},
+    draw,
+    landmark::{self, topology::LandmarkTopology, Landmarks},
     nn::{create_linear_color_mapper, Cnn, CnnInputShape, NeuralNetwork, Outputs},
     slice::SliceExt,
 };
@@ -72,6 +73,10 @@ impl landmark::Network for MediaPipeFaceMesh {
             out[2] = z;
         }
     }
+
+    fn topology(&self) -> Option<&'static LandmarkTopology> {
+        Some(&TOPOLOGY)
+    }
 }
 
 /// Landmark results estimated by [`MediaPipeFaceMesh`].
@@ -179,9 +184,7 @@ impl LandmarkResult {
     }
 
     fn draw_impl(&self, image: &mut ImageViewMut<'_>) {
-        for &[x, y, ..] in self.landmarks().positions() {
-            draw::marker(image, x as _, y as _).size(3);
-        }
+        draw::skeleton(image, self.landmarks(), &TOPOLOGY).marker_size(3);
 
         let color = match self.face_confidence() {
             0.75.. => Color::GREEN,
@@ -242,6 +245,10 @@ impl LandmarkResult {
 }
 
 impl landmark::Estimation for LandmarkResult {
+    fn landmarks(&self) -> &Landmarks {
+        &self.landmarks
+    }
+
     fn landmarks_mut(&mut self) -> &mut Landmarks {
         &mut self.landmarks
     }
@@ -273,6 +280,25 @@ pub fn reference_positions() -> impl Iterator<Item = (f32, f32, f32)> {
     REFERENCE_POSITIONS.iter().copied()
 }
 
+/// Size of a unit of the reference face model, in millimetres.
+///
+/// MediaPipe's canonical face model is specified in centimetres.
+const REFERENCE_UNIT_MM: f32 = 10.0;
+
+/// Returns an iterator over the vertices of the reference face model, in millimetres.
+///
+/// This yields the same points as [`reference_positions`], scaled to the size of an average human
+/// face. Fitting these points to the landmarks of a real face yields metric distances.
+pub fn reference_positions_mm() -> impl Iterator<Item = (f32, f32, f32)> {
+    reference_positions().map(|(x, y, z)| {
+        (
+            x * REFERENCE_UNIT_MM,
+            y * REFERENCE_UNIT_MM,
+            z * REFERENCE_UNIT_MM,
+        )
+    })
+}
+
 /// Assigns a name to certain important landmark indices.
 ///
 /// "Left" and "Right" are relative to the input image, not from the PoV of the depicted person.
@@ -282,6 +308,9 @@ pub enum LandmarkIdx {
     MouthRight = 308,
     MouthTop = 13,
     MouthBottom = 14,
+    MouthLeftCorner = 61,
+    MouthRightCorner = 291,
+    NoseTip = 1,
     LeftEyeOuterCorner = 33,
     LeftEyeInnerCorner = 133,
     LeftEyeTop = 159,
@@ -295,6 +324,78 @@ pub enum LandmarkIdx {
 }
 // FIXME: these are swapped or otherwise messed up
 
+static TOPOLOGY: Lazy<LandmarkTopology> = Lazy::new(|| {
+    use LandmarkIdx::*;
+
+    let mut topology = LandmarkTopology::new(LandmarkResult::NUM_LANDMARKS);
+    for (idx, name) in [
+        (MouthLeft, "mouth left"),
+        (MouthRight, "mouth right"),
+        (MouthTop, "mouth top"),
+        (MouthBottom, "mouth bottom"),
+        (MouthLeftCorner, "mouth left corner"),
+        (MouthRightCorner, "mouth right corner"),
+        (NoseTip, "nose tip"),
+        (LeftEyeOuterCorner, "left eye outer corner"),
+        (LeftEyeInnerCorner, "left eye inner corner"),
+        (LeftEyeTop, "left eye top"),
+        (LeftEyeBottom, "left eye bottom"),
+        (RightEyeInnerCorner, "right eye inner corner"),
+        (RightEyeOuterCorner, "right eye outer corner"),
+        (RightEyeTop, "right eye top"),
+        (RightEyeBottom, "right eye bottom"),
+        (RightEyebrowInnerCorner, "right eyebrow inner corner"),
+        (LeftEyebrowInnerCorner, "left eyebrow inner corner"),
+    ] {
+        topology.set_name(idx as usize, name);
+    }
+
+    // Contours from MediaPipe's `face_mesh_connections.py`. Like `LandmarkIdx`, "left" and "right"
+    // are relative to the image.
+    const LEFT_EYE: [&[usize]; 2] = [
+        &[33, 7, 163, 144, 145, 153, 154, 155, 133],
+        &[33, 246, 161, 160, 159, 158, 157, 173, 133],
+    ];
+    const RIGHT_EYE: [&[usize]; 2] = [
+        &[263, 249, 390, 373, 374, 380, 381, 382, 362],
+        &[263, 466, 388, 387, 386, 385, 384, 398, 362],
+    ];
+    const LEFT_EYEBROW: [&[usize]; 2] = [&[46, 53, 52, 65, 55], &[70, 63, 105, 66, 107]];
+    const RIGHT_EYEBROW: [&[usize]; 2] = [&[276, 283, 282, 295, 285], &[300, 293, 334, 296, 336]];
+    const OUTER_LIPS: [&[usize]; 2] = [
+        &[61, 146, 91, 181, 84, 17, 314, 405, 321, 375, 291],
+        &[61, 185, 40, 39, 37, 0, 267, 269, 270, 409, 291],
+    ];
+    const INNER_LIPS: [&[usize]; 2] = [
+        &[78, 95, 88, 178, 87, 14, 317, 402, 318, 324, 308],
+        &[78, 191, 80, 81, 82, 13, 312, 311, 310, 415, 308],
+    ];
+    const FACE_OVAL: &[usize] = &[
+        10, 338, 297, 332, 284, 251, 389, 356, 454, 323, 361, 288, 397, 365, 379, 378, 400, 377,
+        152, 148, 176, 149, 150, 136, 172, 58, 132, 93, 234, 127, 162, 21, 54, 103, 67, 109,
+    ];
+
+    for (name, chains, color) in [
+        ("left eye", LEFT_EYE, Color::CYAN),
+        ("right eye", RIGHT_EYE, Color::CYAN),
+        ("left eyebrow", LEFT_EYEBROW, Color::YELLOW),
+        ("right eyebrow", RIGHT_EYEBROW, Color::YELLOW),
+        ("outer lips", OUTER_LIPS, Color::MAGENTA),
+        ("inner lips", INNER_LIPS, Color::MAGENTA),
+    ] {
+        for chain in chains {
+            topology.add_chain(chain);
+        }
+        let landmarks = chains.iter().flat_map(|chain| chain.iter().copied());
+        topology.add_group(name, landmarks.unique(), Some(color));
+    }
+    topology
+        .add_loop(FACE_OVAL)
+        .add_group("face oval", FACE_OVAL.iter().copied(), None);
+
+    topology
+});
+
 impl Into<usize> for LandmarkIdx {
     #[inline]
     fn into(self) -> usize {
@@ -305,8 +406,13 @@ impl Into<usize> for LandmarkIdx {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{landmark::Estimator, procrustes::ProcrustesAnalyzer, test};
-    use zaru_image::{AsImageView, ImageView};
+    use crate::{
+        landmark::{Estimator, LandmarkTracker, MotionModel},
+        procrustes::ProcrustesAnalyzer,
+        test,
+    };
+    use std::time::Duration;
+    use zaru_image::{AsImageView, Image, ImageView, Rect};
 
     #[track_caller]
     fn check_angle(expected_radians: f32, actual_radians: f32) {
@@ -370,4 +476,43 @@ mod tests {
             10.0,
         );
     }
+
+    #[test]
+    fn tracks_moving_face() {
+        let face = test::sad_linus_cropped();
+        let (w, h) = (face.width(), face.height());
+        let mut canvas = Image::new(w * 4, h * 2);
+        let face_rect =
+            |frame: u32| Rect::from_top_left((frame * w / 4) as i32, (h / 2) as i32, w, h);
+
+        let mut estimator = Estimator::new(MediaPipeFaceMesh);
+        let mut tracker =
+            LandmarkTracker::new(estimator.input_resolution().aspect_ratio().unwrap());
+        tracker.set_motion_model(Some(MotionModel::default()));
+        tracker.set_recovery_attempts(1);
+        tracker.set_roi(face_rect(0));
+
+        // Move the face a quarter of its width to the right in each frame.
+        let frame_time = Duration::from_millis(33);
+        for frame in 0..12 {
+            canvas.clear(Color::BLACK);
+            let mut view = canvas.view_mut(face_rect(frame));
+            view.blend_from(face);
+            drop(view);
+
+            let res = tracker
+                .track_at(&mut estimator, &canvas, frame_time * frame)
+                .unwrap_or_else(|| panic!("tracking lost in frame {frame}"));
+            let landmarks = res.estimation().landmarks();
+            let (x, y) = landmarks
+                .positions()
+                .iter()
+                .fold((0.0, 0.0), |(x, y), p| (x + p[0], y + p[1]));
+            let n = landmarks.len() as f32;
+            assert!(
+                face_rect(frame).contains_point((x / n) as i64, (y / n) as i64),
+                "landmarks not on the face in frame {frame}"
+            );
+        }
+    }
 }
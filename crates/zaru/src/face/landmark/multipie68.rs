@@ -6,10 +6,12 @@
 //! [Multi-PIE dataset]: http://www.cs.cmu.edu/afs/cs/project/PIE/MultiPie/Multi-Pie/Home.html
 
 use once_cell::sync::Lazy;
+use zaru_image::{AsImageViewMut, Color};
 use zaru_utils::iter::zip_exact;
 
 use crate::{
-    landmark::{Estimation, Landmarks, Network},
+    draw,
+    landmark::{topology::LandmarkTopology, Estimation, Landmarks, Network},
     nn::{create_linear_color_mapper, Cnn, CnnInputShape, NeuralNetwork, Outputs},
     slice::SliceExt,
 };
@@ -24,6 +26,11 @@ impl LandmarkResult {
     pub fn landmarks(&self) -> &Landmarks {
         &self.landmarks
     }
+
+    /// Draws the landmarks and their connections onto an image.
+    pub fn draw<I: AsImageViewMut>(&self, image: &mut I) {
+        draw::skeleton(image, &self.landmarks, &TOPOLOGY).marker_size(3);
+    }
 }
 
 impl Default for LandmarkResult {
@@ -35,11 +42,43 @@ impl Default for LandmarkResult {
 }
 
 impl Estimation for LandmarkResult {
+    fn landmarks(&self) -> &Landmarks {
+        &self.landmarks
+    }
+
     fn landmarks_mut(&mut self) -> &mut Landmarks {
         &mut self.landmarks
     }
 }
 
+/// The landmark groups of the 68-point markup.
+///
+/// Unlike the original markup, "left" and "right" are relative to the image, not from the PoV of
+/// the depicted person.
+static TOPOLOGY: Lazy<LandmarkTopology> = Lazy::new(|| {
+    let mut topology = LandmarkTopology::new(NUM_LANDMARKS);
+    for (name, landmarks, closed, color) in [
+        ("jaw", 0..17, false, None),
+        ("left eyebrow", 17..22, false, Some(Color::YELLOW)),
+        ("right eyebrow", 22..27, false, Some(Color::YELLOW)),
+        ("nose bridge", 27..31, false, None),
+        ("nostrils", 31..36, false, None),
+        ("left eye", 36..42, true, Some(Color::CYAN)),
+        ("right eye", 42..48, true, Some(Color::CYAN)),
+        ("outer lips", 48..60, true, Some(Color::MAGENTA)),
+        ("inner lips", 60..68, true, Some(Color::MAGENTA)),
+    ] {
+        let landmarks = landmarks.collect::<Vec<_>>();
+        if closed {
+            topology.add_loop(&landmarks);
+        } else {
+            topology.add_chain(&landmarks);
+        }
+        topology.add_group(name, landmarks, color);
+    }
+    topology
+});
+
 /// The network from [`Peppa-Facial-Landmark-PyTorch`].
 ///
 /// This network is fairly fast to infer, but generates inaccurate results.
@@ -78,6 +117,10 @@ impl Network for PeppaFacialLandmark {
             out[1] = y * res.height() as f32;
         }
     }
+
+    fn topology(&self) -> Option<&'static LandmarkTopology> {
+        Some(&TOPOLOGY)
+    }
 }
 
 /// The landmark network used by [FaceONNX].
@@ -120,4 +163,8 @@ impl Network for FaceOnnx {
             out[1] = y * res.height() as f32;
         }
     }
+
+    fn topology(&self) -> Option<&'static LandmarkTopology> {
+        Some(&TOPOLOGY)
+    }
 }
@@ -0,0 +1,475 @@
+//! Face recognition.
+//!
+//! Faces are recognized by computing an [`Embedding`] for each face with a [`FaceEmbedder`]. The
+//! embeddings of two images of the same person are similar, while those of different people are
+//! not, as measured by [`Embedding::similarity`].
+//!
+//! A [`Gallery`] stores the embeddings of known identities, and can be searched for the identity
+//! that best matches a new face.
+//!
+//! This uses a [MobileFaceNet] network, which expects faces that are aligned to a standard template
+//! (see [`alignment`][super::alignment]).
+//!
+//! The [`eval`] module can be used to benchmark recognition accuracy.
+//!
+//! [MobileFaceNet]: https://arxiv.org/abs/1804.07573
+
+pub mod eval;
+
+use std::{fs, path::Path};
+
+use anyhow::{ensure, Context};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use zaru_image::{AsImageView, Image, Resolution};
+use zaru_utils::{iter::zip_exact, num::TotalF32};
+
+use crate::{
+    nn::{create_linear_color_mapper, Cnn, CnnInputShape, NeuralNetwork},
+    timer::Timer,
+};
+
+use super::alignment::Alignment;
+
+static MODEL: Lazy<Cnn> = Lazy::new(|| {
+    let model_data = include_blob::include_bytes!("../../3rdparty/onnx/mobilefacenet.onnx");
+    Cnn::new(
+        NeuralNetwork::from_onnx(model_data)
+            .unwrap()
+            .load()
+            .unwrap(),
+        CnnInputShape::NCHW,
+        create_linear_color_mapper(-1.0..=1.0),
+    )
+    .unwrap()
+});
+
+/// A face embedding: a feature vector describing the identity of a face.
+///
+/// Embeddings are normalized to unit length.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Embedding {
+    raw: Vec<f32>,
+}
+
+impl Embedding {
+    /// Creates an embedding from a raw feature vector, normalizing it to unit length.
+    pub fn from_raw(raw: &[f32]) -> Self {
+        let norm = raw.iter().map(|v| v * v).sum::<f32>().sqrt();
+        let norm = if norm == 0.0 { 1.0 } else { norm };
+        Self {
+            raw: raw.iter().map(|v| v / norm).collect(),
+        }
+    }
+
+    /// Returns the normalized feature vector.
+    #[inline]
+    pub fn as_slice(&self) -> &[f32] {
+        &self.raw
+    }
+
+    /// Computes the cosine similarity between `self` and `other`.
+    ///
+    /// The result is in range -1.0 to 1.0, where higher values mean that the faces are more likely
+    /// to belong to the same person.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the embeddings have different dimensions (which happens when they were
+    /// computed by different networks).
+    pub fn similarity(&self, other: &Self) -> f32 {
+        zip_exact(&self.raw, &other.raw).map(|(a, b)| a * b).sum()
+    }
+}
+
+/// Computes face [`Embedding`]s with the MobileFaceNet network.
+pub struct FaceEmbedder {
+    model: &'static Cnn,
+    t_align: Timer,
+    t_resize: Timer,
+    t_infer: Timer,
+}
+
+impl Default for FaceEmbedder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FaceEmbedder {
+    /// Creates a new face embedder.
+    pub fn new() -> Self {
+        Self {
+            model: &MODEL,
+            t_align: Timer::new("align"),
+            t_resize: Timer::new("resize"),
+            t_infer: Timer::new("infer"),
+        }
+    }
+
+    /// Returns the expected input resolution of the internal neural network.
+    pub fn input_resolution(&self) -> Resolution {
+        self.model.input_resolution()
+    }
+
+    /// Computes the embedding of an aligned face crop.
+    ///
+    /// `face` should be produced by [`Alignment::crop`]. It will be resized to the network's input
+    /// resolution if necessary. [`FaceEmbedder::embed_aligned`] can be used to perform the
+    /// alignment and embedding in one step.
+    pub fn embed<V: AsImageView>(&mut self, face: &V) -> Embedding {
+        let mut face = face.as_view();
+        let resized;
+        if face.resolution() != self.input_resolution() {
+            resized = self
+                .t_resize
+                .time(|| face.aspect_aware_resize(self.model.input_resolution()));
+            face = resized.as_view();
+        }
+
+        let result = self.t_infer.time(|| self.model.estimate(&face)).unwrap();
+        Embedding::from_raw(result[0].index([0]).as_slice())
+    }
+
+    /// Aligns the face described by `alignment` and computes its embedding.
+    pub fn embed_aligned(&mut self, image: &Image, alignment: &Alignment) -> Embedding {
+        let face = self.t_align.time(|| alignment.crop(image));
+        self.embed(&face)
+    }
+
+    /// Returns profiling timers for face alignment, image resizing, and neural inference.
+    pub fn timers(&self) -> impl Iterator<Item = &Timer> + '_ {
+        [&self.t_align, &self.t_resize, &self.t_infer].into_iter()
+    }
+}
+
+/// A known person, identified by name, with one or more enrolled [`Embedding`]s.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Identity {
+    name: String,
+    embeddings: Vec<Embedding>,
+}
+
+impl Identity {
+    /// Returns the name of this identity.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the embeddings enrolled for this identity.
+    #[inline]
+    pub fn embeddings(&self) -> &[Embedding] {
+        &self.embeddings
+    }
+
+    /// Returns the highest similarity between `embedding` and any of this identity's embeddings.
+    fn similarity(&self, embedding: &Embedding) -> f32 {
+        self.embeddings
+            .iter()
+            .map(|e| e.similarity(embedding))
+            .max_by_key(|s| TotalF32(*s))
+            .unwrap_or(-1.0)
+    }
+}
+
+/// The result of searching a [`Gallery`] for a face.
+#[derive(Debug, Clone, Copy)]
+pub struct Match<'a> {
+    identity: &'a Identity,
+    similarity: f32,
+}
+
+impl<'a> Match<'a> {
+    /// Returns the matched identity.
+    #[inline]
+    pub fn identity(&self) -> &'a Identity {
+        self.identity
+    }
+
+    /// Returns the name of the matched identity.
+    #[inline]
+    pub fn name(&self) -> &'a str {
+        &self.identity.name
+    }
+
+    /// Returns the similarity between the face and the closest embedding of the identity.
+    #[inline]
+    pub fn similarity(&self) -> f32 {
+        self.similarity
+    }
+}
+
+/// A collection of known identities that faces can be matched against.
+///
+/// Galleries can be stored on disk with [`Gallery::save`] and loaded with [`Gallery::load`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Gallery {
+    threshold: f32,
+    identities: Vec<Identity>,
+}
+
+impl Default for Gallery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Gallery {
+    /// Default similarity threshold for a face to be considered a match.
+    ///
+    /// This is an uncalibrated placeholder (the midpoint of the positive similarity range), not a
+    /// value derived from any dataset, so its false accept and false reject rates are unknown. The
+    /// threshold should be calibrated on labeled images taken under the conditions the gallery is
+    /// used in, using [`calibrate_threshold`] or the [`eval`] module.
+    pub const DEFAULT_THRESHOLD: f32 = 0.5;
+
+    /// Creates an empty gallery.
+    pub fn new() -> Self {
+        Self {
+            threshold: Self::DEFAULT_THRESHOLD,
+            identities: Vec::new(),
+        }
+    }
+
+    /// Returns the similarity threshold used by [`Gallery::identify`].
+    #[inline]
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    /// Sets the similarity threshold used by [`Gallery::identify`].
+    ///
+    /// By default, [`Gallery::DEFAULT_THRESHOLD`] is used.
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.threshold = threshold;
+    }
+
+    /// Returns an iterator over all enrolled identities.
+    pub fn identities(&self) -> impl Iterator<Item = &Identity> + '_ {
+        self.identities.iter()
+    }
+
+    /// Returns the number of enrolled identities.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.identities.len()
+    }
+
+    /// Returns whether the gallery contains no identities.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.identities.is_empty()
+    }
+
+    /// Enrolls an embedding for the identity called `name`.
+    ///
+    /// If the identity already exists, the embedding is added to it. Enrolling several embeddings
+    /// per identity (for example, from different angles or lighting conditions) improves matching
+    /// accuracy.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `embedding` has a different dimension than the embeddings already enrolled.
+    pub fn enroll(&mut self, name: impl Into<String>, embedding: Embedding) {
+        if let Some(existing) = self.identities.first().and_then(|i| i.embeddings.first()) {
+            assert_eq!(
+                existing.raw.len(),
+                embedding.raw.len(),
+                "embedding dimension does not match gallery"
+            );
+        }
+
+        let name = name.into();
+        match self.identities.iter_mut().find(|i| i.name == name) {
+            Some(identity) => identity.embeddings.push(embedding),
+            None => self.identities.push(Identity {
+                name,
+                embeddings: vec![embedding],
+            }),
+        }
+    }
+
+    /// Removes the identity called `name` from the gallery.
+    ///
+    /// Returns whether the identity existed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.identities.len();
+        self.identities.retain(|i| i.name != name);
+        self.identities.len() != len
+    }
+
+    /// Returns the identity that best matches `embedding`, regardless of the threshold.
+    ///
+    /// Returns [`None`] if the gallery is empty.
+    pub fn best_match(&self, embedding: &Embedding) -> Option<Match<'_>> {
+        self.identities
+            .iter()
+            .map(|identity| Match {
+                identity,
+                similarity: identity.similarity(embedding),
+            })
+            .max_by_key(|m| TotalF32(m.similarity))
+    }
+
+    /// Identifies the face described by `embedding`.
+    ///
+    /// Returns the best matching identity if its similarity reaches the
+    /// [threshold][Gallery::threshold], and [`None`] if the face is unknown.
+    pub fn identify(&self, embedding: &Embedding) -> Option<Match<'_>> {
+        self.best_match(embedding)
+            .filter(|m| m.similarity >= self.threshold)
+    }
+
+    /// Parses a gallery from its JSON representation.
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let gallery: Self = serde_json::from_str(json)?;
+        let mut dims = gallery
+            .identities
+            .iter()
+            .flat_map(|i| &i.embeddings)
+            .map(|e| e.raw.len());
+        if let Some(first) = dims.next() {
+            ensure!(
+                dims.all(|d| d == first),
+                "gallery contains embeddings of different dimensions"
+            );
+        }
+        Ok(gallery)
+    }
+
+    /// Serializes this gallery to JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Loads a gallery from a JSON file.
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("failed to read '{}'", path.display()))?;
+        Self::from_json(&json).with_context(|| format!("failed to parse '{}'", path.display()))
+    }
+
+    /// Writes this gallery to a JSON file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_json())
+            .with_context(|| format!("failed to write '{}'", path.display()))
+    }
+}
+
+/// Computes a similarity threshold from the similarities of face pairs of *different* people.
+///
+/// The returned threshold is the lowest one at which at most a fraction of `false_accept_rate` of
+/// the given pairs would be (wrongly) considered a match. It can be passed to
+/// [`Gallery::set_threshold`].
+///
+/// # Panics
+///
+/// This panics if `impostor_similarities` is empty.
+pub fn calibrate_threshold(impostor_similarities: &[f32], false_accept_rate: f32) -> f32 {
+    assert!(
+        !impostor_similarities.is_empty(),
+        "need at least one impostor pair to calibrate threshold"
+    );
+
+    let mut sorted = impostor_similarities.to_vec();
+    sorted.sort_by_key(|s| TotalF32(-*s));
+    // Number of impostor pairs that may be accepted.
+    let accepted = (false_accept_rate.clamp(0.0, 1.0) * sorted.len() as f32).floor() as usize;
+    match sorted.get(accepted) {
+        // Just above the highest similarity that must be rejected.
+        Some(&rejected) => next_up(rejected),
+        None => -1.0,
+    }
+}
+
+/// Returns the smallest `f32` greater than `value`.
+fn next_up(value: f32) -> f32 {
+    if value == 0.0 {
+        f32::from_bits(1)
+    } else if value > 0.0 {
+        f32::from_bits(value.to_bits() + 1)
+    } else {
+        f32::from_bits(value.to_bits() - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    #[test]
+    fn embedding_similarity() {
+        let a = Embedding::from_raw(&[3.0, 4.0]);
+        assert_relative_eq!(a.as_slice()[0], 0.6);
+        assert_relative_eq!(a.as_slice()[1], 0.8);
+        assert_relative_eq!(a.similarity(&a), 1.0);
+
+        let b = Embedding::from_raw(&[-4.0, 3.0]);
+        assert_relative_eq!(a.similarity(&b), 0.0);
+        let c = Embedding::from_raw(&[-6.0, -8.0]);
+        assert_relative_eq!(a.similarity(&c), -1.0);
+    }
+
+    #[test]
+    fn gallery_identify() {
+        let mut gallery = Gallery::new();
+        gallery.enroll("alice", Embedding::from_raw(&[1.0, 0.0, 0.0]));
+        gallery.enroll("bob", Embedding::from_raw(&[0.0, 1.0, 0.0]));
+        gallery.enroll("alice", Embedding::from_raw(&[0.0, 0.0, 1.0]));
+        assert_eq!(gallery.len(), 2);
+
+        let probe = Embedding::from_raw(&[0.1, 0.2, 1.0]);
+        let m = gallery.identify(&probe).unwrap();
+        assert_eq!(m.name(), "alice");
+        assert!(m.similarity() > 0.9);
+
+        // Equally similar to everyone (with a similarity of 0.577).
+        let unknown = Embedding::from_raw(&[1.0, 1.0, 1.0]);
+        gallery.set_threshold(0.7);
+        assert!(gallery.identify(&unknown).is_none());
+        assert!(gallery.best_match(&unknown).is_some());
+        gallery.set_threshold(0.5);
+        assert!(gallery.identify(&unknown).is_some());
+
+        assert!(gallery.remove("alice"));
+        assert!(!gallery.remove("alice"));
+        assert_eq!(gallery.identify(&probe).map(|m| m.name()), None);
+    }
+
+    #[test]
+    fn gallery_json_roundtrip() {
+        let mut gallery = Gallery::new();
+        gallery.set_threshold(0.42);
+        gallery.enroll("alice", Embedding::from_raw(&[1.0, 2.0]));
+        gallery.enroll("bob", Embedding::from_raw(&[-1.0, 0.5]));
+
+        let parsed = Gallery::from_json(&gallery.to_json()).unwrap();
+        assert_eq!(parsed, gallery);
+
+        let mismatched =
+            r#"{"threshold":0.5,"identities":[{"name":"a","embeddings":[[1.0],[1.0,0.0]]}]}"#;
+        assert!(Gallery::from_json(mismatched).is_err());
+    }
+
+    #[test]
+    fn threshold_calibration() {
+        let impostors = [0.1, 0.5, 0.2, 0.3, 0.0, 0.4, 0.25, 0.15, 0.35, 0.05];
+
+        // Rejects all impostors.
+        let t = calibrate_threshold(&impostors, 0.0);
+        assert!(t > 0.5 && t < 0.5001);
+        assert!(impostors.iter().all(|&s| s < t));
+
+        // Accepts the highest-scoring 20% of impostors.
+        let t = calibrate_threshold(&impostors, 0.2);
+        assert_eq!(impostors.iter().filter(|&&s| s >= t).count(), 2);
+
+        assert_eq!(calibrate_threshold(&impostors, 1.0), -1.0);
+    }
+}
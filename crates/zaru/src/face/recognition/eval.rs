@@ -0,0 +1,736 @@
+//! Face verification benchmarks.
+//!
+//! Face verification decides whether two face images show the same person, by comparing the
+//! [`Embedding::similarity`][super::Embedding::similarity] of their embeddings against a threshold.
+//! This module evaluates how well that works on a list of labeled image pairs, following the
+//! protocol of the [Labeled Faces in the Wild][LFW] benchmark:
+//!
+//! - The pairs are split into folds (10 for LFW) that don't share any identities.
+//! - The verification accuracy is computed with cross-validation: for every fold, the threshold
+//!   that maximizes the accuracy on all *other* folds is picked and then used to classify the pairs
+//!   in the fold.
+//! - Threshold-independent metrics (the ROC curve, its AUC, and the true accept rate at fixed false
+//!   accept rates) are computed over all pairs.
+//!
+//! Pair lists can be loaded from LFW's `pairs.txt`, or generated from a directory with one
+//! subdirectory of images per person. Generated pair lists only depend on the directory contents,
+//! so results are reproducible between runs.
+//!
+//! [LFW]: http://vis-www.cs.umass.edu/lfw/
+
+use std::{
+    cmp::Ordering,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, ensure, Context};
+use serde::Serialize;
+
+/// A pair of face images that either show the same person or two different people.
+#[derive(Debug, Clone)]
+pub struct ImagePair {
+    pub a: PathBuf,
+    pub b: PathBuf,
+    /// Whether both images show the same person.
+    pub same: bool,
+    /// The cross-validation fold this pair belongs to.
+    pub fold: usize,
+}
+
+/// A list of labeled [`ImagePair`]s, split into cross-validation folds.
+#[derive(Debug, Clone)]
+pub struct PairList {
+    pairs: Vec<ImagePair>,
+    folds: usize,
+}
+
+impl PairList {
+    /// Loads a pair list in the format of LFW's `pairs.txt` (or `pairsDevTest.txt`).
+    ///
+    /// Image paths are resolved relative to `image_dir`, which is expected to contain a directory
+    /// per person, with images named `<name>_<number>.jpg`.
+    pub fn load_lfw<P: AsRef<Path>, I: AsRef<Path>>(
+        pairs: P,
+        image_dir: I,
+    ) -> anyhow::Result<Self> {
+        let path = pairs.as_ref();
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read '{}'", path.display()))?;
+        Self::parse_lfw(&text, image_dir.as_ref())
+            .with_context(|| format!("failed to parse '{}'", path.display()))
+    }
+
+    fn parse_lfw(text: &str, image_dir: &Path) -> anyhow::Result<Self> {
+        let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+        let Some(header) = lines.next() else {
+            bail!("empty pair list");
+        };
+
+        // The header is either `<folds> <pairs>` or just `<pairs>` (for lists without folds). Each
+        // fold consists of `<pairs>` matched pairs, followed by `<pairs>` mismatched ones.
+        let header = header
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<Vec<usize>, _>>()
+            .with_context(|| format!("invalid header '{}'", header))?;
+        let (folds, per_fold) = match header[..] {
+            [folds, pairs] => (folds, pairs),
+            [pairs] => (1, pairs),
+            _ => bail!("invalid header"),
+        };
+        ensure!(folds > 0 && per_fold > 0, "pair list is empty");
+
+        let image = |name: &str, number: &str| -> anyhow::Result<PathBuf> {
+            let number: u32 = number
+                .parse()
+                .with_context(|| format!("invalid image number '{}'", number))?;
+            Ok(image_dir
+                .join(name)
+                .join(format!("{}_{:04}.jpg", name, number)))
+        };
+
+        let mut pairs = Vec::with_capacity(folds * per_fold * 2);
+        for (i, line) in lines.enumerate() {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let pair = match fields[..] {
+                [name, a, b] => ImagePair {
+                    a: image(name, a)?,
+                    b: image(name, b)?,
+                    same: true,
+                    fold: i / (per_fold * 2),
+                },
+                [name_a, a, name_b, b] => ImagePair {
+                    a: image(name_a, a)?,
+                    b: image(name_b, b)?,
+                    same: false,
+                    fold: i / (per_fold * 2),
+                },
+                _ => bail!("invalid pair '{}'", line),
+            };
+            pairs.push(pair);
+        }
+
+        ensure!(
+            pairs.len() == folds * per_fold * 2,
+            "expected {} pairs, found {}",
+            folds * per_fold * 2,
+            pairs.len(),
+        );
+
+        Ok(Self { pairs, folds })
+    }
+
+    /// Generates a pair list from a directory containing one subdirectory of images per person.
+    ///
+    /// People are distributed among `folds` folds, and every fold gets `pairs_per_fold` pairs, half
+    /// of which are matched. The pairs are chosen pseudo-randomly, but the same directory contents
+    /// always result in the same pair list.
+    pub fn from_directory<P: AsRef<Path>>(
+        image_dir: P,
+        folds: usize,
+        pairs_per_fold: usize,
+    ) -> anyhow::Result<Self> {
+        let image_dir = image_dir.as_ref();
+        let mut identities = Vec::new();
+        for entry in fs::read_dir(image_dir)
+            .with_context(|| format!("failed to read '{}'", image_dir.display()))?
+        {
+            let path = entry?.path();
+            if !path.is_dir() {
+                continue;
+            }
+            let mut images = Vec::new();
+            for entry in fs::read_dir(&path)? {
+                let path = entry?.path();
+                if path.is_file() {
+                    images.push(path);
+                }
+            }
+            images.sort();
+            identities.push((path, images));
+        }
+        // `read_dir` does not guarantee any order.
+        identities.sort();
+
+        Self::generate(
+            &identities
+                .into_iter()
+                .map(|(_, images)| images)
+                .collect::<Vec<_>>(),
+            folds,
+            pairs_per_fold,
+        )
+    }
+
+    fn generate(
+        identities: &[Vec<PathBuf>],
+        folds: usize,
+        pairs_per_fold: usize,
+    ) -> anyhow::Result<Self> {
+        ensure!(folds > 0, "need at least one fold");
+
+        let mut rng = SplitMix64(0x5a52_5546_4143_4553);
+        let mut pairs = Vec::with_capacity(folds * pairs_per_fold);
+        for fold in 0..folds {
+            let members = identities
+                .iter()
+                .skip(fold)
+                .step_by(folds)
+                .filter(|images| !images.is_empty())
+                .collect::<Vec<_>>();
+            let repeated = members
+                .iter()
+                .filter(|images| images.len() >= 2)
+                .collect::<Vec<_>>();
+            ensure!(
+                members.len() >= 2 && !repeated.is_empty(),
+                "fold {} needs at least 2 people, one of which with multiple images",
+                fold,
+            );
+
+            let matched = pairs_per_fold / 2;
+            for i in 0..pairs_per_fold {
+                let pair = if i < matched {
+                    let images = repeated[rng.below(repeated.len())];
+                    let a = rng.below(images.len());
+                    let b = (a + 1 + rng.below(images.len() - 1)) % images.len();
+                    ImagePair {
+                        a: images[a].clone(),
+                        b: images[b].clone(),
+                        same: true,
+                        fold,
+                    }
+                } else {
+                    let a = rng.below(members.len());
+                    let b = (a + 1 + rng.below(members.len() - 1)) % members.len();
+                    ImagePair {
+                        a: members[a][rng.below(members[a].len())].clone(),
+                        b: members[b][rng.below(members[b].len())].clone(),
+                        same: false,
+                        fold,
+                    }
+                };
+                pairs.push(pair);
+            }
+        }
+
+        Ok(Self { pairs, folds })
+    }
+
+    /// Returns all pairs, ordered by fold.
+    pub fn pairs(&self) -> &[ImagePair] {
+        &self.pairs
+    }
+
+    /// Returns the number of cross-validation folds.
+    pub fn folds(&self) -> usize {
+        self.folds
+    }
+
+    /// Returns all distinct image paths referenced by the pairs, in sorted order.
+    pub fn image_paths(&self) -> Vec<&Path> {
+        let mut paths = self
+            .pairs
+            .iter()
+            .flat_map(|pair| [&*pair.a, &*pair.b])
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+
+    /// Returns the number of pairs.
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// Returns whether the list contains no pairs.
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+}
+
+/// Minimal deterministic PRNG, so that generated pair lists are reproducible.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in range `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// Accumulates verification results and computes evaluation metrics from them.
+pub struct Evaluator {
+    /// Similarity, ground truth and fold of every pair.
+    results: Vec<(f32, bool, usize)>,
+    failures: usize,
+    far_targets: Vec<f32>,
+}
+
+impl Evaluator {
+    /// The default false accept rates at which the true accept rate is reported.
+    pub const DEFAULT_FAR_TARGETS: [f32; 3] = [1e-1, 1e-2, 1e-3];
+
+    /// Creates an evaluator without any results, reporting the true accept rate at
+    /// [`Evaluator::DEFAULT_FAR_TARGETS`].
+    pub fn new() -> Self {
+        Self {
+            results: Vec::new(),
+            failures: 0,
+            far_targets: Self::DEFAULT_FAR_TARGETS.to_vec(),
+        }
+    }
+
+    /// Sets the false accept rates at which to report the true accept rate.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `targets` contains values outside of 0.0 to 1.0.
+    pub fn set_far_targets(&mut self, targets: &[f32]) {
+        for &far in targets {
+            assert!((0.0..=1.0).contains(&far), "FAR target {far} out of range");
+        }
+        self.far_targets = targets.to_vec();
+    }
+
+    /// Records the result of comparing the images of `pair`.
+    ///
+    /// `similarity` should be [`None`] if no embedding could be computed for one of the images (eg.
+    /// because no face was detected). Such pairs are counted as failures, and are treated as
+    /// rejected at every threshold, so that the metrics are comparable between pipelines that fail
+    /// on different images.
+    pub fn add_pair(&mut self, pair: &ImagePair, similarity: Option<f32>) {
+        if similarity.is_none() {
+            self.failures += 1;
+        }
+        self.results.push((
+            similarity.unwrap_or(f32::NEG_INFINITY),
+            pair.same,
+            pair.fold,
+        ));
+    }
+
+    /// Computes the evaluation metrics of all results recorded so far.
+    pub fn report(&self) -> Report {
+        let mut sorted = self.results.clone();
+        sort_descending(&mut sorted);
+
+        let roc = roc_curve(&sorted);
+        let auc = area_under_curve(&roc);
+        let tar_at_far = self
+            .far_targets
+            .iter()
+            .map(|&far| {
+                // Best operating point that stays within the FAR budget.
+                let point = roc
+                    .iter()
+                    .filter(|p| p.false_accept_rate <= far)
+                    .max_by(|a, b| {
+                        a.true_accept_rate
+                            .partial_cmp(&b.true_accept_rate)
+                            .unwrap_or(Ordering::Equal)
+                    });
+                TarAtFar {
+                    far,
+                    tar: point.map_or(0.0, |p| p.true_accept_rate),
+                    threshold: point.map_or(f32::INFINITY, |p| p.threshold),
+                }
+            })
+            .collect();
+
+        let num_folds = self.results.iter().map(|r| r.2 + 1).max().unwrap_or(0);
+        let folds = (0..num_folds)
+            .filter(|&fold| self.results.iter().any(|r| r.2 == fold))
+            .map(|fold| {
+                let (test, mut train): (Vec<_>, Vec<_>) =
+                    self.results.iter().copied().partition(|r| r.2 == fold);
+                // Without other folds, the threshold can only be fit to the fold itself.
+                if train.is_empty() {
+                    train = test.clone();
+                }
+                sort_descending(&mut train);
+                let threshold = best_threshold(&train);
+                FoldReport {
+                    pairs: test.len(),
+                    threshold,
+                    accuracy: accuracy(&test, threshold),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let n = folds.len().max(1) as f32;
+        let mean_accuracy = folds.iter().map(|f| f.accuracy).sum::<f32>() / n;
+        let variance = folds
+            .iter()
+            .map(|f| (f.accuracy - mean_accuracy).powi(2))
+            .sum::<f32>()
+            / n;
+
+        Report {
+            pairs: self.results.len(),
+            matched_pairs: self.results.iter().filter(|r| r.1).count(),
+            failures: self.failures,
+            auc,
+            accuracy: mean_accuracy,
+            accuracy_std: variance.sqrt(),
+            threshold: folds.iter().map(|f| f.threshold).sum::<f32>() / n,
+            tar_at_far,
+            folds,
+            roc,
+        }
+    }
+}
+
+impl Default for Evaluator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn sort_descending(results: &mut [(f32, bool, usize)]) {
+    results.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+}
+
+/// Computes the ROC curve of results sorted by descending similarity.
+fn roc_curve(sorted: &[(f32, bool, usize)]) -> Vec<RocPoint> {
+    let positives = sorted.iter().filter(|r| r.1).count().max(1) as f32;
+    let negatives = sorted.iter().filter(|r| !r.1).count().max(1) as f32;
+
+    let mut curve = Vec::new();
+    let (mut tp, mut fp) = (0, 0);
+    for (i, &(similarity, same, _)) in sorted.iter().enumerate() {
+        // Failed pairs are never accepted.
+        if similarity == f32::NEG_INFINITY {
+            break;
+        }
+        if same {
+            tp += 1;
+        } else {
+            fp += 1;
+        }
+
+        // Only emit a point once all pairs with the same similarity have been counted.
+        if sorted.get(i + 1).map(|next| next.0) == Some(similarity) {
+            continue;
+        }
+        curve.push(RocPoint {
+            threshold: similarity,
+            false_accept_rate: fp as f32 / negatives,
+            true_accept_rate: tp as f32 / positives,
+        });
+    }
+    curve
+}
+
+/// Computes the area under a ROC curve with the trapezoidal rule.
+///
+/// The curve implicitly starts at (0, 0) and ends at (1, 1).
+fn area_under_curve(curve: &[RocPoint]) -> f32 {
+    let mut area = 0.0;
+    let (mut prev_far, mut prev_tar) = (0.0, 0.0);
+    let end = RocPoint {
+        threshold: f32::NEG_INFINITY,
+        false_accept_rate: 1.0,
+        true_accept_rate: 1.0,
+    };
+    for point in curve.iter().chain([&end]) {
+        area += (point.false_accept_rate - prev_far) * (point.true_accept_rate + prev_tar) / 2.0;
+        prev_far = point.false_accept_rate;
+        prev_tar = point.true_accept_rate;
+    }
+    area
+}
+
+/// Finds the threshold that maximizes the accuracy on results sorted by descending similarity.
+///
+/// Pairs are accepted if their similarity is at least the threshold. Of several equally good
+/// thresholds, the highest one is returned.
+fn best_threshold(sorted: &[(f32, bool, usize)]) -> f32 {
+    // Start by rejecting everything.
+    let mut correct = sorted.iter().filter(|r| !r.1).count() as isize;
+    let (mut best, mut best_correct) = (f32::INFINITY, correct);
+    for (i, &(similarity, same, _)) in sorted.iter().enumerate() {
+        if similarity == f32::NEG_INFINITY {
+            break;
+        }
+        correct += if same { 1 } else { -1 };
+        if sorted.get(i + 1).map(|next| next.0) == Some(similarity) {
+            continue;
+        }
+        if correct > best_correct {
+            best = similarity;
+            best_correct = correct;
+        }
+    }
+    best
+}
+
+fn accuracy(results: &[(f32, bool, usize)], threshold: f32) -> f32 {
+    let correct = results.iter().filter(|r| (r.0 >= threshold) == r.1).count();
+    correct as f32 / results.len().max(1) as f32
+}
+
+/// Evaluation results computed by [`Evaluator::report`].
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    /// Number of evaluated pairs.
+    pub pairs: usize,
+    /// Number of pairs showing the same person.
+    pub matched_pairs: usize,
+    /// Number of pairs for which no similarity could be computed.
+    pub failures: usize,
+    /// Area under the ROC curve.
+    pub auc: f32,
+    /// Mean cross-validated verification accuracy.
+    pub accuracy: f32,
+    /// Standard deviation of the verification accuracy over all folds.
+    pub accuracy_std: f32,
+    /// Mean of the best-accuracy thresholds of all folds.
+    pub threshold: f32,
+    pub tar_at_far: Vec<TarAtFar>,
+    pub folds: Vec<FoldReport>,
+    /// ROC curve, ordered by descending similarity threshold.
+    pub roc: Vec<RocPoint>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} pairs ({} matched), {} failures",
+            self.pairs, self.matched_pairs, self.failures
+        )?;
+        writeln!(f)?;
+
+        writeln!(f, "{:>8} {:>10} {:>10}", "fold", "threshold", "accuracy")?;
+        for (i, fold) in self.folds.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>8} {:>10.4} {:>10.4}",
+                i, fold.threshold, fold.accuracy
+            )?;
+        }
+        writeln!(f)?;
+
+        writeln!(f, "{:>10} {:>10} {:>10}", "FAR", "TAR", "threshold")?;
+        for t in &self.tar_at_far {
+            writeln!(f, "{:>10.0e} {:>10.4} {:>10.4}", t.far, t.tar, t.threshold)?;
+        }
+        writeln!(f)?;
+
+        writeln!(f, "AUC: {:.4}", self.auc)?;
+        write!(
+            f,
+            "accuracy: {:.4} ± {:.4} (threshold {:.4})",
+            self.accuracy, self.accuracy_std, self.threshold
+        )
+    }
+}
+
+/// The true accept rate at a fixed false accept rate.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct TarAtFar {
+    /// The maximum false accept rate.
+    pub far: f32,
+    pub tar: f32,
+    /// The similarity threshold at which the true accept rate is reached.
+    pub threshold: f32,
+}
+
+/// Cross-validation results of a single fold.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct FoldReport {
+    /// Number of pairs in the fold.
+    pub pairs: usize,
+    /// The best-accuracy threshold of all other folds.
+    pub threshold: f32,
+    /// Accuracy on this fold when using `threshold`.
+    pub accuracy: f32,
+}
+
+/// A point on a ROC curve.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct RocPoint {
+    /// The similarity threshold at which this point is reached.
+    pub threshold: f32,
+    pub false_accept_rate: f32,
+    pub true_accept_rate: f32,
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    fn pair(same: bool, fold: usize) -> ImagePair {
+        ImagePair {
+            a: PathBuf::new(),
+            b: PathBuf::new(),
+            same,
+            fold,
+        }
+    }
+
+    #[test]
+    fn perfect_separation() {
+        let mut eval = Evaluator::new();
+        for fold in 0..2 {
+            eval.add_pair(&pair(true, fold), Some(0.9));
+            eval.add_pair(&pair(true, fold), Some(0.7));
+            eval.add_pair(&pair(false, fold), Some(0.3));
+            eval.add_pair(&pair(false, fold), Some(0.1));
+        }
+
+        let report = eval.report();
+        assert_eq!(report.pairs, 8);
+        assert_eq!(report.matched_pairs, 4);
+        assert_relative_eq!(report.auc, 1.0);
+        assert_relative_eq!(report.accuracy, 1.0);
+        assert_relative_eq!(report.accuracy_std, 0.0);
+        assert_relative_eq!(report.threshold, 0.7);
+        for t in &report.tar_at_far {
+            assert_relative_eq!(t.tar, 1.0);
+        }
+    }
+
+    #[test]
+    fn overlapping_scores() {
+        let mut eval = Evaluator::new();
+        eval.set_far_targets(&[0.0, 0.5]);
+        eval.add_pair(&pair(true, 0), Some(0.9));
+        eval.add_pair(&pair(false, 0), Some(0.8));
+        eval.add_pair(&pair(true, 0), Some(0.6));
+        eval.add_pair(&pair(false, 0), Some(0.2));
+        // Failures are never accepted.
+        eval.add_pair(&pair(true, 0), None);
+        eval.add_pair(&pair(false, 0), None);
+
+        let report = eval.report();
+        assert_eq!(report.failures, 2);
+        let roc = report
+            .roc
+            .iter()
+            .map(|p| (p.false_accept_rate, p.true_accept_rate))
+            .collect::<Vec<_>>();
+        let third = 1.0 / 3.0;
+        assert_eq!(
+            roc,
+            [
+                (0.0, third),
+                (third, third),
+                (third, 2.0 * third),
+                (2.0 * third, 2.0 * third)
+            ]
+        );
+        assert_relative_eq!(report.auc, 11.0 / 18.0);
+
+        assert_relative_eq!(report.tar_at_far[0].tar, third);
+        assert_relative_eq!(report.tar_at_far[0].threshold, 0.9);
+        assert_relative_eq!(report.tar_at_far[1].tar, 2.0 * third);
+        assert_relative_eq!(report.tar_at_far[1].threshold, 0.6);
+
+        // Accepting 0.9 and rejecting everything else, or accepting everything down to 0.6, are
+        // equally good; the higher threshold is picked.
+        assert_relative_eq!(report.threshold, 0.9);
+        assert_relative_eq!(report.accuracy, 4.0 / 6.0);
+    }
+
+    #[test]
+    fn cross_validation_uses_other_folds() {
+        let mut eval = Evaluator::new();
+        // Fold 1 prefers a much lower threshold than fold 0.
+        eval.add_pair(&pair(true, 0), Some(0.8));
+        eval.add_pair(&pair(false, 0), Some(0.6));
+        eval.add_pair(&pair(true, 1), Some(0.4));
+        eval.add_pair(&pair(false, 1), Some(0.2));
+
+        let report = eval.report();
+        assert_relative_eq!(report.folds[0].threshold, 0.4);
+        assert_relative_eq!(report.folds[0].accuracy, 0.5);
+        assert_relative_eq!(report.folds[1].threshold, 0.8);
+        assert_relative_eq!(report.folds[1].accuracy, 0.5);
+    }
+
+    #[test]
+    fn parse_lfw() {
+        let text = "\
+2\t2
+Abel_Pacheco\t1\t4
+Akhmed_Zakayev\t1\t3
+Abdel_Madi_Shabneh\t1\tDean_Barker\t1
+Abdel_Madi_Shabneh\t1\tGiancarlo_Fisichella\t1
+Zico\t1\t2
+Zico\t2\t3
+AJ_Cook\t1\tMarsha_Thomason\t1
+Aaron_Sorkin\t2\tFrank_Solich\t5
+";
+        let list = PairList::parse_lfw(text, Path::new("lfw")).unwrap();
+        assert_eq!(list.folds(), 2);
+        assert_eq!(list.len(), 8);
+
+        let pairs = list.pairs();
+        assert_eq!(
+            pairs[0].a,
+            Path::new("lfw/Abel_Pacheco/Abel_Pacheco_0001.jpg")
+        );
+        assert_eq!(
+            pairs[0].b,
+            Path::new("lfw/Abel_Pacheco/Abel_Pacheco_0004.jpg")
+        );
+        assert!(pairs[0].same);
+        assert!(!pairs[2].same);
+        assert_eq!(
+            pairs[2].b,
+            Path::new("lfw/Dean_Barker/Dean_Barker_0001.jpg")
+        );
+        assert_eq!(pairs.iter().filter(|p| p.fold == 1).count(), 4);
+        assert!(pairs[4].same && pairs[4].fold == 1);
+
+        assert_eq!(list.image_paths().len(), 14);
+
+        assert!(PairList::parse_lfw("1\t2\nZico\t1\t2\n", Path::new("")).is_err());
+        assert!(PairList::parse_lfw("1\t1\nZico\t1\nZico\t1\t2\n", Path::new("")).is_err());
+    }
+
+    #[test]
+    fn generated_pairs_are_reproducible() {
+        let identities = (0..6)
+            .map(|person| {
+                (0..person % 3 + 1)
+                    .map(|image| PathBuf::from(format!("{}/{}.jpg", person, image)))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let list = PairList::generate(&identities, 2, 10).unwrap();
+        assert_eq!(list.len(), 20);
+        for pair in list.pairs() {
+            assert_ne!(pair.a, pair.b);
+            let person = |path: &Path| path.parent().unwrap().to_str().unwrap().parse::<usize>();
+            let (a, b) = (person(&pair.a).unwrap(), person(&pair.b).unwrap());
+            assert_eq!(a == b, pair.same);
+            // People are assigned to folds round-robin.
+            assert_eq!(a % 2, pair.fold);
+            assert_eq!(b % 2, pair.fold);
+        }
+        assert_eq!(list.pairs().iter().filter(|p| p.same).count(), 10);
+
+        let again = PairList::generate(&identities, 2, 10).unwrap();
+        for (a, b) in list.pairs().iter().zip(again.pairs()) {
+            assert_eq!((&a.a, &a.b), (&b.a, &b.b));
+        }
+
+        assert!(PairList::generate(&identities, 6, 10).is_err());
+    }
+}
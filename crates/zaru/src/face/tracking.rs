@@ -0,0 +1,665 @@
+//! Detection and tracking of multiple faces.
+//!
+//! This is a higher-level module that combines face detection, face mesh landmark estimation, iris
+//! landmark estimation, head pose estimation and expression estimation into a self-contained face
+//! tracking solution.
+//!
+//! # Head Pose
+//!
+//! The head pose is always estimated relative to the reference face model (see
+//! [`Face::head_rotation`]). If the [`CameraModel`] of the input images is known, it can be passed
+//! to [`FaceTracker::set_camera`], which enables estimation of the head pose in camera space, in
+//! millimetres (see [`Face::camera_pose`]).
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use nalgebra::{UnitQuaternion, Vector3};
+use pawawwewism::{promise, Promise, PromiseHandle, Worker};
+use zaru_image::{Image, RotatedRect};
+
+use crate::{
+    camera::CameraModel,
+    filter::{ema::Ema, TimeBasedFilter},
+    landmark::{
+        frame_delta, recording::RecordedTarget, Estimator, LandmarkFilter, MultiTracker, Network,
+        TargetId,
+    },
+    pnp::{PnpResult, PnpSolver},
+    procrustes::ProcrustesAnalyzer,
+};
+
+use super::{
+    detection::{self, DetectionNetwork},
+    expression::{Expression, ExpressionEstimator},
+    eye::{
+        features::{EyeFeatureTracker, EyeFeatures},
+        EyeLandmarks, EyeNetwork,
+    },
+    landmark::mediapipe_facemesh::{self, LandmarkResult, MediaPipeFaceMesh},
+};
+
+/// Relative margin added around the eye rectangles computed from the face mesh.
+const EYE_MARGIN: f32 = 0.9;
+
+/// Self-contained face detector, tracker, and landmarker.
+///
+/// For every tracked face, this computes the [`MediaPipeFaceMesh`] landmarks, the iris landmarks of
+/// both eyes (along with the [`EyeFeatures`] derived from them), the head pose, and the facial
+/// [`Expression`].
+///
+/// Faces are detected and tracked by a [`MultiTracker`], so detection and face mesh estimation
+/// always run on background workers. Iris landmark estimation can optionally run on the calling
+/// thread instead (see [`FaceTracker::set_eye_workers`]).
+pub struct FaceTracker {
+    tracker: MultiTracker<detection::Detector, MediaPipeFaceMesh>,
+    make_eye_filter: Box<dyn Fn() -> LandmarkFilter + Send>,
+    make_rotation_filter: Option<Box<MakePoseFilter<UnitQuaternion<f32>>>>,
+    make_translation_filter: Option<Box<MakePoseFilter<Vector3<f32>>>>,
+    eye_workers: bool,
+    states: HashMap<FaceId, FaceState>,
+    procrustes: ProcrustesAnalyzer,
+    pnp: PnpSolver,
+    camera: Option<CameraModel>,
+    epoch: Instant,
+    image: Option<(Arc<Image>, Duration)>,
+    faces: Vec<Face>,
+}
+
+/// The default [`FaceTracker`] uses the short-range face detection network.
+impl Default for FaceTracker {
+    fn default() -> Self {
+        Self::new(detection::ShortRangeNetwork)
+    }
+}
+
+impl FaceTracker {
+    /// Default interval for face detection, when at least one face is in view.
+    pub const DEFAULT_REDETECT_INTERVAL: Duration = Duration::from_millis(300);
+
+    /// Default smoothing factor of the [`Ema`] filter applied to face mesh and iris landmarks.
+    pub const DEFAULT_FILTER_ALPHA: f32 = 0.7;
+
+    /// Relative padding added to the tight face detection rectangle to obtain the initial RoI.
+    pub const DETECTION_PADDING: f32 = 0.25;
+
+    /// Creates a new [`FaceTracker`] that uses the given face detection network.
+    pub fn new<N: DetectionNetwork>(network: N) -> Self {
+        let mut tracker = MultiTracker::new(detection::Detector::new(network), MediaPipeFaceMesh);
+        tracker.set_redetect_interval(Self::DEFAULT_REDETECT_INTERVAL);
+        tracker.set_detection_padding(Self::DETECTION_PADDING);
+        tracker.set_detection_zoom(true);
+        tracker.set_filter(|| {
+            LandmarkFilter::new(
+                Ema::new(Self::DEFAULT_FILTER_ALPHA),
+                LandmarkResult::NUM_LANDMARKS,
+            )
+        });
+
+        Self {
+            tracker,
+            make_eye_filter: Box::new(|| {
+                LandmarkFilter::new(
+                    Ema::new(Self::DEFAULT_FILTER_ALPHA),
+                    EyeLandmarks::NUM_LANDMARKS,
+                )
+            }),
+            make_rotation_filter: None,
+            make_translation_filter: None,
+            eye_workers: true,
+            states: HashMap::new(),
+            procrustes: ProcrustesAnalyzer::new(mediapipe_facemesh::reference_positions()),
+            pnp: PnpSolver::new(mediapipe_facemesh::reference_positions_mm()),
+            camera: None,
+            epoch: Instant::now(),
+            image: None,
+            faces: Vec::new(),
+        }
+    }
+
+    /// Enables or disables detection zoom.
+    ///
+    /// Detection zoom is enabled by default, since the face detection networks are unable to
+    /// detect faces that are far away from the camera otherwise. See
+    /// [`MultiTracker::set_detection_zoom`] for details.
+    pub fn set_detection_zoom(&mut self, zoom: bool) {
+        self.tracker.set_detection_zoom(zoom);
+    }
+
+    /// Sets the redetection interval.
+    ///
+    /// See [`MultiTracker::set_redetect_interval`] for how redetection works.
+    ///
+    /// By default, [`Self::DEFAULT_REDETECT_INTERVAL`] is used.
+    pub fn set_redetect_interval(&mut self, interval: Duration) {
+        self.tracker.set_redetect_interval(interval);
+    }
+
+    /// Sets a function that creates the [`LandmarkFilter`] for the face mesh landmarks of every
+    /// newly tracked face.
+    ///
+    /// The filter has to be created for [`LandmarkResult::NUM_LANDMARKS`] landmarks. By default, an
+    /// [`Ema`] filter with [`Self::DEFAULT_FILTER_ALPHA`] is used.
+    pub fn set_landmark_filter<F>(&mut self, make_filter: F)
+    where
+        F: Fn() -> LandmarkFilter + Send + 'static,
+    {
+        self.tracker.set_filter(make_filter);
+    }
+
+    /// Sets a function that creates the [`LandmarkFilter`] for the iris landmarks of every eye of
+    /// every newly tracked face.
+    ///
+    /// The filter has to be created for [`EyeLandmarks::NUM_LANDMARKS`] landmarks. By default, an
+    /// [`Ema`] filter with [`Self::DEFAULT_FILTER_ALPHA`] is used.
+    pub fn set_eye_filter<F>(&mut self, make_filter: F)
+    where
+        F: Fn() -> LandmarkFilter + Send + 'static,
+    {
+        self.make_eye_filter = Box::new(make_filter);
+    }
+
+    /// Sets the filter to apply to the [head rotation][Face::head_rotation] of every newly tracked
+    /// face.
+    ///
+    /// Rotations are filtered as a whole, so the filtered rotation is always valid and does not
+    /// suffer from wrap-around glitches. For example, a [`OneEuroFilter`] can be used to reduce
+    /// jitter while preserving fast head movements.
+    ///
+    /// By default, the head rotation is not filtered.
+    ///
+    /// [`OneEuroFilter`]: crate::filter::one_euro::OneEuroFilter
+    pub fn set_head_rotation_filter<F>(&mut self, filter: F)
+    where
+        F: TimeBasedFilter<UnitQuaternion<f32>> + Send + Sync + 'static,
+        F::State: Send,
+    {
+        self.make_rotation_filter = Some(pose_filter(filter));
+    }
+
+    /// Sets the filter to apply to the [head translation][Face::head_translation] of every newly
+    /// tracked face.
+    ///
+    /// By default, the head translation is not filtered.
+    pub fn set_head_translation_filter<F>(&mut self, filter: F)
+    where
+        F: TimeBasedFilter<Vector3<f32>> + Send + Sync + 'static,
+        F::State: Send,
+    {
+        self.make_translation_filter = Some(pose_filter(filter));
+    }
+
+    /// Sets the model of the camera that captures the input images.
+    ///
+    /// When a camera model is set, the head pose of every face is additionally estimated in camera
+    /// space by solving the Perspective-n-Point problem for the face mesh landmarks (see
+    /// [`Face::camera_pose`]). Passing [`None`] disables this.
+    ///
+    /// By default, no camera model is set.
+    pub fn set_camera(&mut self, camera: Option<CameraModel>) {
+        self.camera = camera;
+    }
+
+    /// Sets whether iris landmarks are estimated on dedicated worker threads.
+    ///
+    /// When enabled (the default), every tracked face gets one worker per eye, and the landmarks of
+    /// all eyes are computed in parallel. When disabled, iris landmarks are computed sequentially
+    /// on the thread calling [`FaceTracker::track`].
+    ///
+    /// This only affects faces that start being tracked after the call.
+    pub fn set_eye_workers(&mut self, enable: bool) {
+        self.eye_workers = enable;
+    }
+
+    /// Calibrates the neutral expression of the face with the given ID, using its landmarks in the
+    /// previous image passed to [`FaceTracker::track`].
+    ///
+    /// The calibration is kept for as long as the face is tracked. Calling this repeatedly averages
+    /// the neutral expression over several frames, see [`ExpressionEstimator::calibrate_neutral`].
+    ///
+    /// Returns `false` if no face with this ID was tracked in the previous image.
+    pub fn calibrate_neutral_expression(&mut self, id: FaceId) -> bool {
+        let (Some(face), Some(state)) = (
+            self.faces.iter().find(|face| face.id == id),
+            self.states.get_mut(&id),
+        ) else {
+            return false;
+        };
+        state.expression.calibrate_neutral(&face.landmarks);
+        true
+    }
+
+    /// Sets the gap ratio of the fully open eyes of the face with the given ID, see
+    /// [`EyeFeatureTracker::set_open_ratio`].
+    ///
+    /// The ratio can be measured with
+    /// [`EyeMeasurement::gap_ratio`][crate::face::eye::features::EyeMeasurement::gap_ratio] while
+    /// the person keeps their eyes open normally. Like the neutral expression, it is kept for as
+    /// long as the face is tracked.
+    ///
+    /// Returns `false` if no face with this ID is being tracked.
+    pub fn set_eye_open_ratio(&mut self, id: FaceId, ratio: f32) -> bool {
+        let Some(state) = self.states.get_mut(&id) else {
+            return false;
+        };
+        state.left_eye_features.set_open_ratio(ratio);
+        state.right_eye_features.set_open_ratio(ratio);
+        true
+    }
+
+    /// Returns the tracking data of all faces in the previous image passed to
+    /// [`FaceTracker::track`].
+    pub fn faces(&self) -> &[Face] {
+        &self.faces
+    }
+
+    /// Blocks until all tracking computations from the last call to `track` are finished, and
+    /// restarts them on `image`.
+    ///
+    /// After this method returns, [`FaceTracker::faces`] will return the state of all faces in the
+    /// previous image passed to `track`.
+    ///
+    /// The current time is used as the frame timestamp. Use [`FaceTracker::track_at`] to pass an
+    /// explicit timestamp instead.
+    pub fn track(&mut self, image: Arc<Image>) {
+        let timestamp = self.epoch.elapsed();
+        self.track_at(image, timestamp);
+    }
+
+    /// Like [`FaceTracker::track`], but uses `timestamp` as the time at which `image` was captured.
+    ///
+    /// The timestamp is passed to the face mesh and iris [`LandmarkFilter`]s, so that processing a
+    /// recording with the timestamps of its frames produces the same results regardless of
+    /// processing speed. See [`MultiTracker::track_at`] for details.
+    pub fn track_at(&mut self, image: Arc<Image>, timestamp: Duration) {
+        self.tracker.track_at(image.clone(), timestamp);
+        self.faces.clear();
+        let Some((prev, prev_timestamp)) = self.image.replace((image, timestamp)) else {
+            return;
+        };
+
+        let eye_aspect = EyeNetwork.cnn().input_resolution().aspect_ratio().unwrap();
+        let eye_rect = |rect: RotatedRect| rect.grow_rel(EYE_MARGIN).grow_to_fit_aspect(eye_aspect);
+
+        // Kick off iris estimation for all faces first, so that the eye workers run in parallel.
+        let mut jobs = Vec::new();
+        for target in self.tracker.targets() {
+            let landmarks = target.estimation();
+            let state = self.states.entry(target.id()).or_insert_with(|| FaceState {
+                left_eye: EyeEstimator::new(Eye::Left, (self.make_eye_filter)(), self.eye_workers),
+                right_eye: EyeEstimator::new(
+                    Eye::Right,
+                    (self.make_eye_filter)(),
+                    self.eye_workers,
+                ),
+                rotation_filter: self.make_rotation_filter.as_ref().map(|make| make()),
+                translation_filter: self.make_translation_filter.as_ref().map(|make| make()),
+                left_eye_features: EyeFeatureTracker::new(),
+                right_eye_features: EyeFeatureTracker::new(),
+                expression: ExpressionEstimator::new(),
+            });
+            let left =
+                state
+                    .left_eye
+                    .start(prev.clone(), eye_rect(landmarks.left_eye()), prev_timestamp);
+            let right = state.right_eye.start(
+                prev.clone(),
+                eye_rect(landmarks.right_eye()),
+                prev_timestamp,
+            );
+            jobs.push((
+                target.id(),
+                target.view_rect(),
+                landmarks.clone(),
+                left,
+                right,
+            ));
+        }
+
+        // Discard the eye estimators and filters of faces that are no longer tracked.
+        self.states
+            .retain(|id, _| jobs.iter().any(|(job_id, ..)| job_id == id));
+
+        for (id, view_rect, landmarks, left, right) in jobs {
+            let pose = self
+                .procrustes
+                .analyze(landmarks.landmarks().positions().iter().map(|&[x, y, z]| {
+                    // Flip Y to bring us to canonical 3D coordinates (where Y points up).
+                    (x, -y, z)
+                }));
+            let camera_pose = self.camera.as_ref().and_then(|camera| {
+                self.pnp.solve(
+                    camera,
+                    landmarks
+                        .landmarks()
+                        .positions()
+                        .iter()
+                        .map(|&[x, y, _]| (x, y)),
+                )
+            });
+
+            let state = self.states.get_mut(&id).unwrap();
+            let mut head_rotation = pose.rotation();
+            if let Some(filter) = &mut state.rotation_filter {
+                head_rotation = filter(head_rotation, prev_timestamp);
+            }
+            let mut head_translation = pose.translation();
+            if let Some(filter) = &mut state.translation_filter {
+                head_translation = filter(head_translation, prev_timestamp);
+            }
+            let expression = state.expression.estimate(&landmarks);
+            let left_eye = left.wait();
+            let right_eye = right.wait();
+            let left_eye_features =
+                state
+                    .left_eye_features
+                    .track(&left_eye, head_rotation, prev_timestamp);
+            let right_eye_features =
+                state
+                    .right_eye_features
+                    .track(&right_eye, head_rotation, prev_timestamp);
+
+            self.faces.push(Face {
+                id,
+                view_rect,
+                landmarks,
+                left_eye,
+                right_eye,
+                left_eye_features,
+                right_eye_features,
+                head_rotation,
+                head_translation,
+                camera_pose,
+                expression,
+            });
+        }
+    }
+}
+
+/// ID of a tracked face.
+///
+/// The assigned [`FaceId`]s are unique per [`FaceTracker`] assigning them. They are reused between
+/// frames for as long as the face is tracked.
+pub type FaceId = TargetId;
+
+/// Tracking data of a face in the input image.
+#[derive(Clone)]
+pub struct Face {
+    id: FaceId,
+    view_rect: RotatedRect,
+    landmarks: LandmarkResult,
+    left_eye: EyeLandmarks,
+    right_eye: EyeLandmarks,
+    left_eye_features: EyeFeatures,
+    right_eye_features: EyeFeatures,
+    head_rotation: UnitQuaternion<f32>,
+    head_translation: Vector3<f32>,
+    camera_pose: Option<PnpResult>,
+    expression: Expression,
+}
+
+impl Face {
+    /// Returns the unique ID of this face.
+    #[inline]
+    pub fn id(&self) -> FaceId {
+        self.id
+    }
+
+    /// Returns the face's region of interest in the original image.
+    #[inline]
+    pub fn view_rect(&self) -> RotatedRect {
+        self.view_rect
+    }
+
+    /// Returns the face mesh landmarks, in global image coordinates.
+    #[inline]
+    pub fn landmark_result(&self) -> &LandmarkResult {
+        &self.landmarks
+    }
+
+    /// Returns the landmarks of the left eye (from the perspective of the input image), in global
+    /// image coordinates.
+    #[inline]
+    pub fn left_eye(&self) -> &EyeLandmarks {
+        &self.left_eye
+    }
+
+    /// Returns the landmarks of the right eye (from the perspective of the input image), in global
+    /// image coordinates.
+    #[inline]
+    pub fn right_eye(&self) -> &EyeLandmarks {
+        &self.right_eye
+    }
+
+    /// Returns the eyelid gap and iris offset of the left eye (from the perspective of the input
+    /// image).
+    ///
+    /// These are computed by an [`EyeFeatureTracker`], so they are already corrected for head
+    /// rotation, and extrapolated while the eye cannot be measured reliably. The ratio at which the
+    /// eye counts as fully open can be set with [`FaceTracker::set_eye_open_ratio`].
+    #[inline]
+    pub fn left_eye_features(&self) -> EyeFeatures {
+        self.left_eye_features
+    }
+
+    /// Returns the eyelid gap and iris offset of the right eye (from the perspective of the input
+    /// image).
+    ///
+    /// See [`Face::left_eye_features`] for details.
+    #[inline]
+    pub fn right_eye_features(&self) -> EyeFeatures {
+        self.right_eye_features
+    }
+
+    /// Returns the rotation of the head, relative to a face looking straight into the camera.
+    ///
+    /// The rotation uses a coordinate system where X points right, Y points up, and Z points into
+    /// the image.
+    #[inline]
+    pub fn head_rotation(&self) -> UnitQuaternion<f32> {
+        self.head_rotation
+    }
+
+    /// Returns the translation of the head relative to the reference face mesh, in pixels.
+    ///
+    /// This uses the same coordinate system as [`Face::head_rotation`].
+    #[inline]
+    pub fn head_translation(&self) -> Vector3<f32> {
+        self.head_translation
+    }
+
+    /// Returns the pose of the head in camera space, if a camera model was set via
+    /// [`FaceTracker::set_camera`].
+    ///
+    /// The pose transforms points of the reference face model (see
+    /// [`mediapipe_facemesh::reference_positions_mm`]) into camera space, where X points right, Y
+    /// points down, and Z points forward. Distances are in millimetres, so
+    /// [`PnpResult::translation`] is the position of the head in front of the camera.
+    ///
+    /// Unlike [`Face::head_rotation`], this pose takes perspective into account, but it is not
+    /// affected by [`FaceTracker::set_head_rotation_filter`]. Note that the reference model has Y
+    /// pointing up and Z pointing out of the face, so a face looking straight into the camera
+    /// results in a rotation of 180° around the X axis.
+    #[inline]
+    pub fn camera_pose(&self) -> Option<PnpResult> {
+        self.camera_pose
+    }
+
+    /// Returns the facial expression, with head rotation removed.
+    ///
+    /// The expression is relative to the reference face model until a neutral expression is
+    /// calibrated with [`FaceTracker::calibrate_neutral_expression`].
+    #[inline]
+    pub fn expression(&self) -> Expression {
+        self.expression
+    }
+}
+
+/// Records the ID, region of interest, confidence and face mesh landmarks of a face.
+impl From<&Face> for RecordedTarget {
+    fn from(face: &Face) -> Self {
+        RecordedTarget::new(face.landmarks.landmarks().clone())
+            .with_id(face.id)
+            .with_roi(face.view_rect)
+            .with_confidence(face.landmarks.face_confidence())
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Eye {
+    Left,
+    Right,
+}
+
+/// Per-face state kept in addition to the [`MultiTracker`] target.
+struct FaceState {
+    left_eye: EyeEstimator,
+    right_eye: EyeEstimator,
+    rotation_filter: Option<Box<PoseFilterFn<UnitQuaternion<f32>>>>,
+    translation_filter: Option<Box<PoseFilterFn<Vector3<f32>>>>,
+    left_eye_features: EyeFeatureTracker,
+    right_eye_features: EyeFeatureTracker,
+    expression: ExpressionEstimator,
+}
+
+/// Filters a head pose component of a single face captured at the given timestamp.
+type PoseFilterFn<V> = dyn FnMut(V, Duration) -> V + Send;
+
+/// Creates the [`PoseFilterFn`] for a newly tracked face.
+type MakePoseFilter<V> = dyn Fn() -> Box<PoseFilterFn<V>> + Send;
+
+/// Shares the parameters of `filter` between all faces, while giving each face its own state.
+fn pose_filter<V, F>(filter: F) -> Box<MakePoseFilter<V>>
+where
+    V: Clone + Send + 'static,
+    F: TimeBasedFilter<V> + Send + Sync + 'static,
+    F::State: Send,
+{
+    let filter = Arc::new(filter);
+    Box::new(move || {
+        let filter = filter.clone();
+        let mut state = F::State::default();
+        let mut last = None;
+        let mut output = None;
+        Box::new(move |value, timestamp: Duration| {
+            match frame_delta(&mut last, timestamp) {
+                Some(elapsed) => {
+                    let value = filter.filter(&mut state, value, elapsed);
+                    output = Some(value.clone());
+                    value
+                }
+                // A repeated timestamp yields the previous pose without updating the filter.
+                None => output.clone().unwrap_or(value),
+            }
+        })
+    })
+}
+
+type EyeInput = (Arc<Image>, RotatedRect, Duration, Promise<EyeLandmarks>);
+
+enum EyeEstimator {
+    Inline(Eye, Box<Estimator<EyeLandmarks>>),
+    Worker(Worker<EyeInput>),
+}
+
+impl EyeEstimator {
+    fn new(eye: Eye, filter: LandmarkFilter, worker: bool) -> Self {
+        let mut estimator = Estimator::new(EyeNetwork);
+        estimator.set_filter(filter);
+        if !worker {
+            return Self::Inline(eye, Box::new(estimator));
+        }
+
+        let name = match eye {
+            Eye::Left => "left iris",
+            Eye::Right => "right iris",
+        };
+        let worker = Worker::builder()
+            .name(name)
+            .spawn(move |(image, rect, timestamp, promise): EyeInput| {
+                promise.fulfill(estimate_eye(&mut estimator, eye, &image, rect, timestamp));
+            })
+            .unwrap();
+        Self::Worker(worker)
+    }
+
+    fn start(&mut self, image: Arc<Image>, rect: RotatedRect, timestamp: Duration) -> EyeJob {
+        match self {
+            Self::Inline(eye, estimator) => {
+                EyeJob::Done(estimate_eye(estimator, *eye, &image, rect, timestamp))
+            }
+            Self::Worker(worker) => {
+                let (promise, handle) = promise();
+                worker.send((image, rect, timestamp, promise));
+                EyeJob::Pending(handle)
+            }
+        }
+    }
+}
+
+enum EyeJob {
+    Done(EyeLandmarks),
+    Pending(PromiseHandle<EyeLandmarks>),
+}
+
+impl EyeJob {
+    fn wait(self) -> EyeLandmarks {
+        match self {
+            Self::Done(landmarks) => landmarks,
+            Self::Pending(handle) => handle.block().unwrap(),
+        }
+    }
+}
+
+/// Estimates the landmarks of `eye`, located in `rect`, and maps them to `image` coordinates.
+fn estimate_eye(
+    estimator: &mut Estimator<EyeLandmarks>,
+    eye: Eye,
+    image: &Image,
+    rect: RotatedRect,
+    timestamp: Duration,
+) -> EyeLandmarks {
+    let view = image.view(rect);
+    let marks = match eye {
+        Eye::Left => estimator.estimate_at(&view, timestamp),
+        Eye::Right => {
+            // The network only handles left eyes, so flip the image and the resulting landmarks.
+            let flipped = view.flip_horizontal();
+            let marks = estimator.estimate_at(&flipped, timestamp);
+            marks.flip_horizontal_in_place(flipped.resolution());
+            marks
+        }
+    };
+
+    marks.landmarks_mut().map_positions(|[x, y, z]| {
+        let [x, y] = rect.transform_out_f32(x, y);
+        [x, y, z]
+    });
+    marks.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::filter::one_euro::OneEuroFilter;
+
+    use super::*;
+
+    #[test]
+    fn pose_filter_repeated_timestamp() {
+        let make_filter = pose_filter(OneEuroFilter::new(1.0, 0.5));
+        let mut filter = make_filter();
+
+        let a = UnitQuaternion::from_euler_angles(0.1, 0.2, 0.3);
+        let b = UnitQuaternion::from_euler_angles(0.3, 0.2, 0.1);
+        filter(a, Duration::from_millis(0));
+        let first = filter(b, Duration::from_millis(33));
+
+        // No time has passed, so the previous rotation is returned instead of a NaN quaternion.
+        let repeated = filter(a, Duration::from_millis(33));
+        assert_eq!(repeated, first);
+        assert!(!repeated.coords.iter().any(|c| c.is_nan()));
+    }
+}
@@ -53,7 +53,14 @@
 //!
 //! ["JSON Lines"]: https://jsonlines.org/
 
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
+use zaru::face::{
+    expression::{Blendshape, Expression},
+    eye::features::EyeFeatures,
+    tracking::Face,
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TrackerMessage {
@@ -127,6 +134,18 @@ pub struct Features {
     /// fields may be omitted entirely.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub right_eye: Option<Eye>,
+
+    /// Facial expression, as blendshape coefficients keyed by their [ARKit] names (eg.
+    /// `"jawOpen"`).
+    ///
+    /// Coefficients are in range `[0.0, 1.0]`, where 0.0 corresponds to the subject's neutral
+    /// expression and 1.0 means that the blendshape is fully expressed. Trackers may support any
+    /// subset of the ARKit blendshapes (or none at all); consumers should treat missing
+    /// blendshapes as 0.0.
+    ///
+    /// [ARKit]: https://developer.apple.com/documentation/arkit/arfaceanchor/blendshapelocation
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub blendshapes: BTreeMap<String, f32>,
 }
 
 /// Iris and eyelid information for a single eye.
@@ -162,6 +181,41 @@ pub struct Eye {
     pub eyelid_gap: Option<f32>,
 }
 
+/// Describes the eyes and expression of a face tracked by a
+/// [`FaceTracker`][zaru::face::tracking::FaceTracker].
+impl From<&Face> for Features {
+    fn from(face: &Face) -> Self {
+        Self {
+            left_eye: Some(face.left_eye_features().into()),
+            right_eye: Some(face.right_eye_features().into()),
+            blendshapes: blendshape_map(face.expression().iter()),
+        }
+    }
+}
+
+/// Collects blendshape coefficients (eg. from [`Expression::iter`]) into a map keyed by their
+/// ARKit names, as used by [`Features::blendshapes`].
+fn blendshape_map(
+    coefficients: impl IntoIterator<Item = (Blendshape, f32)>,
+) -> BTreeMap<String, f32> {
+    coefficients
+        .into_iter()
+        .map(|(blendshape, coefficient)| (blendshape.name().to_string(), coefficient))
+        .collect()
+}
+
+impl From<EyeFeatures> for Eye {
+    fn from(features: EyeFeatures) -> Self {
+        // `EyeFeatures` already uses the conventions of this format, and is extrapolated while the
+        // eye cannot be measured, as required.
+        let [x, y] = features.iris_offset();
+        Self {
+            iris_offset: Some(Vec2 { x, y }),
+            eyelid_gap: Some(features.eyelid_gap()),
+        }
+    }
+}
+
 /// A quaternion of the form `q = r * x*i * y*j * z*k`.
 ///
 /// Note that these are not necessarily normalized to have unit norm, so they cannot be used as
@@ -191,3 +245,31 @@ pub struct Vec3 {
 }
 
 // TODO: tongue tracking data, you perverts
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blendshapes_roundtrip() {
+        let mut blendshapes = blendshape_map(Expression::default().iter());
+        assert_eq!(blendshapes.len(), Blendshape::COUNT);
+        assert_eq!(blendshapes["jawOpen"], 0.0);
+
+        blendshapes.extend(blendshape_map([
+            (Blendshape::JawOpen, 0.75),
+            (Blendshape::MouthSmileLeft, 0.25),
+        ]));
+        let features = Features {
+            left_eye: None,
+            right_eye: None,
+            blendshapes,
+        };
+        let json = serde_json::to_string(&features).unwrap();
+        assert!(json.contains(r#""jawOpen":0.75"#));
+        assert!(json.contains(r#""mouthSmileLeft":0.25"#));
+
+        let parsed: Features = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.blendshapes, features.blendshapes);
+    }
+}
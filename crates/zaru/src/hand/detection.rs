@@ -1,7 +1,7 @@
 //! Palm detection.
 
-use nalgebra::{Point2, Rotation2, Vector2};
 use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 use zaru_image::{
     draw, AsImageView, AsImageViewMut, Color, ImageView, ImageViewMut, Rect, Resolution,
     RotatedRect,
@@ -10,9 +10,9 @@ use zaru_utils::num::sigmoid;
 
 use crate::{
     detection::{
-        nms::NonMaxSuppression,
+        nms::{IouMode, NonMaxSuppression},
         ssd::{Anchor, AnchorParams, Anchors, LayerInfo},
-        BoundingRect, RawDetection,
+        BoundingRect, DetectionLike, Detector, RawDetection,
     },
     nn::{create_linear_color_mapper, point_to_img, Cnn, CnnInputShape, NeuralNetwork},
     timer::Timer,
@@ -35,12 +35,15 @@ impl PalmDetector {
 
     pub fn new<N: PalmDetectionNetwork>(network: N) -> Self {
         drop(network);
+        // Hands are frequently seen at an angle, so compute overlap using rotated boxes.
+        let mut nms = NonMaxSuppression::new();
+        nms.set_iou_mode(IouMode::Rotated);
         Self {
             cnn: N::cnn(),
             anchors: Anchors::calculate(&AnchorParams {
                 layers: &[LayerInfo::new(2, 24, 24), LayerInfo::new(6, 12, 12)],
             }),
-            nms: NonMaxSuppression::new(),
+            nms,
             thresh: Self::DEFAULT_THRESH,
             t_resize: Timer::new("resize"),
             t_infer: Timer::new("infer"),
@@ -116,6 +119,34 @@ impl PalmDetector {
     }
 }
 
+impl Detector for PalmDetector {
+    type Detection = Detection;
+
+    fn input_resolution(&self) -> Resolution {
+        self.input_resolution()
+    }
+
+    fn detect(&mut self, image: ImageView<'_>) -> &[Detection] {
+        self.detect_impl(image)
+    }
+
+    fn timers(&self) -> Box<dyn Iterator<Item = &Timer> + '_> {
+        Box::new(self.timers())
+    }
+
+    fn threshold(&self) -> f32 {
+        self.thresh
+    }
+
+    fn set_threshold(&mut self, threshold: f32) {
+        self.thresh = threshold;
+    }
+
+    fn nms_mut(&mut self) -> &mut NonMaxSuppression {
+        &mut self.nms
+    }
+}
+
 fn extract_detection(
     anchor: &Anchor,
     input_res: Resolution,
@@ -138,7 +169,7 @@ fn extract_detection(
         )
     };
 
-    RawDetection::with_keypoints(
+    let mut det = RawDetection::with_keypoints(
         confidence,
         BoundingRect::from_center(xc, yc, w, h),
         vec![
@@ -150,10 +181,12 @@ fn extract_detection(
             lm(box_params[14], box_params[15]),
             lm(box_params[16], box_params[17]),
         ],
-    )
+    );
+    det.set_rotation_from_keypoints(Keypoint::Wrist as usize, Keypoint::MiddleFingerMcp as usize);
+    det
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Detection {
     raw: RawDetection,
     full_res: Resolution,
@@ -168,6 +201,13 @@ impl Detection {
         self.raw.bounding_rect().to_rect(&self.full_res)
     }
 
+    /// Returns the bounding rectangle of the palm, rotated by [`Detection::rotation_radians`].
+    pub fn rotated_bounding_rect(&self) -> RotatedRect {
+        self.raw
+            .rotated_bounding_rect()
+            .to_rotated_rect_in(&self.full_res)
+    }
+
     pub fn keypoint(&self, keypoint: Keypoint) -> (i32, i32) {
         let p = self.raw.keypoints()[keypoint as usize];
         point_to_img(p.x(), p.y(), &self.full_res)
@@ -177,13 +217,7 @@ impl Detection {
     ///
     /// A rotation of 0° means that fingers are pointed upwards.
     pub fn rotation_radians(&self) -> f32 {
-        let (x, y) = self.keypoint(Keypoint::MiddleFingerMcp);
-        let finger = Point2::new(x as f32, y as f32);
-        let (x, y) = self.keypoint(Keypoint::Wrist);
-        let wrist = Point2::new(x as f32, y as f32);
-
-        let rel = wrist - finger;
-        Rotation2::rotation_between(&Vector2::y(), &rel).angle()
+        self.raw.rotation_radians()
     }
 
     /// Draws the bounding box of this detection onto an image.
@@ -242,6 +276,20 @@ impl Detection {
     }
 }
 
+impl DetectionLike for Detection {
+    fn from_raw(raw: RawDetection, full_res: Resolution) -> Self {
+        Self { raw, full_res }
+    }
+
+    fn raw(&self) -> &RawDetection {
+        &self.raw
+    }
+
+    fn full_resolution(&self) -> Resolution {
+        self.full_res
+    }
+}
+
 /// A keypoint of a [`Detection`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Keypoint {
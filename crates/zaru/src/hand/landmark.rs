@@ -2,11 +2,12 @@
 
 use nalgebra::{Point2, Rotation2, Vector2};
 use once_cell::sync::Lazy;
-use zaru_image::{draw, AsImageViewMut, Color, ImageViewMut};
+use zaru_image::{AsImageViewMut, Color, ImageViewMut};
 use zaru_utils::iter::zip_exact;
 
 use crate::{
-    landmark::{Confidence, Estimation, Landmarks, Network},
+    draw,
+    landmark::{topology::LandmarkTopology, Confidence, Estimation, Landmarks, Network},
     nn::{create_linear_color_mapper, Cnn, CnnInputShape, NeuralNetwork, Outputs},
     slice::SliceExt,
 };
@@ -151,19 +152,15 @@ impl LandmarkResult {
             &format!("presence={:.2}", self.presence()),
         );
 
-        for (a, b) in CONNECTIVITY {
-            let [a_x, a_y, _] = self.landmark_position(*a as usize);
-            let [b_x, b_y, _] = self.landmark_position(*b as usize);
-
-            draw::line(target, a_x as _, a_y as _, b_x as _, b_y as _).color(Color::GREEN);
-        }
-        for [x, y, _] in self.landmark_positions() {
-            draw::marker(target, x as i32, y as i32);
-        }
+        draw::skeleton(target, &self.landmarks, &TOPOLOGY);
     }
 }
 
 impl Estimation for LandmarkResult {
+    fn landmarks(&self) -> &Landmarks {
+        &self.landmarks
+    }
+
     fn landmarks_mut(&mut self) -> &mut Landmarks {
         &mut self.landmarks
     }
@@ -235,38 +232,72 @@ const PALM_LANDMARKS: &[LandmarkIdx] = {
     ]
 };
 
-const CONNECTIVITY: &[(LandmarkIdx, LandmarkIdx)] = {
+static TOPOLOGY: Lazy<LandmarkTopology> = Lazy::new(|| {
     use LandmarkIdx::*;
-    &[
-        // Surround the palm:
-        (Wrist, ThumbCmc),
-        (ThumbCmc, IndexFingerMcp),
-        (IndexFingerMcp, MiddleFingerMcp),
-        (MiddleFingerMcp, RingFingerMcp),
-        (RingFingerMcp, PinkyMcp),
-        (PinkyMcp, Wrist),
-        // Thumb:
-        (ThumbCmc, ThumbMcp),
-        (ThumbMcp, ThumbIp),
-        (ThumbIp, ThumbTip),
-        // Index:
-        (IndexFingerMcp, IndexFingerPip),
-        (IndexFingerPip, IndexFingerDip),
-        (IndexFingerDip, IndexFingerTip),
-        // Middle:
-        (MiddleFingerMcp, MiddleFingerPip),
-        (MiddleFingerPip, MiddleFingerDip),
-        (MiddleFingerDip, MiddleFingerTip),
-        // Ring:
-        (RingFingerMcp, RingFingerPip),
-        (RingFingerPip, RingFingerDip),
-        (RingFingerDip, RingFingerTip),
-        // Pinky:
-        (PinkyMcp, PinkyPip),
-        (PinkyPip, PinkyDip),
-        (PinkyDip, PinkyTip),
-    ]
-};
+
+    let mut topology = LandmarkTopology::new(21);
+    for (idx, name) in [
+        (Wrist, "wrist"),
+        (ThumbCmc, "thumb cmc"),
+        (ThumbMcp, "thumb mcp"),
+        (ThumbIp, "thumb ip"),
+        (ThumbTip, "thumb tip"),
+        (IndexFingerMcp, "index finger mcp"),
+        (IndexFingerPip, "index finger pip"),
+        (IndexFingerDip, "index finger dip"),
+        (IndexFingerTip, "index finger tip"),
+        (MiddleFingerMcp, "middle finger mcp"),
+        (MiddleFingerPip, "middle finger pip"),
+        (MiddleFingerDip, "middle finger dip"),
+        (MiddleFingerTip, "middle finger tip"),
+        (RingFingerMcp, "ring finger mcp"),
+        (RingFingerPip, "ring finger pip"),
+        (RingFingerDip, "ring finger dip"),
+        (RingFingerTip, "ring finger tip"),
+        (PinkyMcp, "pinky mcp"),
+        (PinkyPip, "pinky pip"),
+        (PinkyDip, "pinky dip"),
+        (PinkyTip, "pinky tip"),
+    ] {
+        topology.set_name(idx as usize, name);
+    }
+
+    let palm = PALM_LANDMARKS
+        .iter()
+        .map(|&lm| lm as usize)
+        .collect::<Vec<_>>();
+    topology.add_loop(&palm).add_group("palm", palm, None);
+    for (name, finger) in [
+        ("thumb", [ThumbCmc, ThumbMcp, ThumbIp, ThumbTip]),
+        (
+            "index finger",
+            [
+                IndexFingerMcp,
+                IndexFingerPip,
+                IndexFingerDip,
+                IndexFingerTip,
+            ],
+        ),
+        (
+            "middle finger",
+            [
+                MiddleFingerMcp,
+                MiddleFingerPip,
+                MiddleFingerDip,
+                MiddleFingerTip,
+            ],
+        ),
+        (
+            "ring finger",
+            [RingFingerMcp, RingFingerPip, RingFingerDip, RingFingerTip],
+        ),
+        ("pinky", [PinkyMcp, PinkyPip, PinkyDip, PinkyTip]),
+    ] {
+        let finger = finger.map(|lm| lm as usize);
+        topology.add_chain(&finger).add_group(name, finger, None);
+    }
+    topology
+});
 
 /// A lightweight but fairly inaccurate landmark estimation network.
 ///
@@ -299,6 +330,10 @@ impl Network for LiteNetwork {
     fn extract(&self, outputs: &Outputs, estimation: &mut Self::Output) {
         extract(outputs, estimation);
     }
+
+    fn topology(&self) -> Option<&'static LandmarkTopology> {
+        Some(&TOPOLOGY)
+    }
 }
 
 /// A somewhat more accurate landmark estimation network that takes about 25-30% longer to infer
@@ -330,6 +365,10 @@ impl Network for FullNetwork {
     fn extract(&self, outputs: &Outputs, estimation: &mut Self::Output) {
         extract(outputs, estimation);
     }
+
+    fn topology(&self) -> Option<&'static LandmarkTopology> {
+        Some(&TOPOLOGY)
+    }
 }
 
 fn extract(outputs: &Outputs, estimation: &mut LandmarkResult) {
@@ -3,36 +3,37 @@
 //! This is a higher-level module that provides a self-contained hand tracking solution that will
 //! detect and track any number of hands, and compute landmarks for each one.
 
-use pawawwewism::{promise, Promise, PromiseHandle, Worker};
-use std::{
-    sync::{Arc, Mutex},
-    time::{Duration, Instant},
-};
+use std::{sync::Arc, time::Duration};
+
 use zaru_image::{Image, RotatedRect};
 
-use crate::landmark::{Estimator, LandmarkTracker, Network};
+use crate::{
+    landmark::{
+        recording::RecordedTarget, topology::LandmarkTopology, MultiTracker, Network, TargetId,
+        TrackedTarget,
+    },
+    nn::{Cnn, Outputs},
+};
 
 use super::{
-    detection::{self, Detection, PalmDetector},
+    detection::{self, PalmDetector},
     landmark::LandmarkResult,
 };
 
 /// Self-contained hand detector, tracker, and landmarker.
+///
+/// This is a [`MultiTracker`] configured for palm detection and hand landmark estimation.
 pub struct HandTracker {
-    hands: Vec<TrackedHand>,
-    next_hand_id: HandId,
-    detector: Worker<(Arc<Image>, Promise<Vec<Detection>>)>,
-    detections_handle: Option<PromiseHandle<Vec<Detection>>>,
-    next_det: Instant,
-    det_interval: Duration,
-    make_estimator: Box<dyn Fn() -> Estimator<LandmarkResult>>,
-    iou_thresh: f32,
+    tracker: MultiTracker<PalmDetector, AnyNetwork>,
 }
 
-/// We use a custom padding for the [`LandmarkTracker`] since the default loses tracking when the
-/// hand is closed.
+/// We use a custom padding for the [`LandmarkTracker`][crate::landmark::LandmarkTracker] since the
+/// default loses tracking when the hand is closed.
 const ROI_PADDING: f32 = 0.4;
 
+/// Palm -> Hand grow factor.
+const PALM_PADDING: f32 = 1.5;
+
 impl HandTracker {
     /// Default intersection-over-union threshold for deduplicating tracking regions.
     pub const DEFAULT_IOU_THRESH: f32 = 0.3;
@@ -44,69 +45,39 @@ impl HandTracker {
     pub fn new<D, L>(detector: D, landmarker: L) -> Self
     where
         D: detection::PalmDetectionNetwork,
-        L: Network<Output = LandmarkResult> + Clone + 'static,
+        L: Network<Output = LandmarkResult>,
     {
-        let mut palm_detector = PalmDetector::new(detector);
-        Self {
-            hands: Vec::new(),
-            next_hand_id: HandId(0),
-            detector: Worker::builder()
-                .name("palm detector")
-                .spawn(
-                    move |(image, promise): (Arc<Image>, Promise<Vec<Detection>>)| {
-                        let detections = palm_detector.detect(&*image);
-                        promise.fulfill(detections.to_vec());
-                    },
-                )
-                .unwrap(),
-            detections_handle: None,
-            next_det: Instant::now(),
-            det_interval: Self::DEFAULT_REDETECT_INTERVAL,
-            make_estimator: Box::new(move || Estimator::new(landmarker.clone())),
-            iou_thresh: Self::DEFAULT_IOU_THRESH,
-        }
+        let landmarker = AnyNetwork(Arc::new(landmarker));
+        let mut tracker = MultiTracker::new(PalmDetector::new(detector), landmarker);
+        tracker.set_iou_thresh(Self::DEFAULT_IOU_THRESH);
+        tracker.set_redetect_interval(Self::DEFAULT_REDETECT_INTERVAL);
+        tracker.set_detection_padding(PALM_PADDING);
+        tracker.set_roi_padding(ROI_PADDING);
+        Self { tracker }
     }
 
     /// Sets the redetection interval.
     ///
-    /// Redetection works as follows:
-    /// - if no hands are currently being tracked, `track` will always attempt to trigger a
-    ///   detection
-    /// - otherwise, if the last detection was triggered more than the redetect interval ago,
-    ///   `track` will attempt to trigger another detection
-    ///
-    /// "Attempt" means that if a detection is already ongoing, nothing happens, and if not, one is
-    /// started.
-    ///
-    /// This scheme ensures that newly appearing hands get picked up in a timely fashion, but
-    /// without blocking (detection is expensive, so it runs in its own worker).
+    /// See [`MultiTracker::set_redetect_interval`] for how redetection works.
     ///
     /// By default, [`Self::DEFAULT_REDETECT_INTERVAL`] is used.
     pub fn set_redetect_interval(&mut self, interval: Duration) {
-        self.det_interval = interval;
+        self.tracker.set_redetect_interval(interval);
     }
 
     /// Sets the intersection-over-union threshold at which two tracking regions are considered to
     /// overlap.
     ///
-    /// Since redetection will also detect all hands that are already being tracked, this threshold
-    /// is used to ensure that each hand is only tracked once. No new tracking worker is spawned if
-    /// a detection overlaps with an existing tracker's region of interest.
+    /// See [`MultiTracker::set_iou_thresh`].
     ///
     /// By default, [`Self::DEFAULT_IOU_THRESH`] is used.
     pub fn set_iou_thresh(&mut self, thresh: f32) {
-        self.iou_thresh = thresh;
+        self.tracker.set_iou_thresh(thresh);
     }
 
     /// Returns an iterator over the tracking data for each hand.
     pub fn hands(&self) -> impl Iterator<Item = HandData<'_>> {
-        self.hands.iter().filter_map(|hand| {
-            hand.lm.as_ref().map(|lm| HandData {
-                id: hand.id,
-                lm,
-                view_rect: *hand.roi.lock().unwrap(),
-            })
-        })
+        self.tracker.targets().map(HandData)
     }
 
     /// Blocks until all tracking computations from the last call to `track` are finished, and
@@ -115,155 +86,69 @@ impl HandTracker {
     /// After this method returns, [`HandTracker::hands`] will return the state of all hands in the
     /// previous image passed to `track`.
     pub fn track(&mut self, image: Arc<Image>) {
-        self.hands.retain_mut(|hand| {
-            let (promise, ph) = promise();
-            let old_ph = std::mem::replace(&mut hand.ph, ph);
-            match old_ph.block().unwrap() {
-                Some(lm) => {
-                    hand.worker.send((image.clone(), promise));
-                    hand.lm = Some(lm);
-                    true
-                }
-                None => false,
-            }
-        });
-
-        let mut detections = match &self.detections_handle {
-            Some(handle) if !handle.will_block() => {
-                self.detections_handle.take().unwrap().block().unwrap()
-            }
-            _ => Vec::new(),
-        };
-
-        let grow_by = 1.5; // Palm -> Hand grow factor
-
-        // Compute IoU with existing RoIs, discard detection if it overlaps with any, spawn tracker
-        // when it doesn't.
-        detections.retain(|det| {
-            for hand in &self.hands {
-                if hand
-                    .roi
-                    .lock()
-                    .unwrap()
-                    .rect()
-                    .iou(&det.bounding_rect().grow_rel(grow_by))
-                    >= self.iou_thresh
-                {
-                    // Overlap
-                    return false;
-                }
-            }
-
-            true
-        });
-
-        self.hands.extend(detections.iter().map(|det| {
-            let roi = RotatedRect::new(
-                det.bounding_rect().grow_rel(grow_by),
-                det.rotation_radians(),
-            );
-            let mut estimator = (self.make_estimator)();
-            let mut tracker =
-                LandmarkTracker::new(estimator.input_resolution().aspect_ratio().unwrap());
-            tracker.set_roi_padding(ROI_PADDING);
-            tracker.set_roi(roi);
-            let roi_arc = Arc::new(Mutex::new(roi));
-            let roi_arc2 = roi_arc.clone();
-            let mut worker = Worker::builder()
-                .name("hand tracker")
-                .spawn(move |(image, promise): (Arc<Image>, Promise<_>)| {
-                    match tracker.track(&mut estimator, &*image) {
-                        Some(res) => {
-                            *roi_arc2.lock().unwrap() = res.updated_roi();
+        self.tracker.track(image);
+    }
 
-                            let lm = res.estimation().clone();
-                            promise.fulfill(Some(lm));
-                        }
-                        None => {
-                            log::trace!("tracking lost");
-                            promise.fulfill(None);
-                        }
-                    }
-                })
-                .unwrap();
+    /// Like [`HandTracker::track`], but uses `timestamp` as the time at which `image` was captured.
+    ///
+    /// See [`MultiTracker::track_at`].
+    pub fn track_at(&mut self, image: Arc<Image>, timestamp: Duration) {
+        self.tracker.track_at(image, timestamp);
+    }
+}
 
-            let id = self.next_hand_id;
-            self.next_hand_id.0 += 1;
-            let (promise, ph) = promise();
-            worker.send((image.clone(), promise));
-            TrackedHand {
-                id,
-                roi: roi_arc.clone(),
-                worker,
-                ph,
-                lm: None,
-            }
-        }));
+/// Type-erased hand landmark network, so that [`HandTracker`] doesn't need a type parameter.
+#[derive(Clone)]
+struct AnyNetwork(Arc<dyn Network<Output = LandmarkResult>>);
 
-        // Check if any of the tracked regions started to overlap, and remove one of them.
-        for i in (0..self.hands.len()).rev() {
-            let roi = *self.hands[i].roi.lock().unwrap();
+impl Network for AnyNetwork {
+    type Output = LandmarkResult;
 
-            for j in 0..i {
-                let other_roi = *self.hands[j].roi.lock().unwrap();
-                // FIXME: IoU computation ignores rotation because hard
-                if roi.rect().iou(&other_roi.rect()) >= self.iou_thresh {
-                    self.hands.swap_remove(i);
-                    break;
-                }
-            }
-        }
+    fn cnn(&self) -> &Cnn {
+        self.0.cnn()
+    }
 
-        if (self.hands.is_empty() || Instant::now() >= self.next_det)
-            && self.detections_handle.is_none()
-        {
-            // We want to start a detection, and none is currently running, so start one.
-            let (promise, handle) = promise();
-            self.detector.send((image.clone(), promise));
-            self.detections_handle = Some(handle);
-            self.next_det += self.det_interval;
-        }
+    fn extract(&self, outputs: &Outputs, estimation: &mut LandmarkResult) {
+        self.0.extract(outputs, estimation);
     }
-}
 
-struct TrackedHand {
-    id: HandId,
-    roi: Arc<Mutex<RotatedRect>>,
-    worker: Worker<(Arc<Image>, Promise<Option<LandmarkResult>>)>,
-    ph: PromiseHandle<Option<LandmarkResult>>,
-    lm: Option<LandmarkResult>,
+    fn topology(&self) -> Option<&'static LandmarkTopology> {
+        self.0.topology()
+    }
 }
 
 /// ID of a tracked hand.
 ///
 /// The assigned [`HandId`]s are unique per [`HandTracker`] assigning them. They are reused between
 /// frames for as long as the hand is tracked.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct HandId(u64);
+pub type HandId = TargetId;
 
 /// Tracking data returned for a hand in the input image.
-pub struct HandData<'a> {
-    id: HandId,
-    lm: &'a LandmarkResult,
-    view_rect: RotatedRect,
-}
+pub struct HandData<'a>(TrackedTarget<'a, LandmarkResult>);
 
 impl<'a> HandData<'a> {
     /// Returns the unique ID of this hand.
     #[inline]
     pub fn id(&self) -> HandId {
-        self.id
+        self.0.id()
     }
 
     /// Returns the hand landmarks, in global image coordinates.
     #[inline]
     pub fn landmark_result(&self) -> &LandmarkResult {
-        self.lm
+        self.0.estimation()
     }
 
     /// Returns the hand's bounding rectangle in the original image.
     #[inline]
     pub fn view_rect(&self) -> RotatedRect {
-        self.view_rect
+        self.0.view_rect()
+    }
+}
+
+/// Records the ID, bounding rectangle, presence and landmarks of a hand.
+impl From<HandData<'_>> for RecordedTarget {
+    fn from(hand: HandData<'_>) -> Self {
+        hand.0.into()
     }
 }
@@ -1,30 +1,74 @@
 //! Common code for visual landmark estimation.
 
-use std::iter;
+use std::{
+    f32::consts::{PI, TAU},
+    iter,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
-use zaru_image::{AsImageView, AspectRatio, ImageView, Resolution, RotatedRect};
+use pawawwewism::{promise, Promise, PromiseHandle, Worker};
+use zaru_image::{
+    AsImageView, AspectRatio, Color, Image, ImageView, Rect, Resolution, RotatedRect,
+};
 use zaru_utils::iter::zip_exact;
 
 use crate::{
-    filter::Filter,
+    detection::{DetectionLike, Detector, RotatedBoundingRect},
+    filter::{alpha_beta::AlphaBetaFilter, Filter, FilterBase, PredictiveFilter, TimeBasedFilter},
     nn::{Cnn, Outputs},
     timer::Timer,
 };
 
+use self::topology::LandmarkTopology;
+
+pub mod recording;
+pub mod topology;
+
 type Position = [f32; 3];
 
+/// Threshold above which [`Landmark::visibility`] and [`Landmark::presence`] values are considered
+/// to indicate a visible or present landmark.
+pub const VISIBILITY_THRESHOLD: f32 = 0.5;
+
+/// Color used by the `draw` methods of landmark results for landmarks that are not
+/// [visible][Landmark::is_visible].
+///
+/// Hidden landmarks, and edges ending at them, are drawn in this color rather than being omitted
+/// (see [`crate::draw::skeleton`]).
+pub const HIDDEN_COLOR: Color = Color::from_rgb8(127, 127, 127);
+
+/// A list of landmarks, with optional per-landmark attributes.
+///
+/// In addition to its position, each landmark can have a *confidence*, *visibility* and *presence*
+/// value. These are stored in separate channels that are only allocated when a network produces
+/// them (by calling [`Landmarks::confidences_mut`], [`Landmarks::visibilities_mut`] or
+/// [`Landmarks::presences_mut`]).
+///
+/// - Confidence describes how confident the network is in the landmark's position.
+/// - Visibility is the probability that the landmark is visible, and not occluded by another object
+///   or a different part of the same object.
+/// - Presence is the probability that the landmark is located inside of the input image.
+///
+/// All attributes are expected to be in range 0.0 to 1.0.
 #[derive(Default, Clone)]
 pub struct Landmarks {
     positions: Vec<Position>,
+    confidences: Option<Vec<f32>>,
+    visibilities: Option<Vec<f32>>,
+    presences: Option<Vec<f32>>,
 }
 
 impl Landmarks {
     /// Creates a new [`Landmarks`] collection containing `len` preallocated landmarks.
     ///
-    /// All landmarks will start with all coordinates at `0.0`.
+    /// All landmarks will start with all coordinates at `0.0`, and without any attribute channels.
     pub fn new(len: usize) -> Self {
         Self {
             positions: vec![[0.0, 0.0, 0.0]; len],
+            confidences: None,
+            visibilities: None,
+            presences: None,
         }
     }
 
@@ -33,12 +77,16 @@ impl Landmarks {
     }
 
     pub fn iter(&self) -> impl Iterator<Item = Landmark> + Clone + '_ {
-        self.positions.iter().map(|&pos| Landmark { pos })
+        (0..self.len()).map(|index| self.landmark(index))
     }
 
     pub fn landmark(&self, index: usize) -> Landmark {
+        let attr = |channel: &Option<Vec<f32>>| channel.as_ref().map(|values| values[index]);
         Landmark {
             pos: self.positions[index],
+            confidence: attr(&self.confidences),
+            visibility: attr(&self.visibilities),
+            presence: attr(&self.presences),
         }
     }
 
@@ -50,6 +98,45 @@ impl Landmarks {
         &mut self.positions
     }
 
+    /// Returns the per-landmark confidence values, if the channel is present.
+    pub fn confidences(&self) -> Option<&[f32]> {
+        self.confidences.as_deref()
+    }
+
+    /// Returns a mutable reference to the per-landmark confidence values.
+    ///
+    /// If the channel does not exist yet, it is created, and all values are initialized to 1.0.
+    pub fn confidences_mut(&mut self) -> &mut [f32] {
+        let len = self.len();
+        self.confidences.get_or_insert_with(|| vec![1.0; len])
+    }
+
+    /// Returns the per-landmark visibility values, if the channel is present.
+    pub fn visibilities(&self) -> Option<&[f32]> {
+        self.visibilities.as_deref()
+    }
+
+    /// Returns a mutable reference to the per-landmark visibility values.
+    ///
+    /// If the channel does not exist yet, it is created, and all values are initialized to 1.0.
+    pub fn visibilities_mut(&mut self) -> &mut [f32] {
+        let len = self.len();
+        self.visibilities.get_or_insert_with(|| vec![1.0; len])
+    }
+
+    /// Returns the per-landmark presence values, if the channel is present.
+    pub fn presences(&self) -> Option<&[f32]> {
+        self.presences.as_deref()
+    }
+
+    /// Returns a mutable reference to the per-landmark presence values.
+    ///
+    /// If the channel does not exist yet, it is created, and all values are initialized to 1.0.
+    pub fn presences_mut(&mut self) -> &mut [f32] {
+        let len = self.len();
+        self.presences.get_or_insert_with(|| vec![1.0; len])
+    }
+
     pub fn average(&self) -> Position {
         let mut center = [0.0; 3];
         for pos in self.positions() {
@@ -79,6 +166,9 @@ impl Landmarks {
 #[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
 pub struct Landmark {
     pos: [f32; 3],
+    confidence: Option<f32>,
+    visibility: Option<f32>,
+    presence: Option<f32>,
 }
 
 impl Landmark {
@@ -101,6 +191,34 @@ impl Landmark {
     pub fn z(&self) -> f32 {
         self.pos[2]
     }
+
+    /// Returns the confidence of the landmark position, if the network provides one.
+    #[inline]
+    pub fn confidence(&self) -> Option<f32> {
+        self.confidence
+    }
+
+    /// Returns the probability that the landmark is visible (not occluded), if the network
+    /// provides one.
+    #[inline]
+    pub fn visibility(&self) -> Option<f32> {
+        self.visibility
+    }
+
+    /// Returns the probability that the landmark is inside the image, if the network provides one.
+    #[inline]
+    pub fn presence(&self) -> Option<f32> {
+        self.presence
+    }
+
+    /// Returns whether this landmark is visible and present.
+    ///
+    /// Landmarks without visibility or presence values are considered visible.
+    #[inline]
+    pub fn is_visible(&self) -> bool {
+        self.visibility.unwrap_or(1.0) >= VISIBILITY_THRESHOLD
+            && self.presence.unwrap_or(1.0) >= VISIBILITY_THRESHOLD
+    }
 }
 
 /// Batch-filter for landmarks.
@@ -108,15 +226,52 @@ impl Landmark {
 /// This should be applied to the unadjusted landmarks output by the neural network, otherwise the
 /// filter parameters require tuning that depends on the input image size, which may vary across
 /// invocations.
+///
+/// Only landmark positions are filtered. Confidence, visibility and presence values are passed
+/// through unchanged.
+///
+/// # Timestamps
+///
+/// Filters created with [`LandmarkFilter::time_based`] use the time between frames to drive a
+/// [`TimeBasedFilter`]. When the landmarks are filtered via [`LandmarkFilter::filter`], the
+/// real-world time at which the method is called is used. [`LandmarkFilter::filter_at`] instead
+/// takes an explicit frame timestamp, which makes the result independent of processing speed. This
+/// allows offline processing of recorded footage to produce the same results as a live run.
 pub struct LandmarkFilter {
-    filter: Box<dyn FnMut(&mut Landmarks) + Send>,
+    filter: Box<FilterFn>,
+    epoch: Instant,
+}
+
+/// Filters a batch of landmarks captured at the given timestamp.
+type FilterFn = dyn FnMut(&mut Landmarks, Duration) + Send;
+
+/// Returns the time in seconds since the last frame, and records `timestamp` as the last frame's
+/// timestamp. The first frame uses a time delta of 0.0.
+///
+/// Returns [`None`] if `timestamp` is not after the last frame's timestamp. Time-based filters
+/// can't be updated without time passing (the One Euro filter would produce NaN, for example), so
+/// the caller should reuse its last output instead.
+pub(crate) fn frame_delta(last: &mut Option<Duration>, timestamp: Duration) -> Option<f32> {
+    match *last {
+        Some(prev) if timestamp <= prev => None,
+        prev => {
+            *last = Some(timestamp);
+            Some(prev.map_or(0.0, |prev| seconds_between(prev, timestamp)))
+        }
+    }
+}
+
+/// Returns the time in seconds from `start` to `end`, or 0.0 if `end` is before `start`.
+fn seconds_between(start: Duration, end: Duration) -> f32 {
+    end.saturating_sub(start).as_secs_f32()
 }
 
 /// The default [`LandmarkFilter`] does not perform any filtering.
 impl Default for LandmarkFilter {
     fn default() -> Self {
         Self {
-            filter: Box::new(|_| ()),
+            filter: Box::new(|_, _| ()),
+            epoch: Instant::now(),
         }
     }
 }
@@ -133,41 +288,145 @@ impl LandmarkFilter {
     where
         F::State: Send,
     {
-        let mut states = iter::repeat_with(|| {
-            [
-                F::State::default(),
-                F::State::default(),
-                F::State::default(),
-            ]
-        })
-        .take(num_landmarks)
-        .collect::<Vec<_>>();
+        let mut states = Self::states::<F>(num_landmarks);
 
         Self {
-            filter: Box::new(move |landmarks| {
+            filter: Box::new(move |landmarks, _| {
                 for (lm, state) in zip_exact(&mut landmarks.positions, &mut states) {
                     for (coord, state) in zip_exact(lm, state) {
                         *coord = filter.filter(state, *coord);
                     }
                 }
             }),
+            epoch: Instant::now(),
+        }
+    }
+
+    /// Creates a new landmark filter using a [`TimeBasedFilter`].
+    ///
+    /// The time delta passed to `filter` is derived from the timestamps of subsequent frames (see
+    /// [`LandmarkFilter::filter_at`]). The first frame uses a time delta of 0.0. Frames that do not
+    /// advance the timestamp leave the filter state untouched, and yield the previous output.
+    ///
+    /// # Parameters
+    ///
+    /// - `filter` is the set of filter parameters to use.
+    /// - `num_landmarks` is the number of landmarks that will be filtered with this filter in each
+    ///   batch.
+    pub fn time_based<F: TimeBasedFilter<f32> + Send + 'static>(
+        filter: F,
+        num_landmarks: usize,
+    ) -> Self
+    where
+        F::State: Send,
+    {
+        let mut states = Self::states::<F>(num_landmarks);
+        let mut last = None;
+        let mut output = Vec::new();
+
+        Self {
+            filter: Box::new(move |landmarks, timestamp: Duration| {
+                let Some(elapsed) = frame_delta(&mut last, timestamp) else {
+                    landmarks.positions.clone_from(&output);
+                    return;
+                };
+                for (lm, state) in zip_exact(&mut landmarks.positions, &mut states) {
+                    for (coord, state) in zip_exact(lm, state) {
+                        *coord = filter.filter(state, *coord, elapsed);
+                    }
+                }
+                output.clone_from(&landmarks.positions);
+            }),
+            epoch: Instant::now(),
+        }
+    }
+
+    /// Creates a new landmark filter using a [`TimeBasedFilter`] that filters each landmark's
+    /// position as a whole.
+    ///
+    /// Unlike [`LandmarkFilter::time_based`], which filters each coordinate separately, this passes
+    /// the 3D position of each landmark to `filter`. This is meant for filters with a coupled
+    /// state, like [`VectorKalmanFilter`][crate::filter::kalman::VectorKalmanFilter].
+    ///
+    /// # Parameters
+    ///
+    /// - `filter` is the set of filter parameters to use.
+    /// - `num_landmarks` is the number of landmarks that will be filtered with this filter in each
+    ///   batch.
+    pub fn time_based_points<F: TimeBasedFilter<Position> + Send + 'static>(
+        filter: F,
+        num_landmarks: usize,
+    ) -> Self
+    where
+        F::State: Send,
+    {
+        let mut states = iter::repeat_with(F::State::default)
+            .take(num_landmarks)
+            .collect::<Vec<_>>();
+        let mut last = None;
+        let mut output = Vec::new();
+
+        Self {
+            filter: Box::new(move |landmarks, timestamp: Duration| {
+                let Some(elapsed) = frame_delta(&mut last, timestamp) else {
+                    landmarks.positions.clone_from(&output);
+                    return;
+                };
+                for (lm, state) in zip_exact(&mut landmarks.positions, &mut states) {
+                    *lm = filter.filter(state, *lm, elapsed);
+                }
+                output.clone_from(&landmarks.positions);
+            }),
+            epoch: Instant::now(),
         }
     }
 
-    /// Filters a list of landmarks in-place.
+    fn states<F: FilterBase<f32>>(num_landmarks: usize) -> Vec<[F::State; 3]> {
+        iter::repeat_with(|| {
+            [
+                F::State::default(),
+                F::State::default(),
+                F::State::default(),
+            ]
+        })
+        .take(num_landmarks)
+        .collect()
+    }
+
+    /// Filters a list of landmarks in-place, using the current time as the frame timestamp.
     ///
     /// # Panics
     ///
     /// This method panics if `landmarks` does not have exactly as many entries as were specified in
     /// the `num_landmarks` parameter in the call to [`LandmarkFilter::new`].
     pub fn filter(&mut self, landmarks: &mut Landmarks) {
-        (self.filter)(landmarks);
+        let timestamp = self.epoch.elapsed();
+        (self.filter)(landmarks, timestamp);
+    }
+
+    /// Filters a list of landmarks in-place, using an explicit frame timestamp.
+    ///
+    /// `timestamp` is the time of the frame the landmarks were computed from, relative to an
+    /// arbitrary (but fixed) reference point, like the start of a recording. Timestamps of
+    /// subsequent calls are expected to increase monotonically. Calls to
+    /// [`LandmarkFilter::filter`] and [`LandmarkFilter::filter_at`] should not be mixed, since
+    /// they use unrelated time references.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `landmarks` does not have exactly as many entries as were specified in
+    /// the `num_landmarks` parameter in the call to [`LandmarkFilter::new`].
+    pub fn filter_at(&mut self, landmarks: &mut Landmarks, timestamp: Duration) {
+        (self.filter)(landmarks, timestamp);
     }
 }
 
 /// Trait for landmark estimation results returned by [`Estimator::estimate`].
 pub trait Estimation: Send + Sync + 'static {
     /// Returns the predicted [`Landmarks`].
+    fn landmarks(&self) -> &Landmarks;
+
+    /// Returns a mutable reference to the predicted [`Landmarks`].
     fn landmarks_mut(&mut self) -> &mut Landmarks;
 
     /// Returns the estimated clockwise object rotation in radians.
@@ -206,6 +465,13 @@ pub trait Network: Send + Sync + 'static {
     ///
     /// The landmark positions are expected to be in the coordinate system of the network's input.
     fn extract(&self, outputs: &Outputs, estimation: &mut Self::Output);
+
+    /// Returns the [`LandmarkTopology`] describing the landmarks output by this network.
+    ///
+    /// The default implementation returns [`None`], indicating that no topology is known.
+    fn topology(&self) -> Option<&'static LandmarkTopology> {
+        None
+    }
 }
 
 /// Neural-network based landmark estimator.
@@ -241,6 +507,11 @@ impl<E: Estimation> Estimator<E> {
         self.network.cnn().input_resolution()
     }
 
+    /// Returns the [`LandmarkTopology`] of the network's landmarks, if it provides one.
+    pub fn topology(&self) -> Option<&'static LandmarkTopology> {
+        self.network.topology()
+    }
+
     /// Returns profiling timers for this landmark estimator.
     pub fn timers(&self) -> impl Iterator<Item = &Timer> + '_ {
         [&self.t_infer, &self.t_filter].into_iter()
@@ -264,10 +535,27 @@ impl<E: Estimation> Estimator<E> {
     /// a larger base image, this may include more pixels from the base image that aren't included
     /// in `image`. Otherwise, it adds black bars to pad the image to the right aspect ratio.
     pub fn estimate<V: AsImageView>(&mut self, image: &V) -> &mut E {
-        self.estimate_impl(image.as_view())
+        self.estimate_impl(image.as_view(), None)
+    }
+
+    /// Performs landmark estimation on `image`, a frame captured at `timestamp`.
+    ///
+    /// This works like [`Estimator::estimate`], but passes `timestamp` to the [`LandmarkFilter`]
+    /// instead of using the current time (see [`LandmarkFilter::filter_at`]).
+    pub fn estimate_at<V: AsImageView>(&mut self, image: &V, timestamp: Duration) -> &mut E {
+        self.estimate_impl(image.as_view(), Some(timestamp))
     }
 
-    fn estimate_impl(&mut self, image: ImageView<'_>) -> &mut E {
+    fn estimate_impl(&mut self, image: ImageView<'_>, timestamp: Option<Duration>) -> &mut E {
+        let rect = self.infer(image);
+        self.finish(rect, timestamp)
+    }
+
+    /// Runs the network on `image` and extracts its outputs, without filtering the landmarks or
+    /// mapping them back into `image`.
+    ///
+    /// Returns the (possibly oversized) rectangle in `image` that was passed to the network.
+    fn infer(&mut self, image: ImageView<'_>) -> Rect {
         let cnn = self.network.cnn();
         let input_res = cnn.input_resolution();
 
@@ -281,11 +569,23 @@ impl<E: Estimation> Estimator<E> {
         log::trace!("inference result: {:?}", outputs);
 
         self.network.extract(&outputs, &mut self.estimation);
+        rect
+    }
+
+    /// Filters the landmarks extracted by [`Estimator::infer`] and maps them back into the
+    /// coordinate system of the image.
+    fn finish(&mut self, rect: Rect, timestamp: Option<Duration>) -> &mut E {
+        let input_res = self.network.cnn().input_resolution();
 
         // Importantly, the filter uses the network's coordinates, which makes filter parameters
         // independent of the image's dimensions.
-        self.t_filter
-            .time(|| self.filter.filter(self.estimation.landmarks_mut()));
+        self.t_filter.time(|| {
+            let landmarks = self.estimation.landmarks_mut();
+            match timestamp {
+                Some(timestamp) => self.filter.filter_at(landmarks, timestamp),
+                None => self.filter.filter(landmarks),
+            }
+        });
 
         // Map landmark coordinates back into the input image.
         let scale = rect.width() as f32 / input_res.width() as f32;
@@ -318,6 +618,10 @@ pub struct LandmarkTracker {
     loss_thresh: f32,
     roi_padding: f32,
     input_ratio: AspectRatio,
+    motion: Option<MotionModel>,
+    recovery_attempts: u32,
+    recovery_growth: f32,
+    epoch: Instant,
 }
 
 impl LandmarkTracker {
@@ -325,6 +629,13 @@ impl LandmarkTracker {
 
     pub const DEFAULT_ROI_PADDING: f32 = 0.3;
 
+    /// By default, tracking is lost immediately when the confidence drops below the loss
+    /// threshold.
+    pub const DEFAULT_RECOVERY_ATTEMPTS: u32 = 0;
+
+    /// Default relative amount by which the RoI is widened for each recovery attempt.
+    pub const DEFAULT_RECOVERY_GROWTH: f32 = 0.5;
+
     /// Creates a new [`LandmarkTracker`].
     pub fn new(input_ratio: AspectRatio) -> Self {
         Self {
@@ -332,6 +643,10 @@ impl LandmarkTracker {
             loss_thresh: Self::DEFAULT_LOSS_THRESHOLD,
             roi_padding: Self::DEFAULT_ROI_PADDING,
             input_ratio,
+            motion: None,
+            recovery_attempts: Self::DEFAULT_RECOVERY_ATTEMPTS,
+            recovery_growth: Self::DEFAULT_RECOVERY_GROWTH,
+            epoch: Instant::now(),
         }
     }
 
@@ -366,6 +681,49 @@ impl LandmarkTracker {
         self.roi_padding = padding;
     }
 
+    /// Sets the [`MotionModel`] used to predict the RoI of the next frame.
+    ///
+    /// Without a motion model, the RoI of the next frame is the (padded) bounding rectangle of the
+    /// landmarks in the current frame, so fast-moving objects can leave the RoI before the tracker
+    /// catches up. With a motion model, the RoI is instead moved, rotated and scaled according to
+    /// the object's recent motion.
+    ///
+    /// Predictions are based on the frame timestamps passed to [`LandmarkTracker::track_at`], or
+    /// the current time when using [`LandmarkTracker::track`].
+    ///
+    /// By default, no motion model is used.
+    pub fn set_motion_model(&mut self, model: Option<MotionModel>) {
+        self.motion = model;
+    }
+
+    /// Sets the number of attempts made to recover tracking before it is considered lost.
+    ///
+    /// When the confidence of the estimated landmarks falls below the loss threshold, the tracker
+    /// widens the RoI by the recovery growth (see [`LandmarkTracker::set_recovery_growth`]) and
+    /// runs the estimator on the same frame again, up to `attempts` times. Tracking is only
+    /// considered lost when none of the attempts succeed. This costs additional inference time in
+    /// frames where the object is about to be lost, but is typically much cheaper than redetecting
+    /// the object.
+    ///
+    /// By default, [`LandmarkTracker::DEFAULT_RECOVERY_ATTEMPTS`] is used.
+    pub fn set_recovery_attempts(&mut self, attempts: u32) {
+        self.recovery_attempts = attempts;
+    }
+
+    /// Sets the relative amount by which the RoI is widened for each recovery attempt.
+    ///
+    /// The growth is applied like the RoI padding (see [`LandmarkTracker::set_roi_padding`]).
+    ///
+    /// By default, [`LandmarkTracker::DEFAULT_RECOVERY_GROWTH`] is used.
+    ///
+    /// # Panics
+    ///
+    /// This method panics when `growth` is not greater than 0.0, or when it is NaN.
+    pub fn set_recovery_growth(&mut self, growth: f32) {
+        assert!(growth > 0.0);
+        self.recovery_growth = growth;
+    }
+
     /// Returns the current region of interest.
     ///
     /// If no region of interest is being tracked, or tracking was lost, returns [`None`].
@@ -378,8 +736,13 @@ impl LandmarkTracker {
     /// This can be passed either a [`Rect`][crate::image::Rect] or a [`RotatedRect`].
     ///
     /// Note that this does not apply RoI padding. The rectangle is used as-is.
+    ///
+    /// This also resets the state of the [`MotionModel`], if one is used.
     pub fn set_roi(&mut self, roi: impl Into<RotatedRect>) {
         self.roi = Some(roi.into());
+        if let Some(motion) = &mut self.motion {
+            motion.reset();
+        }
     }
 
     /// Performs landmark tracking on `full_image`.
@@ -389,7 +752,8 @@ impl LandmarkTracker {
     ///
     /// If the estimator indicates that tracking is lost, the RoI is cleared and `None` is returned.
     /// Otherwise, the RoI is updated to the bounding rectangle of all landmarks, with some added
-    /// padding that can be configured with [`LandmarkTracker::set_roi_padding`].
+    /// padding that can be configured with [`LandmarkTracker::set_roi_padding`]. Landmarks that are
+    /// not [visible][Landmark::is_visible] are ignored when computing the RoI.
     ///
     /// The returned [`TrackingResult`] grants access to the estimated landmarks, using `full_image`
     /// coordinates.
@@ -406,39 +770,71 @@ impl LandmarkTracker {
         E: Estimation + Confidence + Default,
         V: AsImageView,
     {
-        let roi = self.roi?;
-        let view_rect = roi.map(|rect| rect.grow_to_fit_aspect(self.input_ratio));
-        let full_image = full_image.as_view();
-        let view = full_image.view(view_rect);
-        let estimation = estimator.estimate(&view);
-        if estimation.confidence() < self.loss_thresh {
-            log::trace!(
-                "LandmarkTracker: confidence {}, loss threshold {} -> LOST",
-                estimation.confidence(),
-                self.loss_thresh,
-            );
+        self.track_impl(estimator, full_image.as_view(), None)
+    }
+
+    /// Performs landmark tracking on `full_image`, a frame captured at `timestamp`.
+    ///
+    /// This works like [`LandmarkTracker::track`], but uses [`Estimator::estimate_at`] to pass the
+    /// frame timestamp to the estimator's [`LandmarkFilter`].
+    pub fn track_at<'e, E, V>(
+        &mut self,
+        estimator: &'e mut Estimator<E>,
+        full_image: &V,
+        timestamp: Duration,
+    ) -> Option<TrackingResult<'e, E>>
+    where
+        E: Estimation + Confidence + Default,
+        V: AsImageView,
+    {
+        self.track_impl(estimator, full_image.as_view(), Some(timestamp))
+    }
 
-            self.roi = None;
-            return None;
+    fn track_impl<'e, E>(
+        &mut self,
+        estimator: &'e mut Estimator<E>,
+        full_image: ImageView<'_>,
+        timestamp: Option<Duration>,
+    ) -> Option<TrackingResult<'e, E>>
+    where
+        E: Estimation + Confidence + Default,
+    {
+        let mut roi = self.roi?;
+        let now = timestamp.unwrap_or_else(|| self.epoch.elapsed());
+        if let Some(predicted) = self.motion.as_ref().and_then(|motion| motion.predict(now)) {
+            roi = predicted.grow_rel(self.roi_padding);
         }
 
-        let angle = roi.rotation_radians() + estimation.angle_radians().unwrap_or(0.0);
+        let (view_rect, rect) = self.infer_with_recovery(roi, |view_rect| {
+            let rect = estimator.infer(full_image.view(view_rect));
+            (estimator.estimation.confidence(), rect)
+        })?;
+        let estimation = estimator.finish(rect, timestamp);
+
+        let angle = view_rect.rotation_radians() + estimation.angle_radians().unwrap_or(0.0);
 
         // Map all landmarks to the image coordinate system.
         for [x, y, _] in estimation.landmarks_mut().positions_mut() {
             [*x, *y] = view_rect.transform_out_f32(*x, *y);
         }
 
+        // Landmarks that are occluded or outside of the image have unreliable positions, so they
+        // are excluded from the RoI, unless that would exclude all of them.
+        let landmarks = estimation.landmarks_mut();
+        let all_hidden = landmarks.iter().all(|lm| !lm.is_visible());
         let updated_roi = RotatedRect::bounding(
             angle,
-            estimation
-                .landmarks_mut()
+            landmarks
                 .iter()
+                .filter(|lm| all_hidden || lm.is_visible())
                 .map(|lm| (lm.x().round() as i32, lm.y().round() as i32)),
         )
         .unwrap();
 
         self.roi = Some(updated_roi.map(|rect| rect.grow_rel(self.roi_padding)));
+        if let Some(motion) = &mut self.motion {
+            motion.update(updated_roi, now);
+        }
 
         Some(TrackingResult {
             view_rect,
@@ -446,6 +842,51 @@ impl LandmarkTracker {
             updated_roi,
         })
     }
+
+    /// Runs the estimator on `roi`, widening it for every attempt whose confidence falls below the
+    /// loss threshold, until the recovery attempts are exhausted.
+    ///
+    /// `infer` runs the estimator on the given view rectangle, and returns the confidence of the
+    /// estimation along with an arbitrary value. Returns the view rectangle and value of the
+    /// successful attempt, or [`None`] if tracking was lost.
+    fn infer_with_recovery<T>(
+        &mut self,
+        mut roi: RotatedRect,
+        mut infer: impl FnMut(RotatedRect) -> (f32, T),
+    ) -> Option<(RotatedRect, T)> {
+        let mut attempt = 0;
+        loop {
+            let view_rect = roi.map(|rect| rect.grow_to_fit_aspect(self.input_ratio));
+            let (confidence, value) = infer(view_rect);
+            if confidence >= self.loss_thresh {
+                return Some((view_rect, value));
+            }
+
+            if attempt == self.recovery_attempts {
+                log::trace!(
+                    "LandmarkTracker: confidence {}, loss threshold {} -> LOST",
+                    confidence,
+                    self.loss_thresh,
+                );
+
+                self.roi = None;
+                if let Some(motion) = &mut self.motion {
+                    motion.reset();
+                }
+                return None;
+            }
+
+            attempt += 1;
+            log::trace!(
+                "LandmarkTracker: confidence {}, loss threshold {} -> widening RoI ({}/{})",
+                confidence,
+                self.loss_thresh,
+                attempt,
+                self.recovery_attempts,
+            );
+            roi = roi.grow_rel(self.recovery_growth);
+        }
+    }
 }
 
 /// The result returned by [`LandmarkTracker::track`].
@@ -474,3 +915,755 @@ impl<'a, E: Estimation> TrackingResult<'a, E> {
         self.updated_roi
     }
 }
+
+/// Predicts the motion of a [`LandmarkTracker`]'s region of interest.
+///
+/// The motion model tracks the center position, rotation, width and height of the RoI with a
+/// [`PredictiveFilter`], and extrapolates them to the timestamp of the next frame. This allows the
+/// tracker to keep up with fast-moving objects.
+///
+/// The [`Default`] motion model uses an [`AlphaBetaFilter`], which assumes that the object moves
+/// with a roughly constant velocity between frames.
+pub struct MotionModel {
+    inner: Box<dyn DynMotionModel>,
+}
+
+impl Default for MotionModel {
+    fn default() -> Self {
+        Self::new(AlphaBetaFilter::new(
+            Self::DEFAULT_ALPHA,
+            Self::DEFAULT_BETA,
+        ))
+    }
+}
+
+impl MotionModel {
+    /// `alpha` parameter of the [`AlphaBetaFilter`] used by the default motion model.
+    pub const DEFAULT_ALPHA: f32 = 0.8;
+
+    /// `beta` parameter of the [`AlphaBetaFilter`] used by the default motion model.
+    pub const DEFAULT_BETA: f32 = 0.5;
+
+    /// Creates a motion model that uses `filter` to track and predict the RoI parameters.
+    pub fn new<F: PredictiveFilter<f32> + Send + 'static>(filter: F) -> Self
+    where
+        F::State: Send,
+    {
+        Self {
+            inner: Box::new(FilterMotionModel {
+                filter,
+                states: Default::default(),
+                last: None,
+                last_angle: None,
+            }),
+        }
+    }
+
+    /// Feeds the RoI observed at `timestamp` into the model.
+    pub fn update(&mut self, roi: RotatedRect, timestamp: Duration) {
+        self.inner.update(roi, timestamp);
+    }
+
+    /// Predicts the RoI at `timestamp`.
+    ///
+    /// Returns [`None`] if no RoI has been observed since the model was created or last reset.
+    pub fn predict(&self, timestamp: Duration) -> Option<RotatedRect> {
+        self.inner.predict(timestamp)
+    }
+
+    /// Resets the model, discarding all previous observations.
+    pub fn reset(&mut self) {
+        self.inner.reset();
+    }
+}
+
+trait DynMotionModel: Send {
+    fn update(&mut self, roi: RotatedRect, timestamp: Duration);
+    fn predict(&self, timestamp: Duration) -> Option<RotatedRect>;
+    fn reset(&mut self);
+}
+
+/// Filter states for the RoI's center X and Y coordinates, rotation, width, and height.
+type RoiStates<S> = [S; 5];
+
+struct FilterMotionModel<F: PredictiveFilter<f32>> {
+    filter: F,
+    states: RoiStates<F::State>,
+    /// Timestamp of the last observation.
+    last: Option<Duration>,
+    /// (Unwrapped) rotation of the last observation.
+    last_angle: Option<f32>,
+}
+
+impl<F: PredictiveFilter<f32> + Send> DynMotionModel for FilterMotionModel<F>
+where
+    F::State: Send,
+{
+    fn update(&mut self, roi: RotatedRect, timestamp: Duration) {
+        let Some(elapsed) = frame_delta(&mut self.last, timestamp) else {
+            // Velocities can't be estimated without a time difference.
+            return;
+        };
+        let mut angle = roi.rotation_radians();
+        if let Some(last_angle) = self.last_angle {
+            // Keep the angle continuous, so that it can be extrapolated across the -π/π boundary.
+            angle = last_angle + (angle - last_angle + PI).rem_euclid(TAU) - PI;
+        }
+        self.last_angle = Some(angle);
+
+        let (x, y) = roi.center();
+        let values = [
+            x,
+            y,
+            angle,
+            roi.rect().width() as f32,
+            roi.rect().height() as f32,
+        ];
+        for (state, value) in zip_exact(&mut self.states, values) {
+            self.filter.filter(state, value, elapsed);
+        }
+    }
+
+    fn predict(&self, timestamp: Duration) -> Option<RotatedRect> {
+        let elapsed = seconds_between(self.last?, timestamp);
+        let mut values = [0.0; 5];
+        for (state, value) in zip_exact(&self.states, &mut values) {
+            *value = self.filter.predict(state, elapsed)?;
+        }
+
+        let [x, y, angle, width, height] = values;
+        let rect = Rect::from_center(
+            x.round() as i32,
+            y.round() as i32,
+            width.round().max(1.0) as u32,
+            height.round().max(1.0) as u32,
+        );
+        Some(RotatedRect::new(rect, angle))
+    }
+
+    fn reset(&mut self) {
+        self.states = Default::default();
+        self.last = None;
+        self.last_angle = None;
+    }
+}
+
+/// Detects and tracks any number of objects, and computes landmarks for each one.
+///
+/// A [`MultiTracker`] combines a [`Detector`] with a landmark estimation [`Network`]: the detector
+/// runs periodically in a background worker to pick up new objects, and every detected object is
+/// then tracked by its own [`LandmarkTracker`] and [`Estimator`], running in a dedicated worker.
+///
+/// Each tracked object is assigned a [`TargetId`] that stays the same for as long as the object is
+/// tracked.
+pub struct MultiTracker<D: Detector, N: Network>
+where
+    D::Detection: Send + 'static,
+{
+    targets: Vec<Target<N::Output>>,
+    next_target_id: TargetId,
+    detector: Worker<DetectorInput<D::Detection>>,
+    detections_handle: Option<PromiseHandle<Detections<D::Detection>>>,
+    epoch: Instant,
+    next_det: Duration,
+    det_interval: Duration,
+    landmarker: N,
+    make_filter: Option<Box<dyn Fn() -> LandmarkFilter + Send>>,
+    make_motion_model: Option<Box<dyn Fn() -> MotionModel + Send>>,
+    recovery_attempts: u32,
+    iou_thresh: f32,
+    detection_padding: f32,
+    roi_padding: f32,
+    zoom: bool,
+}
+
+impl<D, N> MultiTracker<D, N>
+where
+    D: Detector + Send + 'static,
+    D::Detection: Send + 'static,
+    N: Network + Clone,
+    N::Output: Confidence + Default + Clone,
+{
+    /// Default intersection-over-union threshold for deduplicating tracking regions.
+    pub const DEFAULT_IOU_THRESH: f32 = 0.3;
+
+    /// Default interval for object detection, when at least one object is in view.
+    pub const DEFAULT_REDETECT_INTERVAL: Duration = Duration::from_millis(300);
+
+    /// Default relative padding added to detections to obtain the initial tracking region.
+    pub const DEFAULT_DETECTION_PADDING: f32 = 0.0;
+
+    /// Creates a new [`MultiTracker`] that uses `detector` to find objects and `landmarker` to
+    /// estimate their landmarks.
+    ///
+    /// `detector` is moved to a background worker, so it has to be fully configured before it is
+    /// passed to this function.
+    pub fn new(mut detector: D, landmarker: N) -> Self {
+        let input_ratio = detector.input_resolution().aspect_ratio().unwrap();
+        Self {
+            targets: Vec::new(),
+            next_target_id: TargetId(0),
+            detector: Worker::builder()
+                .name("detector")
+                .spawn(move |(image, zoom, promise): DetectorInput<D::Detection>| {
+                    let view_rect = if zoom {
+                        image.resolution().fit_aspect_ratio(input_ratio)
+                    } else {
+                        image.rect()
+                    };
+                    let detections = detector.detect(image.view(view_rect));
+                    promise.fulfill(Detections {
+                        view_rect,
+                        detections: detections.to_vec(),
+                    });
+                })
+                .unwrap(),
+            detections_handle: None,
+            epoch: Instant::now(),
+            next_det: Duration::ZERO,
+            det_interval: Self::DEFAULT_REDETECT_INTERVAL,
+            landmarker,
+            make_filter: None,
+            make_motion_model: None,
+            recovery_attempts: LandmarkTracker::DEFAULT_RECOVERY_ATTEMPTS,
+            iou_thresh: Self::DEFAULT_IOU_THRESH,
+            detection_padding: Self::DEFAULT_DETECTION_PADDING,
+            roi_padding: LandmarkTracker::DEFAULT_ROI_PADDING,
+            zoom: false,
+        }
+    }
+
+    /// Sets the redetection interval.
+    ///
+    /// Redetection works as follows:
+    /// - if no objects are currently being tracked, `track` will always attempt to trigger a
+    ///   detection
+    /// - otherwise, if the last detection was triggered more than the redetect interval ago,
+    ///   `track` will attempt to trigger another detection
+    ///
+    /// "Attempt" means that if a detection is already ongoing, nothing happens, and if not, one is
+    /// started.
+    ///
+    /// This scheme ensures that newly appearing objects get picked up in a timely fashion, but
+    /// without blocking (detection is expensive, so it runs in its own worker).
+    ///
+    /// By default, [`Self::DEFAULT_REDETECT_INTERVAL`] is used.
+    pub fn set_redetect_interval(&mut self, interval: Duration) {
+        self.det_interval = interval;
+    }
+
+    /// Sets the intersection-over-union threshold at which two tracking regions are considered to
+    /// overlap.
+    ///
+    /// Since redetection will also detect all objects that are already being tracked, this
+    /// threshold is used to ensure that each object is only tracked once. No new tracking worker is
+    /// spawned if a detection overlaps with an existing tracker's region of interest.
+    ///
+    /// By default, [`Self::DEFAULT_IOU_THRESH`] is used.
+    pub fn set_iou_thresh(&mut self, thresh: f32) {
+        self.iou_thresh = thresh;
+    }
+
+    /// Sets the relative amount of padding to add to a detection's bounding rectangle to obtain the
+    /// initial region of interest of its tracker.
+    ///
+    /// This is needed when the detector only detects a part of the object that landmarks are
+    /// estimated for (for example, the palm of a hand). The padding is applied like
+    /// [`LandmarkTracker::set_roi_padding`].
+    ///
+    /// By default, [`Self::DEFAULT_DETECTION_PADDING`] is used.
+    ///
+    /// # Panics
+    ///
+    /// This method panics when `padding` is less than 0.0 or when it is NaN.
+    pub fn set_detection_padding(&mut self, padding: f32) {
+        assert!(padding >= 0.0);
+        self.detection_padding = padding;
+    }
+
+    /// Sets the RoI padding used by the [`LandmarkTracker`] of every newly tracked object.
+    ///
+    /// By default, [`LandmarkTracker::DEFAULT_ROI_PADDING`] is used.
+    ///
+    /// # Panics
+    ///
+    /// This method panics when `padding` is less than 0.0 or when it is NaN.
+    pub fn set_roi_padding(&mut self, padding: f32) {
+        assert!(padding >= 0.0);
+        self.roi_padding = padding;
+    }
+
+    /// Enables or disables detection zoom.
+    ///
+    /// When enabled, detection is only performed on the largest centered part of the input image
+    /// that matches the aspect ratio of the detector's input. This makes the outer edges of the
+    /// image unusable for detecting new objects, but avoids adding padding to the image and thus
+    /// significantly improves the detection distance. Tracking of already detected objects is not
+    /// affected.
+    ///
+    /// Detection zoom is disabled by default.
+    pub fn set_detection_zoom(&mut self, zoom: bool) {
+        self.zoom = zoom;
+    }
+
+    /// Sets a function that creates the [`LandmarkFilter`] for the [`Estimator`] of every newly
+    /// tracked object.
+    ///
+    /// Every tracked object needs its own filter state, so this takes a function that is invoked
+    /// whenever tracking of a new object starts. Objects that are already being tracked keep their
+    /// current filter.
+    ///
+    /// By default, no filtering is performed.
+    pub fn set_filter<F>(&mut self, make_filter: F)
+    where
+        F: Fn() -> LandmarkFilter + Send + 'static,
+    {
+        self.make_filter = Some(Box::new(make_filter));
+    }
+
+    /// Sets a function that creates the [`MotionModel`] for the [`LandmarkTracker`] of every newly
+    /// tracked object.
+    ///
+    /// Like the [`LandmarkFilter`], the motion model keeps per-object state, so this takes a function
+    /// that is invoked whenever tracking of a new object starts.
+    ///
+    /// By default, no motion model is used (see [`LandmarkTracker::set_motion_model`]).
+    pub fn set_motion_model<F>(&mut self, make_motion_model: F)
+    where
+        F: Fn() -> MotionModel + Send + 'static,
+    {
+        self.make_motion_model = Some(Box::new(make_motion_model));
+    }
+
+    /// Sets the number of attempts the [`LandmarkTracker`] of every newly tracked object makes to
+    /// recover tracking before the object is considered lost.
+    ///
+    /// See [`LandmarkTracker::set_recovery_attempts`].
+    pub fn set_recovery_attempts(&mut self, attempts: u32) {
+        self.recovery_attempts = attempts;
+    }
+
+    /// Returns an iterator over the tracking data for each object.
+    pub fn targets(&self) -> impl Iterator<Item = TrackedTarget<'_, N::Output>> {
+        self.targets.iter().filter_map(|target| {
+            target.estimation.as_ref().map(|estimation| TrackedTarget {
+                id: target.id,
+                estimation,
+                view_rect: *target.roi.lock().unwrap(),
+            })
+        })
+    }
+
+    /// Blocks until all tracking computations from the last call to `track` are finished, and
+    /// restarts them on `image`.
+    ///
+    /// After this method returns, [`MultiTracker::targets`] will return the state of all objects in
+    /// the previous image passed to `track`.
+    ///
+    /// The current time is used as the frame timestamp. Use [`MultiTracker::track_at`] to pass an
+    /// explicit timestamp instead.
+    pub fn track(&mut self, image: Arc<Image>) {
+        let timestamp = self.epoch.elapsed();
+        self.track_at(image, timestamp);
+    }
+
+    /// Like [`MultiTracker::track`], but uses `timestamp` as the time at which `image` was
+    /// captured.
+    ///
+    /// The timestamp is passed to the [`LandmarkFilter`] of every tracked object, and also drives
+    /// the redetection interval. Timestamps are relative to an arbitrary (but fixed) reference
+    /// point, and are expected to increase monotonically. Calls to [`MultiTracker::track`] and
+    /// [`MultiTracker::track_at`] should not be mixed.
+    pub fn track_at(&mut self, image: Arc<Image>, timestamp: Duration) {
+        self.targets.retain_mut(|target| {
+            let (promise, ph) = promise();
+            let old_ph = std::mem::replace(&mut target.ph, ph);
+            match old_ph.block().unwrap() {
+                Some(estimation) => {
+                    target.worker.send((image.clone(), timestamp, promise));
+                    target.estimation = Some(estimation);
+                    true
+                }
+                None => false,
+            }
+        });
+
+        let detections = match &self.detections_handle {
+            Some(handle) if !handle.will_block() => {
+                self.detections_handle.take().unwrap().block().unwrap()
+            }
+            _ => Detections {
+                view_rect: image.rect(),
+                detections: Vec::new(),
+            },
+        };
+
+        for det in &detections.detections {
+            let (x, y) = (detections.view_rect.x(), detections.view_rect.y());
+            let rect = det
+                .bounding_rect()
+                .move_by(x, y)
+                .grow_rel(self.detection_padding);
+
+            // Compute IoU with existing RoIs, discard detection if it overlaps with any, spawn
+            // tracker when it doesn't.
+            if self
+                .targets
+                .iter()
+                .any(|target| target.roi.lock().unwrap().rect().iou(&rect) >= self.iou_thresh)
+            {
+                continue;
+            }
+
+            let roi = RotatedRect::new(rect, det.rotation_radians());
+            let target = self.spawn_target(roi, image.clone(), timestamp);
+            self.targets.push(target);
+        }
+
+        // Check if any of the tracked regions started to overlap, and remove one of them.
+        for i in (0..self.targets.len()).rev() {
+            let roi = *self.targets[i].roi.lock().unwrap();
+
+            for j in 0..i {
+                let other_roi = *self.targets[j].roi.lock().unwrap();
+                let iou = RotatedBoundingRect::from(roi).iou(&RotatedBoundingRect::from(other_roi));
+                if iou >= self.iou_thresh {
+                    self.targets.swap_remove(i);
+                    break;
+                }
+            }
+        }
+
+        if (self.targets.is_empty() || timestamp >= self.next_det)
+            && self.detections_handle.is_none()
+        {
+            // We want to start a detection, and none is currently running, so start one.
+            let (promise, handle) = promise();
+            self.detector.send((image, self.zoom, promise));
+            self.detections_handle = Some(handle);
+            self.next_det = timestamp + self.det_interval;
+        }
+    }
+
+    fn spawn_target(
+        &mut self,
+        roi: RotatedRect,
+        image: Arc<Image>,
+        timestamp: Duration,
+    ) -> Target<N::Output> {
+        let mut estimator = Estimator::new(self.landmarker.clone());
+        if let Some(make_filter) = &self.make_filter {
+            estimator.set_filter(make_filter());
+        }
+        let mut tracker =
+            LandmarkTracker::new(estimator.input_resolution().aspect_ratio().unwrap());
+        tracker.set_roi_padding(self.roi_padding);
+        tracker.set_recovery_attempts(self.recovery_attempts);
+        tracker.set_motion_model(self.make_motion_model.as_ref().map(|make| make()));
+        tracker.set_roi(roi);
+        let roi_arc = Arc::new(Mutex::new(roi));
+        let roi_arc2 = roi_arc.clone();
+        let mut worker = Worker::builder()
+            .name("landmark tracker")
+            .spawn(move |(image, timestamp, promise): TargetInput<N::Output>| {
+                match tracker.track_at(&mut estimator, &*image, timestamp) {
+                    Some(res) => {
+                        *roi_arc2.lock().unwrap() = res.updated_roi();
+                        promise.fulfill(Some(res.estimation().clone()));
+                    }
+                    None => {
+                        log::trace!("tracking lost");
+                        promise.fulfill(None);
+                    }
+                }
+            })
+            .unwrap();
+
+        let id = self.next_target_id;
+        self.next_target_id.0 += 1;
+        let (promise, ph) = promise();
+        worker.send((image, timestamp, promise));
+        Target {
+            id,
+            roi: roi_arc,
+            worker,
+            ph,
+            estimation: None,
+        }
+    }
+}
+
+type DetectorInput<T> = (Arc<Image>, bool, Promise<Detections<T>>);
+
+/// Detections made by the detector worker, in the coordinate system of `view_rect`.
+struct Detections<T> {
+    view_rect: Rect,
+    detections: Vec<T>,
+}
+
+type TargetInput<E> = (Arc<Image>, Duration, Promise<Option<E>>);
+
+struct Target<E: Estimation> {
+    id: TargetId,
+    roi: Arc<Mutex<RotatedRect>>,
+    worker: Worker<TargetInput<E>>,
+    ph: PromiseHandle<Option<E>>,
+    estimation: Option<E>,
+}
+
+/// ID of an object tracked by a [`MultiTracker`].
+///
+/// The assigned [`TargetId`]s are unique per [`MultiTracker`] assigning them. They are reused
+/// between frames for as long as the object is tracked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TargetId(u64);
+
+/// Tracking data returned for an object in the input image.
+pub struct TrackedTarget<'a, E> {
+    id: TargetId,
+    estimation: &'a E,
+    view_rect: RotatedRect,
+}
+
+impl<'a, E> TrackedTarget<'a, E> {
+    /// Returns the unique ID of this object.
+    #[inline]
+    pub fn id(&self) -> TargetId {
+        self.id
+    }
+
+    /// Returns the estimation result, with landmarks in global image coordinates.
+    #[inline]
+    pub fn estimation(&self) -> &'a E {
+        self.estimation
+    }
+
+    /// Returns the object's region of interest in the original image.
+    #[inline]
+    pub fn view_rect(&self) -> RotatedRect {
+        self.view_rect
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    #[test]
+    fn attribute_channels() {
+        let mut landmarks = Landmarks::new(3);
+        assert_eq!(landmarks.visibilities(), None);
+        assert_eq!(landmarks.landmark(0).visibility(), None);
+        assert!(landmarks.iter().all(|lm| lm.is_visible()));
+
+        landmarks.visibilities_mut()[1] = 0.25;
+        landmarks.presences_mut()[2] = 0.0;
+        assert_eq!(landmarks.visibilities(), Some(&[1.0, 0.25, 1.0][..]));
+        assert_eq!(landmarks.confidences(), None);
+
+        let lm = landmarks.landmark(1);
+        assert_eq!(lm.visibility(), Some(0.25));
+        assert_eq!(lm.presence(), Some(1.0));
+        assert_eq!(lm.confidence(), None);
+
+        let visible = landmarks
+            .iter()
+            .map(|lm| lm.is_visible())
+            .collect::<Vec<_>>();
+        assert_eq!(visible, [true, false, false]);
+
+        // Filtering only touches positions.
+        let mut filter = LandmarkFilter::default();
+        filter.filter(&mut landmarks);
+        assert_eq!(landmarks.landmark(1).visibility(), Some(0.25));
+    }
+
+    #[test]
+    fn point_filter() {
+        use crate::filter::kalman::{KalmanFilter, KalmanModel, VectorKalmanFilter};
+
+        let model = KalmanModel::ConstantVelocity;
+        let mut points =
+            LandmarkFilter::time_based_points(VectorKalmanFilter::new(model, 10.0, 0.5), 2);
+        let mut coords = LandmarkFilter::time_based(KalmanFilter::new(model, 10.0, 0.5), 2);
+
+        // With isotropic noise, filtering whole points matches filtering each coordinate.
+        for frame in 0..10 {
+            let t = frame as f32 * 0.1;
+            let mut landmarks = Landmarks::new(2);
+            landmarks.positions_mut()[0] = [t, t * t, 1.0];
+            landmarks.positions_mut()[1] = [t.sin(), -t, t.cos()];
+            let mut expected = landmarks.clone();
+
+            let timestamp = Duration::from_millis(frame * 100);
+            points.filter_at(&mut landmarks, timestamp);
+            coords.filter_at(&mut expected, timestamp);
+            for (a, b) in zip_exact(landmarks.positions(), expected.positions()) {
+                for (a, b) in zip_exact(a, b) {
+                    assert!((a - b).abs() < 1e-4, "{a} != {b}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn timestamped_filter() {
+        use crate::filter::one_euro::OneEuroFilter;
+
+        let inputs = [0.0, 1.0, 1.5, 4.0, 3.0];
+        let timestamps = [0, 33, 66, 66, 100].map(Duration::from_millis);
+        // Time since the previous frame, or `None` if the timestamp repeats.
+        let deltas = [Some(0.0), Some(0.033), Some(0.033), None, Some(0.034)];
+
+        let mut filter = LandmarkFilter::time_based(OneEuroFilter::new(1.0, 0.1), 1);
+        let params = OneEuroFilter::new(1.0, 0.1);
+        let mut state = Default::default();
+        let mut expected = 0.0;
+        for ((x, timestamp), elapsed) in zip_exact(zip_exact(inputs, timestamps), deltas) {
+            let mut landmarks = Landmarks::new(1);
+            landmarks.positions_mut()[0] = [x, -x, 0.0];
+            filter.filter_at(&mut landmarks, timestamp);
+            let [fx, fy, _] = landmarks.positions()[0];
+
+            // A repeated timestamp yields the previous output without updating the filter.
+            if let Some(elapsed) = elapsed {
+                expected = TimeBasedFilter::filter(&params, &mut state, x, elapsed);
+            }
+            assert!(fx.is_finite());
+            assert_relative_eq!(fx, expected);
+            assert_relative_eq!(fy, -expected);
+        }
+    }
+
+    /// Generates the RoI of an object moving along a synthetic trajectory.
+    fn moving_roi(frame: u32) -> RotatedRect {
+        let t = frame as f32;
+        RotatedRect::new(
+            Rect::from_center(
+                100 + 12 * frame as i32,
+                200 - 5 * frame as i32,
+                80 + frame,
+                100,
+            ),
+            (170.0 + 4.0 * t).to_radians(),
+        )
+    }
+
+    #[test]
+    fn motion_model_predicts_constant_motion() {
+        let frame_time = Duration::from_millis(40);
+        let mut model = MotionModel::default();
+        assert!(model.predict(Duration::ZERO).is_none());
+
+        for frame in 0..30 {
+            model.update(moving_roi(frame), frame_time * frame);
+        }
+
+        // The prediction for the next frame follows the motion, even across the -180°/180° angle
+        // boundary.
+        let predicted = model.predict(frame_time * 30).unwrap();
+        let expected = moving_roi(30);
+        let (px, py) = predicted.center();
+        let (ex, ey) = expected.center();
+        assert!((px - ex).abs() <= 2.0, "{px} vs {ex}");
+        assert!((py - ey).abs() <= 2.0, "{py} vs {ey}");
+        assert!(predicted.rect().width().abs_diff(expected.rect().width()) <= 2);
+        assert_eq!(predicted.rect().height(), expected.rect().height());
+        let angle_diff =
+            (predicted.rotation_radians() - expected.rotation_radians() + PI).rem_euclid(TAU) - PI;
+        assert!(angle_diff.abs() < 1.0f32.to_radians(), "{angle_diff}");
+
+        // Without motion, the last RoI is predicted.
+        model.reset();
+        assert!(model.predict(Duration::ZERO).is_none());
+        for frame in 0..5 {
+            model.update(moving_roi(0), frame_time * frame);
+        }
+        let predicted = model.predict(frame_time * 5).unwrap();
+        assert_eq!(predicted.center(), moving_roi(0).center());
+    }
+
+    /// Stands in for an [`Estimator`] that only reports a high confidence once the view is large
+    /// enough to contain the whole object.
+    struct FakeEstimator {
+        min_size: u32,
+        views: Vec<RotatedRect>,
+    }
+
+    impl FakeEstimator {
+        fn infer(&mut self, view: RotatedRect) -> (f32, ()) {
+            self.views.push(view);
+            let confidence = if view.rect().width() >= self.min_size {
+                0.9
+            } else {
+                0.1
+            };
+            (confidence, ())
+        }
+    }
+
+    #[test]
+    fn recovery_widens_roi() {
+        let roi = RotatedRect::from(Rect::from_center(100, 100, 80, 80));
+        let mut tracker = LandmarkTracker::new(AspectRatio::SQUARE);
+        tracker.set_recovery_attempts(2);
+        tracker.set_recovery_growth(0.5);
+
+        // The first widened RoI is large enough.
+        let mut fake = FakeEstimator {
+            min_size: 150,
+            views: Vec::new(),
+        };
+        tracker.set_roi(roi);
+        let (view_rect, ()) = tracker
+            .infer_with_recovery(roi, |view| fake.infer(view))
+            .unwrap();
+        let widths = fake
+            .views
+            .iter()
+            .map(|v| v.rect().width())
+            .collect::<Vec<_>>();
+        assert_eq!(widths, [80, 160]);
+        assert_eq!(view_rect, Rect::from_center(100, 100, 160, 160).into());
+        assert_eq!(tracker.roi(), Some(&roi));
+
+        // Tracking is lost once all recovery attempts are exhausted.
+        let mut fake = FakeEstimator {
+            min_size: 1000,
+            views: Vec::new(),
+        };
+        tracker.set_motion_model(Some(MotionModel::default()));
+        tracker.set_roi(roi);
+        tracker.motion.as_mut().unwrap().update(roi, Duration::ZERO);
+        assert!(tracker
+            .infer_with_recovery(roi, |view| fake.infer(view))
+            .is_none());
+        let widths = fake
+            .views
+            .iter()
+            .map(|v| v.rect().width())
+            .collect::<Vec<_>>();
+        assert_eq!(widths, [80, 160, 320]);
+        assert_eq!(tracker.roi(), None);
+        assert!(tracker
+            .motion
+            .as_ref()
+            .unwrap()
+            .predict(Duration::ZERO)
+            .is_none());
+
+        // Without recovery attempts, tracking is lost immediately.
+        let mut fake = FakeEstimator {
+            min_size: 150,
+            views: Vec::new(),
+        };
+        tracker.set_recovery_attempts(0);
+        tracker.set_roi(roi);
+        assert!(tracker
+            .infer_with_recovery(roi, |view| fake.infer(view))
+            .is_none());
+        assert_eq!(fake.views.len(), 1);
+    }
+}
@@ -0,0 +1,730 @@
+//! Recording and replay of landmark streams.
+//!
+//! A [`Recorder`] writes the output of an [`Estimator`][super::Estimator] or tracker to a file, one
+//! [`RecordedFrame`] per processed image. Each frame stores its timestamp, and the ID, region of
+//! interest, confidence and [`Landmarks`] (including confidence, visibility and presence channels)
+//! of every target. [`RecordedFrame::from_targets`] creates a frame from the output of a
+//! [`MultiTracker`][super::MultiTracker], [`HandTracker`][crate::hand::tracking::HandTracker] or
+//! [`FaceTracker`][crate::face::tracking::FaceTracker].
+//!
+//! A [`Replay`] reads a recording back. By default, it delivers frames with the same timing as the
+//! original recording, so that downstream code (gesture recognition, avatar animation, etc.) can be
+//! developed and tested without a camera. Since frames keep their original timestamps, passing them
+//! to timestamp-aware code like [`LandmarkFilter::filter_at`][super::LandmarkFilter::filter_at]
+//! produces the same results as the live run.
+//!
+//! # Formats
+//!
+//! Two file formats are supported (see [`Format`]):
+//!
+//! - A compact binary format, which is the default.
+//! - JSON Lines, with one JSON object per frame, which is meant for consumption by other tools.
+//!
+//! [`Replay`] detects the format of a recording automatically, and [`convert`] can be used to
+//! export a binary recording as JSON Lines.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, ensure, Context};
+use serde::{Deserialize, Serialize};
+use zaru_image::{Rect, RotatedRect};
+
+use super::{Confidence, Estimation, Landmarks, TargetId, TrackedTarget};
+
+/// Magic bytes at the start of a binary recording.
+const MAGIC: &[u8; 4] = b"ZLMR";
+
+/// Version of the binary format.
+const VERSION: u32 = 1;
+
+/// Upper bound on target and landmark counts, to detect corrupted files early.
+const MAX_COUNT: u32 = 1 << 20;
+
+const HAS_ID: u8 = 1 << 0;
+const HAS_ROI: u8 = 1 << 1;
+const HAS_CONFIDENCES: u8 = 1 << 2;
+const HAS_VISIBILITIES: u8 = 1 << 3;
+const HAS_PRESENCES: u8 = 1 << 4;
+const HAS_CONFIDENCE: u8 = 1 << 5;
+
+/// The landmarks of a single target in a [`RecordedFrame`].
+#[derive(Clone)]
+pub struct RecordedTarget {
+    id: Option<TargetId>,
+    roi: Option<RotatedRect>,
+    confidence: Option<f32>,
+    landmarks: Landmarks,
+}
+
+impl RecordedTarget {
+    /// Creates a recorded target from its landmarks.
+    ///
+    /// The landmarks are expected to be in the coordinate system of the full input image.
+    pub fn new(landmarks: Landmarks) -> Self {
+        Self {
+            id: None,
+            roi: None,
+            confidence: None,
+            landmarks,
+        }
+    }
+
+    /// Sets the ID assigned to the target by a [`MultiTracker`][super::MultiTracker].
+    pub fn with_id(self, id: TargetId) -> Self {
+        Self {
+            id: Some(id),
+            ..self
+        }
+    }
+
+    /// Sets the region of interest the landmarks were computed in.
+    pub fn with_roi(self, roi: RotatedRect) -> Self {
+        Self {
+            roi: Some(roi),
+            ..self
+        }
+    }
+
+    /// Sets the confidence of the target, as returned by [`Confidence::confidence`].
+    pub fn with_confidence(self, confidence: f32) -> Self {
+        Self {
+            confidence: Some(confidence),
+            ..self
+        }
+    }
+
+    /// Returns the ID of the target, if it was recorded.
+    pub fn id(&self) -> Option<TargetId> {
+        self.id
+    }
+
+    /// Returns the region of interest of the target, if it was recorded.
+    pub fn roi(&self) -> Option<RotatedRect> {
+        self.roi
+    }
+
+    /// Returns the confidence of the target, if it was recorded.
+    pub fn confidence(&self) -> Option<f32> {
+        self.confidence
+    }
+
+    /// Returns the recorded landmarks.
+    pub fn landmarks(&self) -> &Landmarks {
+        &self.landmarks
+    }
+}
+
+/// Records the ID, region of interest, confidence and landmarks of a target tracked by a
+/// [`MultiTracker`][super::MultiTracker].
+impl<E: Estimation + Confidence> From<TrackedTarget<'_, E>> for RecordedTarget {
+    fn from(target: TrackedTarget<'_, E>) -> Self {
+        RecordedTarget::new(target.estimation().landmarks().clone())
+            .with_id(target.id())
+            .with_roi(target.view_rect())
+            .with_confidence(target.estimation().confidence())
+    }
+}
+
+/// The targets recorded for a single input image.
+#[derive(Clone)]
+pub struct RecordedFrame {
+    timestamp: Duration,
+    targets: Vec<RecordedTarget>,
+}
+
+impl RecordedFrame {
+    /// Creates an empty frame for an image captured at `timestamp`.
+    pub fn new(timestamp: Duration) -> Self {
+        Self {
+            timestamp,
+            targets: Vec::new(),
+        }
+    }
+
+    /// Creates a frame for an image captured at `timestamp`, containing `targets`.
+    ///
+    /// This accepts the output of the trackers directly, for example
+    /// `RecordedFrame::from_targets(timestamp, hand_tracker.hands())` or
+    /// `RecordedFrame::from_targets(timestamp, face_tracker.faces())`.
+    pub fn from_targets<T: Into<RecordedTarget>>(
+        timestamp: Duration,
+        targets: impl IntoIterator<Item = T>,
+    ) -> Self {
+        Self {
+            timestamp,
+            targets: targets.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Adds a target to the frame.
+    pub fn push(&mut self, target: RecordedTarget) {
+        self.targets.push(target);
+    }
+
+    /// Returns the timestamp of the frame.
+    pub fn timestamp(&self) -> Duration {
+        self.timestamp
+    }
+
+    /// Returns the targets recorded in this frame.
+    pub fn targets(&self) -> &[RecordedTarget] {
+        &self.targets
+    }
+}
+
+/// File format of a landmark recording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Compact binary format.
+    Binary,
+    /// [JSON Lines](https://jsonlines.org/), with one JSON object per frame.
+    JsonLines,
+}
+
+impl Format {
+    /// Determines the format to use for a file: `.jsonl` files use [`Format::JsonLines`], all
+    /// other files [`Format::Binary`].
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension() {
+            Some(ext) if ext == "jsonl" => Format::JsonLines,
+            _ => Format::Binary,
+        }
+    }
+}
+
+/// Writes [`RecordedFrame`]s to a file or other output stream.
+pub struct Recorder<W: Write> {
+    writer: W,
+    format: Format,
+}
+
+impl Recorder<BufWriter<File>> {
+    /// Creates a recording at `path`.
+    ///
+    /// The format is chosen according to the file extension (see [`Format::from_path`]).
+    pub fn create<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .with_context(|| format!("failed to create recording '{}'", path.display()))?;
+        Self::new(BufWriter::new(file), Format::from_path(path))
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    /// Creates a recorder that writes frames to `writer` in the given format.
+    pub fn new(mut writer: W, format: Format) -> anyhow::Result<Self> {
+        if format == Format::Binary {
+            writer.write_all(MAGIC)?;
+            writer.write_all(&VERSION.to_le_bytes())?;
+        }
+        Ok(Self { writer, format })
+    }
+
+    /// Appends `frame` to the recording.
+    pub fn record(&mut self, frame: &RecordedFrame) -> anyhow::Result<()> {
+        match self.format {
+            Format::Binary => write_frame(&mut self.writer, frame)?,
+            Format::JsonLines => {
+                serde_json::to_writer(&mut self.writer, &JsonFrame::from(frame))?;
+                self.writer.write_all(b"\n")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Flushes all buffered data and returns the underlying writer.
+    pub fn finish(mut self) -> anyhow::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Controls the speed at which a [`Replay`] delivers frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pacing {
+    /// Frames are delivered with the same timing as in the recording, as if they were produced
+    /// live.
+    RealTime,
+    /// Frames are delivered as fast as they can be read.
+    Unlimited,
+}
+
+/// Reads [`RecordedFrame`]s from a landmark recording.
+///
+/// [`Replay`] implements [`Iterator`], yielding one frame (or error) at a time.
+pub struct Replay<R: BufRead> {
+    reader: R,
+    format: Format,
+    pacing: Pacing,
+    /// When the first frame was delivered, and its timestamp.
+    start: Option<(Instant, Duration)>,
+    line: String,
+}
+
+impl Replay<BufReader<File>> {
+    /// Opens the recording at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .with_context(|| format!("failed to open recording '{}'", path.display()))?;
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: BufRead> Replay<R> {
+    /// Creates a replay of the recording read from `reader`.
+    ///
+    /// The format of the recording is detected automatically. Frames are delivered with
+    /// [`Pacing::RealTime`] by default.
+    pub fn new(mut reader: R) -> anyhow::Result<Self> {
+        let format = if reader.fill_buf()?.starts_with(MAGIC) {
+            let mut header = [0; 8];
+            reader.read_exact(&mut header)?;
+            let version = u32::from_le_bytes(header[4..].try_into().unwrap());
+            ensure!(
+                version == VERSION,
+                "unsupported landmark recording version {version}"
+            );
+            Format::Binary
+        } else {
+            Format::JsonLines
+        };
+
+        Ok(Self {
+            reader,
+            format,
+            pacing: Pacing::RealTime,
+            start: None,
+            line: String::new(),
+        })
+    }
+
+    /// Returns the format of the recording.
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Sets the speed at which frames are delivered.
+    pub fn set_pacing(&mut self, pacing: Pacing) {
+        self.pacing = pacing;
+    }
+
+    /// Reads the next frame from the recording.
+    ///
+    /// When using [`Pacing::RealTime`], this blocks until the frame is due. Returns [`None`] once
+    /// the end of the recording is reached.
+    pub fn next_frame(&mut self) -> anyhow::Result<Option<RecordedFrame>> {
+        let frame = match self.format {
+            Format::Binary => {
+                if self.reader.fill_buf()?.is_empty() {
+                    return Ok(None);
+                }
+                read_frame(&mut self.reader)?
+            }
+            Format::JsonLines => loop {
+                self.line.clear();
+                if self.reader.read_line(&mut self.line)? == 0 {
+                    return Ok(None);
+                }
+                if !self.line.trim().is_empty() {
+                    break serde_json::from_str::<JsonFrame>(&self.line)?.try_into()?;
+                }
+            },
+        };
+
+        match self.start {
+            None => self.start = Some((Instant::now(), frame.timestamp)),
+            Some((start, first)) => {
+                if self.pacing == Pacing::RealTime {
+                    let due = start + frame.timestamp.saturating_sub(first);
+                    thread::sleep(due.saturating_duration_since(Instant::now()));
+                }
+            }
+        }
+
+        Ok(Some(frame))
+    }
+}
+
+impl<R: BufRead> Iterator for Replay<R> {
+    type Item = anyhow::Result<RecordedFrame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_frame().transpose()
+    }
+}
+
+/// Converts the recording at `input` to the format indicated by the extension of `output` (see
+/// [`Format::from_path`]).
+///
+/// This can be used to export a binary recording as JSON Lines.
+pub fn convert<P: AsRef<Path>, Q: AsRef<Path>>(input: P, output: Q) -> anyhow::Result<()> {
+    let mut replay = Replay::open(input)?;
+    replay.set_pacing(Pacing::Unlimited);
+    let mut recorder = Recorder::create(output)?;
+    for frame in replay {
+        recorder.record(&frame?)?;
+    }
+    recorder.finish()?;
+    Ok(())
+}
+
+fn write_frame<W: Write>(w: &mut W, frame: &RecordedFrame) -> anyhow::Result<()> {
+    w.write_all(&(frame.timestamp.as_nanos() as u64).to_le_bytes())?;
+    w.write_all(&(frame.targets.len() as u32).to_le_bytes())?;
+    for target in &frame.targets {
+        let lms = &target.landmarks;
+        let mut flags = 0;
+        for (present, flag) in [
+            (target.id.is_some(), HAS_ID),
+            (target.roi.is_some(), HAS_ROI),
+            (target.confidence.is_some(), HAS_CONFIDENCE),
+            (lms.confidences.is_some(), HAS_CONFIDENCES),
+            (lms.visibilities.is_some(), HAS_VISIBILITIES),
+            (lms.presences.is_some(), HAS_PRESENCES),
+        ] {
+            if present {
+                flags |= flag;
+            }
+        }
+        w.write_all(&[flags])?;
+
+        if let Some(id) = target.id {
+            w.write_all(&id.0.to_le_bytes())?;
+        }
+        if let Some(roi) = target.roi {
+            let rect = roi.rect();
+            w.write_all(&rect.x().to_le_bytes())?;
+            w.write_all(&rect.y().to_le_bytes())?;
+            w.write_all(&rect.width().to_le_bytes())?;
+            w.write_all(&rect.height().to_le_bytes())?;
+            w.write_all(&roi.rotation_radians().to_le_bytes())?;
+        }
+        if let Some(confidence) = target.confidence {
+            w.write_all(&confidence.to_le_bytes())?;
+        }
+
+        w.write_all(&(lms.len() as u32).to_le_bytes())?;
+        for value in lms.positions.iter().flatten() {
+            w.write_all(&value.to_le_bytes())?;
+        }
+        for channel in [&lms.confidences, &lms.visibilities, &lms.presences]
+            .into_iter()
+            .flatten()
+        {
+            for value in channel {
+                w.write_all(&value.to_le_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn read_frame<R: Read>(r: &mut R) -> anyhow::Result<RecordedFrame> {
+    let mut frame = RecordedFrame::new(Duration::from_nanos(read_u64(r)?));
+    let num_targets = read_count(r)?;
+    for _ in 0..num_targets {
+        let mut flags = [0];
+        r.read_exact(&mut flags)?;
+        let [flags] = flags;
+
+        let id = if flags & HAS_ID != 0 {
+            Some(TargetId(read_u64(r)?))
+        } else {
+            None
+        };
+        let roi = if flags & HAS_ROI != 0 {
+            let x = read_u32(r)? as i32;
+            let y = read_u32(r)? as i32;
+            let width = read_u32(r)?;
+            let height = read_u32(r)?;
+            let radians = read_f32(r)?;
+            Some(RotatedRect::new(
+                Rect::from_top_left(x, y, width, height),
+                radians,
+            ))
+        } else {
+            None
+        };
+        let confidence = if flags & HAS_CONFIDENCE != 0 {
+            Some(read_f32(r)?)
+        } else {
+            None
+        };
+
+        let len = read_count(r)? as usize;
+        let mut landmarks = Landmarks::new(len);
+        for position in &mut landmarks.positions {
+            for coord in position {
+                *coord = read_f32(r)?;
+            }
+        }
+        for (flag, channel) in [
+            (HAS_CONFIDENCES, &mut landmarks.confidences),
+            (HAS_VISIBILITIES, &mut landmarks.visibilities),
+            (HAS_PRESENCES, &mut landmarks.presences),
+        ] {
+            if flags & flag != 0 {
+                *channel = Some((0..len).map(|_| read_f32(r)).collect::<Result<_, _>>()?);
+            }
+        }
+
+        frame.push(RecordedTarget {
+            id,
+            roi,
+            confidence,
+            landmarks,
+        });
+    }
+    Ok(frame)
+}
+
+fn read_u32<R: Read>(r: &mut R) -> anyhow::Result<u32> {
+    let mut buf = [0; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(r: &mut R) -> anyhow::Result<u64> {
+    let mut buf = [0; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_f32<R: Read>(r: &mut R) -> anyhow::Result<f32> {
+    Ok(f32::from_bits(read_u32(r)?))
+}
+
+fn read_count<R: Read>(r: &mut R) -> anyhow::Result<u32> {
+    let count = read_u32(r)?;
+    if count > MAX_COUNT {
+        bail!("invalid count {count} in landmark recording");
+    }
+    Ok(count)
+}
+
+/// JSON representation of a [`RecordedFrame`].
+#[derive(Serialize, Deserialize)]
+struct JsonFrame {
+    timestamp_ns: u64,
+    targets: Vec<JsonTarget>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonTarget {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    roi: Option<JsonRoi>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    confidence: Option<f32>,
+    positions: Vec<[f32; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    confidences: Option<Vec<f32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    visibilities: Option<Vec<f32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    presences: Option<Vec<f32>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonRoi {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    radians: f32,
+}
+
+impl From<&RecordedFrame> for JsonFrame {
+    fn from(frame: &RecordedFrame) -> Self {
+        Self {
+            timestamp_ns: frame.timestamp.as_nanos() as u64,
+            targets: frame
+                .targets
+                .iter()
+                .map(|target| JsonTarget {
+                    id: target.id.map(|id| id.0),
+                    roi: target.roi.map(|roi| JsonRoi {
+                        x: roi.rect().x(),
+                        y: roi.rect().y(),
+                        width: roi.rect().width(),
+                        height: roi.rect().height(),
+                        radians: roi.rotation_radians(),
+                    }),
+                    confidence: target.confidence,
+                    positions: target.landmarks.positions.clone(),
+                    confidences: target.landmarks.confidences.clone(),
+                    visibilities: target.landmarks.visibilities.clone(),
+                    presences: target.landmarks.presences.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<JsonFrame> for RecordedFrame {
+    type Error = anyhow::Error;
+
+    fn try_from(frame: JsonFrame) -> anyhow::Result<Self> {
+        let mut recorded = RecordedFrame::new(Duration::from_nanos(frame.timestamp_ns));
+        for target in frame.targets {
+            let len = target.positions.len();
+            for channel in [&target.confidences, &target.visibilities, &target.presences]
+                .into_iter()
+                .flatten()
+            {
+                ensure!(
+                    channel.len() == len,
+                    "landmark channel has {} entries, expected {len}",
+                    channel.len()
+                );
+            }
+
+            recorded.push(RecordedTarget {
+                id: target.id.map(TargetId),
+                roi: target.roi.map(|roi| {
+                    RotatedRect::new(
+                        Rect::from_top_left(roi.x, roi.y, roi.width, roi.height),
+                        roi.radians,
+                    )
+                }),
+                confidence: target.confidence,
+                landmarks: Landmarks {
+                    positions: target.positions,
+                    confidences: target.confidences,
+                    visibilities: target.visibilities,
+                    presences: target.presences,
+                },
+            });
+        }
+        Ok(recorded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames() -> Vec<RecordedFrame> {
+        (0..3)
+            .map(|i| {
+                let mut frame = RecordedFrame::new(Duration::from_millis(20 * i));
+                let mut landmarks = Landmarks::new(2);
+                landmarks.positions_mut()[0] = [i as f32, 1.5, -2.0];
+                landmarks.positions_mut()[1] = [0.25, i as f32 * 10.0, 3.0];
+                landmarks.visibilities_mut()[1] = 0.125;
+                frame.push(
+                    RecordedTarget::new(landmarks.clone())
+                        .with_id(TargetId(i + 7))
+                        .with_roi(RotatedRect::new(Rect::from_top_left(-4, 5, 60, 80), 0.5))
+                        .with_confidence(0.75),
+                );
+                if i == 1 {
+                    frame.push(RecordedTarget::new(landmarks));
+                }
+                frame
+            })
+            .collect()
+    }
+
+    fn assert_frames_eq(a: &[RecordedFrame], b: &[RecordedFrame]) {
+        assert_eq!(a.len(), b.len());
+        for (a, b) in a.iter().zip(b) {
+            assert_eq!(a.timestamp(), b.timestamp());
+            assert_eq!(a.targets().len(), b.targets().len());
+            for (a, b) in a.targets().iter().zip(b.targets()) {
+                assert_eq!(a.id(), b.id());
+                assert_eq!(a.roi(), b.roi());
+                assert_eq!(a.confidence(), b.confidence());
+                assert_eq!(a.landmarks().positions(), b.landmarks().positions());
+                assert_eq!(a.landmarks().confidences(), b.landmarks().confidences());
+                assert_eq!(a.landmarks().visibilities(), b.landmarks().visibilities());
+                assert_eq!(a.landmarks().presences(), b.landmarks().presences());
+            }
+        }
+    }
+
+    fn roundtrip(format: Format) -> Vec<RecordedFrame> {
+        let mut recorder = Recorder::new(Vec::new(), format).unwrap();
+        for frame in frames() {
+            recorder.record(&frame).unwrap();
+        }
+        let data = recorder.finish().unwrap();
+
+        let mut replay = Replay::new(&data[..]).unwrap();
+        assert_eq!(replay.format(), format);
+        replay.set_pacing(Pacing::Unlimited);
+        replay.collect::<anyhow::Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn binary_roundtrip() {
+        assert_frames_eq(&roundtrip(Format::Binary), &frames());
+    }
+
+    #[test]
+    fn json_roundtrip() {
+        assert_frames_eq(&roundtrip(Format::JsonLines), &frames());
+    }
+
+    #[test]
+    fn from_tracked_targets() {
+        struct Estimate(Landmarks);
+        impl Estimation for Estimate {
+            fn landmarks(&self) -> &Landmarks {
+                &self.0
+            }
+            fn landmarks_mut(&mut self) -> &mut Landmarks {
+                &mut self.0
+            }
+        }
+        impl Confidence for Estimate {
+            fn confidence(&self) -> f32 {
+                0.875
+            }
+        }
+
+        let mut landmarks = Landmarks::new(1);
+        landmarks.positions_mut()[0] = [1.0, 2.0, 3.0];
+        let estimate = Estimate(landmarks);
+        let roi = RotatedRect::new(Rect::from_top_left(1, 2, 30, 40), 0.25);
+        let frame = RecordedFrame::from_targets(
+            Duration::from_millis(5),
+            [TrackedTarget {
+                id: TargetId(3),
+                estimation: &estimate,
+                view_rect: roi,
+            }],
+        );
+
+        assert_eq!(frame.timestamp(), Duration::from_millis(5));
+        let [target] = frame.targets() else {
+            panic!("expected a single target");
+        };
+        assert_eq!(target.id(), Some(TargetId(3)));
+        assert_eq!(target.roi(), Some(roi));
+        assert_eq!(target.confidence(), Some(0.875));
+        assert_eq!(target.landmarks().positions(), [[1.0, 2.0, 3.0]]);
+    }
+
+    #[test]
+    fn real_time_replay() {
+        let mut recorder = Recorder::new(Vec::new(), Format::Binary).unwrap();
+        for frame in frames() {
+            recorder.record(&frame).unwrap();
+        }
+        let data = recorder.finish().unwrap();
+
+        let start = Instant::now();
+        let replayed = Replay::new(&data[..]).unwrap().count();
+        assert_eq!(replayed, 3);
+        assert!(start.elapsed() >= Duration::from_millis(40));
+    }
+}
@@ -0,0 +1,409 @@
+//! Descriptions of the structure of a set of landmarks.
+//!
+//! A [`LandmarkTopology`] assigns names to landmark indices, describes which landmarks are
+//! connected by edges, groups related landmarks together (for example "left eyebrow" or "index
+//! finger"), and defines the colors to use when visualizing them.
+//!
+//! Every [`Network`] in Zaru exposes its topology via [`Network::topology`], which allows drawing
+//! any landmark set with [`draw::skeleton`] instead of hard-coding connections for each network.
+//!
+//! Topologies can be (de)serialized with [`serde`], and saved to and loaded from JSON files with
+//! [`LandmarkTopology::save`] and [`LandmarkTopology::load`].
+//!
+//! [`Network`]: super::Network
+//! [`Network::topology`]: super::Network::topology
+//! [`draw::skeleton`]: crate::draw::skeleton
+
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use zaru_image::Color;
+
+/// Describes the names, connectivity, groups and colors of a set of landmarks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "RawTopology", try_from = "RawTopology")]
+pub struct LandmarkTopology {
+    names: Vec<Option<String>>,
+    edges: Vec<(usize, usize)>,
+    groups: Vec<LandmarkGroup>,
+    color: Color,
+    landmark_color: Color,
+}
+
+impl LandmarkTopology {
+    /// The default color of edges.
+    pub const DEFAULT_COLOR: Color = Color::GREEN;
+
+    /// The default color of landmarks.
+    pub const DEFAULT_LANDMARK_COLOR: Color = Color::RED;
+
+    /// Creates a topology for `landmark_count` unnamed and unconnected landmarks.
+    pub fn new(landmark_count: usize) -> Self {
+        Self {
+            names: vec![None; landmark_count],
+            edges: Vec::new(),
+            groups: Vec::new(),
+            color: Self::DEFAULT_COLOR,
+            landmark_color: Self::DEFAULT_LANDMARK_COLOR,
+        }
+    }
+
+    /// Returns the number of landmarks described by this topology.
+    #[inline]
+    pub fn landmark_count(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Assigns a name to the landmark at `index`.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `index` is out of bounds.
+    pub fn set_name(&mut self, index: usize, name: impl Into<String>) -> &mut Self {
+        self.check_index(index);
+        self.names[index] = Some(name.into());
+        self
+    }
+
+    /// Returns the name of the landmark at `index`, if it has one.
+    pub fn name(&self, index: usize) -> Option<&str> {
+        self.names.get(index)?.as_deref()
+    }
+
+    /// Returns the index of the landmark called `name`.
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n.as_deref() == Some(name))
+    }
+
+    /// Adds an edge between the landmarks at `a` and `b`.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `a` or `b` is out of bounds.
+    pub fn add_edge(&mut self, a: usize, b: usize) -> &mut Self {
+        self.check_index(a);
+        self.check_index(b);
+        self.edges.push((a, b));
+        self
+    }
+
+    /// Connects each landmark in `landmarks` to the next one.
+    pub fn add_chain(&mut self, landmarks: &[usize]) -> &mut Self {
+        for pair in landmarks.windows(2) {
+            self.add_edge(pair[0], pair[1]);
+        }
+        self
+    }
+
+    /// Connects each landmark in `landmarks` to the next one, and the last landmark to the first.
+    pub fn add_loop(&mut self, landmarks: &[usize]) -> &mut Self {
+        self.add_chain(landmarks);
+        if let (Some(&first), Some(&last)) = (landmarks.first(), landmarks.last()) {
+            if landmarks.len() > 2 {
+                self.add_edge(last, first);
+            }
+        }
+        self
+    }
+
+    /// Returns the list of edges connecting landmarks.
+    #[inline]
+    pub fn edges(&self) -> &[(usize, usize)] {
+        &self.edges
+    }
+
+    /// Adds a named group of landmarks.
+    ///
+    /// If `color` is [`Some`], the landmarks in the group, and the edges between them, will be
+    /// drawn in that color.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if any of the indices in `landmarks` is out of bounds.
+    pub fn add_group(
+        &mut self,
+        name: impl Into<String>,
+        landmarks: impl IntoIterator<Item = usize>,
+        color: Option<Color>,
+    ) -> &mut Self {
+        let landmarks = landmarks.into_iter().collect::<Vec<_>>();
+        for &index in &landmarks {
+            self.check_index(index);
+        }
+        self.groups.push(LandmarkGroup {
+            name: name.into(),
+            landmarks,
+            color,
+        });
+        self
+    }
+
+    /// Returns all landmark groups.
+    #[inline]
+    pub fn groups(&self) -> &[LandmarkGroup] {
+        &self.groups
+    }
+
+    /// Returns the group called `name`.
+    pub fn group(&self, name: &str) -> Option<&LandmarkGroup> {
+        self.groups.iter().find(|group| group.name == name)
+    }
+
+    /// Sets the default color to draw edges with.
+    ///
+    /// By default, [`LandmarkTopology::DEFAULT_COLOR`] is used.
+    pub fn set_color(&mut self, color: Color) -> &mut Self {
+        self.color = color;
+        self
+    }
+
+    /// Returns the default edge color.
+    #[inline]
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    /// Sets the default color to draw landmarks with.
+    ///
+    /// By default, [`LandmarkTopology::DEFAULT_LANDMARK_COLOR`] is used.
+    pub fn set_landmark_color(&mut self, color: Color) -> &mut Self {
+        self.landmark_color = color;
+        self
+    }
+
+    /// Returns the color to draw the landmark at `index` with.
+    ///
+    /// This is the color of the first colored group containing the landmark, or the default
+    /// landmark color if there is no such group.
+    pub fn landmark_color(&self, index: usize) -> Color {
+        self.groups
+            .iter()
+            .find(|group| group.color.is_some() && group.contains(index))
+            .and_then(|group| group.color)
+            .unwrap_or(self.landmark_color)
+    }
+
+    /// Returns the color to draw the edge between `a` and `b` with.
+    ///
+    /// This is the color of the first colored group containing both landmarks, or the default edge
+    /// color if there is no such group.
+    pub fn edge_color(&self, a: usize, b: usize) -> Color {
+        self.groups
+            .iter()
+            .find(|group| group.color.is_some() && group.contains(a) && group.contains(b))
+            .and_then(|group| group.color)
+            .unwrap_or(self.color)
+    }
+
+    /// Parses a topology from its JSON representation.
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Serializes this topology to JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// Loads a topology from a JSON file.
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("failed to read '{}'", path.display()))?;
+        Self::from_json(&json).with_context(|| format!("failed to parse '{}'", path.display()))
+    }
+
+    /// Writes this topology to a JSON file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_json())
+            .with_context(|| format!("failed to write '{}'", path.display()))
+    }
+
+    fn check_index(&self, index: usize) {
+        assert!(
+            index < self.landmark_count(),
+            "landmark index {} out of bounds (topology has {} landmarks)",
+            index,
+            self.landmark_count(),
+        );
+    }
+}
+
+/// A named group of landmarks in a [`LandmarkTopology`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LandmarkGroup {
+    name: String,
+    landmarks: Vec<usize>,
+    color: Option<Color>,
+}
+
+impl LandmarkGroup {
+    /// Returns the name of this group.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the indices of the landmarks in this group.
+    #[inline]
+    pub fn landmarks(&self) -> &[usize] {
+        &self.landmarks
+    }
+
+    /// Returns the color the group's landmarks and edges should be drawn with, if it has one.
+    #[inline]
+    pub fn color(&self) -> Option<Color> {
+        self.color
+    }
+
+    /// Returns whether the landmark at `index` is part of this group.
+    pub fn contains(&self, index: usize) -> bool {
+        self.landmarks.contains(&index)
+    }
+}
+
+/// Serialized form of a [`LandmarkTopology`].
+///
+/// Colors are stored as `[r, g, b, a]` arrays.
+#[derive(Clone, Serialize, Deserialize)]
+struct RawTopology {
+    names: Vec<Option<String>>,
+    edges: Vec<(usize, usize)>,
+    #[serde(default)]
+    groups: Vec<RawGroup>,
+    color: [u8; 4],
+    landmark_color: [u8; 4],
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct RawGroup {
+    name: String,
+    landmarks: Vec<usize>,
+    #[serde(default)]
+    color: Option<[u8; 4]>,
+}
+
+fn color_to_raw(color: Color) -> [u8; 4] {
+    [color.r(), color.g(), color.b(), color.a()]
+}
+
+fn color_from_raw([r, g, b, a]: [u8; 4]) -> Color {
+    Color::from_rgb8(r, g, b).with_alpha(a)
+}
+
+impl From<LandmarkTopology> for RawTopology {
+    fn from(topology: LandmarkTopology) -> Self {
+        Self {
+            names: topology.names,
+            edges: topology.edges,
+            groups: topology
+                .groups
+                .into_iter()
+                .map(|group| RawGroup {
+                    name: group.name,
+                    landmarks: group.landmarks,
+                    color: group.color.map(color_to_raw),
+                })
+                .collect(),
+            color: color_to_raw(topology.color),
+            landmark_color: color_to_raw(topology.landmark_color),
+        }
+    }
+}
+
+impl TryFrom<RawTopology> for LandmarkTopology {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawTopology) -> anyhow::Result<Self> {
+        let count = raw.names.len();
+        let check = |index: usize| {
+            if index >= count {
+                bail!(
+                    "landmark index {} out of bounds (topology has {} landmarks)",
+                    index,
+                    count
+                );
+            }
+            Ok(())
+        };
+
+        for &(a, b) in &raw.edges {
+            check(a)?;
+            check(b)?;
+        }
+        for group in &raw.groups {
+            for &index in &group.landmarks {
+                check(index)?;
+            }
+        }
+
+        Ok(Self {
+            names: raw.names,
+            edges: raw.edges,
+            groups: raw
+                .groups
+                .into_iter()
+                .map(|group| LandmarkGroup {
+                    name: group.name,
+                    landmarks: group.landmarks,
+                    color: group.color.map(color_from_raw),
+                })
+                .collect(),
+            color: color_from_raw(raw.color),
+            landmark_color: color_from_raw(raw.landmark_color),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle() -> LandmarkTopology {
+        let mut topology = LandmarkTopology::new(4);
+        topology
+            .set_name(0, "top")
+            .set_name(1, "left")
+            .set_name(2, "right")
+            .add_loop(&[0, 1, 2])
+            .add_edge(2, 3)
+            .add_group("base", [1, 2], Some(Color::YELLOW));
+        topology
+    }
+
+    #[test]
+    fn lookup() {
+        let topology = triangle();
+        assert_eq!(topology.landmark_count(), 4);
+        assert_eq!(topology.edges(), &[(0, 1), (1, 2), (2, 0), (2, 3)]);
+        assert_eq!(topology.name(1), Some("left"));
+        assert_eq!(topology.name(3), None);
+        assert_eq!(topology.index_of("right"), Some(2));
+        assert_eq!(topology.group("base").unwrap().landmarks(), &[1, 2]);
+
+        assert_eq!(topology.edge_color(1, 2), Color::YELLOW);
+        assert_eq!(topology.edge_color(0, 1), LandmarkTopology::DEFAULT_COLOR);
+        assert_eq!(topology.landmark_color(1), Color::YELLOW);
+        assert_eq!(
+            topology.landmark_color(0),
+            LandmarkTopology::DEFAULT_LANDMARK_COLOR
+        );
+    }
+
+    #[test]
+    fn json_roundtrip() {
+        let topology = triangle();
+        let json = topology.to_json();
+        assert_eq!(LandmarkTopology::from_json(&json).unwrap(), topology);
+
+        let invalid = r#"{
+            "names": [null, null],
+            "edges": [[0, 2]],
+            "color": [0, 255, 0, 255],
+            "landmark_color": [255, 0, 0, 255]
+        }"#;
+        assert!(LandmarkTopology::from_json(invalid).is_err());
+    }
+}
@@ -31,12 +31,14 @@
 use log::LevelFilter;
 
 pub mod body;
+pub mod calibration;
 pub mod detection;
+pub mod draw;
 pub mod face;
 pub mod hand;
 pub mod landmark;
 
-pub use zaru_utils::{filter, iter, num, procrustes, slice, timer};
+pub use zaru_utils::{assignment, camera, filter, iter, num, pnp, procrustes, slice, timer};
 #[doc(inline)]
 pub use {zaru_gui as gui, zaru_image as image, zaru_nn as nn, zaru_video as video};
 